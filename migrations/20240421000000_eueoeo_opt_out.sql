@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE `users` ADD COLUMN `opted_out` BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE `history` ADD COLUMN `weight` INTEGER NOT NULL DEFAULT 1;
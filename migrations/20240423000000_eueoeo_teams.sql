@@ -0,0 +1,5 @@
+-- Add migration script here
+CREATE TABLE `teams` (
+    `user_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `name` TEXT NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Add migration script here
+CREATE TABLE `member_count_snapshots` (
+    `month` TEXT PRIMARY KEY NOT NULL,
+    `member_count` INTEGER NOT NULL
+);
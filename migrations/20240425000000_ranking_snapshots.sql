@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE `ranking_snapshots` (
+    `date` INTEGER(64) NOT NULL,
+    `kind` TEXT NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `rank` INTEGER NOT NULL,
+    PRIMARY KEY (`date`, `kind`, `user_id`)
+);
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE `perfect_months` (
+    `user_id` INTEGER(64) NOT NULL,
+    `month` TEXT NOT NULL,
+    PRIMARY KEY (`user_id`, `month`)
+);
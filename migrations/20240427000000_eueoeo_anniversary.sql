@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE `users` ADD COLUMN `first_date` INTEGER(64);
+ALTER TABLE `users` ADD COLUMN `anniversary_year` INTEGER NOT NULL DEFAULT 0;
+UPDATE `users` SET `first_date` = (
+    SELECT min(`date`) FROM `history` WHERE `history`.`user_id` = `users`.`user_id`
+);
@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE `scheduled_events` (
+    `discord_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `guild_id` INTEGER(64) NOT NULL,
+    `name` TEXT NOT NULL,
+    `description` TEXT,
+    `location` TEXT,
+    `start_time` INTEGER(64) NOT NULL,
+    `end_time` INTEGER(64),
+    `updated_at` INTEGER(64) NOT NULL
+);
+CREATE TABLE `scheduled_event_attendees` (
+    `discord_id` INTEGER(64) NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    PRIMARY KEY (`discord_id`, `user_id`)
+);
+ALTER TABLE `users` ADD COLUMN `ics_token` TEXT;
@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE `shared_calendars` (
+    `guild_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `calendar_id` TEXT NOT NULL
+);
+CREATE TABLE `shared_events` (
+    `discord_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `google_event_id` TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE `imported_events` (
+    `google_event_id` TEXT PRIMARY KEY NOT NULL,
+    `discord_id` INTEGER(64) NOT NULL,
+    `updated_at` INTEGER(64) NOT NULL
+);
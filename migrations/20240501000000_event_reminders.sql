@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE `event_reminders` (
+    `discord_id` INTEGER(64) NOT NULL,
+    `start_time` INTEGER(64) NOT NULL,
+    PRIMARY KEY (`discord_id`, `start_time`)
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE `google_sync_queue` (
+    `discord_id` INTEGER(64) NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `calendar_id` TEXT NOT NULL,
+    `operation` TEXT NOT NULL,
+    `attempts` INTEGER NOT NULL DEFAULT 0,
+    `next_attempt_at` INTEGER(64) NOT NULL,
+    PRIMARY KEY (`discord_id`, `user_id`)
+);
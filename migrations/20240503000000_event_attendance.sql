@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE `attended_events` (
+    `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `discord_id` INTEGER(64) NOT NULL,
+    `guild_id` INTEGER(64) NOT NULL,
+    `name` TEXT NOT NULL,
+    `start_time` INTEGER(64) NOT NULL,
+    UNIQUE (`discord_id`, `start_time`)
+);
+
+CREATE TABLE `event_attendance` (
+    `event_id` INTEGER NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `interested` BOOLEAN NOT NULL DEFAULT FALSE,
+    `present` BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (`event_id`, `user_id`)
+);
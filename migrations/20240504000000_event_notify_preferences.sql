@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE `event_notify_preferences` (
+    `user_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `dm` BOOLEAN NOT NULL,
+    `lead_minutes` INTEGER NOT NULL
+);
+
+CREATE TABLE `event_user_reminders` (
+    `discord_id` INTEGER(64) NOT NULL,
+    `start_time` INTEGER(64) NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    PRIMARY KEY (`discord_id`, `start_time`, `user_id`)
+);
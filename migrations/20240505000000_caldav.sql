@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE `users` ADD COLUMN `calendar_backend` TEXT NOT NULL DEFAULT 'google';
+ALTER TABLE `users` ADD COLUMN `caldav_url` TEXT;
+ALTER TABLE `users` ADD COLUMN `caldav_username` TEXT;
+ALTER TABLE `users` ADD COLUMN `caldav_password` TEXT;
+-- calendars are resolved from `users` on retry
+ALTER TABLE `google_sync_queue` DROP COLUMN `calendar_id`;
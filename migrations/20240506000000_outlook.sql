@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE `users` ADD COLUMN `outlook_access_token` TEXT;
+ALTER TABLE `users` ADD COLUMN `outlook_refresh_token` TEXT;
+ALTER TABLE `users` ADD COLUMN `outlook_token_expires_at` INTEGER;
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE `event_threads` (
+    `discord_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `thread_id` INTEGER(64) NOT NULL,
+    `message_id` INTEGER(64) NOT NULL,
+    -- last status announced in the thread
+    `status` INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE `google_watch_channels` (
+    `channel_id` TEXT PRIMARY KEY NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `calendar_id` TEXT NOT NULL,
+    `resource_id` TEXT NOT NULL,
+    -- sent back with notifications to verify them
+    `token` TEXT NOT NULL,
+    `expiration` INTEGER(64) NOT NULL,
+    `sync_token` TEXT
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE `sync_log` (
+    `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `discord_id` INTEGER(64) NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `action` TEXT NOT NULL,
+    `success` BOOLEAN NOT NULL,
+    `error` TEXT,
+    `created_at` INTEGER(64) NOT NULL
+);
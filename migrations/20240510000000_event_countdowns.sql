@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE `event_countdowns` (
+    `discord_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `channel_id` INTEGER(64) NOT NULL,
+    `message_id` INTEGER(64) NOT NULL,
+    -- last rendered message not to edit it without changes
+    `content` TEXT NOT NULL
+);
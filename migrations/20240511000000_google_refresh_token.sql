@@ -0,0 +1,3 @@
+-- Add migration script here
+-- encrypted with the configured token key
+ALTER TABLE `users` ADD COLUMN `google_refresh_token` TEXT;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- IANA time zone name. the server default is used when it is not set.
+ALTER TABLE `users` ADD COLUMN `time_zone` TEXT;
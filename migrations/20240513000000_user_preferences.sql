@@ -0,0 +1,8 @@
+-- Add migration script here
+-- `value` is serialized as json
+CREATE TABLE `user_preferences` (
+    `user_id` INTEGER(64) NOT NULL,
+    `key` TEXT NOT NULL,
+    `value` TEXT NOT NULL,
+    PRIMARY KEY (`user_id`, `key`)
+);
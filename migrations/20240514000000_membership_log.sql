@@ -0,0 +1,11 @@
+-- Add migration script here
+-- `joined_at` is unknown for members who left before the log is started
+CREATE TABLE `membership_log` (
+    `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `joined_at` INTEGER(64),
+    `left_at` INTEGER(64)
+);
+CREATE INDEX `membership_log_user_id` ON `membership_log` (`user_id`);
+-- records of departed users are kept to restore on rejoin
+ALTER TABLE `users` ADD COLUMN `departed` BOOLEAN NOT NULL DEFAULT FALSE;
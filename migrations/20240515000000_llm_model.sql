@@ -0,0 +1,3 @@
+-- Add migration script here
+-- model chosen by `/llm model`. the first configured model is used when it is not set.
+ALTER TABLE `llm_config` ADD COLUMN `model` TEXT;
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- turns of conversations with the bot. `parent_message_id` is the message replied to.
+-- `user_id` is the asker, answers of the bot have the asker of the question.
+CREATE TABLE `llm_conversations` (
+    `message_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `parent_message_id` INTEGER(64),
+    `root_message_id` INTEGER(64) NOT NULL,
+    `user_id` INTEGER(64),
+    `role` TEXT NOT NULL,
+    `content` TEXT NOT NULL,
+    `created_at` INTEGER(64) NOT NULL
+);
+CREATE INDEX `llm_conversations_root_message_id` ON `llm_conversations` (`root_message_id`);
+CREATE INDEX `llm_conversations_user_id` ON `llm_conversations` (`user_id`);
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- requests to the LLM to limit them per user
+CREATE TABLE `llm_requests` (
+    `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `user_id` INTEGER(64) NOT NULL,
+    `requested_at` INTEGER(64) NOT NULL
+);
+CREATE INDEX `llm_requests_user_id_requested_at` ON `llm_requests` (`user_id`, `requested_at`);
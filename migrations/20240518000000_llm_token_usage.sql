@@ -0,0 +1,5 @@
+-- Add migration script here
+-- token counts are filled when the answer is finished
+ALTER TABLE `llm_requests` ADD COLUMN `model` TEXT;
+ALTER TABLE `llm_requests` ADD COLUMN `prompt_tokens` INTEGER;
+ALTER TABLE `llm_requests` ADD COLUMN `response_tokens` INTEGER;
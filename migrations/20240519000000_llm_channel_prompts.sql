@@ -0,0 +1,6 @@
+-- Add migration script here
+-- prompts used instead of the one in `llm_config` for the channel
+CREATE TABLE `llm_channel_prompts` (
+    `channel_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `prompt` TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- named prompts to switch the prompt with `/llm persona use`
+CREATE TABLE `llm_personas` (
+    `name` TEXT PRIMARY KEY NOT NULL,
+    `prompt` TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- set by `/llm config`. values of the config file are used when they are not set.
+ALTER TABLE `llm_config` ADD COLUMN `temperature` REAL;
+ALTER TABLE `llm_config` ADD COLUMN `top_p` REAL;
+ALTER TABLE `llm_config` ADD COLUMN `max_output_tokens` INTEGER;
+-- one threshold for every harm category
+ALTER TABLE `llm_config` ADD COLUMN `safety_threshold` TEXT;
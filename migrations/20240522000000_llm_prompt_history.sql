@@ -0,0 +1,15 @@
+-- Add migration script here
+-- every change of the prompts. `channel_id` is NULL for the global prompt and an empty prompt clears it.
+CREATE TABLE `llm_prompt_history` (
+    `version` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `channel_id` INTEGER(64),
+    `prompt` TEXT NOT NULL,
+    `changed_by` INTEGER(64),
+    `changed_at` INTEGER(64) NOT NULL
+);
+CREATE INDEX `llm_prompt_history_channel_id` ON `llm_prompt_history` (`channel_id`);
+-- current prompts are the first versions
+INSERT INTO `llm_prompt_history` (`channel_id`, `prompt`, `changed_at`)
+SELECT NULL, `prompt`, strftime('%s', 'now') FROM `llm_config` WHERE `prompt` != '';
+INSERT INTO `llm_prompt_history` (`channel_id`, `prompt`, `changed_at`)
+SELECT `channel_id`, `prompt`, strftime('%s', 'now') FROM `llm_channel_prompts`;
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- requests in DMs have their own quota
+ALTER TABLE `llm_requests` ADD COLUMN `is_dm` BOOLEAN NOT NULL DEFAULT FALSE;
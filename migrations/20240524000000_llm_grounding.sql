@@ -0,0 +1,3 @@
+-- Add migration script here
+-- NULL follows the config file
+ALTER TABLE `llm_config` ADD COLUMN `grounding` BOOLEAN;
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE `llm_memory` (
+    `message_id` INTEGER PRIMARY KEY NOT NULL,
+    `channel_id` INTEGER NOT NULL,
+    `user_id` INTEGER NOT NULL,
+    `content` TEXT NOT NULL,
+    `created_at` INTEGER NOT NULL,
+    -- little endian f32 values. NULL until the message is embedded.
+    `embedding` BLOB
+);
+CREATE INDEX `llm_memory_user_id` ON `llm_memory` (`user_id`);
+CREATE INDEX `llm_memory_pending` ON `llm_memory` (`message_id`) WHERE `embedding` IS NULL;
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- warnings of the monthly cost budget posted once for each level
+CREATE TABLE `llm_cost_warnings` (
+    `month` TEXT NOT NULL,
+    `level` INTEGER NOT NULL,
+    `warned_at` INTEGER NOT NULL,
+    PRIMARY KEY (`month`, `level`)
+);
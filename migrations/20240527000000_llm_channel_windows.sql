@@ -0,0 +1,6 @@
+-- Add migration script here
+-- channels where the recent messages are given to the LLM as the conversation
+CREATE TABLE `llm_channel_windows` (
+    `channel_id` INTEGER PRIMARY KEY NOT NULL,
+    `size` INTEGER NOT NULL
+);
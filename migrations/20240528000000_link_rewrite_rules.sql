@@ -0,0 +1,11 @@
+-- Add migration script here
+-- links matched by `pattern` are replaced with `replacement`, in the syntax of the regex crate
+CREATE TABLE `link_rewrite_rules` (
+    `id` INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    `pattern` TEXT NOT NULL,
+    `replacement` TEXT NOT NULL,
+    `enabled` BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+INSERT INTO `link_rewrite_rules` (`pattern`, `replacement`) VALUES
+    ('://(x|twitter)\.com/([^/]+)/status/(\d+)(\?[a-zA-Z0-9%\-_&=]+)?', '://vxtwitter.com/$2/status/$3');
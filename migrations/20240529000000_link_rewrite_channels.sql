@@ -0,0 +1,6 @@
+-- Add migration script here
+-- link rewriting is turned on or off in channels or categories. it is on where not set.
+CREATE TABLE `link_rewrite_channels` (
+    `channel_id` INTEGER PRIMARY KEY NOT NULL,
+    `enabled` BOOLEAN NOT NULL
+);
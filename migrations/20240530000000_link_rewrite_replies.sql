@@ -0,0 +1,8 @@
+-- Add migration script here
+-- replies with fixed links, to edit them instead of replying again when the original is edited.
+-- only recent ones are kept.
+CREATE TABLE `link_rewrite_replies` (
+    `message_id` INTEGER PRIMARY KEY NOT NULL,
+    `reply_id` INTEGER NOT NULL,
+    `created_at` INTEGER NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- comma separated domains filling `{mirror}` of the replacement in the order of preference
+ALTER TABLE `link_rewrite_rules` ADD COLUMN `mirrors` TEXT NOT NULL DEFAULT '';
+
+UPDATE `link_rewrite_rules`
+SET `replacement` = '://{mirror}/$2/status/$3', `mirrors` = 'vxtwitter.com,fxtwitter.com,fixupx.com'
+WHERE `replacement` = '://vxtwitter.com/$2/status/$3';
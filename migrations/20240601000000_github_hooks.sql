@@ -0,0 +1,7 @@
+-- Add migration script here
+-- events of github repositories are posted to the channels. repositories are `owner/name` in lower case.
+CREATE TABLE `github_hook_channels` (
+    `repository` TEXT NOT NULL,
+    `channel_id` INTEGER(64) NOT NULL,
+    PRIMARY KEY (`repository`, `channel_id`)
+);
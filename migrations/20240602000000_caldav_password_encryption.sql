@@ -0,0 +1,3 @@
+-- Add migration script here
+-- caldav passwords are encrypted with the google token key. plain ones stored before are encrypted on start.
+ALTER TABLE `users` ADD COLUMN `caldav_password_encrypted` BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,12 @@
+-- threads are saved as soon as they are created, before their summaries are posted
+CREATE TABLE `event_threads_new` (
+    `discord_id` INTEGER(64) PRIMARY KEY NOT NULL,
+    `thread_id` INTEGER(64) NOT NULL,
+    -- summary posted in the thread, NULL until it is posted
+    `message_id` INTEGER(64),
+    -- last status announced in the thread
+    `status` INTEGER NOT NULL
+);
+INSERT INTO `event_threads_new` SELECT `discord_id`, `thread_id`, `message_id`, `status` FROM `event_threads`;
+DROP TABLE `event_threads`;
+ALTER TABLE `event_threads_new` RENAME TO `event_threads`;
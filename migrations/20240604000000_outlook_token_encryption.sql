@@ -0,0 +1,3 @@
+-- Add migration script here
+-- outlook tokens are encrypted with the google token key. plain ones stored before are encrypted on start.
+ALTER TABLE `users` ADD COLUMN `outlook_tokens_encrypted` BOOLEAN NOT NULL DEFAULT FALSE;
@@ -1,17 +1,29 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use chrono::{DateTime, Duration, TimeZone, Utc};
 
+use anyhow::Context as _;
 use async_trait::async_trait;
-use log::info;
+use log::{error, info};
 use serde::Deserialize;
 use serenity::{
-    client::{Context, EventHandler},
+    client::{bridge::gateway::event::ShardStageUpdateEvent, Context, EventHandler},
+    gateway::ConnectionStage,
     http::CacheHttp,
     model::{
-        application::interaction::{modal::ModalSubmitInteraction, Interaction, InteractionType},
-        channel::Message,
+        application::interaction::{
+            message_component::MessageComponentInteraction, modal::ModalSubmitInteraction,
+            Interaction, InteractionResponseType, InteractionType,
+        },
+        channel::{Message, Reaction},
+        event::MessageUpdateEvent,
         gateway::GatewayIntents,
         guild::Member,
-        id::{ChannelId, GuildId, UserId},
+        id::{ChannelId, GuildId, MessageId, UserId},
+        permissions::Permissions,
         prelude::{
             interaction::{
                 application_command::{ApplicationCommandInteraction, CommandDataOption},
@@ -20,12 +32,21 @@ use serenity::{
             Channel, GuildScheduledEventUserAddEvent, GuildScheduledEventUserRemoveEvent, Ready,
             ResumedEvent, ScheduledEvent,
         },
+        user::User,
     },
+    prelude::TypeMapKey,
     Client,
 };
+use sqlx::SqlitePool;
+
+use crate::llm::LlmTool;
 
 pub mod application_command;
 
+// buttons of the confirmation message of `/user forget`
+pub const FORGET_USER_CONFIRM: &str = "forget_user_confirm";
+pub const FORGET_USER_CANCEL: &str = "forget_user_cancel";
+
 pub enum ScheduledEventUpdated<'a> {
     Created(&'a ScheduledEvent),
     Updated(&'a ScheduledEvent),
@@ -40,6 +61,17 @@ pub trait SubApplication {
     async fn ready(&self, _context: &Context, _guild_id: GuildId) {}
     async fn resume(&self, _context: &Context) {}
     async fn message(&self, _context: &Context, _message: &Message) {}
+    // only for edits of the content
+    async fn message_update(&self, _context: &Context, _message: &Message) {}
+    // for both single and bulk deletions
+    async fn message_delete(
+        &self,
+        _context: &Context,
+        _channel_id: ChannelId,
+        _message_ids: &[MessageId],
+    ) {
+    }
+    async fn reaction_add(&self, _context: &Context, _reaction: &Reaction) {}
     async fn application_command_interaction_create(
         &self,
         _context: &Context,
@@ -57,15 +89,135 @@ pub trait SubApplication {
     async fn modal_submit(&self, _context: &Context, _modal: &ModalSubmitInteraction) -> bool {
         false
     }
+    async fn message_component(
+        &self,
+        _context: &Context,
+        _interaction: &MessageComponentInteraction,
+    ) -> bool {
+        false
+    }
+    // remove or anonymize every record of the user. returns what is removed to report.
+    // the `users` row itself is removed by the user module.
+    async fn forget_user(&self, _user_id: UserId) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    // records of the user for `/user export`, keyed by what they are
+    async fn export_user(
+        &self,
+        _user_id: UserId,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        Ok(Default::default())
+    }
     async fn update_member(&self, _member: &Member) -> anyhow::Result<()> {
         Ok(())
     }
+    async fn member_join(&self, _context: &Context, _member: &Member) {}
+    async fn member_leave(&self, _context: &Context, _user: &User) {}
     async fn guild_scheduled_event(&self, _context: &Context, _event: ScheduledEventUpdated<'_>) {}
+    // functions the LLM can call to look up data of the application
+    fn llm_tools(&self) -> Vec<LlmTool> {
+        Vec::new()
+    }
+    // `None` if the function is not one of `llm_tools`
+    async fn call_llm_tool(
+        &self,
+        _context: &Context,
+        _guild_id: GuildId,
+        _name: &str,
+        _args: &serde_json::Value,
+    ) -> Option<anyhow::Result<serde_json::Value>> {
+        None
+    }
+}
+
+pub(crate) type Applications = Arc<Vec<Box<dyn SubApplication + Send + Sync>>>;
+
+// lets sub applications reach each other, like the user module gathering exports
+pub(crate) struct SubApplications;
+
+impl TypeMapKey for SubApplications {
+    type Value = Applications;
+}
+
+// whether the gateway is connected, shared with the web for readiness checks
+#[derive(Clone, Default)]
+pub(crate) struct GatewayStatus(Arc<AtomicBool>);
+
+impl GatewayStatus {
+    pub(crate) fn is_connected(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.0.store(connected, Ordering::SeqCst);
+    }
 }
 
 struct Handler {
-    applications: Vec<Box<dyn SubApplication + Send + Sync>>,
+    db_pool: SqlitePool,
+    applications: Applications,
     guild_id: GuildId,
+    status: GatewayStatus,
+}
+
+impl Handler {
+    // every application is asked, so purging is done here rather than in the user module
+    async fn forget_user(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+    ) -> anyhow::Result<()> {
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await?;
+
+        let user_id = interaction.user.id;
+        info!("Forget user({user_id})");
+        let mut removed = Vec::new();
+        let mut failed = false;
+        for app in self.applications.iter() {
+            match app.forget_user(user_id).await {
+                Ok(items) => removed.extend(items),
+                Err(e) => {
+                    error!("Failed to forget user({user_id}) - {e:?}");
+                    failed = true;
+                }
+            }
+        }
+        // applications may need the `users` row until they finish. it is kept to retry on failures.
+        if !failed {
+            let raw_user_id = *user_id.as_u64() as i64;
+            match sqlx::query!("DELETE FROM `users` WHERE `user_id` = ?", raw_user_id)
+                .execute(&self.db_pool)
+                .await
+            {
+                Ok(result) if result.rows_affected() > 0 => removed.push("사용자 정보".to_string()),
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Failed to delete user({user_id}) - {e:?}");
+                    failed = true;
+                }
+            }
+        }
+
+        let mut content = if failed {
+            "일부 데이터를 삭제하지 못했습니다. 관리자에게 문의해주세요.".to_string()
+        } else {
+            "데이터를 삭제했습니다.".to_string()
+        };
+        for item in removed {
+            content.push_str(&format!("\n- {item}"));
+        }
+        interaction
+            .edit_original_interaction_response(&context.http, |r| {
+                r.content(content).components(|c| c)
+            })
+            .await?;
+
+        Ok(())
+    }
 }
 
 pub trait IntoSnowflakes {
@@ -146,6 +298,8 @@ pub trait CommandDataOptionHelper {
     fn as_str(&self) -> Option<&str>;
     fn as_u64(&self) -> Option<u64>;
     fn as_i64(&self) -> Option<i64>;
+    fn as_bool(&self) -> Option<bool>;
+    fn as_f64(&self) -> Option<f64>;
     unsafe fn as_str_unchecked(&self) -> &str;
     unsafe fn as_i64_unchecked(&self) -> i64;
 }
@@ -163,6 +317,14 @@ impl CommandDataOptionHelper for CommandDataOption {
         self.value.as_ref().and_then(|v| v.as_i64())
     }
 
+    fn as_bool(&self) -> Option<bool> {
+        self.value.as_ref().and_then(|v| v.as_bool())
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        self.value.as_ref().and_then(|v| v.as_f64())
+    }
+
     unsafe fn as_str_unchecked(&self) -> &str {
         self.value
             .as_ref()
@@ -193,6 +355,14 @@ impl<T: CommandDataOptionHelper> CommandDataOptionHelper for Option<&T> {
         self.and_then(|o| o.as_i64())
     }
 
+    fn as_bool(&self) -> Option<bool> {
+        self.and_then(|o| o.as_bool())
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        self.and_then(|o| o.as_f64())
+    }
+
     unsafe fn as_str_unchecked(&self) -> &str {
         self.unwrap_unchecked().as_str_unchecked()
     }
@@ -226,7 +396,7 @@ impl EventHandler for Handler {
                         largest_user_id = Some(member.user.id);
                     }
 
-                    for app in &self.applications {
+                    for app in self.applications.iter() {
                         app.update_member(&member)
                             .await
                             .expect("Failed to update member");
@@ -244,26 +414,73 @@ impl EventHandler for Handler {
     }
 
     async fn resume(&self, context: Context, _: ResumedEvent) {
-        for app in &self.applications {
+        self.status.set_connected(true);
+        for app in self.applications.iter() {
             app.resume(&context).await;
         }
     }
 
+    async fn shard_stage_update(&self, _context: Context, event: ShardStageUpdateEvent) {
+        if matches!(event.old, ConnectionStage::Connected)
+            != matches!(event.new, ConnectionStage::Connected)
+        {
+            info!("Gateway connection stage - {}", event.new);
+        }
+        self.status
+            .set_connected(matches!(event.new, ConnectionStage::Connected));
+    }
+
     // on connected to discord
     async fn ready(&self, ctx: Context, _data_about_bot: Ready) {
-        for app in &self.applications {
+        self.status.set_connected(true);
+        for app in self.applications.iter() {
             app.ready(&ctx, self.guild_id).await;
         }
 
         info!("ready");
     }
 
-    async fn guild_member_addition(&self, _: Context, new_member: Member) {
-        for app in &self.applications {
+    async fn guild_member_addition(&self, context: Context, new_member: Member) {
+        for app in self.applications.iter() {
             app.update_member(&new_member)
                 .await
                 .expect("Failed to update member");
         }
+        if new_member.guild_id != self.guild_id {
+            return;
+        }
+
+        for app in self.applications.iter() {
+            app.member_join(&context, &new_member).await;
+        }
+    }
+
+    async fn guild_member_removal(
+        &self,
+        context: Context,
+        guild_id: GuildId,
+        user: User,
+        _member: Option<Member>,
+    ) {
+        if guild_id != self.guild_id {
+            return;
+        }
+
+        for app in self.applications.iter() {
+            app.member_leave(&context, &user).await;
+        }
+    }
+
+    async fn guild_member_update(&self, _: Context, _old: Option<Member>, new: Member) {
+        if new.guild_id != self.guild_id {
+            return;
+        }
+
+        for app in self.applications.iter() {
+            app.update_member(&new)
+                .await
+                .expect("Failed to update member");
+        }
     }
 
     // run on any message event
@@ -276,11 +493,84 @@ impl EventHandler for Handler {
             return;
         }
 
-        for app in &self.applications {
+        for app in self.applications.iter() {
             app.message(&ctx, &message).await;
         }
     }
 
+    async fn message_update(
+        &self,
+        context: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // updates without the content are made by discord, such as adding embeds
+        if event.guild_id != Some(self.guild_id) || event.content.is_none() {
+            return;
+        }
+
+        // the message is given only when it is in the cache
+        let message = match new {
+            Some(message) => message,
+            None => match event.channel_id.message(&context, event.id).await {
+                Ok(message) => message,
+                Err(e) => {
+                    error!("Failed to get updated message({}) - {e:?}", event.id);
+                    return;
+                }
+            },
+        };
+
+        for app in self.applications.iter() {
+            app.message_update(&context, &message).await;
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        context: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if guild_id != Some(self.guild_id) {
+            return;
+        }
+
+        for app in self.applications.iter() {
+            app.message_delete(&context, channel_id, &[deleted_message_id])
+                .await;
+        }
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        context: Context,
+        channel_id: ChannelId,
+        deleted_message_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if guild_id != Some(self.guild_id) {
+            return;
+        }
+
+        for app in self.applications.iter() {
+            app.message_delete(&context, channel_id, &deleted_message_ids)
+                .await;
+        }
+    }
+
+    async fn reaction_add(&self, context: Context, reaction: Reaction) {
+        if reaction.guild_id != Some(self.guild_id) {
+            return;
+        }
+
+        for app in self.applications.iter() {
+            app.reaction_add(&context, &reaction).await;
+        }
+    }
+
     // run on firing slash command
     async fn interaction_create(&self, context: Context, interaction: Interaction) {
         match interaction.kind() {
@@ -294,7 +584,7 @@ impl EventHandler for Handler {
                     return;
                 }
 
-                for app in &self.applications {
+                for app in self.applications.iter() {
                     if app
                         .application_command_interaction_create(&context, &interaction)
                         .await
@@ -310,7 +600,7 @@ impl EventHandler for Handler {
                     return;
                 };
 
-                for app in &self.applications {
+                for app in self.applications.iter() {
                     app.autocomplete(&context, &autocomplete).await;
                 }
             }
@@ -319,30 +609,55 @@ impl EventHandler for Handler {
                     return;
                 };
 
-                for app in &self.applications {
+                for app in self.applications.iter() {
                     app.modal_submit(&context, &modal_submit).await;
                 }
             }
+            InteractionType::MessageComponent => {
+                let Some(component) = interaction.message_component() else {
+                    return;
+                };
+                // components in DMs are handled too as the LLM answers there
+                if component
+                    .guild_id
+                    .map(|id| id != self.guild_id)
+                    .unwrap_or(false)
+                {
+                    return;
+                }
+
+                if component.data.custom_id == FORGET_USER_CONFIRM {
+                    if let Err(e) = self.forget_user(&context, &component).await {
+                        error!("Failed to handle forget confirmation - {e:?}");
+                    }
+                    return;
+                }
+                for app in self.applications.iter() {
+                    if app.message_component(&context, &component).await {
+                        return;
+                    }
+                }
+            }
             _ => {}
         }
     }
 
     async fn guild_scheduled_event_create(&self, context: Context, event: ScheduledEvent) {
-        for sub_app in &self.applications {
+        for sub_app in self.applications.iter() {
             sub_app
                 .guild_scheduled_event(&context, ScheduledEventUpdated::Created(&event))
                 .await;
         }
     }
     async fn guild_scheduled_event_update(&self, context: Context, event: ScheduledEvent) {
-        for sub_app in &self.applications {
+        for sub_app in self.applications.iter() {
             sub_app
                 .guild_scheduled_event(&context, ScheduledEventUpdated::Updated(&event))
                 .await;
         }
     }
     async fn guild_scheduled_event_delete(&self, context: Context, event: ScheduledEvent) {
-        for sub_app in &self.applications {
+        for sub_app in self.applications.iter() {
             sub_app
                 .guild_scheduled_event(&context, ScheduledEventUpdated::Deleted(&event))
                 .await;
@@ -354,7 +669,7 @@ impl EventHandler for Handler {
         context: Context,
         subscribed: GuildScheduledEventUserAddEvent,
     ) {
-        for sub_app in &self.applications {
+        for sub_app in self.applications.iter() {
             sub_app
                 .guild_scheduled_event(&context, ScheduledEventUpdated::UserAdded(&subscribed))
                 .await;
@@ -365,7 +680,7 @@ impl EventHandler for Handler {
         context: Context,
         unsubscribed: GuildScheduledEventUserRemoveEvent,
     ) {
-        for sub_app in &self.applications {
+        for sub_app in self.applications.iter() {
             sub_app
                 .guild_scheduled_event(&context, ScheduledEventUpdated::UserRemoved(&unsubscribed))
                 .await;
@@ -377,17 +692,56 @@ impl EventHandler for Handler {
 pub(crate) struct Config {
     token: String,
     guild_id: u64,
-    application_id: u64,
+    pub(crate) application_id: u64,
+}
+
+// client of the REST API, for callers without the gateway such as the web
+pub(crate) fn http(config: &Config) -> serenity::http::Http {
+    serenity::http::Http::new(&config.token)
+}
+
+// whether the user is a member of the guild
+pub(crate) async fn is_member(config: &Config, user_id: u64) -> anyhow::Result<bool> {
+    match http(config).get_member(config.guild_id, user_id).await {
+        Ok(_) => Ok(true),
+        Err(serenity::Error::Http(e))
+            if e.status_code()
+                .map_or(false, |status| status.as_u16() == 404) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e).context("Failed to get member"),
+    }
+}
+
+// permissions of a member of the guild
+pub(crate) async fn member_permissions(
+    config: &Config,
+    user_id: u64,
+) -> anyhow::Result<Permissions> {
+    let http = http(config);
+    let guild = http
+        .get_guild(config.guild_id)
+        .await
+        .context("Failed to get guild")?;
+
+    guild
+        .member_permissions(&http, UserId(user_id))
+        .await
+        .context("Failed to get permissions of member")
 }
 
 pub(crate) async fn start(
+    db_pool: SqlitePool,
     config: &super::Config,
     sub_applications: Vec<Box<dyn SubApplication + Send + Sync>>,
+    status: GatewayStatus,
     mut stop_signal: tokio::sync::broadcast::Receiver<()>,
 ) -> anyhow::Result<()> {
     let token = &config.discord.token;
     let guild_id = config.discord.guild_id;
     let application_id = config.discord.application_id;
+    let applications: Applications = Arc::new(sub_applications);
 
     // prepare serenity(discord api framework)
     let mut client = Client::builder(
@@ -395,14 +749,20 @@ pub(crate) async fn start(
         GatewayIntents::GUILDS
             | GatewayIntents::GUILD_MEMBERS
             | GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS
+            | GatewayIntents::DIRECT_MESSAGES
             | GatewayIntents::GUILD_PRESENCES
             | GatewayIntents::MESSAGE_CONTENT
-            | GatewayIntents::GUILD_SCHEDULED_EVENTS,
+            | GatewayIntents::GUILD_SCHEDULED_EVENTS
+            | GatewayIntents::GUILD_VOICE_STATES,
     )
     .application_id(application_id)
+    .type_map_insert::<SubApplications>(applications.clone())
     .event_handler(Handler {
+        db_pool,
         guild_id: GuildId(guild_id),
-        applications: sub_applications,
+        applications,
+        status,
     })
     .await?;
 
@@ -35,6 +35,11 @@ pub struct ApplicationCommandOption<'a> {
     pub options: Vec<ApplicationCommandOption<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub autocomplete: Option<bool>,
+    // bounds of integer and number options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_value: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<i64>,
 }
 
 #[derive(Debug, Default, serde::Serialize)]
@@ -1,7 +1,13 @@
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
 use anyhow::Context as _;
 use async_trait::async_trait;
 use chrono::{Datelike, FixedOffset, TimeZone, Timelike};
 use log::{error, info, trace};
+use once_cell::sync::OnceCell;
 use serde::Deserialize;
 use serenity::{
     builder::{CreateEmbed, CreateInteractionResponseData, CreateMessage},
@@ -10,7 +16,7 @@ use serenity::{
             application_command::{ApplicationCommandInteraction, CommandDataOption},
             InteractionResponseType,
         },
-        ChannelId, GuildId, Member, Message, MessageId,
+        ChannelId, GuildId, Member, Message, MessageId, User, UserId,
     },
     prelude::Context,
 };
@@ -21,22 +27,116 @@ use crate::discord::{
     IntoSnowflakes, SubApplication,
 };
 
+mod anniversary;
+mod heatmap;
+mod import;
+mod membership;
+mod non_eueoeo;
+mod perfect;
+mod ranking;
+mod strings;
+mod team;
+mod tools;
+mod trend;
+
+use heatmap::HEATMAP_FILENAME;
+pub(crate) use import::import_history;
+use non_eueoeo::NonEueoeoPolicy;
+use strings::{fill, Strings};
+
 const EUEOEO: &str = "으어어";
 const COMMAND_NAME: &str = "eueoeo";
 
 const MESSAGES_LIMIT: u64 = 100;
 const MAX_RESPONSE_COUNT: usize = 25;
+// posting rate of this many recent days is used to predict the next milestone
+const MILESTONE_RATE_DAYS: i64 = 90;
+// bounds of years to query. discord has no message before it started.
+const MIN_YEAR: i64 = 2015;
+const MAX_YEAR: i64 = 9999;
+// the active days filter of the total ranking covers a year at most
+const MAX_ACTIVE_DAYS: i64 = 366;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Config {
     channel_id: u64,
     init_message_id: u64,
+    // messages posted within this many minutes after midnight count toward the previous day
+    #[serde(default)]
+    day_grace_minutes: u32,
+    #[serde(default = "default_special_days")]
+    special_days: Vec<SpecialDay>,
+    #[serde(default)]
+    admin_role_ids: Vec<u64>,
+    #[serde(default)]
+    non_eueoeo_policy: NonEueoeoPolicy,
+    #[serde(default)]
+    strings: Strings,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+enum SpecialDayRule {
+    // any message counts
+    AcceptAny,
+    // eueoeo counts twice
+    DoubleCount,
+    // themed keyword counts as well as eueoeo
+    Keyword(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct SpecialDay {
+    month: u32,
+    day: u32,
+    rule: SpecialDayRule,
+}
+
+fn default_special_days() -> Vec<SpecialDay> {
+    // April Fools
+    vec![SpecialDay {
+        month: 4,
+        day: 1,
+        rule: SpecialDayRule::AcceptAny,
+    }]
+}
+
+fn find_special_day_rule(
+    special_days: &[SpecialDay],
+    date: chrono::NaiveDate,
+) -> Option<&SpecialDayRule> {
+    special_days
+        .iter()
+        .find(|special_day| special_day.month == date.month() && special_day.day == date.day())
+        .map(|special_day| &special_day.rule)
+}
+
+fn is_eueoeo(content: &str, date: chrono::NaiveDate, special_days: &[SpecialDay]) -> bool {
+    match find_special_day_rule(special_days, date) {
+        Some(SpecialDayRule::AcceptAny) => true,
+        Some(SpecialDayRule::Keyword(keyword)) => content == keyword || content == EUEOEO,
+        Some(SpecialDayRule::DoubleCount) | None => content == EUEOEO,
+    }
+}
+
+fn history_weight(date: chrono::NaiveDate, special_days: &[SpecialDay]) -> i64 {
+    match find_special_day_rule(special_days, date) {
+        Some(SpecialDayRule::DoubleCount) => 2,
+        _ => 1,
+    }
 }
 
 pub struct DiscordHandler {
     db_pool: SqlitePool,
     init_message_id: MessageId,
     channel_id: ChannelId,
+    basis_offset: FixedOffset,
+    special_days: Vec<SpecialDay>,
+    admin_role_ids: Vec<u64>,
+    non_eueoeo_policy: NonEueoeoPolicy,
+    strings: Strings,
+    overflow_webhook_url: OnceCell<String>,
+    daily_job_started: AtomicBool,
 }
 
 impl DiscordHandler {
@@ -68,39 +168,90 @@ impl DiscordHandler {
             db_pool,
             init_message_id: last_message_id,
             channel_id: ChannelId(config.eueoeo.channel_id),
+            // shifting the offset back by the grace period makes every date calculation
+            // treat the grace window as a part of the previous day
+            basis_offset: FixedOffset::east_opt(
+                9 * 3600 - config.eueoeo.day_grace_minutes as i32 * 60,
+            )
+            .expect("day_grace_minutes is too large"),
+            special_days: config.eueoeo.special_days.clone(),
+            admin_role_ids: config.eueoeo.admin_role_ids.clone(),
+            non_eueoeo_policy: config.eueoeo.non_eueoeo_policy.clone(),
+            strings: config.eueoeo.strings.clone(),
+            overflow_webhook_url: OnceCell::new(),
+            daily_job_started: AtomicBool::new(false),
         }
     }
 }
 
 trait FutabaMessage {
-    fn check_message(&self) -> bool;
+    fn check_message(&self, offset: &FixedOffset, special_days: &[SpecialDay]) -> bool;
 }
 
 impl FutabaMessage for Message {
     // Is eueoeo by human?
-    fn check_message(&self) -> bool {
+    fn check_message(&self, offset: &FixedOffset, special_days: &[SpecialDay]) -> bool {
         if self.author.bot || self.edited_timestamp.is_some() {
             return false;
         }
 
-        let date = self
-            .timestamp
-            .with_timezone(&chrono::FixedOffset::east_opt(9 * 3600).unwrap())
-            .date_naive();
-        if date.month() == 4 && date.day() == 1 {
-            true
-        } else {
-            self.content == EUEOEO
-        }
+        let date = self.timestamp.with_timezone(offset).date_naive();
+        is_eueoeo(&self.content, date, special_days)
     }
 }
 
+// `None` for years out of the range of dates
+fn yearly_stats_range(offset: &FixedOffset, year: Option<i32>) -> Option<(i32, i64, i64, i64)> {
+    let offset = *offset;
+    let now = chrono::Local::now();
+    let current_year = now.year();
+    let year = year.unwrap_or(current_year);
+    let begin_date = offset.with_ymd_and_hms(year, 1, 1, 0, 0, 0).latest()?;
+    let end_date = if year != current_year {
+        offset
+            .with_ymd_and_hms(year.checked_add(1)?, 1, 1, 0, 0, 0)
+            .latest()?
+    } else {
+        now.with_timezone(&offset)
+            .with_hour(0)
+            .unwrap()
+            .with_minute(0)
+            .unwrap()
+            .with_second(0)
+            .unwrap()
+            + chrono::Duration::days(1)
+    };
+    let days = (end_date - begin_date).num_days();
+    let begin_date_snowflakes = begin_date.into_snowflakes();
+    let end_date_snowflakes = end_date.into_snowflakes();
+    info!(
+        "yearly stats {}({}) ~ {}({}) ({} days)",
+        begin_date, begin_date_snowflakes, end_date, end_date_snowflakes, days
+    );
+
+    Some((year, days, begin_date_snowflakes, end_date_snowflakes))
+}
+
+// the current year is always in the range
+fn current_yearly_stats_range(offset: &FixedOffset) -> (i32, i64, i64, i64) {
+    yearly_stats_range(offset, None).expect("Current year is out of range")
+}
+
+fn date_to_timestamp(date: chrono::NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp()
+}
+
+// if there is no nickname, use member's name
+fn display_name(member: &Member) -> &str {
+    member.nick.as_ref().unwrap_or(&member.user.name)
+}
+
 trait Stat {
     fn title(&self) -> &str;
-    fn value(&self) -> String;
+    fn value(&self, strings: &Strings) -> String;
 
-    fn insert_as_field(&self, e: &mut CreateEmbed) {
-        e.field(self.title(), self.value(), true);
+    fn insert_as_field(&self, e: &mut CreateEmbed, strings: &Strings) {
+        e.field(self.title(), self.value(strings), true);
     }
 }
 
@@ -109,7 +260,7 @@ impl Stat for &(String, i64) {
         &self.0
     }
 
-    fn value(&self) -> String {
+    fn value(&self, _: &Strings) -> String {
         self.1.to_string()
     }
 }
@@ -144,8 +295,15 @@ impl<'a> Stat for YearlyStat<'a> {
         self.name
     }
 
-    fn value(&self) -> String {
-        format!("{} ({}%)", self.count, self.count * 100 / self.total_days)
+    fn value(&self, strings: &Strings) -> String {
+        fill(
+            &strings.yearly_value,
+            &[
+                ("count", &self.count),
+                // double counted days can make the count larger than the days
+                ("ratio", &(self.count * 100 / self.total_days).min(100)),
+            ],
+        )
     }
 }
 
@@ -177,15 +335,16 @@ trait EmendableMessage {
         &'a mut self,
         title: &str,
         stats: I,
+        strings: &Strings,
     ) -> &'a mut Self {
         let mut stats = stats.peekable();
         if stats.peek().is_none() {
-            self.content("Empty records")
+            self.content(&strings.empty_records)
         } else {
             self.embed(move |e| {
                 e.title(title);
                 for stat in stats {
-                    stat.insert_as_field(e);
+                    stat.insert_as_field(e, strings);
                 }
                 e
             })
@@ -229,27 +388,59 @@ enum MissingDays {
 impl MissingDays {
     const DETAIL_LIMIT_COUNT: i64 = 10;
 
-    fn render(&self) -> String {
+    fn render(&self, strings: &Strings) -> String {
         match self {
             MissingDays::Detailed(missing_days) => {
                 if missing_days.is_empty() {
-                    "없음".to_string()
+                    strings.none.clone()
                 } else {
                     let all_missing_days = missing_days
                         .iter()
                         .map(|date| date.format("%m/%d").to_string())
                         .collect::<Vec<_>>()
                         .join(", ");
-                    format!("{}일 - {}", missing_days.len(), all_missing_days,)
+                    fill(
+                        &strings.missing_days_detail,
+                        &[("count", &missing_days.len()), ("days", &all_missing_days)],
+                    )
                 }
             }
-            MissingDays::Count(count) => {
-                format!("{}일", count)
-            }
+            MissingDays::Count(count) => fill(&strings.days, &[("days", count)]),
+        }
+    }
+}
+
+#[derive(Default)]
+struct DaysCount {
+    count: i64,
+    days: i64,
+}
+
+impl DaysCount {
+    fn render(&self) -> String {
+        if self.days == 0 {
+            format!("{}/{}", self.count, self.days)
+        } else {
+            format!(
+                "{}/{} ({}%)",
+                self.count,
+                self.days,
+                self.count * 100 / self.days
+            )
         }
     }
 }
 
+// every 100 until 1000, and every 1000 after that
+fn next_milestone(count: i64) -> i64 {
+    let step = if count < 1000 { 100 } else { 1000 };
+    (count / step + 1) * step
+}
+
+fn is_weekend(date: chrono::NaiveDate) -> bool {
+    matches!(date.weekday(), chrono::Weekday::Sat | chrono::Weekday::Sun)
+}
+
 struct UserDetail {
     name: String,
     longest_streaks: i64,
@@ -258,6 +449,11 @@ struct UserDetail {
     yearly_count: i64,
     yearly_ratio: i8,
     total_count: i64,
+    first_date: Option<chrono::NaiveDate>,
+    weekday: DaysCount,
+    weekend: DaysCount,
+    // next milestone and the expected date to reach
+    milestone: Option<(i64, chrono::NaiveDate)>,
     missing_days: MissingDays,
 }
 
@@ -266,8 +462,11 @@ impl DiscordHandler {
         trace!("insert {}", &message.id);
         let message_id = *message.id.as_u64() as i64;
         let author_id = *message.author.id.as_u64() as i64;
-        let offset = FixedOffset::east_opt(9 * 3600).unwrap();
-        let message_date = message.timestamp.with_timezone(&offset).date_naive();
+        let message_date = message
+            .timestamp
+            .with_timezone(&self.basis_offset)
+            .date_naive();
+        let weight = history_weight(message_date, &self.special_days);
         let prev_date = message_date
             .pred_opt()
             .unwrap()
@@ -281,10 +480,11 @@ impl DiscordHandler {
             .and_utc()
             .timestamp();
         let affected = match sqlx::query!(
-            "INSERT INTO history (message_id, user_id, date) VALUES (?, ?, ?)",
+            "INSERT INTO history (message_id, user_id, date, weight) VALUES (?, ?, ?, ?)",
             message_id,
             author_id,
-            message_date
+            message_date,
+            weight
         )
         .execute(&self.db_pool)
         .await
@@ -333,14 +533,17 @@ impl DiscordHandler {
             };
             sqlx::query!(
                 r#"UPDATE users SET 
-                    count = count + 1, 
+                    count = count + ?, 
                     longest_streaks = ?, 
                     current_streaks = ?, 
-                    last_date = ? 
+                    last_date = ?,
+                    first_date = coalesce(first_date, ?)
                 WHERE user_id = ?"#,
+                weight,
                 longest_streaks,
                 current_streaks,
                 message_date,
+                message_date,
                 author_id
             )
             .execute(&self.db_pool)
@@ -352,61 +555,102 @@ impl DiscordHandler {
         }
     }
 
-    async fn fetch_statistics(&self) -> Vec<(String, i64)> {
-        let stats =
-            sqlx::query!("SELECT name, count from users WHERE count > 0 ORDER BY count desc")
-                .fetch_all(&self.db_pool)
-                .await
-                .unwrap();
+    // `active_since` is a date and `member_ids` is a json array of user ids to filter ranking
+    async fn fetch_statistics(
+        &self,
+        active_since: Option<i64>,
+        member_ids: Option<String>,
+    ) -> Vec<(String, i64)> {
+        // previous ranking is not comparable with filtered one
+        let previous_date = if active_since.is_none() && member_ids.is_none() {
+            self.previous_ranking_date(None)
+        } else {
+            None
+        };
+        let stats = sqlx::query!(
+            r#"SELECT
+                users.name,
+                users.count,
+                users.departed AS "departed: bool",
+                RANK() OVER (ORDER BY users.count DESC) AS "rank!: i64",
+                previous.rank AS "previous_rank?: i64"
+            FROM
+                users
+            LEFT JOIN
+                ranking_snapshots AS previous ON
+                    previous.user_id = users.user_id AND
+                    previous.kind = 'total' AND
+                    previous.date = (
+                        SELECT max(date) FROM ranking_snapshots WHERE kind = 'total' AND date <= ?
+                    )
+            WHERE
+                users.count > 0 AND
+                NOT users.opted_out AND
+                (? IS NULL OR users.last_date >= ?) AND
+                (? IS NULL OR users.user_id IN (SELECT value FROM json_each(?)))
+            ORDER BY
+                users.count DESC"#,
+            previous_date,
+            active_since,
+            active_since,
+            member_ids,
+            member_ids
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .unwrap();
 
         stats
             .into_iter()
-            .map(|stat| (stat.name, stat.count))
+            .map(|stat| {
+                (
+                    self.stat_name(stat.name, stat.departed)
+                        + &ranking::rank_movement(stat.rank, stat.previous_rank),
+                    stat.count,
+                )
+            })
             .collect()
     }
 
-    fn basis_offset() -> FixedOffset {
-        FixedOffset::east_opt(9 * 3600).unwrap()
+    // departed users are kept in rankings but marked
+    fn stat_name(&self, name: String, departed: bool) -> String {
+        if departed {
+            name + &self.strings.departed
+        } else {
+            name
+        }
+    }
+
+    fn get_yearly_stats_range(&self, year: Option<i32>) -> Option<(i32, i64, i64, i64)> {
+        yearly_stats_range(&self.basis_offset, year)
     }
 
-    fn get_yearly_stats_range(year: Option<i32>) -> (i32, i64, i64, i64) {
-        let offset = Self::basis_offset();
-        let now = chrono::Local::now();
-        let current_year = now.year();
-        let year = year.unwrap_or(current_year);
+    fn get_monthly_stats_range(&self) -> (i64, i64, i64) {
+        let offset = self.basis_offset;
+        let today = chrono::Local::now().with_timezone(&offset).date_naive();
         let begin_date = offset
-            .with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+            .with_ymd_and_hms(today.year(), today.month(), 1, 0, 0, 0)
+            .latest()
+            .unwrap();
+        let end_date = offset
+            .from_local_datetime(&today.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap())
             .latest()
             .unwrap();
-        let end_date = if year != current_year {
-            offset
-                .with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0)
-                .latest()
-                .unwrap()
-        } else {
-            now.with_timezone(&offset)
-                .with_hour(0)
-                .unwrap()
-                .with_minute(0)
-                .unwrap()
-                .with_second(0)
-                .unwrap()
-                + chrono::Duration::days(1)
-        };
         let days = (end_date - begin_date).num_days();
         let begin_date_snowflakes = begin_date.into_snowflakes();
         let end_date_snowflakes = end_date.into_snowflakes();
         info!(
-            "yearly stats {}({}) ~ {}({}) ({} days)",
+            "monthly stats {}({}) ~ {}({}) ({} days)",
             begin_date, begin_date_snowflakes, end_date, end_date_snowflakes, days
         );
 
-        (year, days, begin_date_snowflakes, end_date_snowflakes)
+        (days, begin_date_snowflakes, end_date_snowflakes)
     }
 
-    fn get_current_streak_range() -> (i64, i64) {
-        let offset = FixedOffset::east_opt(9 * 3600).unwrap();
-        let now = chrono::Local::now().with_timezone(&offset).date_naive();
+    fn get_current_streak_range(&self) -> (i64, i64) {
+        let now = chrono::Local::now()
+            .with_timezone(&self.basis_offset)
+            .date_naive();
         let begin = now.pred_opt().unwrap();
         let end = now.succ_opt().unwrap();
         info!("current streak range at {}: {} ~ {}", now, begin, end);
@@ -416,23 +660,36 @@ impl DiscordHandler {
         )
     }
 
-    async fn fetch_yearly_statistics(&self, year: Option<i32>) -> (i32, YearlyStats) {
+    async fn fetch_yearly_statistics(&self, year: Option<i32>) -> Option<(i32, YearlyStats)> {
         let (year, days, begin_date_snowflakes, end_date_snowflakes) =
-            Self::get_yearly_stats_range(year);
+            self.get_yearly_stats_range(year)?;
+        let previous_date = self.previous_ranking_date(Some(year));
         let stats = sqlx::query!(
             r#"SELECT
                 users.name,
-                count(history.message_id) AS "count: i64"
+                users.departed AS "departed: bool",
+                sum(history.weight) AS "count: i64",
+                RANK() OVER (ORDER BY sum(history.weight) DESC) AS "rank!: i64",
+                previous.rank AS "previous_rank?: i64"
             FROM
                 history
             INNER JOIN
                 users ON history.user_id = users.user_id
+            LEFT JOIN
+                ranking_snapshots AS previous ON
+                    previous.user_id = history.user_id AND
+                    previous.kind = 'yearly' AND
+                    previous.date = (
+                        SELECT max(date) FROM ranking_snapshots WHERE kind = 'yearly' AND date <= ?
+                    )
             WHERE
                 history.message_id >= ? AND
-                history.message_id < ?
+                history.message_id < ? AND
+                NOT users.opted_out
             GROUP BY
                 history.user_id;
             "#,
+            previous_date,
             begin_date_snowflakes,
             end_date_snowflakes
         )
@@ -443,19 +700,25 @@ impl DiscordHandler {
         // order by is not works correctly.
         let mut stats = stats
             .into_iter()
-            .map(|stat| (stat.name, stat.count))
+            .map(|stat| {
+                (
+                    self.stat_name(stat.name, stat.departed)
+                        + &ranking::rank_movement(stat.rank, stat.previous_rank),
+                    stat.count,
+                )
+            })
             .collect::<Vec<_>>();
 
         stats.sort_by_cached_key(|i| i.1);
         stats.reverse();
 
-        (
+        Some((
             year,
             YearlyStats {
                 stats,
                 total_days: days,
             },
-        )
+        ))
     }
 
     async fn fetch_streaks(&self, longest: bool) -> Vec<(String, i64)> {
@@ -467,7 +730,7 @@ impl DiscordHandler {
                 let stats = sqlx::query!($query, $($args)*).fetch_all(&self.db_pool).await.unwrap();
                 stats
                     .into_iter()
-                    .map(|stat| (stat.name, stat.streaks))
+                    .map(|stat| (self.stat_name(stat.name, stat.departed), stat.streaks))
                     .collect()
             }};
         }
@@ -476,23 +739,27 @@ impl DiscordHandler {
             fetch_streaks!(
                 r#"SELECT
                     name,
+                    departed AS "departed: bool",
                     longest_streaks as streaks
                 FROM
                     users
+                WHERE
+                    NOT opted_out
                 ORDER BY
                     longest_streaks DESC;
                 "#
             )
         } else {
-            let (begin, end) = Self::get_current_streak_range();
+            let (begin, end) = self.get_current_streak_range();
             fetch_streaks!(
                 r#"SELECT
                     name,
+                    departed AS "departed: bool",
                     current_streaks as streaks
                 FROM
                     users
                 WHERE
-                    last_date >= ? AND last_date < ?
+                    last_date >= ? AND last_date < ? AND NOT opted_out
                 ORDER BY
                     current_streaks DESC;
                 "#,
@@ -502,12 +769,52 @@ impl DiscordHandler {
         }
     }
 
+    async fn predict_milestone(
+        &self,
+        user_id: i64,
+        count: i64,
+    ) -> anyhow::Result<Option<(i64, chrono::NaiveDate)>> {
+        let today = chrono::Local::now()
+            .with_timezone(&self.basis_offset)
+            .date_naive();
+        let since = date_to_timestamp(today - chrono::Duration::days(MILESTONE_RATE_DAYS - 1));
+        let recent_count = sqlx::query!(
+            r#"SELECT
+                coalesce(sum(weight), 0) AS "count!: i64"
+            FROM
+                history
+            WHERE
+                user_id = ? AND
+                date >= ?"#,
+            user_id,
+            since
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to fetch recent posting rate")?
+        .count;
+        if recent_count == 0 {
+            return Ok(None);
+        }
+
+        let milestone = next_milestone(count);
+        // ceil of remaining / (recent_count / MILESTONE_RATE_DAYS)
+        let remaining_days =
+            ((milestone - count) * MILESTONE_RATE_DAYS + recent_count - 1) / recent_count;
+
+        Ok(today
+            .checked_add_days(chrono::Days::new(remaining_days as u64))
+            .map(|date| (milestone, date)))
+    }
+
     async fn fetch_user_details(&self, user_id: i64) -> UserDetail {
         let ret = sqlx::query!(
             r#"SELECT
                 name,
+                count,
                 longest_streaks,
-                current_streaks
+                current_streaks,
+                first_date
             FROM
                 users
             WHERE
@@ -519,7 +826,7 @@ impl DiscordHandler {
         .unwrap();
 
         let (year, days, begin_date_snowflakes, end_date_snowflakes) =
-            Self::get_yearly_stats_range(None);
+            current_yearly_stats_range(&self.basis_offset);
         let history = sqlx::query!(
             r#"SELECT
                 history.message_id as message_id
@@ -541,10 +848,28 @@ impl DiscordHandler {
         .unwrap();
         let yearly_count = history.len() as i64;
 
+        let mut weekday = DaysCount::default();
+        let mut weekend = DaysCount::default();
+        let begin_date = from_snowflakes(&self.basis_offset, begin_date_snowflakes).date_naive();
+        for date in begin_date.iter_days().take(days as usize) {
+            if is_weekend(date) {
+                weekend.days += 1;
+            } else {
+                weekday.days += 1;
+            }
+        }
+        for item in &history {
+            if is_weekend(from_snowflakes(&self.basis_offset, item.message_id).date_naive()) {
+                weekend.count += 1;
+            } else {
+                weekday.count += 1;
+            }
+        }
+
         let missing_count = days - yearly_count;
         let missing_days = if missing_count < MissingDays::DETAIL_LIMIT_COUNT {
             MissingDays::Detailed({
-                let offset = FixedOffset::east_opt(9 * 3600).unwrap();
+                let offset = self.basis_offset;
                 let single_day_snowflakes_delta = chrono::Duration::days(1).into_snowflakes();
                 let mut date_cursor_0 = begin_date_snowflakes;
                 let mut date_cursor_1 = date_cursor_0 + single_day_snowflakes_delta;
@@ -565,6 +890,14 @@ impl DiscordHandler {
             MissingDays::Count(missing_count)
         };
 
+        let milestone = self
+            .predict_milestone(user_id, ret.count)
+            .await
+            .unwrap_or_else(|e| {
+                error!("{e:?}");
+                None
+            });
+
         let total_count = sqlx::query!(
             r#"
             SELECT
@@ -587,29 +920,240 @@ impl DiscordHandler {
             current_streaks: ret.current_streaks,
             year,
             yearly_count,
-            yearly_ratio: (yearly_count * 100 / days) as _,
+            yearly_ratio: (yearly_count * 100 / days).min(100) as _,
             total_count,
+            first_date: ret
+                .first_date
+                .and_then(|date| chrono::DateTime::from_timestamp(date, 0))
+                .map(|date| date.date_naive()),
+            weekday,
+            weekend,
+            milestone,
             missing_days,
         }
     }
 
+    async fn is_opted_out(&self, user_id: i64) -> anyhow::Result<bool> {
+        let row = sqlx::query!("SELECT opted_out FROM users WHERE user_id = ?", user_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to query opt-out flag")?;
+
+        Ok(row.map(|row| row.opted_out).unwrap_or(false))
+    }
+
+    async fn set_opted_out(&self, user_id: i64, opted_out: bool) -> anyhow::Result<()> {
+        sqlx::query!(
+            "UPDATE users SET opted_out = ? WHERE user_id = ?",
+            opted_out,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to update opt-out flag")?;
+
+        Ok(())
+    }
+
+    fn start_daily_job(&self, context: &Context) {
+        if self.daily_job_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let http = context.http.clone();
+        let offset = self.basis_offset;
+        let anniversary_message = self.strings.anniversary.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = ranking::snapshot_rankings(&db_pool, &offset).await {
+                    error!("Failed to snapshot rankings - {e:?}");
+                }
+                if let Err(e) = perfect::award_perfect_months(&db_pool, &offset).await {
+                    error!("Failed to award perfect months - {e:?}");
+                }
+                if let Err(e) = anniversary::congratulate_anniversaries(
+                    &db_pool,
+                    &http,
+                    &offset,
+                    &anniversary_message,
+                )
+                .await
+                {
+                    error!("Failed to congratulate anniversaries - {e:?}");
+                }
+
+                let now = chrono::Local::now().with_timezone(&offset);
+                let tomorrow = offset
+                    .from_local_datetime(
+                        &now.date_naive()
+                            .succ_opt()
+                            .unwrap()
+                            .and_hms_opt(0, 0, 0)
+                            .unwrap(),
+                    )
+                    .latest()
+                    .unwrap();
+                tokio::time::sleep((tomorrow - now).to_std().unwrap_or_default()).await;
+            }
+        });
+    }
+
+    async fn is_admin(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> serenity::Result<bool> {
+        let guild_id = unsafe { interaction.guild_id.unwrap_unchecked() };
+        for role in &self.admin_role_ids {
+            if interaction.user.has_role(context, guild_id, *role).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // names are updated by member events, but refresh them lazily in case of missed events.
+    // it goes over every user, so it runs in background not to delay the response.
+    fn refresh_member_names(&self, context: &Context, guild_id: GuildId) {
+        let db_pool = self.db_pool.clone();
+        let cache = context.cache.clone();
+        tokio::spawn(async move {
+            let users = match sqlx::query!("SELECT user_id, name FROM users")
+                .fetch_all(&db_pool)
+                .await
+            {
+                Ok(users) => users,
+                Err(e) => {
+                    error!("Failed to fetch user names - {e:?}");
+                    return;
+                }
+            };
+
+            for user in users {
+                let Some(member) = cache.member(guild_id, user.user_id as u64) else {
+                    continue;
+                };
+                let name = display_name(&member);
+                if name != user.name {
+                    if let Err(e) = sqlx::query!(
+                        "UPDATE users SET name = ? WHERE user_id = ?",
+                        name,
+                        user.user_id
+                    )
+                    .execute(&db_pool)
+                    .await
+                    {
+                        error!("Failed to refresh user name - {e:?}");
+                    }
+                }
+            }
+        });
+    }
+
+    // insert history of a page in a single transaction and update aggregates once per user
     async fn process_message_history(
         &self,
         messages: &[Message],
     ) -> anyhow::Result<Option<MessageId>> {
-        let mut most_new_id = 0;
-        let queries = messages.iter().filter_map(|message| {
-            most_new_id = std::cmp::max(most_new_id, *message.id.as_u64());
+        const SINGLE_DAY: i64 = 24 * 3600;
+
+        let most_new_id = messages
+            .iter()
+            .map(|message| *message.id.as_u64())
+            .max()
+            .unwrap_or_default();
+
+        let mut tx = self.db_pool.begin().await?;
+        // dates and weights of inserted history by user in order of message
+        let mut inserted: BTreeMap<i64, Vec<(i64, i64)>> = BTreeMap::new();
+        for message in messages
+            .iter()
+            .filter(|message| message.check_message(&self.basis_offset, &self.special_days))
+        {
+            trace!("insert {}", &message.id);
+            let message_id = *message.id.as_u64() as i64;
+            let author_id = *message.author.id.as_u64() as i64;
+            let message_date = message
+                .timestamp
+                .with_timezone(&self.basis_offset)
+                .date_naive();
+            let weight = history_weight(message_date, &self.special_days);
+            let message_date = date_to_timestamp(message_date);
+
+            let result = sqlx::query!(
+                "INSERT OR IGNORE INTO history (message_id, user_id, date, weight) VALUES (?, ?, ?, ?)",
+                message_id,
+                author_id,
+                message_date,
+                weight
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to insert history")?;
+            if result.rows_affected() == 0 {
+                info!(
+                    "Duplicated item - user: {}, message_id: {}, date: {}",
+                    author_id, message_id, message_date
+                );
+                continue;
+            }
+            inserted
+                .entry(author_id)
+                .or_default()
+                .push((message_date, weight));
+        }
 
-            if message.check_message() {
-                Some(self.incr_counter(message))
-            } else {
-                None
+        for (user_id, history) in inserted {
+            let Some(data) = sqlx::query!(
+                "SELECT count, longest_streaks, current_streaks, last_date FROM users WHERE user_id = ?",
+                user_id
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to query user info")?
+            else {
+                info!("Try to increase counter for unknown user - {}", user_id);
+                continue;
+            };
+
+            let first_date = history[0].0;
+            let mut count = data.count;
+            let mut longest_streaks = data.longest_streaks;
+            let mut current_streaks = data.current_streaks;
+            let mut last_date = data.last_date;
+            for (date, weight) in history {
+                count += weight;
+                current_streaks = if last_date + SINGLE_DAY == date {
+                    current_streaks + 1
+                } else {
+                    1
+                };
+                longest_streaks = std::cmp::max(longest_streaks, current_streaks);
+                last_date = date;
             }
-        });
-        for query in queries {
-            query.await.context("Failed to increase counter")?;
+
+            sqlx::query!(
+                r#"UPDATE users SET
+                    count = ?,
+                    longest_streaks = ?,
+                    current_streaks = ?,
+                    last_date = ?,
+                    first_date = coalesce(first_date, ?)
+                WHERE user_id = ?"#,
+                count,
+                longest_streaks,
+                current_streaks,
+                last_date,
+                first_date,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to update counter of user({user_id})"))?;
         }
+        tx.commit().await?;
 
         Ok(if messages.len() < MESSAGES_LIMIT as _ {
             None
@@ -667,6 +1211,23 @@ impl DiscordHandler {
         }
     }
 
+    async fn respond_invalid_year(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        year: i32,
+    ) -> serenity::Result<()> {
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.content(fill(&self.strings.invalid_year, &[("year", &year)]))
+                            .ephemeral(true)
+                    })
+            })
+            .await
+    }
+
     async fn handle_year_command(
         &self,
         context: &Context,
@@ -675,15 +1236,23 @@ impl DiscordHandler {
     ) -> serenity::Result<()> {
         let [year] = option.get_options(&["year"]);
         let year_arg = year.as_i64().map(|v| v as i32);
-        let (year, stats) = self.fetch_yearly_statistics(year_arg).await;
+        let Some((year, stats)) = self.fetch_yearly_statistics(year_arg).await else {
+            return self
+                .respond_invalid_year(context, interaction, year_arg.unwrap_or_default())
+                .await;
+        };
         interaction
             .create_interaction_response(&context.http, |r| {
                 r.kind(InteractionResponseType::ChannelMessageWithSource)
                     .interaction_response_data(|d| {
                         let stat_iter = stats.iter().take(MAX_RESPONSE_COUNT);
                         d.create_statistics(
-                            &format!("으어어 {} ({}일)", year, stats.total_days),
+                            &fill(
+                                &self.strings.yearly_title,
+                                &[("year", &year), ("days", &stats.total_days)],
+                            ),
                             stat_iter,
+                            &self.strings,
                         )
                     })
             })
@@ -699,8 +1268,8 @@ impl DiscordHandler {
         let [ranking_basis] = option.get_options(&["type"]);
         let ranking_basis = unsafe { ranking_basis.as_str_unchecked() };
         let (stat_name, streak_arg) = match ranking_basis {
-            "current" => ("현재 연속", false),
-            "longest" => ("최장 연속", true),
+            "current" => (&self.strings.current_streaks, false),
+            "longest" => (&self.strings.longest_streaks, true),
             _ => unsafe { std::hint::unreachable_unchecked() },
         };
         let stats = self.fetch_streaks(streak_arg).await;
@@ -708,7 +1277,11 @@ impl DiscordHandler {
             .create_interaction_response(&context.http, |r| {
                 r.kind(InteractionResponseType::ChannelMessageWithSource)
                     .interaction_response_data(|d| {
-                        d.create_statistics(&format!("{} 으어어", stat_name), stats.iter())
+                        d.create_statistics(
+                            &fill(&self.strings.streaks_title, &[("kind", stat_name)]),
+                            stats.iter(),
+                            &self.strings,
+                        )
                     })
             })
             .await
@@ -720,7 +1293,7 @@ impl DiscordHandler {
         interaction: &ApplicationCommandInteraction,
         option: &CommandDataOption,
     ) -> serenity::Result<()> {
-        let [user_id] = option.get_options(&["user"]);
+        let [user_id, year] = option.get_options(&["user", "year"]);
 
         let user_id: i64 = unsafe {
             if let Some(user) = user_id {
@@ -736,6 +1309,27 @@ impl DiscordHandler {
             }
         };
 
+        if self.is_opted_out(user_id).await.unwrap_or_else(|e| {
+            error!("{e:?}");
+            false
+        }) {
+            return interaction
+                .create_interaction_response(&context.http, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.content(&self.strings.private_user).ephemeral(true)
+                        })
+                })
+                .await;
+        }
+
+        let year = year.as_i64().map(|v| v as i32);
+        if let Some(year) = year {
+            if self.get_yearly_stats_range(Some(year)).is_none() {
+                return self.respond_invalid_year(context, interaction, year).await;
+            }
+        }
+
         let user_joined_at = {
             let member = context.cache.member(
                 unsafe { interaction.guild_id.unwrap_unchecked() },
@@ -744,42 +1338,106 @@ impl DiscordHandler {
             let member = unsafe { member.unwrap_unchecked() };
             unsafe { member.joined_at.unwrap_unchecked() }
         };
-        let user_joined_at = chrono::Local.from_utc_datetime(&user_joined_at.naive_utc());
-        let total_days = (chrono::Local::now() - user_joined_at).num_days();
+        // the join date is shown in the time zone of the viewer
+        let time_zone =
+            crate::user::user_time_zone(&self.db_pool, interaction.user.id.0 as i64).await;
+        let user_joined_at = time_zone.from_utc_datetime(&user_joined_at.naive_utc());
+        let total_days = (chrono::Utc::now().with_timezone(&time_zone) - user_joined_at).num_days();
         let user_detail = self.fetch_user_details(user_id).await;
+        let heatmap_image = self
+            .create_heatmap(user_id, year)
+            .await
+            .map_err(|e| error!("Failed to create heatmap - {e:?}"))
+            .ok();
 
         interaction
             .create_interaction_response(&context.http, |r| {
                 r.kind(InteractionResponseType::ChannelMessageWithSource)
                     .interaction_response_data(|d| {
                         d.embed(|e| {
-                            e.title(format!("으어어 by {}", &user_detail.name))
-                                .field("최장 연속", user_detail.longest_streaks, false)
-                                .field("현재 연속", user_detail.current_streaks, false)
-                                .field(
-                                    format!("{}년", user_detail.year),
-                                    format!(
-                                        "{} ({}%)",
-                                        user_detail.yearly_count, user_detail.yearly_ratio
-                                    ),
+                            let year = &user_detail.year;
+                            e.title(fill(
+                                &self.strings.user_title,
+                                &[("name", &user_detail.name)],
+                            ))
+                            .field(
+                                &self.strings.longest_streaks,
+                                user_detail.longest_streaks,
+                                false,
+                            )
+                            .field(
+                                &self.strings.current_streaks,
+                                user_detail.current_streaks,
+                                false,
+                            )
+                            .field(
+                                fill(&self.strings.year, &[("year", year)]),
+                                fill(
+                                    &self.strings.yearly_value,
+                                    &[
+                                        ("count", &user_detail.yearly_count),
+                                        ("ratio", &user_detail.yearly_ratio),
+                                    ],
+                                ),
+                                false,
+                            )
+                            .field(
+                                fill(&self.strings.weekday, &[("year", year)]),
+                                user_detail.weekday.render(),
+                                true,
+                            )
+                            .field(
+                                fill(&self.strings.weekend, &[("year", year)]),
+                                user_detail.weekend.render(),
+                                true,
+                            )
+                            .field(
+                                &self.strings.since_joined,
+                                format!(
+                                    "{}/{} ({}%)\n{}",
+                                    user_detail.total_count,
+                                    total_days,
+                                    (user_detail.total_count * 100) / total_days,
+                                    user_joined_at.format("%Y-%m-%d")
+                                ),
+                                false,
+                            )
+                            .field(
+                                fill(&self.strings.missing_days, &[("year", year)]),
+                                user_detail.missing_days.render(&self.strings),
+                                false,
+                            );
+                            if let Some(first_date) = user_detail.first_date {
+                                e.field(
+                                    &self.strings.club_member,
+                                    fill(&self.strings.club_member_since, &[("date", &first_date)]),
                                     false,
-                                )
-                                .field(
-                                    "가입 후",
-                                    format!(
-                                        "{}/{} ({}%)",
-                                        user_detail.total_count,
-                                        total_days,
-                                        (user_detail.total_count * 100) / total_days
+                                );
+                            }
+                            if let Some((milestone, date)) = &user_detail.milestone {
+                                e.field(
+                                    fill(&self.strings.milestone, &[("milestone", milestone)]),
+                                    fill(
+                                        &self.strings.milestone_date,
+                                        &[("date", date), ("days", &MILESTONE_RATE_DAYS)],
                                     ),
                                     false,
-                                )
-                                .field(
-                                    format!("빼먹은 날 ({}년)", user_detail.year),
-                                    user_detail.missing_days.render(),
+                                );
+                            }
+                            if let Some((year, _)) = &heatmap_image {
+                                e.field(
+                                    fill(&self.strings.heatmap, &[("year", year)]),
+                                    "\u{200b}",
                                     false,
                                 )
-                        })
+                                .image(format!("attachment://{}", HEATMAP_FILENAME));
+                            }
+                            e
+                        });
+                        if let Some((_, png)) = &heatmap_image {
+                            d.add_file((png.as_slice(), HEATMAP_FILENAME));
+                        }
+                        d
                     })
             })
             .await
@@ -789,25 +1447,87 @@ impl DiscordHandler {
         &self,
         context: &Context,
         interaction: &ApplicationCommandInteraction,
-        _option: &CommandDataOption,
+        option: &CommandDataOption,
     ) -> serenity::Result<()> {
-        let stats = self.fetch_statistics().await;
+        let [active_days, members_only] = option.get_options(&["active_days", "members_only"]);
+        let mut title = self.strings.total_title.clone();
+
+        let active_days = active_days
+            .as_i64()
+            .filter(|days| (1..=MAX_ACTIVE_DAYS).contains(days));
+        let active_since = active_days.and_then(|days| {
+            let today = chrono::Local::now()
+                .with_timezone(&self.basis_offset)
+                .date_naive();
+            today
+                .checked_sub_signed(chrono::Duration::days(days - 1))
+                .map(date_to_timestamp)
+        });
+        if let Some(days) = active_days {
+            title += &fill(&self.strings.active_days_filter, &[("days", &days)]);
+        }
+
+        let member_ids = if members_only.as_bool().unwrap_or(false) {
+            context
+                .cache
+                .guild_field(unsafe { interaction.guild_id.unwrap_unchecked() }, |g| {
+                    g.members
+                        .keys()
+                        .map(|id| *id.as_u64() as i64)
+                        .collect::<Vec<_>>()
+                })
+                .map(|ids| serde_json::to_string(&ids).unwrap())
+        } else {
+            None
+        };
+        if member_ids.is_some() {
+            title += &self.strings.members_only_filter;
+        }
+
+        let stats = self.fetch_statistics(active_since, member_ids).await;
         interaction
             .create_interaction_response(&context.http, |r| {
                 r.kind(InteractionResponseType::ChannelMessageWithSource)
                     .interaction_response_data(|d| {
-                        d.create_statistics("으어어", stats.iter().take(MAX_RESPONSE_COUNT))
+                        d.create_statistics(
+                            &title,
+                            stats.iter().take(MAX_RESPONSE_COUNT),
+                            &self.strings,
+                        )
                     })
             })
             .await
     }
+
+    async fn handle_opt_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        opted_out: bool,
+    ) -> serenity::Result<()> {
+        let user_id = *interaction.user.id.as_u64() as i64;
+        let content = match self.set_opted_out(user_id, opted_out).await {
+            Ok(()) if opted_out => &self.strings.opted_out,
+            Ok(()) => &self.strings.opted_in,
+            Err(e) => {
+                error!("{e:?}");
+                &self.strings.setting_failed
+            }
+        };
+
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content(content).ephemeral(true))
+            })
+            .await
+    }
 }
 
 #[async_trait]
 impl SubApplication for DiscordHandler {
     async fn update_member(&self, member: &Member) -> anyhow::Result<()> {
-        // if there is no nickname, use member's name
-        let name = member.nick.as_ref().unwrap_or(&member.user.name).clone();
+        let name = display_name(member).to_string();
         let user_id = *member.user.id.as_u64() as i64;
 
         info!(
@@ -828,7 +1548,136 @@ impl SubApplication for DiscordHandler {
         Ok(())
     }
 
-    async fn cache_ready(&self, context: &Context, _guild_id: GuildId) {
+    async fn forget_user(&self, user_id: UserId) -> anyhow::Result<Vec<String>> {
+        let user_id = *user_id.as_u64() as i64;
+        let mut tx = self.db_pool.begin().await?;
+        let history = sqlx::query!("DELETE FROM history WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete history")?
+            .rows_affected();
+        let team = sqlx::query!("DELETE FROM teams WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete team")?
+            .rows_affected();
+        sqlx::query!("DELETE FROM ranking_snapshots WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete ranking snapshots")?;
+        sqlx::query!("DELETE FROM perfect_months WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete perfect months")?;
+        sqlx::query!("DELETE FROM membership_log WHERE user_id = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete membership log")?;
+        tx.commit().await?;
+
+        let mut removed = Vec::new();
+        if history > 0 {
+            removed.push(format!("으어어 기록 {history}건"));
+        }
+        if team > 0 {
+            removed.push("으어어 팀".to_string());
+        }
+
+        Ok(removed)
+    }
+
+    async fn export_user(
+        &self,
+        user_id: UserId,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        let user_id = *user_id.as_u64() as i64;
+        let history = sqlx::query!(
+            "SELECT message_id, date, weight FROM history WHERE user_id = ? ORDER BY date",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get history")?;
+        let team = sqlx::query_scalar!("SELECT name FROM teams WHERE user_id = ?", user_id)
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to get team")?;
+        let membership = sqlx::query!(
+            "SELECT joined_at, left_at FROM membership_log WHERE user_id = ? ORDER BY id",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get membership log")?;
+
+        let mut export = serde_json::Map::new();
+        export.insert(
+            "eueoeo_history".to_string(),
+            history
+                .into_iter()
+                .map(|row| {
+                    serde_json::json!({
+                        "message_id": row.message_id.to_string(),
+                        "date": chrono::DateTime::from_timestamp(row.date, 0)
+                            .map(|date| date.date_naive().to_string()),
+                        "weight": row.weight,
+                    })
+                })
+                .collect(),
+        );
+        export.insert("eueoeo_team".to_string(), team.into());
+        export.insert(
+            "membership".to_string(),
+            membership
+                .into_iter()
+                .map(|row| {
+                    serde_json::json!({
+                        "joined_at": row.joined_at,
+                        "left_at": row.left_at,
+                    })
+                })
+                .collect(),
+        );
+
+        Ok(export)
+    }
+
+    fn llm_tools(&self) -> Vec<crate::llm::LlmTool> {
+        tools::declarations()
+    }
+
+    async fn call_llm_tool(
+        &self,
+        _context: &Context,
+        _guild_id: GuildId,
+        name: &str,
+        args: &serde_json::Value,
+    ) -> Option<anyhow::Result<serde_json::Value>> {
+        self.call_tool(name, args).await
+    }
+
+    async fn member_join(&self, context: &Context, member: &Member) {
+        match self.record_join(member).await {
+            Ok(Some(count)) => {
+                if let Err(e) = self.welcome_back(context, member, count).await {
+                    error!("{e:?}");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to record join of {} - {e:?}", member.user.id),
+        }
+    }
+
+    async fn member_leave(&self, _context: &Context, user: &User) {
+        if let Err(e) = self.record_leave(user).await {
+            error!("Failed to record leave of {} - {e:?}", user.id);
+        }
+    }
+
+    async fn cache_ready(&self, context: &Context, guild_id: GuildId) {
+        if let Err(e) = self.snapshot_member_count(context, guild_id).await {
+            error!("{e:?}");
+        }
         self.retrieve_missing_messages(context).await;
     }
 
@@ -837,6 +1686,8 @@ impl SubApplication for DiscordHandler {
     }
 
     async fn ready(&self, context: &Context, guild_id: GuildId) {
+        self.start_daily_job(context);
+
         // register or update slash command
         let command = ApplicationCommand {
             name: COMMAND_NAME,
@@ -850,6 +1701,8 @@ impl SubApplication for DiscordHandler {
                         kind: ApplicationCommandOptionType::Integer,
                         name: "year",
                         description: "default is current year.",
+                        min_value: Some(MIN_YEAR),
+                        max_value: Some(MAX_YEAR),
                         ..Default::default()
                     }],
                     ..Default::default()
@@ -881,18 +1734,59 @@ impl SubApplication for DiscordHandler {
                     kind: ApplicationCommandOptionType::SubCommand,
                     name: "user",
                     description: "user detail",
-                    options: vec![ApplicationCommandOption {
-                        kind: ApplicationCommandOptionType::User,
-                        name: "user",
-                        description: "If not specified, show details of you",
-                        ..Default::default()
-                    }],
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::User,
+                            name: "user",
+                            description: "If not specified, show details of you",
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Integer,
+                            name: "year",
+                            description: "year of heatmap. default is current year.",
+                            min_value: Some(MIN_YEAR),
+                            max_value: Some(MAX_YEAR),
+                            ..Default::default()
+                        },
+                    ],
                     ..Default::default()
                 },
                 ApplicationCommandOption {
                     kind: ApplicationCommandOptionType::SubCommand,
                     name: "total",
                     description: "total ranking",
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Integer,
+                            name: "active_days",
+                            description: "only users posted in the last N days",
+                            min_value: Some(1),
+                            max_value: Some(MAX_ACTIVE_DAYS),
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Boolean,
+                            name: "members_only",
+                            description: "only users still in the guild",
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                team::command_option(),
+                trend::command_option(),
+                perfect::command_option(),
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "optout",
+                    description: "hide me from rankings and stats",
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "optin",
+                    description: "show me in rankings and stats again",
                     ..Default::default()
                 },
             ],
@@ -913,11 +1807,8 @@ impl SubApplication for DiscordHandler {
             return;
         }
 
-        if !message.check_message() {
-            message
-                .delete(context)
-                .await
-                .expect("Failed to remove Non-eueoeo message");
+        if !message.check_message(&self.basis_offset, &self.special_days) {
+            self.handle_non_eueoeo_message(context, message).await;
             return;
         }
 
@@ -935,6 +1826,8 @@ impl SubApplication for DiscordHandler {
             return false;
         }
 
+        self.refresh_member_names(context, unsafe { interaction.guild_id.unwrap_unchecked() });
+
         let option = unsafe { interaction.data.options.first().unwrap_unchecked() };
         if let Err(e) = match option.name.as_str() {
             "year" => self.handle_year_command(context, interaction, option).await,
@@ -947,6 +1840,14 @@ impl SubApplication for DiscordHandler {
                 self.handle_total_command(context, interaction, option)
                     .await
             }
+            "optout" => self.handle_opt_command(context, interaction, true).await,
+            "optin" => self.handle_opt_command(context, interaction, false).await,
+            "team" => self.handle_team_command(context, interaction, option).await,
+            "trend" => self.handle_trend_command(context, interaction).await,
+            "perfect" => {
+                self.handle_perfect_command(context, interaction, option)
+                    .await
+            }
             _ => unsafe { std::hint::unreachable_unchecked() },
         } {
             error!("Failed to send message: {:?}", e);
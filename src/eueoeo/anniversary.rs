@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use chrono::{Datelike, FixedOffset, NaiveDate};
+use log::{error, info};
+use serenity::{http::Http, model::prelude::UserId};
+use sqlx::SqlitePool;
+
+use super::fill;
+
+// people started on February 29th are congratulated on February 28th in common years
+fn anniversary_of(first_date: NaiveDate, year: i32) -> Option<NaiveDate> {
+    first_date
+        .with_year(year)
+        .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+}
+
+pub(super) async fn congratulate_anniversaries(
+    db_pool: &SqlitePool,
+    http: &Arc<Http>,
+    offset: &FixedOffset,
+    template: &str,
+) -> anyhow::Result<()> {
+    let today = chrono::Local::now().with_timezone(offset).date_naive();
+    let year = today.year();
+
+    let users = sqlx::query!(
+        r#"SELECT
+            user_id,
+            first_date AS "first_date!: i64"
+        FROM
+            users
+        WHERE
+            first_date IS NOT NULL AND
+            anniversary_year < ? AND
+            NOT opted_out"#,
+        year
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to fetch first eueoeo dates")?;
+
+    for user in users {
+        let Some(first_date) =
+            chrono::DateTime::from_timestamp(user.first_date, 0).map(|date| date.date_naive())
+        else {
+            continue;
+        };
+        let years = year - first_date.year();
+        if years <= 0 || anniversary_of(first_date, year) != Some(today) {
+            continue;
+        }
+
+        // mark before sending not to congratulate twice on restart
+        sqlx::query!(
+            "UPDATE users SET anniversary_year = ? WHERE user_id = ?",
+            year,
+            user.user_id
+        )
+        .execute(db_pool)
+        .await
+        .context("Failed to mark anniversary")?;
+
+        let result = async {
+            UserId(user.user_id as u64)
+                .create_dm_channel(http)
+                .await?
+                .say(
+                    http,
+                    fill(template, &[("date", &first_date), ("years", &years)]),
+                )
+                .await
+        }
+        .await;
+        match result {
+            Ok(_) => info!("Congratulate {}th anniversary of {}", years, user.user_id),
+            Err(e) => error!(
+                "Failed to congratulate anniversary of {} - {e:?}",
+                user.user_id
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use anyhow::Context as _;
+use chrono::{Datelike, NaiveDate};
+
+use super::{yearly_stats_range, DiscordHandler};
+
+pub(super) const HEATMAP_FILENAME: &str = "heatmap.png";
+
+const CELL_SIZE: u32 = 11;
+const CELL_GAP: u32 = 3;
+const MARGIN: u32 = 6;
+
+const BACKGROUND: [u8; 3] = [0xff, 0xff, 0xff];
+const EMPTY: [u8; 3] = [0xeb, 0xed, 0xf0];
+const POSTED: [u8; 3] = [0x40, 0xc4, 0x63];
+const WEIGHTED: [u8; 3] = [0x21, 0x6e, 0x39];
+
+// render posting days of the year as a github style contribution grid.
+// columns are weeks and rows are weekdays beginning with sunday.
+fn render_heatmap(
+    year: i32,
+    last_date: NaiveDate,
+    days: &HashMap<NaiveDate, i64>,
+) -> anyhow::Result<Vec<u8>> {
+    let first_date = NaiveDate::from_ymd_opt(year, 1, 1).context("Invalid year")?;
+    let end_date = NaiveDate::from_ymd_opt(year, 12, 31).context("Invalid year")?;
+    let first_weekday = first_date.weekday().num_days_from_sunday();
+    let weeks = (first_weekday + end_date.ordinal() + 6) / 7;
+
+    let width = MARGIN * 2 + weeks * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+    let height = MARGIN * 2 + 7 * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+    let mut pixels = BACKGROUND.repeat((width * height) as usize);
+
+    for date in first_date.iter_days().take_while(|date| *date <= end_date) {
+        // days not reached yet are left blank
+        if date > last_date {
+            break;
+        }
+        let color = match days.get(&date) {
+            None => EMPTY,
+            Some(1) => POSTED,
+            Some(_) => WEIGHTED,
+        };
+
+        let index = first_weekday + date.ordinal0();
+        let left = MARGIN + (index / 7) * (CELL_SIZE + CELL_GAP);
+        let top = MARGIN + (index % 7) * (CELL_SIZE + CELL_GAP);
+        for y in top..top + CELL_SIZE {
+            let row = (y * width) as usize;
+            for x in left..left + CELL_SIZE {
+                let offset = (row + x as usize) * 3;
+                pixels[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .context("Failed to write png header")?;
+    writer
+        .write_image_data(&pixels)
+        .context("Failed to write png data")?;
+    writer.finish().context("Failed to finish png")?;
+
+    Ok(png)
+}
+
+impl DiscordHandler {
+    pub(super) async fn create_heatmap(
+        &self,
+        user_id: i64,
+        year: Option<i32>,
+    ) -> anyhow::Result<(i32, Vec<u8>)> {
+        let (year, _, begin_date_snowflakes, end_date_snowflakes) =
+            yearly_stats_range(&self.basis_offset, year).context("Year is out of range")?;
+
+        let days = sqlx::query!(
+            r#"SELECT
+                date,
+                sum(weight) AS "weight!: i64"
+            FROM
+                history
+            WHERE
+                user_id = ? AND
+                message_id >= ? AND
+                message_id < ?
+            GROUP BY
+                date"#,
+            user_id,
+            begin_date_snowflakes,
+            end_date_snowflakes
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch history for heatmap")?
+        .into_iter()
+        .filter_map(|row| {
+            chrono::DateTime::from_timestamp(row.date, 0)
+                .map(|date| (date.date_naive(), row.weight))
+        })
+        .collect::<HashMap<_, _>>();
+
+        let today = chrono::Local::now()
+            .with_timezone(&self.basis_offset)
+            .date_naive();
+
+        Ok((year, render_heatmap(year, today, &days)?))
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use chrono::{DateTime, FixedOffset};
+use log::{info, warn};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use super::{history_weight, is_eueoeo, DiscordHandler, FutabaMessage, SpecialDay};
+
+// subset of the channel export format of DiscordChatExporter
+#[derive(Debug, Deserialize)]
+struct ChannelExport {
+    channel: ExportedChannel,
+    messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportedChannel {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedMessage {
+    id: String,
+    timestamp: String,
+    timestamp_edited: Option<String>,
+    content: String,
+    author: ExportedAuthor,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedAuthor {
+    id: String,
+    name: String,
+    nickname: Option<String>,
+    is_bot: bool,
+}
+
+impl ExportedMessage {
+    fn parsed_timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.timestamp).ok()
+    }
+}
+
+impl FutabaMessage for ExportedMessage {
+    fn check_message(&self, offset: &FixedOffset, special_days: &[SpecialDay]) -> bool {
+        if self.author.is_bot || self.timestamp_edited.is_some() {
+            return false;
+        }
+
+        let Some(timestamp) = self.parsed_timestamp() else {
+            return false;
+        };
+        is_eueoeo(
+            &self.content,
+            timestamp.with_timezone(offset).date_naive(),
+            special_days,
+        )
+    }
+}
+
+#[derive(Default)]
+struct Streaks {
+    count: i64,
+    longest: i64,
+    current: i64,
+    last_date: i64,
+    first_date: Option<i64>,
+}
+
+impl DiscordHandler {
+    // recalculate counts and streaks of every user from history
+    pub(super) async fn rebuild_streaks(&self) -> anyhow::Result<()> {
+        const SINGLE_DAY: i64 = 24 * 3600;
+
+        let history = sqlx::query!("SELECT user_id, date, weight FROM history ORDER BY date ASC")
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to fetch history")?;
+
+        let mut streaks: BTreeMap<i64, Streaks> = BTreeMap::new();
+        for record in history {
+            let streak = streaks.entry(record.user_id).or_default();
+            streak.first_date.get_or_insert(record.date);
+            streak.count += record.weight;
+            streak.current = if streak.last_date + SINGLE_DAY == record.date {
+                streak.current + 1
+            } else {
+                1
+            };
+            streak.longest = std::cmp::max(streak.longest, streak.current);
+            streak.last_date = record.date;
+        }
+
+        let mut tx = self.db_pool.begin().await?;
+        for (user_id, streak) in streaks {
+            sqlx::query!(
+                r#"UPDATE users SET
+                    count = ?,
+                    longest_streaks = ?,
+                    current_streaks = ?,
+                    last_date = ?,
+                    first_date = ?
+                WHERE user_id = ?"#,
+                streak.count,
+                streak.longest,
+                streak.current,
+                streak.last_date,
+                streak.first_date,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to update streaks of user({user_id})"))?;
+        }
+        tx.commit().await?;
+
+        info!("Streaks are rebuilt");
+
+        Ok(())
+    }
+}
+
+// backfill history from DiscordChatExporter json which is older than `init_message_id`
+pub(crate) async fn import_history(
+    db_pool: SqlitePool,
+    config: &crate::Config,
+    path: &str,
+) -> anyhow::Result<()> {
+    let export: ChannelExport = serde_json::from_str(
+        &tokio::fs::read_to_string(path)
+            .await
+            .context("Failed to read export file")?,
+    )
+    .context("Failed to parse export file")?;
+
+    if export.channel.id != config.eueoeo.channel_id.to_string() {
+        warn!(
+            "Exported channel({}) is not the eueoeo channel({})",
+            export.channel.id, config.eueoeo.channel_id
+        );
+    }
+
+    let handler = DiscordHandler::new(db_pool, config).await;
+    let init_message_id = config.eueoeo.init_message_id;
+
+    let mut imported = 0;
+    let mut tx = handler.db_pool.begin().await?;
+    for message in &export.messages {
+        let message_id: u64 = message
+            .id
+            .parse()
+            .with_context(|| format!("Invalid message id - {}", message.id))?;
+        if message_id >= init_message_id
+            || !message.check_message(&handler.basis_offset, &handler.special_days)
+        {
+            continue;
+        }
+
+        let message_id = message_id as i64;
+        let author_id: i64 = message
+            .author
+            .id
+            .parse()
+            .with_context(|| format!("Invalid user id - {}", message.author.id))?;
+        let name = message
+            .author
+            .nickname
+            .as_ref()
+            .unwrap_or(&message.author.name);
+        let message_date = message
+            .parsed_timestamp()
+            .expect("timestamp is validated by check_message")
+            .with_timezone(&handler.basis_offset)
+            .date_naive();
+        let weight = history_weight(message_date, &handler.special_days);
+        let message_date = message_date
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp();
+
+        sqlx::query!(
+            "INSERT INTO users (user_id, name) VALUES (?, ?) ON CONFLICT (user_id) DO NOTHING",
+            author_id,
+            name
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to insert user")?;
+
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO history (message_id, user_id, date, weight) VALUES (?, ?, ?, ?)",
+            message_id,
+            author_id,
+            message_date,
+            weight
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to insert history")?;
+        imported += result.rows_affected();
+    }
+    tx.commit().await?;
+
+    info!(
+        "{imported} messages are imported from {} messages",
+        export.messages.len()
+    );
+
+    handler.rebuild_streaks().await
+}
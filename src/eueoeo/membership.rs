@@ -0,0 +1,104 @@
+use anyhow::Context as _;
+use log::info;
+use serenity::{
+    model::prelude::{Member, User},
+    prelude::Context,
+};
+
+use super::{fill, DiscordHandler};
+
+impl DiscordHandler {
+    // returns the eueoeo count to welcome back with if the member left before
+    pub(super) async fn record_join(&self, member: &Member) -> anyhow::Result<Option<i64>> {
+        let user_id = *member.user.id.as_u64() as i64;
+        let joined_at = member.joined_at.map_or_else(
+            || chrono::Utc::now().timestamp(),
+            |date| date.unix_timestamp(),
+        );
+
+        let mut tx = self.db_pool.begin().await?;
+        let departed = sqlx::query!(
+            r#"SELECT departed AS "departed: bool", count FROM users WHERE user_id = ?"#,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to get departed user")?
+        .filter(|user| user.departed);
+        sqlx::query!(
+            "INSERT INTO membership_log (user_id, joined_at) VALUES (?, ?)",
+            user_id,
+            joined_at
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to log join")?;
+        sqlx::query!(
+            "UPDATE users SET departed = FALSE WHERE user_id = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to restore user")?;
+        tx.commit().await?;
+
+        Ok(departed.map(|user| user.count))
+    }
+
+    pub(super) async fn record_leave(&self, user: &User) -> anyhow::Result<()> {
+        let user_id = *user.id.as_u64() as i64;
+        let now = chrono::Utc::now().timestamp();
+
+        let mut tx = self.db_pool.begin().await?;
+        let closed = sqlx::query!(
+            "UPDATE membership_log SET left_at = ?
+            WHERE id = (
+                SELECT max(id) FROM membership_log WHERE user_id = ? AND left_at IS NULL
+            )",
+            now,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to log leave")?
+        .rows_affected();
+        if closed == 0 {
+            sqlx::query!(
+                "INSERT INTO membership_log (user_id, left_at) VALUES (?, ?)",
+                user_id,
+                now
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to log leave")?;
+        }
+        sqlx::query!(
+            "UPDATE users SET departed = TRUE WHERE user_id = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to mark user departed")?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub(super) async fn welcome_back(
+        &self,
+        context: &Context,
+        member: &Member,
+        count: i64,
+    ) -> anyhow::Result<()> {
+        info!("Welcome back user({})", member.user.id);
+        member
+            .user
+            .direct_message(context, |m| {
+                m.content(fill(&self.strings.welcome_back, &[("count", &count)]))
+            })
+            .await
+            .context("Failed to send welcome back message")?;
+
+        Ok(())
+    }
+}
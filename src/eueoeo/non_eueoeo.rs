@@ -0,0 +1,173 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serde::Deserialize;
+use serenity::{
+    model::prelude::{ChannelId, Message},
+    prelude::Context,
+};
+
+use super::{fill, DiscordHandler};
+use crate::discord::ChannelHelper;
+
+const WEBHOOK_NAME: &str = "futaba-eueoeo";
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum NonEueoeoPolicy {
+    #[default]
+    Delete,
+    // delete and send the original content to the author by DM
+    DeleteAndDm,
+    // repost to the thread via webhook and delete the original
+    MoveToThread(u64),
+}
+
+impl DiscordHandler {
+    pub(super) async fn handle_non_eueoeo_message(&self, context: &Context, message: &Message) {
+        match &self.non_eueoeo_policy {
+            NonEueoeoPolicy::Delete => {}
+            NonEueoeoPolicy::DeleteAndDm => {
+                if let Err(e) = self.send_back_by_dm(context, message).await {
+                    error!("Failed to send removed message by DM - {e:?}");
+                }
+            }
+            NonEueoeoPolicy::MoveToThread(thread_id) => {
+                if let Err(e) = self
+                    .repost_to_thread(context, message, ChannelId(*thread_id))
+                    .await
+                {
+                    error!("Failed to move message to overflow thread - {e:?}");
+                    // keep the original message when it could not be moved
+                    return;
+                }
+            }
+        }
+
+        message
+            .delete(context)
+            .await
+            .expect("Failed to remove Non-eueoeo message");
+    }
+
+    // the text is cut to keep urls of the attachments within `max_chars`
+    fn content_with_attachments(message: &Message, max_chars: usize) -> String {
+        let attachments = message
+            .attachments
+            .iter()
+            .map(|attachment| format!("\n{}", attachment.url))
+            .collect::<String>();
+        let max_text_chars = max_chars.saturating_sub(attachments.chars().count());
+        let mut content = if message.content.chars().count() > max_text_chars {
+            let mut text = message
+                .content
+                .chars()
+                .take(max_text_chars.saturating_sub(1))
+                .collect::<String>();
+            text.push('…');
+            text
+        } else {
+            message.content.clone()
+        };
+        content.push_str(&attachments);
+
+        content
+    }
+
+    async fn send_back_by_dm(&self, context: &Context, message: &Message) -> anyhow::Result<()> {
+        let channel = format!("<#{}>", message.channel_id);
+        let template_chars = fill(
+            &self.strings.removed_message,
+            &[("channel", &channel), ("content", &"")],
+        )
+        .chars()
+        .count();
+        let content = Self::content_with_attachments(
+            message,
+            MAX_MESSAGE_LENGTH.saturating_sub(template_chars),
+        );
+        if content.is_empty() {
+            return Ok(());
+        }
+
+        message
+            .author
+            .direct_message(context, |m| {
+                m.content(fill(
+                    &self.strings.removed_message,
+                    &[("channel", &channel), ("content", &content)],
+                ))
+            })
+            .await
+            .context("Failed to send DM")?;
+
+        Ok(())
+    }
+
+    async fn overflow_webhook_url(
+        &self,
+        context: &Context,
+        channel_id: ChannelId,
+    ) -> anyhow::Result<String> {
+        if let Some(url) = self.overflow_webhook_url.get() {
+            return Ok(url.clone());
+        }
+
+        let webhooks = channel_id
+            .webhooks(context)
+            .await
+            .context("Failed to get webhooks")?;
+        let webhook = if let Some(webhook) = webhooks.into_iter().find(|webhook| {
+            webhook.name.as_deref() == Some(WEBHOOK_NAME) && webhook.token.is_some()
+        }) {
+            webhook
+        } else {
+            info!("Create webhook for overflow thread");
+            channel_id
+                .create_webhook(context, WEBHOOK_NAME)
+                .await
+                .context("Failed to create webhook")?
+        };
+        let url = webhook.url().context("Failed to get webhook url")?;
+
+        Ok(self.overflow_webhook_url.get_or_init(|| url).clone())
+    }
+
+    async fn repost_to_thread(
+        &self,
+        context: &Context,
+        message: &Message,
+        thread_id: ChannelId,
+    ) -> anyhow::Result<()> {
+        let content = Self::content_with_attachments(message, MAX_MESSAGE_LENGTH);
+        if content.is_empty() {
+            return Ok(());
+        }
+
+        // webhooks belong to the parent channel and are executed with thread_id
+        let parent_id = thread_id.get_parent_or_self(context).await;
+        let url = self.overflow_webhook_url(context, parent_id).await?;
+
+        let username = message
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.clone())
+            .unwrap_or_else(|| message.author.name.clone());
+        reqwest::Client::new()
+            .post(url)
+            .query(&[("thread_id", thread_id.0)])
+            .json(&serde_json::json!({
+                "content": content,
+                "username": username,
+                "avatar_url": message.author.face(),
+                "allowed_mentions": { "parse": [] },
+            }))
+            .send()
+            .await
+            .context("Failed to execute webhook")?
+            .error_for_status()
+            .context("Webhook returned error")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::Context as _;
+use chrono::{Datelike, FixedOffset};
+use log::{error, info};
+use serenity::{
+    model::prelude::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{fill, DiscordHandler};
+use crate::discord::{application_command::*, CommandDataOptionHelper, CommandHelper};
+
+const HALL_OF_FAME_COUNT: usize = 20;
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommand,
+        name: "perfect",
+        description: "perfect months and hall of fame",
+        options: vec![ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::User,
+            name: "user",
+            description: "If not specified, show perfect months of you",
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+// award every finished month in which the user posted every single day
+pub(super) async fn award_perfect_months(
+    db_pool: &SqlitePool,
+    offset: &FixedOffset,
+) -> anyhow::Result<()> {
+    let today = chrono::Local::now().with_timezone(offset).date_naive();
+    let current_month = format!("{:04}-{:02}", today.year(), today.month());
+
+    let result = sqlx::query!(
+        r#"INSERT OR IGNORE INTO perfect_months (user_id, month)
+        SELECT
+            user_id,
+            strftime('%Y-%m', date, 'unixepoch') AS month
+        FROM
+            history
+        GROUP BY
+            user_id, month
+        HAVING
+            month < ? AND
+            count(DISTINCT date) = CAST(strftime('%d', month || '-01', '+1 month', '-1 day') AS INTEGER)"#,
+        current_month
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to award perfect months")?;
+
+    info!("{} perfect months are awarded", result.rows_affected());
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    async fn fetch_perfect_months(&self, user_id: i64) -> anyhow::Result<Vec<String>> {
+        Ok(sqlx::query!(
+            "SELECT month FROM perfect_months WHERE user_id = ? ORDER BY month ASC",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch perfect months")?
+        .into_iter()
+        .map(|row| row.month)
+        .collect())
+    }
+
+    async fn fetch_hall_of_fame(&self) -> anyhow::Result<Vec<(String, i64)>> {
+        Ok(sqlx::query!(
+            r#"SELECT
+                users.name,
+                count(perfect_months.month) AS "count: i64"
+            FROM
+                perfect_months
+            INNER JOIN
+                users ON perfect_months.user_id = users.user_id
+            WHERE
+                NOT users.opted_out
+            GROUP BY
+                perfect_months.user_id
+            ORDER BY
+                count(perfect_months.month) DESC"#
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch hall of fame")?
+        .into_iter()
+        .map(|row| (row.name, row.count))
+        .collect())
+    }
+
+    pub(super) async fn handle_perfect_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> serenity::Result<()> {
+        let [user] = option.get_options(&["user"]);
+        let user_id: i64 = user
+            .as_str()
+            .and_then(|user| user.parse().ok())
+            .unwrap_or(*interaction.user.id.as_u64() as i64);
+
+        let opted_out = self.is_opted_out(user_id).await.unwrap_or_else(|e| {
+            error!("{e:?}");
+            false
+        });
+        let perfect_months = if opted_out {
+            Ok(Vec::new())
+        } else {
+            self.fetch_perfect_months(user_id).await
+        };
+        let hall_of_fame = self.fetch_hall_of_fame().await;
+        let (perfect_months, hall_of_fame) = match (perfect_months, hall_of_fame) {
+            (Ok(perfect_months), Ok(hall_of_fame)) => (perfect_months, hall_of_fame),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("{e:?}");
+                return interaction
+                    .create_interaction_response(&context.http, |r| {
+                        r.kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|d| {
+                                d.content(&self.strings.query_failed).ephemeral(true)
+                            })
+                    })
+                    .await;
+            }
+        };
+
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.embed(|e| {
+                            e.title(&self.strings.hall_of_fame_title)
+                                .description(format!(
+                                    "<@{user_id}> - {}",
+                                    if opted_out {
+                                        self.strings.private.clone()
+                                    } else if perfect_months.is_empty() {
+                                        self.strings.none.clone()
+                                    } else {
+                                        perfect_months.join(", ")
+                                    }
+                                ));
+                            for (name, count) in hall_of_fame.iter().take(HALL_OF_FAME_COUNT) {
+                                e.field(
+                                    name,
+                                    fill(&self.strings.perfect_months_count, &[("count", count)]),
+                                    true,
+                                );
+                            }
+                            e
+                        })
+                    })
+            })
+            .await
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::Context as _;
+use chrono::FixedOffset;
+use log::info;
+use sqlx::SqlitePool;
+
+use super::{current_yearly_stats_range, date_to_timestamp, DiscordHandler};
+
+// rank movement is shown compared with the ranking of this many days ago
+const RANKING_MOVEMENT_DAYS: u64 = 7;
+
+pub(super) fn rank_movement(rank: i64, previous_rank: Option<i64>) -> String {
+    match previous_rank {
+        Some(previous_rank) if previous_rank > rank => format!(" ▲{}", previous_rank - rank),
+        Some(previous_rank) if previous_rank < rank => format!(" ▼{}", rank - previous_rank),
+        _ => String::new(),
+    }
+}
+
+pub(super) async fn snapshot_rankings(
+    db_pool: &SqlitePool,
+    offset: &FixedOffset,
+) -> anyhow::Result<()> {
+    let today = date_to_timestamp(chrono::Local::now().with_timezone(offset).date_naive());
+    let (_, _, begin_date_snowflakes, end_date_snowflakes) = current_yearly_stats_range(offset);
+
+    let mut tx = db_pool.begin().await?;
+    sqlx::query!(
+        r#"INSERT OR IGNORE INTO ranking_snapshots (date, kind, user_id, rank)
+        SELECT
+            ?,
+            'total',
+            user_id,
+            RANK() OVER (ORDER BY count DESC)
+        FROM
+            users
+        WHERE
+            count > 0 AND
+            NOT opted_out"#,
+        today
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to snapshot total ranking")?;
+    sqlx::query!(
+        r#"INSERT OR IGNORE INTO ranking_snapshots (date, kind, user_id, rank)
+        SELECT
+            ?,
+            'yearly',
+            history.user_id,
+            RANK() OVER (ORDER BY sum(history.weight) DESC)
+        FROM
+            history
+        INNER JOIN
+            users ON history.user_id = users.user_id
+        WHERE
+            history.message_id >= ? AND
+            history.message_id < ? AND
+            NOT users.opted_out
+        GROUP BY
+            history.user_id"#,
+        today,
+        begin_date_snowflakes,
+        end_date_snowflakes
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to snapshot yearly ranking")?;
+    tx.commit().await?;
+
+    info!("Rankings are saved");
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    // date of the snapshot to compare with. It is `None` when it belongs to another year.
+    pub(super) fn previous_ranking_date(&self, year: Option<i32>) -> Option<i64> {
+        let previous_date = chrono::Local::now()
+            .with_timezone(&self.basis_offset)
+            .date_naive()
+            .checked_sub_days(chrono::Days::new(RANKING_MOVEMENT_DAYS))
+            .unwrap();
+
+        match year {
+            Some(year) if chrono::Datelike::year(&previous_date) != year => None,
+            _ => Some(date_to_timestamp(previous_date)),
+        }
+    }
+}
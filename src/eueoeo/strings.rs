@@ -0,0 +1,126 @@
+use std::fmt::Display;
+
+use serde::Deserialize;
+
+// replace `{name}` in the template with the given arguments
+pub(super) fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |ret, (name, value)| {
+            ret.replace(&format!("{{{name}}}"), &value.to_string())
+        })
+}
+
+// user-facing strings of the eueoeo module. every string could be overridden by config.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub(super) struct Strings {
+    pub(super) empty_records: String,
+    pub(super) none: String,
+    pub(super) days: String,
+    pub(super) missing_days_detail: String,
+    pub(super) query_failed: String,
+    pub(super) invalid_year: String,
+    pub(super) setting_failed: String,
+    pub(super) permission_denied: String,
+    pub(super) private_user: String,
+    pub(super) opted_out: String,
+    pub(super) opted_in: String,
+    // rankings
+    pub(super) total_title: String,
+    pub(super) active_days_filter: String,
+    pub(super) members_only_filter: String,
+    pub(super) yearly_title: String,
+    pub(super) yearly_value: String,
+    pub(super) streaks_title: String,
+    pub(super) current_streaks: String,
+    pub(super) longest_streaks: String,
+    // user detail
+    pub(super) user_title: String,
+    pub(super) year: String,
+    pub(super) weekday: String,
+    pub(super) weekend: String,
+    pub(super) since_joined: String,
+    pub(super) missing_days: String,
+    pub(super) club_member: String,
+    pub(super) club_member_since: String,
+    pub(super) heatmap: String,
+    pub(super) milestone: String,
+    pub(super) milestone_date: String,
+    // team
+    pub(super) team_assigned: String,
+    pub(super) team_unassigned: String,
+    pub(super) team_not_assigned: String,
+    pub(super) yearly_team_title: String,
+    pub(super) monthly_team_title: String,
+    pub(super) team_value: String,
+    // trend
+    pub(super) trend_title: String,
+    pub(super) trend_users: String,
+    pub(super) trend_users_ratio: String,
+    // perfect months
+    pub(super) hall_of_fame_title: String,
+    pub(super) private: String,
+    pub(super) perfect_months_count: String,
+    // direct messages
+    pub(super) anniversary: String,
+    pub(super) removed_message: String,
+    pub(super) welcome_back: String,
+    // appended to names of users who left the server
+    pub(super) departed: String,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self {
+            empty_records: "Empty records".to_string(),
+            none: "없음".to_string(),
+            days: "{days}일".to_string(),
+            missing_days_detail: "{count}일 - {days}".to_string(),
+            query_failed: "조회 실패. 오류 발생".to_string(),
+            invalid_year: "{year}년의 기록은 조회할 수 없습니다.".to_string(),
+            setting_failed: "설정 실패. 오류 발생".to_string(),
+            permission_denied: "권한이 없는 명령입니다.".to_string(),
+            private_user: "통계 공개를 원하지 않는 사용자입니다.".to_string(),
+            opted_out: "이제 순위와 통계에 표시되지 않습니다.".to_string(),
+            opted_in: "이제 순위와 통계에 다시 표시됩니다.".to_string(),
+            total_title: "으어어".to_string(),
+            active_days_filter: " (최근 {days}일 활동)".to_string(),
+            members_only_filter: " (현재 멤버)".to_string(),
+            yearly_title: "으어어 {year} ({days}일)".to_string(),
+            yearly_value: "{count} ({ratio}%)".to_string(),
+            streaks_title: "{kind} 으어어".to_string(),
+            current_streaks: "현재 연속".to_string(),
+            longest_streaks: "최장 연속".to_string(),
+            user_title: "으어어 by {name}".to_string(),
+            year: "{year}년".to_string(),
+            weekday: "평일 ({year}년)".to_string(),
+            weekend: "주말 ({year}년)".to_string(),
+            since_joined: "가입 후".to_string(),
+            missing_days: "빼먹은 날 ({year}년)".to_string(),
+            club_member: "으어어 클럽 회원".to_string(),
+            club_member_since: "{date}부터".to_string(),
+            heatmap: "{year}년 기록".to_string(),
+            milestone: "다음 목표 {milestone}회".to_string(),
+            milestone_date: "{date} 달성 예상 (최근 {days}일 기준)".to_string(),
+            team_assigned: "{user} 님을 {team} 팀에 배정했습니다.".to_string(),
+            team_unassigned: "{user} 님을 팀에서 제외했습니다.".to_string(),
+            team_not_assigned: "{user} 님은 팀에 속해있지 않습니다.".to_string(),
+            yearly_team_title: "올해 팀 으어어".to_string(),
+            monthly_team_title: "이번 달 팀 으어어".to_string(),
+            team_value: "{count} ({members}명, {ratio}%)".to_string(),
+            trend_title: "월별 으어어 참여".to_string(),
+            trend_users: "{users}명".to_string(),
+            trend_users_ratio: "{users}명 ({ratio}%)".to_string(),
+            hall_of_fame_title: "완벽한 달 명예의 전당".to_string(),
+            private: "비공개".to_string(),
+            perfect_months_count: "{count}개월".to_string(),
+            anniversary:
+                "🎉 {date}에 첫 으어어를 한 지 {years}년이 되었습니다! 앞으로도 으어어 해주세요."
+                    .to_string(),
+            removed_message: "{channel} 채널에는 으어어만 작성할 수 있어서 메시지가 삭제되었습니다. 작성하신 내용은 다음과 같습니다.\n\n{content}".to_string(),
+            welcome_back: "다시 오신 것을 환영합니다! 이전 으어어 기록 {count}회가 그대로 남아 있습니다."
+                .to_string(),
+            departed: " (떠남)".to_string(),
+        }
+    }
+}
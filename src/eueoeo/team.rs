@@ -0,0 +1,274 @@
+use anyhow::Context as _;
+use serenity::{
+    model::prelude::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use super::{
+    current_yearly_stats_range, fill, DiscordHandler, EmendableMessage, Stat, Strings,
+    MAX_RESPONSE_COUNT,
+};
+use crate::discord::{application_command::*, CommandDataOptionHelper, CommandHelper};
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommandGroup,
+        name: "team",
+        description: "team competition",
+        options: vec![
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "assign",
+                description: "assign user to team (admin only)",
+                options: vec![
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::User,
+                        name: "user",
+                        description: "user to assign",
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "name",
+                        description: "team name",
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "unassign",
+                description: "remove user from team (admin only)",
+                options: vec![ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::User,
+                    name: "user",
+                    description: "user to remove",
+                    required: Some(true),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "ranking",
+                description: "team ranking",
+                options: vec![ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::String,
+                    name: "period",
+                    description: "ranking period",
+                    required: Some(true),
+                    choices: vec![
+                        ApplicationCommandOptionChoice {
+                            name: "yearly",
+                            value: serde_json::json!("yearly"),
+                        },
+                        ApplicationCommandOptionChoice {
+                            name: "monthly",
+                            value: serde_json::json!("monthly"),
+                        },
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+struct TeamStat {
+    name: String,
+    members: i64,
+    count: i64,
+    days: i64,
+    total_days: i64,
+}
+
+impl Stat for &TeamStat {
+    fn title(&self) -> &str {
+        &self.name
+    }
+
+    fn value(&self, strings: &Strings) -> String {
+        // average participation rate of members
+        fill(
+            &strings.team_value,
+            &[
+                ("count", &self.count),
+                ("members", &self.members),
+                (
+                    "ratio",
+                    &(self.days * 100 / (self.members * self.total_days)),
+                ),
+            ],
+        )
+    }
+}
+
+impl DiscordHandler {
+    async fn assign_team(&self, user_id: i64, name: &str) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO teams (user_id, name) VALUES (?, ?)
+            ON CONFLICT (user_id) DO UPDATE SET name = excluded.name",
+            user_id,
+            name
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to assign team")?;
+
+        Ok(())
+    }
+
+    async fn unassign_team(&self, user_id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query!("DELETE FROM teams WHERE user_id = ?", user_id)
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to unassign team")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn fetch_team_statistics(&self, monthly: bool) -> Vec<TeamStat> {
+        let (total_days, begin_date_snowflakes, end_date_snowflakes) = if monthly {
+            self.get_monthly_stats_range()
+        } else {
+            let (_, days, begin, end) = current_yearly_stats_range(&self.basis_offset);
+            (days, begin, end)
+        };
+
+        let stats = sqlx::query!(
+            r#"SELECT
+                teams.name,
+                count(DISTINCT teams.user_id) AS "members: i64",
+                coalesce(sum(history.weight), 0) AS "count: i64",
+                count(history.message_id) AS "days: i64"
+            FROM
+                teams
+            INNER JOIN
+                users ON teams.user_id = users.user_id
+            LEFT JOIN
+                history ON
+                    history.user_id = teams.user_id AND
+                    history.message_id >= ? AND
+                    history.message_id < ?
+            WHERE
+                NOT users.opted_out
+            GROUP BY
+                teams.name;
+            "#,
+            begin_date_snowflakes,
+            end_date_snowflakes
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .unwrap();
+
+        let mut stats = stats
+            .into_iter()
+            .map(|stat| TeamStat {
+                name: stat.name,
+                members: stat.members,
+                count: stat.count,
+                days: stat.days,
+                total_days,
+            })
+            .collect::<Vec<_>>();
+
+        stats.sort_by_cached_key(|i| std::cmp::Reverse(i.count));
+
+        stats
+    }
+
+    pub(super) async fn handle_team_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> serenity::Result<()> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        if sub_option.name == "ranking" {
+            return self
+                .handle_team_ranking_command(context, interaction, sub_option)
+                .await;
+        }
+
+        if !self.is_admin(context, interaction).await? {
+            return interaction
+                .create_interaction_response(&context.http, |r| {
+                    r.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.content(&self.strings.permission_denied).ephemeral(true)
+                        })
+                })
+                .await;
+        }
+
+        let [user, name] = sub_option.get_options(&["user", "name"]);
+        let user_id: i64 = unsafe { user.as_str_unchecked().parse().unwrap_unchecked() };
+        let result = match sub_option.name.as_str() {
+            "assign" => {
+                let name = unsafe { name.as_str_unchecked() };
+                self.assign_team(user_id, name).await.map(|_| {
+                    fill(
+                        &self.strings.team_assigned,
+                        &[("user", &format!("<@{user_id}>")), ("team", &name)],
+                    )
+                })
+            }
+            "unassign" => self.unassign_team(user_id).await.map(|removed| {
+                let template = if removed {
+                    &self.strings.team_unassigned
+                } else {
+                    &self.strings.team_not_assigned
+                };
+                fill(template, &[("user", &format!("<@{user_id}>"))])
+            }),
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        };
+        let content = result.unwrap_or_else(|e| {
+            log::error!("{e:?}");
+            self.strings.setting_failed.clone()
+        });
+
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| d.content(content).ephemeral(true))
+            })
+            .await
+    }
+
+    async fn handle_team_ranking_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> serenity::Result<()> {
+        let [period] = option.get_options(&["period"]);
+        let (title, monthly) = match unsafe { period.as_str_unchecked() } {
+            "yearly" => (&self.strings.yearly_team_title, false),
+            "monthly" => (&self.strings.monthly_team_title, true),
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        };
+        let stats = self.fetch_team_statistics(monthly).await;
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.create_statistics(
+                            title,
+                            stats.iter().take(MAX_RESPONSE_COUNT),
+                            &self.strings,
+                        )
+                    })
+            })
+            .await
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::Context as _;
+use serde_json::json;
+
+use super::DiscordHandler;
+use crate::llm::LlmTool;
+
+// entries of a ranking given to the model
+const RANKING_LIMIT: usize = 10;
+
+pub(super) fn declarations() -> Vec<LlmTool> {
+    vec![
+        LlmTool {
+            name: "get_eueoeo_ranking",
+            description: "Ranking of eueoeo, the daily greeting of the server. \
+                Names may have rank movements like ▲1 appended.",
+            parameters: Some(json!({
+                "type": "object",
+                "properties": {
+                    "kind": {
+                        "type": "string",
+                        "enum": ["total", "yearly", "longest_streak", "current_streak"],
+                        "description": "counts of all time, counts of this year, \
+                            the longest consecutive days or the current consecutive days",
+                    },
+                },
+                "required": ["kind"],
+            })),
+        },
+        LlmTool {
+            name: "get_eueoeo_stats",
+            description: "Eueoeo statistics of a user",
+            parameters: Some(json!({
+                "type": "object",
+                "properties": {
+                    "user": {
+                        "type": "string",
+                        "description": "mention like <@123>, user id or name of the user",
+                    },
+                },
+                "required": ["user"],
+            })),
+        },
+    ]
+}
+
+impl DiscordHandler {
+    async fn find_user(&self, user: &str) -> anyhow::Result<Option<i64>> {
+        let user = user.trim();
+        let id = user
+            .trim_start_matches("<@")
+            .trim_start_matches('!')
+            .trim_end_matches('>');
+        let user_id = match id.parse::<i64>() {
+            Ok(user_id) => {
+                sqlx::query_scalar!("SELECT user_id FROM users WHERE user_id = ?", user_id)
+                    .fetch_optional(&self.db_pool)
+                    .await
+            }
+            Err(_) => {
+                sqlx::query_scalar!(
+                    "SELECT user_id FROM users WHERE name = ? COLLATE NOCASE",
+                    user
+                )
+                .fetch_optional(&self.db_pool)
+                .await
+            }
+        }
+        .context("Failed to find user")?;
+
+        Ok(user_id)
+    }
+
+    pub(super) async fn call_tool(
+        &self,
+        name: &str,
+        args: &serde_json::Value,
+    ) -> Option<anyhow::Result<serde_json::Value>> {
+        match name {
+            "get_eueoeo_ranking" => {
+                let stats = match args["kind"].as_str() {
+                    Some("total") => self.fetch_statistics(None, None).await,
+                    Some("yearly") => {
+                        self.fetch_yearly_statistics(None)
+                            .await
+                            .expect("Current year is out of range")
+                            .1
+                            .stats
+                    }
+                    Some("longest_streak") => self.fetch_streaks(true).await,
+                    Some("current_streak") => self.fetch_streaks(false).await,
+                    kind => return Some(Err(anyhow::anyhow!("Unknown kind {kind:?}"))),
+                };
+
+                Some(Ok(json!({
+                    "ranking": stats
+                        .into_iter()
+                        .take(RANKING_LIMIT)
+                        .map(|(name, count)| json!({ "name": name, "count": count }))
+                        .collect::<Vec<_>>(),
+                })))
+            }
+            "get_eueoeo_stats" => Some(
+                self.user_stats(args["user"].as_str().unwrap_or_default())
+                    .await,
+            ),
+            _ => None,
+        }
+    }
+
+    async fn user_stats(&self, user: &str) -> anyhow::Result<serde_json::Value> {
+        let Some(user_id) = self.find_user(user).await? else {
+            return Ok(json!({ "error": "the user has never eueoeo" }));
+        };
+        // opted out users are hidden from every statistics
+        if self.is_opted_out(user_id).await? {
+            return Ok(json!({ "error": "the user opted out of statistics" }));
+        }
+
+        let detail = self.fetch_user_details(user_id).await;
+        Ok(json!({
+            "name": detail.name,
+            "total_count": detail.total_count,
+            "year": detail.year,
+            "yearly_count": detail.yearly_count,
+            "yearly_ratio_percent": detail.yearly_ratio,
+            "longest_streak": detail.longest_streaks,
+            "current_streak": detail.current_streaks,
+            "first_date": detail.first_date.map(|date| date.to_string()),
+            "next_milestone": detail.milestone.map(|(count, date)| json!({
+                "count": count,
+                "expected_date": date.to_string(),
+            })),
+        }))
+    }
+}
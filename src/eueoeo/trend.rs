@@ -0,0 +1,144 @@
+use anyhow::Context as _;
+use chrono::Datelike;
+use log::error;
+use serenity::{
+    model::prelude::{
+        interaction::{
+            application_command::ApplicationCommandInteraction, InteractionResponseType,
+        },
+        GuildId,
+    },
+    prelude::Context,
+};
+
+use super::{fill, DiscordHandler, EmendableMessage, Stat, Strings};
+use crate::discord::application_command::*;
+
+const TREND_MONTHS: i64 = 12;
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommand,
+        name: "trend",
+        description: "monthly participation trend",
+        ..Default::default()
+    }
+}
+
+struct MonthlyTrend {
+    month: String,
+    users: i64,
+    member_count: Option<i64>,
+}
+
+impl Stat for &MonthlyTrend {
+    fn title(&self) -> &str {
+        &self.month
+    }
+
+    fn value(&self, strings: &Strings) -> String {
+        match self.member_count {
+            Some(member_count) if member_count > 0 => fill(
+                &strings.trend_users_ratio,
+                &[
+                    ("users", &self.users),
+                    ("ratio", &(self.users * 100 / member_count)),
+                ],
+            ),
+            _ => fill(&strings.trend_users, &[("users", &self.users)]),
+        }
+    }
+}
+
+impl DiscordHandler {
+    pub(super) async fn snapshot_member_count(
+        &self,
+        context: &Context,
+        guild_id: GuildId,
+    ) -> anyhow::Result<()> {
+        let Some(member_count) = context.cache.guild_field(guild_id, |g| g.member_count) else {
+            return Ok(());
+        };
+        let member_count = member_count as i64;
+        let today = chrono::Local::now()
+            .with_timezone(&self.basis_offset)
+            .date_naive();
+        let month = format!("{:04}-{:02}", today.year(), today.month());
+
+        sqlx::query!(
+            "INSERT INTO member_count_snapshots (month, member_count) VALUES (?, ?)
+            ON CONFLICT (month) DO UPDATE SET member_count = excluded.member_count",
+            month,
+            member_count
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save member count snapshot")?;
+
+        Ok(())
+    }
+
+    async fn fetch_trend(&self) -> anyhow::Result<Vec<MonthlyTrend>> {
+        let trend = sqlx::query!(
+            r#"SELECT
+                trend.month AS "month!: String",
+                trend.users AS "users!: i64",
+                member_count_snapshots.member_count AS "member_count?: i64"
+            FROM
+                (
+                    SELECT
+                        strftime('%Y-%m', date, 'unixepoch') AS month,
+                        count(DISTINCT user_id) AS users
+                    FROM
+                        history
+                    GROUP BY
+                        month
+                ) AS trend
+            LEFT JOIN
+                member_count_snapshots ON member_count_snapshots.month = trend.month
+            ORDER BY
+                trend.month DESC
+            LIMIT ?"#,
+            TREND_MONTHS
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch participation trend")?;
+
+        Ok(trend
+            .into_iter()
+            .rev()
+            .map(|row| MonthlyTrend {
+                month: row.month,
+                users: row.users,
+                member_count: row.member_count,
+            })
+            .collect())
+    }
+
+    pub(super) async fn handle_trend_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> serenity::Result<()> {
+        if let Err(e) = self
+            .snapshot_member_count(context, unsafe { interaction.guild_id.unwrap_unchecked() })
+            .await
+        {
+            error!("{e:?}");
+        }
+
+        let trend = self.fetch_trend().await.unwrap_or_else(|e| {
+            error!("{e:?}");
+            Vec::new()
+        });
+        interaction
+            .create_interaction_response(&context.http, |r| {
+                r.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.create_statistics(&self.strings.trend_title, trend.iter(), &self.strings)
+                    })
+            })
+            .await
+    }
+}
@@ -1,18 +1,21 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::atomic::AtomicBool};
 
 use anyhow::Context as _;
 use async_trait::async_trait;
-use chrono::DateTime;
+use dashmap::DashMap;
 use google_calendar3::{
-    api::Event as GoogleEvent,
+    api::{AclRule, AclRuleScope, Calendar, Event as GoogleEvent, EventSource},
     hyper::{self, client::HttpConnector},
     hyper_rustls::{self, HttpsConnector},
     oauth2::{self, authenticator::HyperClientBuilder},
     CalendarHub,
 };
-use log::error;
+use hmac::Mac;
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
 use serde::Deserialize;
 use serenity::{
+    http::{Http, UserPagination},
     model::{
         application::{
             component::{ActionRowComponent, InputTextStyle},
@@ -22,57 +25,256 @@ use serenity::{
                 InteractionResponseType,
             },
         },
-        prelude::{GuildId, ScheduledEvent, ScheduledEventId, UserId},
+        prelude::{
+            ChannelId, GuildId, Member, RoleId, ScheduledEvent, ScheduledEventId,
+            ScheduledEventStatus, ScheduledEventUser, UserId,
+        },
+        Permissions,
     },
     prelude::Context,
 };
-use sqlx::{Row, SqlitePool};
+use sqlx::SqlitePool;
+use uuid::Uuid;
 
 use crate::discord::{
     application_command::{
         ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
     },
-    ScheduledEventUpdated, SubApplication,
+    CommandDataOptionHelper, CommandHelper, ScheduledEventUpdated, SubApplication,
 };
 
+mod attendance;
+mod backend;
+mod conference;
+mod conflict;
+mod countdown;
+mod create;
+mod date_time;
+mod google_source;
+mod ics;
+mod news;
+mod recurrence;
+mod reminder;
+mod resync;
+mod sync_log;
+mod sync_queue;
+mod thread;
+mod tools;
+mod upcoming;
+mod watch;
+
+use backend::{caldav_client, encrypt_plain_caldav_passwords, fetch_user_calendars, RejectedUrl};
+use create::CREATE_EVENT_MODAL_ID;
+use date_time::EventTimeZone;
+pub(crate) use ics::events_feed;
+use recurrence::{fetch_recurrence_rule, RecurrenceRule};
+use sync_queue::{SyncOperation, SyncSummary, SyncTask};
+
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct Config {
     google_service_account_path: String,
+    // google calendar whose events are created as discord scheduled events
+    #[serde(default)]
+    source_calendar_id: Option<String>,
+    #[serde(default = "default_source_poll_interval_secs")]
+    source_poll_interval_secs: u64,
+    // remind scheduled events this many minutes before they start
+    #[serde(default = "default_reminder_minutes")]
+    reminder_minutes: i64,
+    #[serde(default)]
+    reminder_channel_id: Option<u64>,
+    // send reminders to interested users by DM as well. users could override it by `/event notify`
+    #[serde(default)]
+    reminder_dm: bool,
+    // channel where a discussion thread is created for each event
+    #[serde(default)]
+    thread_channel_id: Option<u64>,
+    // restore mirrored events edited or deleted in google calendars of users.
+    // google has to reach `https://<web.domain>/events/google/notify`.
+    #[serde(default)]
+    watch_user_calendars: bool,
+    // recent sync results are served on `/events/synclog?token=<token>` when set
+    #[serde(default)]
+    sync_log_token: Option<String>,
+    // role given by `/event subscribe`. mentioned in `news_channel_id` when a new event is created.
+    #[serde(default)]
+    news_role_id: Option<u64>,
+    #[serde(default)]
+    news_channel_id: Option<u64>,
+    // time zone of synced events. IANA name as google calendar takes.
+    #[serde(default = "default_time_zone")]
+    time_zone: String,
+}
+
+fn default_source_poll_interval_secs() -> u64 {
+    300
+}
+
+fn default_reminder_minutes() -> i64 {
+    30
+}
+
+fn default_time_zone() -> String {
+    "Asia/Seoul".to_string()
 }
 
 pub(crate) struct DiscordHandler {
     db_pool: SqlitePool,
     service_account: google_calendar3::oauth2::ServiceAccountKey,
+    web_prefix: String,
+    shared_calendar_id: OnceCell<String>,
+    source_calendar_id: Option<String>,
+    source_poll_interval: std::time::Duration,
+    source_sync_started: AtomicBool,
+    reminder_before: chrono::Duration,
+    reminder_channel_id: Option<ChannelId>,
+    reminder_dm: bool,
+    reminder_started: AtomicBool,
+    thread_channel_id: Option<ChannelId>,
+    watch_user_calendars: bool,
+    watch_started: AtomicBool,
+    countdown_started: AtomicBool,
+    news_role_id: Option<RoleId>,
+    news_channel_id: Option<ChannelId>,
+    sync_retry_started: AtomicBool,
+    // latest update generation of each event being debounced
+    pending_updates: DashMap<ScheduledEventId, u64>,
+    time_zone: EventTimeZone,
 }
 
 const COMMAND_NAME: &str = "event";
+// editing an event fires several updates in a row
+const UPDATE_DEBOUNCE: std::time::Duration = std::time::Duration::from_secs(3);
+
+fn modal_input<'a>(modal: &'a ModalSubmitInteraction, custom_id: &str) -> Option<&'a str> {
+    modal.data.components.iter().find_map(|r| {
+        let ActionRowComponent::InputText(input) = r.components.first()? else {
+            return None;
+        };
+
+        (input.custom_id == custom_id).then_some(input.value.as_str())
+    })
+}
+
+fn can_manage_events(member: Option<&Member>) -> bool {
+    member
+        .and_then(|member| member.permissions)
+        .map_or(false, |permissions| {
+            permissions.contains(Permissions::MANAGE_EVENTS)
+        })
+}
+
+// for tokens given to the web endpoints. MACs of both are compared in constant
+// time not to leak the token by timing.
+fn verify_token(expected: &str, token: &str) -> bool {
+    let Ok(mut expected_mac) = hmac::Hmac::<sha2::Sha256>::new_from_slice(expected.as_bytes())
+    else {
+        return false;
+    };
+    let mut mac = expected_mac.clone();
+    expected_mac.update(expected.as_bytes());
+    mac.update(token.as_bytes());
+
+    mac.verify_slice(&expected_mac.finalize().into_bytes())
+        .is_ok()
+}
+
+// calendars deleted or not shared with the service account anymore
+fn is_calendar_gone(error: &google_calendar3::Error) -> bool {
+    matches!(
+        error,
+        google_calendar3::Error::BadRequest(body)
+            if body["error"]["code"] == 404 || body["error"]["code"] == 410
+    )
+}
+
+// discord returns at most 100 users at once
+async fn fetch_interested_users(
+    http: &Http,
+    event: &ScheduledEvent,
+) -> anyhow::Result<Vec<ScheduledEventUser>> {
+    const PAGE_SIZE: u64 = 100;
+
+    let mut users: Vec<ScheduledEventUser> = Vec::new();
+    loop {
+        let after = users.last().map(|user| UserPagination::After(user.user.id));
+        let page = http
+            .get_scheduled_event_users(
+                event.guild_id.0,
+                event.id.0,
+                Some(PAGE_SIZE),
+                after,
+                Some(false),
+            )
+            .await
+            .context("Failed to get attendees")?;
+        let last_page = (page.len() as u64) < PAGE_SIZE;
+        users.extend(page);
+        if last_page {
+            return Ok(users);
+        }
+    }
+}
 
 impl DiscordHandler {
     pub async fn new(db_pool: SqlitePool, config: &crate::Config) -> anyhow::Result<Self> {
+        // polling without interval would spin
+        anyhow::ensure!(
+            config.events.source_poll_interval_secs > 0,
+            "source_poll_interval_secs must be positive"
+        );
+
         Ok(Self {
             db_pool,
             service_account: google_calendar3::oauth2::read_service_account_key(
                 &config.events.google_service_account_path,
             )
             .await?,
+            web_prefix: format!("https://{}", config.web.domain),
+            shared_calendar_id: OnceCell::new(),
+            source_calendar_id: config.events.source_calendar_id.clone(),
+            source_poll_interval: std::time::Duration::from_secs(
+                config.events.source_poll_interval_secs,
+            ),
+            source_sync_started: AtomicBool::new(false),
+            reminder_before: chrono::Duration::minutes(config.events.reminder_minutes),
+            reminder_channel_id: config.events.reminder_channel_id.map(ChannelId),
+            reminder_dm: config.events.reminder_dm,
+            reminder_started: AtomicBool::new(false),
+            thread_channel_id: config.events.thread_channel_id.map(ChannelId),
+            watch_user_calendars: config.events.watch_user_calendars,
+            watch_started: AtomicBool::new(false),
+            countdown_started: AtomicBool::new(false),
+            news_role_id: config.events.news_role_id.map(RoleId),
+            news_channel_id: config.events.news_channel_id.map(ChannelId),
+            sync_retry_started: AtomicBool::new(false),
+            pending_updates: DashMap::new(),
+            time_zone: EventTimeZone::new(&config.events.time_zone)?,
         })
     }
 
     async fn google_service_account_auth(
-        &self,
+        service_account: google_calendar3::oauth2::ServiceAccountKey,
     ) -> anyhow::Result<
         oauth2::authenticator::Authenticator<
             <oauth2::authenticator::DefaultHyperClient as HyperClientBuilder>::Connector,
         >,
     > {
-        oauth2::ServiceAccountAuthenticator::builder(self.service_account.clone())
+        oauth2::ServiceAccountAuthenticator::builder(service_account)
             .build()
             .await
             .context("Failed to get service account auth")
     }
 
     async fn calendar_hub(&self) -> anyhow::Result<CalendarHub<HttpsConnector<HttpConnector>>> {
-        let auth = self.google_service_account_auth().await?;
+        Self::service_account_calendar_hub(self.service_account.clone()).await
+    }
+
+    // detached from `self` to be used in background tasks
+    async fn service_account_calendar_hub(
+        service_account: google_calendar3::oauth2::ServiceAccountKey,
+    ) -> anyhow::Result<CalendarHub<HttpsConnector<HttpConnector>>> {
+        let auth = Self::google_service_account_auth(service_account).await?;
 
         Ok(CalendarHub::new(
             hyper::Client::builder().build(
@@ -88,37 +290,366 @@ impl DiscordHandler {
 
     async fn discord_event_to_google_event(
         discord_event: &ScheduledEvent,
+        recurrence_rule: Option<&RecurrenceRule>,
+        time_zone: &EventTimeZone,
     ) -> anyhow::Result<GoogleEvent> {
-        fn discord_ts_to_google_date_time(
-            ts: serenity::model::Timestamp,
-        ) -> google_calendar3::api::EventDateTime {
-            let ts = ts.timestamp();
-            google_calendar3::api::EventDateTime {
-                date: None,
-                date_time: DateTime::from_timestamp(ts, 0),
-                time_zone: None,
-            }
-        }
-        let start = discord_ts_to_google_date_time(discord_event.start_time);
-        let end = discord_event
+        let start_ts = discord_event.start_time.unix_timestamp();
+        let duration = discord_event
             .end_time
-            .map(discord_ts_to_google_date_time)
-            .or_else(|| Some(start.clone()));
+            .map(|end_time| end_time.unix_timestamp() - start_ts);
+        // discord moves start time of recurring event to the next occurrence after each one ends.
+        // anchor google event to the beginning of the series to keep past occurrences.
+        let (start_ts, recurrence) = match recurrence_rule {
+            Some(rule) => (rule.start.timestamp(), Some(vec![rule.to_rrule()?])),
+            None => (start_ts, None),
+        };
+        let (start, end) = time_zone.event_date_times(
+            start_ts,
+            duration.map(|duration| start_ts + duration),
+            discord_event.description.as_deref(),
+            recurrence.is_some(),
+        );
+        let event_url = format!(
+            "https://discord.com/events/{}/{}",
+            discord_event.guild_id, discord_event.id
+        );
+        let mut description = discord_event.description.clone().unwrap_or_default();
+        if let Some(image) = &discord_event.image {
+            description.push_str(&format!(
+                "\n\nhttps://cdn.discordapp.com/guild-events/{}/{image}.png?size=1024",
+                discord_event.id
+            ));
+        }
+        description.push_str(&format!("\n\n{event_url}"));
+        // completed events drop the prefix not to be left as live
+        let (status, prefix) = match discord_event.status {
+            ScheduledEventStatus::Active => ("confirmed", "[LIVE] "),
+            ScheduledEventStatus::Canceled => ("cancelled", "[CANCELLED] "),
+            _ => ("confirmed", ""),
+        };
         Ok(GoogleEvent {
-            description: discord_event.description.clone(),
-            end,
+            description: Some(description.trim_start().to_string()),
+            end: Some(end),
             start: Some(start),
-            summary: Some(discord_event.name.clone()),
+            summary: Some(format!("{prefix}{}", discord_event.name)),
+            status: Some(status.to_string()),
             location: discord_event.metadata.as_ref().map(|d| d.location.clone()),
+            recurrence,
+            source: Some(EventSource {
+                title: Some("Discord".to_string()),
+                url: Some(event_url),
+            }),
             ..Default::default()
         })
     }
 
-    async fn update_server_event(
+    // calendar owned by the service account which mirrors every scheduled event of the guild
+    async fn bootstrap_shared_calendar(
+        &self,
+        context: &Context,
+        guild_id: GuildId,
+    ) -> anyhow::Result<String> {
+        let raw_guild_id = *guild_id.as_u64() as i64;
+        let hub = self
+            .calendar_hub()
+            .await
+            .context("Failed to create google calendar hub")?;
+
+        let saved = sqlx::query!(
+            "SELECT `calendar_id` FROM `shared_calendars` WHERE `guild_id` = ?",
+            raw_guild_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get shared calendar from DB")?;
+        if let Some(saved) = saved {
+            match hub.calendars().get(&saved.calendar_id).doit().await {
+                Ok(_) => return Ok(saved.calendar_id),
+                Err(e) if is_calendar_gone(&e) => info!(
+                    "Saved shared calendar({}) is invalid - {e:?}",
+                    saved.calendar_id
+                ),
+                // other errors may be temporary, and the saved calendar is kept
+                Err(e) => return Err(e).context("Failed to get shared calendar"),
+            }
+        }
+
+        info!("Create shared calendar");
+        let calendar_name = context
+            .cache
+            .guild_field(guild_id, |g| g.name.clone())
+            .unwrap_or_else(|| "Futaba".to_string());
+        let calendar_id = hub
+            .calendars()
+            .insert(Calendar {
+                summary: Some(calendar_name),
+                ..Default::default()
+            })
+            .doit()
+            .await
+            .context("Failed to create shared calendar")?
+            .1
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Mandatory field is missing"))?;
+        hub.acl()
+            .insert(
+                AclRule {
+                    etag: None,
+                    id: None,
+                    kind: None,
+                    role: Some("reader".to_string()),
+                    scope: Some(AclRuleScope {
+                        type_: Some("default".to_string()),
+                        value: None,
+                    }),
+                },
+                &calendar_id,
+            )
+            .doit()
+            .await
+            .context("Failed to make shared calendar public")?;
+
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!(
+            "INSERT INTO `shared_calendars` (`guild_id`, `calendar_id`) VALUES (?, ?)
+            ON CONFLICT (`guild_id`) DO UPDATE SET `calendar_id` = excluded.`calendar_id`",
+            raw_guild_id,
+            calendar_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to save shared calendar into DB")?;
+        // synced events belong to the previous calendar
+        sqlx::query!("DELETE FROM `shared_events`")
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear shared events")?;
+        tx.commit().await?;
+
+        Ok(calendar_id)
+    }
+
+    async fn sync_shared_event(
         &self,
         context: &Context,
         event: &ScheduledEvent,
+        deleted: bool,
     ) -> anyhow::Result<()> {
+        let Some(calendar_id) = self.shared_calendar_id.get() else {
+            return Ok(());
+        };
+        let discord_id = *event.id.as_u64() as i64;
+        let saved = sqlx::query!(
+            "SELECT `google_event_id` FROM `shared_events` WHERE `discord_id` = ?",
+            discord_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get shared event from DB")?;
+
+        let hub = self
+            .calendar_hub()
+            .await
+            .context("Failed to create google calendar hub")?;
+        match (saved, deleted) {
+            (Some(saved), true) => {
+                hub.events()
+                    .delete(calendar_id, &saved.google_event_id)
+                    .doit()
+                    .await
+                    .context("Failed to delete shared google event")?;
+                sqlx::query!(
+                    "DELETE FROM `shared_events` WHERE `discord_id` = ?",
+                    discord_id
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to delete shared event in DB")?;
+            }
+            (Some(saved), false) => {
+                let recurrence_rule = fetch_recurrence_rule(&context.http, event).await?;
+                let google_event = Self::discord_event_to_google_event(
+                    event,
+                    recurrence_rule.as_ref(),
+                    &self.time_zone,
+                )
+                .await?;
+                hub.events()
+                    .update(google_event, calendar_id, &saved.google_event_id)
+                    .doit()
+                    .await
+                    .context("Failed to update shared google event")?;
+            }
+            (None, true) => {}
+            (None, false) => {
+                let recurrence_rule = fetch_recurrence_rule(&context.http, event).await?;
+                let google_event = Self::discord_event_to_google_event(
+                    event,
+                    recurrence_rule.as_ref(),
+                    &self.time_zone,
+                )
+                .await?;
+                // the shared event hosts the meeting to give every attendee the same link
+                let wants_meeting = conference::wants_meeting(event);
+                let mut inserted = None;
+                if wants_meeting {
+                    let mut meeting_event = google_event.clone();
+                    conference::request_meeting(&mut meeting_event, discord_id);
+                    match hub
+                        .events()
+                        .insert(meeting_event, calendar_id)
+                        .conference_data_version(1)
+                        .doit()
+                        .await
+                    {
+                        // the service account may not host meetings. the event is inserted without it then.
+                        Err(google_calendar3::Error::BadRequest(e)) => {
+                            warn!("Failed to request meeting of event({}) - {e}", event.id)
+                        }
+                        result => inserted = Some(result),
+                    }
+                }
+                let google_event = match inserted {
+                    Some(result) => result,
+                    None => hub.events().insert(google_event, calendar_id).doit().await,
+                }
+                .context("Failed to insert shared google event")?
+                .1;
+                let google_event_id = google_event.id.as_ref().unwrap();
+                sqlx::query!(
+                    "INSERT INTO `shared_events` (`discord_id`, `google_event_id`) VALUES (?, ?)",
+                    discord_id,
+                    google_event_id
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to insert shared event in DB")?;
+
+                if wants_meeting {
+                    self.write_meeting_link(context, event, &google_event)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn start_shared_calendar(&self, context: &Context, guild_id: GuildId) {
+        let calendar_id = match self.bootstrap_shared_calendar(context, guild_id).await {
+            Ok(calendar_id) => calendar_id,
+            Err(e) => {
+                error!("Failed to bootstrap shared calendar - {e:?}");
+                return;
+            }
+        };
+        info!("Shared calendar is {calendar_id}");
+        let _ = self.shared_calendar_id.set(calendar_id);
+
+        let events = match context.http.get_scheduled_events(guild_id.0, false).await {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Failed to get scheduled events - {e:?}");
+                return;
+            }
+        };
+        for event in &events {
+            if let Err(e) = self.sync_shared_event(context, event, false).await {
+                error!("Failed to sync shared event({}) - {e:?}", event.id);
+            }
+        }
+    }
+
+    // events and attendees are kept apart from google sync to serve ics feeds
+    async fn save_feed_event(
+        &self,
+        event: &ScheduledEvent,
+        attendees: impl Iterator<Item = i64>,
+    ) -> anyhow::Result<()> {
+        let discord_id = *event.id.as_u64() as i64;
+        let guild_id = *event.guild_id.as_u64() as i64;
+        let location = event.metadata.as_ref().map(|d| d.location.clone());
+        let start_time = event.start_time.unix_timestamp();
+        let end_time = event.end_time.map(|t| t.unix_timestamp());
+        let updated_at = chrono::Utc::now().timestamp();
+
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO `scheduled_events`
+                (`discord_id`, `guild_id`, `name`, `description`, `location`, `start_time`, `end_time`, `updated_at`)
+                VALUES
+                (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (`discord_id`) DO UPDATE SET
+                `name` = excluded.`name`,
+                `description` = excluded.`description`,
+                `location` = excluded.`location`,
+                `start_time` = excluded.`start_time`,
+                `end_time` = excluded.`end_time`,
+                `updated_at` = excluded.`updated_at`
+            "#,
+            discord_id,
+            guild_id,
+            event.name,
+            event.description,
+            location,
+            start_time,
+            end_time,
+            updated_at
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to save event for feed")?;
+        sqlx::query!(
+            "DELETE FROM `scheduled_event_attendees` WHERE `discord_id` = ?",
+            discord_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear attendees for feed")?;
+        for user_id in attendees {
+            sqlx::query!(
+                "INSERT INTO `scheduled_event_attendees` (`discord_id`, `user_id`) VALUES (?, ?)",
+                discord_id,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to save attendee for feed")?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn remove_feed_event(&self, event: &ScheduledEvent) -> anyhow::Result<()> {
+        let discord_id = *event.id.as_u64() as i64;
+
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM `scheduled_event_attendees` WHERE `discord_id` = ?",
+            discord_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete attendees for feed")?;
+        sqlx::query!(
+            "DELETE FROM `scheduled_events` WHERE `discord_id` = ?",
+            discord_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete event for feed")?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update_server_event(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<SyncSummary> {
         log::info!("Update event");
         let discord_id = *event.id.as_u64() as i64;
         let mut saved_events: HashMap<_, _> = sqlx::query!(
@@ -132,20 +663,24 @@ impl DiscordHandler {
         .map(|d| (d.user_id, d.google_event_id))
         .collect();
 
-        let users = context
-            .http
-            .get_scheduled_event_users(event.guild_id.0, event.id.0, None, None, Some(false))
-            .await
-            .context("Failed to get attendees")?;
+        let users = fetch_interested_users(&context.http, event).await?;
         log::debug!("saved_events: {saved_events:?}");
 
+        self.save_feed_event(
+            event,
+            users.iter().map(|attendee| attendee.user.id.0 as i64),
+        )
+        .await?;
+
         let hub = self
             .calendar_hub()
             .await
             .context("Failed to create google calendar hub")?;
-        let google_event = Self::discord_event_to_google_event(&event)
-            .await
-            .context("Filed to convert discord event to google event")?;
+        let recurrence_rule = fetch_recurrence_rule(&context.http, event).await?;
+        let google_event =
+            Self::discord_event_to_google_event(event, recurrence_rule.as_ref(), &self.time_zone)
+                .await
+                .context("Filed to convert discord event to google event")?;
         log::debug!("converted event: {event:?}");
         let mut update_attendees = HashMap::new();
         let new_attendees: Vec<_> = users
@@ -162,95 +697,132 @@ impl DiscordHandler {
             .collect();
         let resigned_attendees = saved_events;
         log::debug!("attendees\n\tnew: {new_attendees:?}\n\tresign: {resigned_attendees:?}\n\tupdate: {update_attendees:?}");
-        let user_calendar_map: HashMap<i64, String> = sqlx::query_builder::QueryBuilder::new(
-            "SELECT `user_id`, `google_calendar_id`
-            FROM `users`
-            WHERE
-                `google_calendar_id` IS NOT NULL
-                AND `user_id` IN ",
-        )
-        .push_tuples(
+        let user_calendar_map = fetch_user_calendars(
+            &self.db_pool,
             new_attendees
                 .iter()
                 .copied()
                 .chain(resigned_attendees.keys().copied())
                 .chain(update_attendees.keys().copied()),
-            |mut b, id| {
-                b.push_bind(id);
-            },
         )
-        .build()
-        .fetch_all(&self.db_pool)
-        .await
-        .context("Failed to get user calendars from DB")?
-        .into_iter()
-        .map(|r| (r.get(0), r.get(1)))
-        .collect();
-
-        for (user_id, event_id) in resigned_attendees {
-            if let Some(calendar_id) = user_calendar_map.get(&user_id) {
-                hub.events()
-                    .delete(calendar_id, &event_id)
-                    .doit()
-                    .await
-                    .with_context(|| format!("Failed delete google event for user({user_id})"))?;
+        .await?;
 
-                sqlx::query!(
-                    "DELETE FROM `server_events`
-                    WHERE `discord_id` = ? AND `user_id` = ?",
+        let mut tasks = Vec::new();
+        for (user_id, google_event_id) in resigned_attendees {
+            if let Some(calendar) = user_calendar_map.get(&user_id) {
+                tasks.push(SyncTask {
                     discord_id,
-                    user_id
-                )
-                .execute(&self.db_pool)
-                .await
-                .context("Failed to delete events in discord")?;
+                    user_id,
+                    calendar: calendar.clone(),
+                    operation: SyncOperation::Delete { google_event_id },
+                });
             } else {
                 log::warn!("Linked outdated google event is found. but user({user_id}) does not connected to google");
             }
         }
 
         for user_id in new_attendees {
-            if let Some(calendar_id) = user_calendar_map.get(&user_id) {
-                let event = hub
-                    .events()
-                    .insert(google_event.clone(), &calendar_id)
-                    .doit()
-                    .await
-                    .with_context(|| format!("Failed to insert new event in google(calendar - {calendar_id}) for user({user_id})"))?
-                    .1;
-                let google_event_id = event.id.as_ref().unwrap();
-                sqlx::query!(
-                    r#"
-                    INSERT INTO `server_events`
-                        (`discord_id`, `google_event_id`, `user_id`)
-                        VALUES 
-                        (?, ?, ?)
-                    "#,
+            if let Some(calendar) = user_calendar_map.get(&user_id) {
+                tasks.push(SyncTask {
                     discord_id,
-                    google_event_id,
                     user_id,
-                )
-                .execute(&self.db_pool)
-                .await
-                .context("Failed to insert google event in DB")?;
+                    calendar: calendar.clone(),
+                    operation: SyncOperation::Insert {
+                        event_id: sync_queue::new_event_id(),
+                        event: google_event.clone(),
+                    },
+                });
             } else {
                 log::info!("Google calendar is not connected. Do not create google event for user({user_id}).");
             }
         }
 
-        for (user_id, event_id) in update_attendees {
-            if let Some(calendar_id) = user_calendar_map.get(&user_id) {
-                hub.events()
-                    .update(google_event.clone(), calendar_id, &event_id)
-                    .doit()
-                    .await
-                    .with_context(|| format!("Failed update google event for user({user_id})"))?;
+        for (user_id, google_event_id) in update_attendees {
+            if let Some(calendar) = user_calendar_map.get(&user_id) {
+                tasks.push(SyncTask {
+                    discord_id,
+                    user_id,
+                    calendar: calendar.clone(),
+                    operation: SyncOperation::Update {
+                        google_event_id,
+                        event: google_event.clone(),
+                    },
+                });
             } else {
                 log::warn!("Linked google event is found. but user({user_id}) does not connected to google");
             }
         }
 
-        Ok(())
+        sync_queue::clear_queue(&self.db_pool, discord_id).await?;
+        sync_queue::apply_tasks(&self.db_pool, &hub, tasks).await
+    }
+
+    // remove google events of the discord event from every linked calendar
+    async fn remove_server_event(&self, discord_id: i64) -> anyhow::Result<SyncSummary> {
+        let saved_events = sqlx::query!(
+            "SELECT `user_id`, `google_event_id` FROM `server_events` WHERE `discord_id` = ?",
+            discord_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get saved events from DB")?;
+        let user_calendar_map = fetch_user_calendars(
+            &self.db_pool,
+            saved_events.iter().map(|saved| saved.user_id),
+        )
+        .await?;
+
+        let mut tasks = Vec::new();
+        for saved in saved_events {
+            if let Some(calendar) = user_calendar_map.get(&saved.user_id) {
+                tasks.push(SyncTask {
+                    discord_id,
+                    user_id: saved.user_id,
+                    calendar: calendar.clone(),
+                    operation: SyncOperation::Delete {
+                        google_event_id: saved.google_event_id,
+                    },
+                });
+            } else {
+                log::warn!(
+                    "Linked google event is found. but user({}) does not connected to google",
+                    saved.user_id
+                );
+                sqlx::query!(
+                    "DELETE FROM `server_events` WHERE `discord_id` = ? AND `user_id` = ?",
+                    discord_id,
+                    saved.user_id
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to delete events in discord")?;
+            }
+        }
+
+        sync_queue::clear_queue(&self.db_pool, discord_id).await?;
+        if tasks.is_empty() {
+            return Ok(SyncSummary::default());
+        }
+
+        let hub = self
+            .calendar_hub()
+            .await
+            .context("Failed to create google calendar hub")?;
+        sync_queue::apply_tasks(&self.db_pool, &hub, tasks).await
+    }
+
+    // wait for following updates of the event. returns false when superseded by a newer one.
+    async fn debounce_update(&self, event_id: ScheduledEventId) -> bool {
+        let generation = {
+            let mut generation = self.pending_updates.entry(event_id).or_insert(0);
+            *generation += 1;
+            *generation
+        };
+        tokio::time::sleep(UPDATE_DEBOUNCE).await;
+
+        self.pending_updates
+            .remove_if(&event_id, |_, latest| *latest == generation)
+            .is_some()
     }
 
     async fn update_server_event_user(
@@ -267,11 +839,27 @@ impl DiscordHandler {
             .await
             .context("Failed to get event detail")?;
 
-        self.update_server_event(context, &event).await?;
+        // user changes share the debounce with event updates. sync the whole event.
+        self.sync_updated_event(context, &event).await;
 
         Ok(())
     }
 
+    async fn sync_updated_event(&self, context: &Context, event: &ScheduledEvent) {
+        if let Err(e) = self.sync_shared_event(context, event, false).await {
+            error!("Failed to sync shared event: {e:?}");
+        }
+        if let Err(e) = self.update_server_event(context, event).await {
+            error!("Failed to handle scheduled event update: {e:?}");
+        }
+        if let Err(e) = self.record_attendance(context, event).await {
+            error!("Failed to record attendance: {e:?}");
+        }
+        if let Err(e) = self.sync_event_thread(context, event).await {
+            error!("Failed to sync event thread: {e:?}");
+        }
+    }
+
     async fn handle_register_google_command(
         &self,
         context: &Context,
@@ -321,6 +909,309 @@ impl DiscordHandler {
         Ok(())
     }
 
+    async fn handle_ics_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [reset] = option.get_options(&["reset"]);
+        let reset = reset.as_bool().unwrap_or(false);
+
+        let raw_user_id = interaction.user.id.0 as i64;
+        let new_token = Uuid::new_v4().simple().to_string();
+        let token = sqlx::query!(
+            r#"
+            INSERT INTO `users` (`user_id`, `name`, `ics_token`) VALUES (?, ?, ?)
+            ON CONFLICT (`user_id`) DO UPDATE SET
+                `ics_token` = CASE WHEN ? THEN excluded.`ics_token` ELSE coalesce(`users`.`ics_token`, excluded.`ics_token`) END
+            RETURNING `ics_token` AS "ics_token!: String"
+            "#,
+            raw_user_id,
+            interaction.user.name,
+            new_token,
+            reset
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to issue ics token")?
+        .ics_token;
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| {
+                        b.content(format!(
+                            "참가하는 이벤트를 아래 주소로 구독할 수 있습니다. 주소가 유출되었다면 reset 옵션으로 다시 발급하세요.\n{}/user/{}/events.ics?token={}",
+                            self.web_prefix, raw_user_id, token
+                        ))
+                        .ephemeral(true)
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn unregister_google(&self, user_id: i64, delete_events: bool) -> anyhow::Result<()> {
+        let calendar_id = sqlx::query!(
+            "SELECT `google_calendar_id` FROM `users` WHERE `user_id` = ?",
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get google calendar id from DB")?
+        .and_then(|record| record.google_calendar_id);
+
+        if let (Some(calendar_id), true) = (&calendar_id, delete_events) {
+            let event_ids = sqlx::query!(
+                "SELECT `google_event_id` FROM `server_events` WHERE `user_id` = ?",
+                user_id
+            )
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to get synced events from DB")?;
+
+            let hub = self
+                .calendar_hub()
+                .await
+                .context("Failed to create google calendar hub")?;
+            for record in event_ids {
+                // events could be already removed by the user
+                if let Err(e) = hub
+                    .events()
+                    .delete(calendar_id, &record.google_event_id)
+                    .doit()
+                    .await
+                {
+                    log::warn!(
+                        "Failed to delete google event({}) of user({user_id}) - {e:?}",
+                        record.google_event_id
+                    );
+                }
+            }
+        }
+
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!(
+            "UPDATE `users`
+            SET `google_calendar_id` = NULL, `google_calendar_acl_id` = NULL
+            WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to clear google calendar of user")?;
+        sqlx::query!("DELETE FROM `server_events` WHERE `user_id` = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete synced events in DB")?;
+        sqlx::query!(
+            "DELETE FROM `google_sync_queue` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete pending google syncs in DB")?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // synced events are left in the calendar of the user
+    async fn forget_user_records(&self, user_id: i64) -> anyhow::Result<Vec<String>> {
+        let synced = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM `server_events` WHERE `user_id` = ?"#,
+            user_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to count synced events")?;
+        self.unregister_google(user_id, false).await?;
+
+        let mut tx = self.db_pool.begin().await?;
+        let attendance = sqlx::query!(
+            "DELETE FROM `event_attendance` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete attendance")?
+        .rows_affected();
+        sqlx::query!(
+            "DELETE FROM `scheduled_event_attendees` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete event attendees")?;
+        let preference = sqlx::query!(
+            "DELETE FROM `event_notify_preferences` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete notify preference")?
+        .rows_affected();
+        sqlx::query!(
+            "DELETE FROM `event_user_reminders` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete sent reminders")?;
+        sqlx::query!("DELETE FROM `sync_log` WHERE `user_id` = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete sync log")?;
+        tx.commit().await?;
+        // watch channels are closed by the next renewal as the calendar is gone
+
+        let mut removed = Vec::new();
+        if synced > 0 {
+            removed.push(format!("캘린더 동기화 기록 {synced}건"));
+        }
+        if attendance > 0 {
+            removed.push(format!("이벤트 참석 기록 {attendance}건"));
+        }
+        if preference > 0 {
+            removed.push("이벤트 알림 설정".to_string());
+        }
+
+        Ok(removed)
+    }
+
+    async fn export_user_records(
+        &self,
+        user_id: i64,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        let synced = sqlx::query!(
+            "SELECT `discord_id`, `google_event_id` FROM `server_events` WHERE `user_id` = ?",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get synced events")?;
+        let attendance = sqlx::query!(
+            r#"SELECT `event_id`, `interested` AS "interested: bool", `present` AS "present: bool"
+            FROM `event_attendance` WHERE `user_id` = ?"#,
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get attendance")?;
+        let preference = sqlx::query!(
+            r#"SELECT `dm` AS "dm: bool", `lead_minutes` FROM `event_notify_preferences`
+            WHERE `user_id` = ?"#,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get notify preference")?;
+
+        let mut export = serde_json::Map::new();
+        export.insert(
+            "synced_events".to_string(),
+            synced
+                .into_iter()
+                .map(|row| {
+                    serde_json::json!({
+                        "discord_event_id": row.discord_id.to_string(),
+                        "calendar_event_id": row.google_event_id,
+                    })
+                })
+                .collect(),
+        );
+        export.insert(
+            "event_attendance".to_string(),
+            attendance
+                .into_iter()
+                .map(|row| {
+                    serde_json::json!({
+                        "event_id": row.event_id.to_string(),
+                        "interested": row.interested,
+                        "present": row.present,
+                    })
+                })
+                .collect(),
+        );
+        export.insert(
+            "event_notify_preference".to_string(),
+            preference
+                .map(|row| {
+                    serde_json::json!({
+                        "dm": row.dm,
+                        "lead_minutes": row.lead_minutes,
+                    })
+                })
+                .into(),
+        );
+
+        Ok(export)
+    }
+
+    async fn handle_unregister_google_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [delete_events] = option.get_options(&["delete_events"]);
+        let delete_events = delete_events.as_bool().unwrap_or(false);
+
+        // deleting events takes longer than the interaction allows
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|b| b.ephemeral(true))
+            })
+            .await?;
+
+        let content = match self
+            .unregister_google(interaction.user.id.0 as i64, delete_events)
+            .await
+        {
+            Ok(()) => "해제 완료. 캘린더 공유 설정에서 후타바ID를 직접 제거할 수 있습니다.",
+            Err(e) => {
+                error!("Failed to unregister google calendar - {e:?}");
+                "해제 실패. 오류 발생"
+            }
+        };
+
+        interaction
+            .edit_original_interaction_response(context, |b| b.content(content))
+            .await?;
+        Ok(())
+    }
+
+    async fn handle_info_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let shared_calendar = match self.shared_calendar_id.get() {
+            Some(calendar_id) => format!(
+                "`{calendar_id}`\nhttps://calendar.google.com/calendar/render?cid={calendar_id}"
+            ),
+            None => "준비되지 않음".to_string(),
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| {
+                        b.embed(|e| {
+                            e.title("이벤트 캘린더")
+                                .field("공유 캘린더", shared_calendar, false)
+                                .field("후타바ID", &self.service_account.client_email, false)
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await?;
+        Ok(())
+    }
+
     async fn handle_register_google_calendar_modal_submit(
         &self,
         modal: &ModalSubmitInteraction,
@@ -339,14 +1230,151 @@ impl DiscordHandler {
             .ok_or_else(|| anyhow::anyhow!("Could not find required field"))?;
 
         let raw_user_id = modal.user.id.0 as i64;
+        let mut tx = self.db_pool.begin().await?;
+        Self::forget_events_of_other_backend(&mut tx, raw_user_id, "google").await?;
         sqlx::query!(
-            "UPDATE `users` SET `google_calendar_id` = ? WHERE `user_id` = ?",
+            "UPDATE `users`
+            SET `google_calendar_id` = ?, `calendar_backend` = 'google'
+            WHERE `user_id` = ?",
             calendar_id,
             raw_user_id
         )
-        .execute(&self.db_pool)
+        .execute(&mut *tx)
         .await
         .context("Failed to store google calendar id to DB")?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // synced events are tracked per calendar. stop tracking events of the previous backend.
+    pub(crate) async fn forget_events_of_other_backend(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        user_id: i64,
+        backend: &str,
+    ) -> anyhow::Result<()> {
+        let previous = sqlx::query_scalar!(
+            "SELECT `calendar_backend` FROM `users` WHERE `user_id` = ?",
+            user_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .context("Failed to get calendar backend of user")?;
+        if previous.as_deref() == Some(backend) {
+            return Ok(());
+        }
+
+        sqlx::query!("DELETE FROM `server_events` WHERE `user_id` = ?", user_id)
+            .execute(&mut **tx)
+            .await
+            .context("Failed to delete synced events in DB")?;
+        sqlx::query!(
+            "DELETE FROM `google_sync_queue` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut **tx)
+        .await
+        .context("Failed to delete pending syncs in DB")?;
+
+        Ok(())
+    }
+
+    async fn handle_register_caldav_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        // the password is not stored without the key
+        if let Err(e) = crate::user::check_token_key() {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.content(format!("등록 실패. {e}")).ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::Modal)
+                    .interaction_response_data(|b| {
+                        b.custom_id("register_caldav_calendar")
+                            .title("CalDAV 캘린더 등록")
+                            .components(|b| {
+                                b.create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("캘린더 URL")
+                                            .required(true)
+                                            .custom_id("url")
+                                            .placeholder("https://cloud.example.com/remote.php/dav/calendars/user/personal/")
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                                .create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("사용자 이름")
+                                            .required(true)
+                                            .custom_id("username")
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                                .create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("비밀번호")
+                                            .required(true)
+                                            .custom_id("password")
+                                            .placeholder("계정 비밀번호 대신 앱 비밀번호를 사용하세요.")
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                            })
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn handle_register_caldav_calendar_modal_submit(
+        &self,
+        modal: &ModalSubmitInteraction,
+    ) -> anyhow::Result<()> {
+        let (Some(url), Some(username), Some(password)) = (
+            modal_input(modal, "url"),
+            modal_input(modal, "username"),
+            modal_input(modal, "password"),
+        ) else {
+            anyhow::bail!("Could not find required field");
+        };
+        caldav_client(&url).await?;
+        let password = crate::user::encrypt_secret(&password)?;
+
+        let raw_user_id = modal.user.id.0 as i64;
+        let mut tx = self.db_pool.begin().await?;
+        Self::forget_events_of_other_backend(&mut tx, raw_user_id, "caldav").await?;
+        sqlx::query!(
+            "UPDATE `users`
+            SET
+                `calendar_backend` = 'caldav',
+                `caldav_url` = ?,
+                `caldav_username` = ?,
+                `caldav_password` = ?,
+                `caldav_password_encrypted` = TRUE
+            WHERE `user_id` = ?",
+            url,
+            username,
+            password,
+            raw_user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to store caldav calendar to DB")?;
+        tx.commit().await?;
 
         Ok(())
     }
@@ -359,12 +1387,156 @@ impl SubApplication for DiscordHandler {
         let command = ApplicationCommand {
             name: COMMAND_NAME,
             description: "event setting",
-            options: vec![ApplicationCommandOption {
-                kind: ApplicationCommandOptionType::SubCommand,
-                name: "register_google",
-                description: "register google calendar",
-                ..Default::default()
-            }],
+            options: vec![
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "register_google",
+                    description: "register google calendar",
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "register_caldav",
+                    description: "register caldav calendar instead of google calendar",
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "unregister_google",
+                    description: "unregister google calendar",
+                    options: vec![ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Boolean,
+                        name: "delete_events",
+                        description: "delete synced events from the calendar",
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "info",
+                    description: "show calendar information",
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "ics",
+                    description: "issue ics feed url of your events",
+                    options: vec![ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Boolean,
+                        name: "reset",
+                        description: "issue new url and invalidate the previous one",
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "create",
+                    description: "create new event",
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "resync",
+                    description: "reconcile google calendars with every scheduled event",
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "notify",
+                    description: "set DM reminders of events you are interested in",
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Boolean,
+                            name: "dm",
+                            description: "receive reminders by DM",
+                            required: Some(true),
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Integer,
+                            name: "minutes",
+                            description: "remind this many minutes before events start",
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "attendance",
+                    description: "show attendance of events",
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::User,
+                            name: "user",
+                            description: "show attendance counts of the user",
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::String,
+                            name: "event",
+                            description: "filter events by name",
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "subscribe",
+                    description: "get mentioned when a new event is created",
+                    options: vec![ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Boolean,
+                        name: "enable",
+                        description: "false to unsubscribe",
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "countdown",
+                    description: "post a countdown message of the event",
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::String,
+                            name: "event",
+                            description: "id or link of the event",
+                            required: Some(true),
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Channel,
+                            name: "channel",
+                            description: "channel to post. the current channel by default",
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "synclog",
+                    description: "show recent calendar sync failures",
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::User,
+                            name: "user",
+                            description: "show syncs of the user only",
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::Boolean,
+                            name: "all",
+                            description: "include succeeded syncs",
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            ],
         };
 
         context
@@ -375,23 +1547,46 @@ impl SubApplication for DiscordHandler {
             )
             .await
             .unwrap();
+
+        // the key of google tokens is ready after the user handler is created
+        if let Err(e) = encrypt_plain_caldav_passwords(&self.db_pool).await {
+            error!("Failed to encrypt caldav passwords - {e:?}");
+        }
+
+        self.start_shared_calendar(context, guild_id).await;
+        self.start_source_sync(context, guild_id);
+        self.start_reminder(context, guild_id);
+        self.start_sync_retry();
+        self.start_calendar_watch(context, guild_id);
+        self.start_countdown(context, guild_id);
     }
 
     async fn modal_submit(&self, context: &Context, modal: &ModalSubmitInteraction) -> bool {
-        if modal.data.custom_id == "register_google_calendar" {
-            if let Err(e) = self
-                .handle_register_google_calendar_modal_submit(modal)
-                .await
-            {
-                error!(
-                    "Error occurred while handling register google calendar modal submit - {e:?}"
-                );
+        let register_result = match modal.data.custom_id.as_str() {
+            "register_google_calendar" => Some(
+                self.handle_register_google_calendar_modal_submit(modal)
+                    .await,
+            ),
+            "register_caldav_calendar" => Some(
+                self.handle_register_caldav_calendar_modal_submit(modal)
+                    .await,
+            ),
+            _ => None,
+        };
+        if let Some(result) = register_result {
+            if let Err(e) = result {
+                error!("Error occurred while handling register calendar modal submit - {e:?}");
+                let content = if let Some(reason) = e.downcast_ref::<RejectedUrl>() {
+                    format!("등록 실패. {reason}")
+                } else if let Some(reason) = e.downcast_ref::<crate::user::MissingTokenKey>() {
+                    format!("등록 실패. {reason}")
+                } else {
+                    "등록 실패. 오류 발생".to_string()
+                };
                 if let Err(e) = modal
                     .create_interaction_response(context, |b| {
                         b.kind(InteractionResponseType::ChannelMessageWithSource)
-                            .interaction_response_data(|b| {
-                                b.content("등록 실패. 오류 발생").ephemeral(true)
-                            })
+                            .interaction_response_data(|b| b.content(content).ephemeral(true))
                     })
                     .await
                 {
@@ -412,6 +1607,25 @@ impl SubApplication for DiscordHandler {
             return true;
         }
 
+        if modal.data.custom_id == CREATE_EVENT_MODAL_ID {
+            if let Err(e) = self.handle_create_event_modal_submit(context, modal).await {
+                error!("Error occurred while handling create event modal submit - {e:?}");
+                if let Err(e) = modal
+                    .create_interaction_response(context, |b| {
+                        b.kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|b| {
+                                b.content("이벤트 생성 실패. 오류 발생").ephemeral(true)
+                            })
+                    })
+                    .await
+                {
+                    error!("Failed to send response about handling modal submit failure - {e:?}");
+                }
+            }
+
+            return true;
+        }
+
         false
     }
 
@@ -431,6 +1645,44 @@ impl SubApplication for DiscordHandler {
                 self.handle_register_google_command(context, interaction, option)
                     .await
             }
+            "register_caldav" => {
+                self.handle_register_caldav_command(context, interaction, option)
+                    .await
+            }
+            "unregister_google" => {
+                self.handle_unregister_google_command(context, interaction, option)
+                    .await
+            }
+            "info" => self.handle_info_command(context, interaction, option).await,
+            "ics" => self.handle_ics_command(context, interaction, option).await,
+            "create" => {
+                self.handle_create_command(context, interaction, option)
+                    .await
+            }
+            "resync" => {
+                self.handle_resync_command(context, interaction, option)
+                    .await
+            }
+            "notify" => {
+                self.handle_notify_command(context, interaction, option)
+                    .await
+            }
+            "attendance" => {
+                self.handle_attendance_command(context, interaction, option)
+                    .await
+            }
+            "subscribe" => {
+                self.handle_subscribe_command(context, interaction, option)
+                    .await
+            }
+            "countdown" => {
+                self.handle_countdown_command(context, interaction, option)
+                    .await
+            }
+            "synclog" => {
+                self.handle_synclog_command(context, interaction, option)
+                    .await
+            }
             _ => unsafe { std::hint::unreachable_unchecked() },
         } {
             error!("Failed to handle message: {:?}", e);
@@ -439,16 +1691,72 @@ impl SubApplication for DiscordHandler {
         true
     }
 
+    async fn forget_user(&self, user_id: UserId) -> anyhow::Result<Vec<String>> {
+        self.forget_user_records(user_id.0 as i64).await
+    }
+
+    async fn export_user(
+        &self,
+        user_id: UserId,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        self.export_user_records(user_id.0 as i64).await
+    }
+
+    fn llm_tools(&self) -> Vec<crate::llm::LlmTool> {
+        tools::declarations()
+    }
+
+    async fn call_llm_tool(
+        &self,
+        context: &Context,
+        guild_id: GuildId,
+        name: &str,
+        _args: &serde_json::Value,
+    ) -> Option<anyhow::Result<serde_json::Value>> {
+        tools::call(context, guild_id, name).await
+    }
+
     async fn guild_scheduled_event(&self, context: &Context, event: ScheduledEventUpdated<'_>) {
         match event {
-            ScheduledEventUpdated::Created(event)
-            | ScheduledEventUpdated::Updated(event)
-            | ScheduledEventUpdated::Deleted(event) => {
-                if let Err(e) = self.update_server_event(context, event).await {
-                    error!("Failed to handle scheduled event update: {e:?}");
+            ScheduledEventUpdated::Created(event) => {
+                // announced before the debounce which could drop the creation
+                if let Err(e) = self.announce_new_event(context, event).await {
+                    error!("Failed to announce new event: {e:?}");
+                }
+                if let Err(e) = self.warn_conflicts(context, event).await {
+                    error!("Failed to warn conflicts of new event: {e:?}");
+                }
+                if !self.debounce_update(event.id).await {
+                    return;
+                }
+                self.sync_updated_event(context, event).await;
+            }
+            ScheduledEventUpdated::Updated(event) => {
+                if !self.debounce_update(event.id).await {
+                    return;
+                }
+                self.sync_updated_event(context, event).await;
+            }
+            ScheduledEventUpdated::Deleted(event) => {
+                // drop pending updates
+                self.pending_updates.remove(&event.id);
+                if let Err(e) = self.sync_shared_event(context, event, true).await {
+                    error!("Failed to sync shared event: {e:?}");
+                }
+                if let Err(e) = self.remove_server_event(*event.id.as_u64() as i64).await {
+                    error!("Failed to handle scheduled event deletion: {e:?}");
+                }
+                if let Err(e) = self.remove_feed_event(event).await {
+                    error!("Failed to remove event from feed: {e:?}");
+                }
+                if let Err(e) = self.close_event_thread(context, event).await {
+                    error!("Failed to close event thread: {e:?}");
                 }
             }
             ScheduledEventUpdated::UserAdded(event) => {
+                if !self.debounce_update(event.scheduled_event_id).await {
+                    return;
+                }
                 if let Err(e) = self
                     .update_server_event_user(
                         context,
@@ -463,6 +1771,9 @@ impl SubApplication for DiscordHandler {
                 }
             }
             ScheduledEventUpdated::UserRemoved(event) => {
+                if !self.debounce_update(event.scheduled_event_id).await {
+                    return;
+                }
                 if let Err(e) = self
                     .update_server_event_user(
                         context,
@@ -479,3 +1790,127 @@ impl SubApplication for DiscordHandler {
         }
     }
 }
+
+pub fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new()
+        .route(
+            "/google/notify",
+            axum::routing::post(watch::watch_notification),
+        )
+        .route("/synclog", axum::routing::get(sync_log::sync_log_page))
+        .route("/upcoming", axum::routing::get(upcoming::upcoming_page))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, Query, State};
+    use serde::Deserialize;
+    use serenity::{http::HttpBuilder, model::guild::ScheduledEvent};
+
+    use super::fetch_interested_users;
+
+    const GUILD_ID: u64 = 1;
+    const EVENT_ID: u64 = 2;
+
+    #[derive(Deserialize)]
+    struct UsersQuery {
+        limit: u64,
+        after: Option<u64>,
+    }
+
+    // users of the event are numbered from 1, and `after` of every request is kept
+    #[derive(Clone)]
+    struct Stub {
+        user_count: u64,
+        requests: Arc<Mutex<Vec<Option<u64>>>>,
+    }
+
+    async fn users(
+        State(stub): State<Stub>,
+        Path((_version, guild_id, event_id)): Path<(String, u64, u64)>,
+        Query(query): Query<UsersQuery>,
+    ) -> axum::Json<serde_json::Value> {
+        assert_eq!((guild_id, event_id), (GUILD_ID, EVENT_ID));
+        stub.requests.lock().unwrap().push(query.after);
+
+        let first = query.after.unwrap_or(0) + 1;
+        let last = (first + query.limit).min(stub.user_count + 1);
+        axum::Json(serde_json::Value::Array(
+            (first..last)
+                .map(|id| {
+                    serde_json::json!({
+                        "guild_scheduled_event_id": EVENT_ID.to_string(),
+                        "user": {
+                            "id": id.to_string(),
+                            "username": format!("user{id}"),
+                            "discriminator": "0000",
+                            "avatar": null,
+                        },
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    // fetches users of the stubbed event, with `after` of each request made
+    async fn fetch(user_count: u64) -> (Vec<u64>, Vec<Option<u64>>) {
+        let stub = Stub {
+            user_count,
+            requests: Default::default(),
+        };
+        let router = axum::Router::new()
+            .route(
+                "/api/:version/guilds/:guild_id/scheduled-events/:event_id/users",
+                axum::routing::get(users),
+            )
+            .with_state(stub.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let http = HttpBuilder::new("Bot token")
+            .proxy(format!("http://{address}/"))
+            .unwrap()
+            .ratelimiter_disabled(true)
+            .build();
+        let event = serde_json::from_value::<ScheduledEvent>(serde_json::json!({
+            "id": EVENT_ID.to_string(),
+            "guild_id": GUILD_ID.to_string(),
+            "name": "event",
+            "scheduled_start_time": "2024-01-01T00:00:00Z",
+            "status": 1,
+            "entity_type": 3,
+        }))
+        .unwrap();
+
+        let users = fetch_interested_users(&http, &event).await.unwrap();
+        let requests = stub.requests.lock().unwrap().clone();
+        (
+            users.into_iter().map(|user| user.user.id.0).collect(),
+            requests,
+        )
+    }
+
+    #[tokio::test]
+    async fn fetch_interested_users_follows_pages() {
+        let (users, requests) = fetch(250).await;
+        assert_eq!(users, (1..=250).collect::<Vec<_>>());
+        assert_eq!(requests, vec![None, Some(100), Some(200)]);
+    }
+
+    #[tokio::test]
+    async fn fetch_interested_users_stops_at_empty_page() {
+        let (users, requests) = fetch(100).await;
+        assert_eq!(users, (1..=100).collect::<Vec<_>>());
+        assert_eq!(requests, vec![None, Some(100)]);
+    }
+
+    #[tokio::test]
+    async fn fetch_interested_users_without_users() {
+        let (users, requests) = fetch(0).await;
+        assert!(users.is_empty());
+        assert_eq!(requests, vec![None]);
+    }
+}
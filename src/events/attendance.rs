@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use log::info;
+use serenity::{
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            InteractionResponseType,
+        },
+        prelude::{ScheduledEvent, ScheduledEventStatus},
+    },
+    prelude::Context,
+};
+
+use super::{fetch_interested_users, DiscordHandler};
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+const MAX_EVENTS: i64 = 10;
+
+impl DiscordHandler {
+    // snapshot interested users and users in the event channel when the event goes live
+    pub(super) async fn record_attendance(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<()> {
+        if !matches!(event.status, ScheduledEventStatus::Active) {
+            return Ok(());
+        }
+
+        let discord_id = *event.id.as_u64() as i64;
+        let guild_id = *event.guild_id.as_u64() as i64;
+        // recurring events keep the id. start time distinguishes occurrences.
+        let start_time = event.start_time.unix_timestamp();
+
+        let mut attendance = BTreeMap::new();
+        let interested = fetch_interested_users(&context.http, event).await?;
+        for attendee in interested {
+            attendance
+                .entry(attendee.user.id.0 as i64)
+                .or_insert((false, false))
+                .0 = true;
+        }
+        if let Some(channel_id) = event.channel_id {
+            let present = context
+                .cache
+                .guild_field(event.guild_id, |guild| {
+                    guild
+                        .voice_states
+                        .values()
+                        .filter(|state| state.channel_id == Some(channel_id))
+                        .map(|state| state.user_id.0 as i64)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            for user_id in present {
+                attendance.entry(user_id).or_insert((false, false)).1 = true;
+            }
+        }
+
+        let mut tx = self.db_pool.begin().await?;
+        let Some(event_id) = sqlx::query_scalar!(
+            r#"INSERT OR IGNORE INTO `attended_events` (`discord_id`, `guild_id`, `name`, `start_time`)
+            VALUES (?, ?, ?, ?)
+            RETURNING `id` AS "id!: i64""#,
+            discord_id,
+            guild_id,
+            event.name,
+            start_time
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to save attended event")?
+        else {
+            // already recorded
+            return Ok(());
+        };
+
+        info!(
+            "Record attendance of event({}) - {} users",
+            event.id,
+            attendance.len()
+        );
+        for (user_id, (interested, present)) in attendance {
+            sqlx::query!(
+                "INSERT INTO `event_attendance` (`event_id`, `user_id`, `interested`, `present`)
+                VALUES (?, ?, ?, ?)",
+                event_id,
+                user_id,
+                interested,
+                present
+            )
+            .execute(&mut *tx)
+            .await
+            .context("Failed to save attendance")?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub(super) async fn handle_attendance_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [user, event] = option.get_options(&["user", "event"]);
+
+        if let Some(user) = user {
+            let user_id: i64 = user.as_str().context("Invalid user")?.parse()?;
+            let stat = sqlx::query!(
+                r#"SELECT
+                    coalesce(sum(`interested`), 0) AS "interested!: i64",
+                    coalesce(sum(`present`), 0) AS "present!: i64",
+                    coalesce(sum(`interested` AND `present`), 0) AS "showed!: i64"
+                FROM `event_attendance`
+                WHERE `user_id` = ?"#,
+                user_id
+            )
+            .fetch_one(&self.db_pool)
+            .await
+            .context("Failed to get attendance of user")?;
+            let ratio = if stat.interested > 0 {
+                format!(
+                    "{:.1}%",
+                    stat.showed as f64 * 100.0 / stat.interested as f64
+                )
+            } else {
+                "-".to_string()
+            };
+
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.embed(|e| {
+                                e.title("이벤트 참석 기록")
+                                    .description(format!("<@{user_id}>"))
+                                    .field("관심 표시", format!("{}회", stat.interested), true)
+                                    .field("실제 참석", format!("{}회", stat.present), true)
+                                    .field("관심 표시 후 참석률", ratio, true)
+                            })
+                            .ephemeral(true)
+                        })
+                })
+                .await?;
+
+            return Ok(());
+        }
+
+        let name = event.as_str();
+        let events = sqlx::query!(
+            r#"SELECT
+                `attended_events`.`name`,
+                `attended_events`.`start_time`,
+                coalesce(sum(`event_attendance`.`interested`), 0) AS "interested!: i64",
+                coalesce(sum(`event_attendance`.`present`), 0) AS "present!: i64"
+            FROM `attended_events`
+            LEFT JOIN `event_attendance` ON `event_attendance`.`event_id` = `attended_events`.`id`
+            WHERE ? IS NULL OR `attended_events`.`name` LIKE '%' || ? || '%'
+            GROUP BY `attended_events`.`id`
+            ORDER BY `attended_events`.`start_time` DESC
+            LIMIT ?"#,
+            name,
+            name,
+            MAX_EVENTS
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get attendance of events")?;
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| {
+                        b.embed(|e| {
+                            e.title("이벤트 참석 현황");
+                            if events.is_empty() {
+                                e.description("기록 없음");
+                            }
+                            for event in &events {
+                                e.field(
+                                    format!("{} (<t:{}:d>)", event.name, event.start_time),
+                                    format!(
+                                        "관심 {}명 · 참석 {}명",
+                                        event.interested, event.present
+                                    ),
+                                    false,
+                                );
+                            }
+                            e
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,554 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use google_calendar3::{
+    api::{Event as GoogleEvent, EventDateTime},
+    hyper::client::HttpConnector,
+    hyper_rustls::HttpsConnector,
+    CalendarHub,
+};
+use sqlx::{Row, SqlitePool};
+
+use super::ics::{escape_text, format_time, push_line};
+
+pub(super) type GoogleHub = CalendarHub<HttpsConnector<HttpConnector>>;
+
+// where events of a user are synced
+#[derive(Debug, Clone)]
+pub(super) enum UserCalendar {
+    Google {
+        calendar_id: String,
+    },
+    CalDav {
+        url: String,
+        username: String,
+        password: String,
+    },
+    // tokens are looked up on each request as they expire in an hour
+    Outlook {
+        user_id: i64,
+    },
+}
+
+#[async_trait]
+pub(super) trait CalendarBackend: Send + Sync {
+    // returns id of the created event. event_id is kept while retrying, so an
+    // insert which already reached the calendar is not duplicated.
+    async fn insert_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<String>;
+    async fn update_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<()>;
+    async fn delete_event(&self, event_id: &str) -> anyhow::Result<()>;
+}
+
+struct GoogleBackend<'a> {
+    hub: &'a GoogleHub,
+    calendar_id: &'a str,
+}
+
+#[async_trait]
+impl CalendarBackend for GoogleBackend<'_> {
+    async fn insert_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<String> {
+        let event = GoogleEvent {
+            id: Some(event_id.to_string()),
+            ..event.clone()
+        };
+        match self
+            .hub
+            .events()
+            .insert(event, self.calendar_id)
+            .doit()
+            .await
+        {
+            Ok(_) => {}
+            // inserted by the previous attempt
+            Err(google_calendar3::Error::BadRequest(body)) if body["error"]["code"] == 409 => {}
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to insert new event in google(calendar - {})",
+                        self.calendar_id
+                    )
+                })
+            }
+        }
+
+        Ok(event_id.to_string())
+    }
+
+    async fn update_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<()> {
+        self.hub
+            .events()
+            .update(event.clone(), self.calendar_id, event_id)
+            .doit()
+            .await
+            .context("Failed to update google event")?;
+
+        Ok(())
+    }
+
+    async fn delete_event(&self, event_id: &str) -> anyhow::Result<()> {
+        self.hub
+            .events()
+            .delete(self.calendar_id, event_id)
+            .doit()
+            .await
+            .context("Failed to delete google event")?;
+
+        Ok(())
+    }
+}
+
+struct CalDavBackend<'a> {
+    url: &'a str,
+    username: &'a str,
+    password: &'a str,
+}
+
+fn push_date_time(ics: &mut String, name: &str, date_time: &EventDateTime) {
+    if let Some(date_time) = date_time.date_time {
+        push_line(
+            ics,
+            &format!("{name}:{}", format_time(date_time.timestamp())),
+        );
+    } else if let Some(date) = date_time.date {
+        push_line(ics, &format!("{name};VALUE=DATE:{}", date.format("%Y%m%d")));
+    }
+}
+
+fn render_event(uid: &str, event: &GoogleEvent) -> String {
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//futaba//events//KO");
+    push_line(&mut ics, "BEGIN:VEVENT");
+    push_line(&mut ics, &format!("UID:{uid}"));
+    push_line(
+        &mut ics,
+        &format!("DTSTAMP:{}", format_time(chrono::Utc::now().timestamp())),
+    );
+    if let Some(start) = &event.start {
+        push_date_time(&mut ics, "DTSTART", start);
+    }
+    if let Some(end) = &event.end {
+        push_date_time(&mut ics, "DTEND", end);
+    }
+    for rule in event.recurrence.iter().flatten() {
+        push_line(&mut ics, rule);
+    }
+    if let Some(status) = &event.status {
+        push_line(&mut ics, &format!("STATUS:{}", status.to_uppercase()));
+    }
+    if let Some(summary) = &event.summary {
+        push_line(&mut ics, &format!("SUMMARY:{}", escape_text(summary)));
+    }
+    if let Some(description) = &event.description {
+        push_line(
+            &mut ics,
+            &format!("DESCRIPTION:{}", escape_text(description)),
+        );
+    }
+    if let Some(location) = &event.location {
+        push_line(&mut ics, &format!("LOCATION:{}", escape_text(location)));
+    }
+    if let Some(url) = event.source.as_ref().and_then(|source| source.url.as_ref()) {
+        push_line(&mut ics, &format!("URL:{url}"));
+    }
+    push_line(&mut ics, "END:VEVENT");
+    push_line(&mut ics, "END:VCALENDAR");
+
+    ics
+}
+
+// reason a caldav url given by a user is not requested, told to the user
+#[derive(Debug)]
+pub(super) struct RejectedUrl(&'static str);
+
+impl std::fmt::Display for RejectedUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for RejectedUrl {}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // shared address space of carrier-grade NAT
+        || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64))
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_v4(ip);
+            }
+            let first = ip.segments()[0];
+            !(ip == Ipv6Addr::UNSPECIFIED
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local
+                || first & 0xfe00 == 0xfc00
+                // link-local
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+// urls are given by users, so only https to public hosts is requested.
+// checked before every request, as where the host resolves to can change.
+// the client connects to the checked addresses only, not to resolve the host
+// again into another address.
+pub(super) async fn caldav_client(url: &str) -> Result<reqwest::Client, RejectedUrl> {
+    let url = reqwest::Url::parse(url).map_err(|_| RejectedUrl("올바른 URL이 아닙니다."))?;
+    if url.scheme() != "https" {
+        return Err(RejectedUrl("https URL만 사용할 수 있습니다."));
+    }
+    let host = url
+        .host_str()
+        .ok_or(RejectedUrl("URL에 호스트가 없습니다."))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    // redirects could lead to hosts not checked
+    let builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+    let builder = match host.parse::<IpAddr>() {
+        Ok(ip) if is_public(ip) => builder,
+        Ok(_) => return Err(RejectedUrl("내부 네트워크의 주소는 사용할 수 없습니다.")),
+        Err(_) => {
+            let addresses = tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|_| RejectedUrl("호스트를 찾을 수 없습니다."))?
+                .collect::<Vec<_>>();
+            if addresses.is_empty() || !addresses.iter().all(|address| is_public(address.ip())) {
+                return Err(RejectedUrl("내부 네트워크의 주소는 사용할 수 없습니다."));
+            }
+            builder.resolve_to_addrs(host, &addresses)
+        }
+    };
+
+    Ok(builder.build().expect("Failed to build HTTP client"))
+}
+
+impl CalDavBackend<'_> {
+    fn event_url(&self, uid: &str) -> String {
+        format!("{}/{uid}.ics", self.url.trim_end_matches('/'))
+    }
+
+    async fn put_event(&self, uid: &str, event: &GoogleEvent, create: bool) -> anyhow::Result<()> {
+        let mut request = caldav_client(self.url)
+            .await?
+            .put(self.event_url(uid))
+            .basic_auth(self.username, Some(self.password))
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "text/calendar; charset=utf-8",
+            )
+            .body(render_event(uid, event));
+        if create {
+            request = request.header(reqwest::header::IF_NONE_MATCH, "*");
+        }
+        let response = request
+            .send()
+            .await
+            .context("Failed to send caldav request")?;
+        // created by the previous attempt
+        if !(create && response.status() == reqwest::StatusCode::PRECONDITION_FAILED) {
+            response
+                .error_for_status()
+                .context("Failed to put caldav event")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for CalDavBackend<'_> {
+    async fn insert_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<String> {
+        let uid = format!("{event_id}@futaba");
+        self.put_event(&uid, event, true).await?;
+
+        Ok(uid)
+    }
+
+    async fn update_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<()> {
+        self.put_event(event_id, event, false).await
+    }
+
+    async fn delete_event(&self, event_id: &str) -> anyhow::Result<()> {
+        let response = caldav_client(self.url)
+            .await?
+            .delete(self.event_url(event_id))
+            .basic_auth(self.username, Some(self.password))
+            .send()
+            .await
+            .context("Failed to send caldav request")?;
+        // already removed by the user
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .context("Failed to delete caldav event")?;
+        }
+
+        Ok(())
+    }
+}
+
+const GRAPH_EVENTS_URL: &str = "https://graph.microsoft.com/v1.0/me/events";
+
+struct OutlookBackend<'a> {
+    client: reqwest::Client,
+    db_pool: &'a SqlitePool,
+    user_id: i64,
+}
+
+// graph does not take time zone offsets in date times
+fn graph_date_time(date_time: &EventDateTime) -> Option<serde_json::Value> {
+    let date_time = if let Some(date_time) = date_time.date_time {
+        date_time.naive_utc()
+    } else {
+        date_time.date?.and_hms_opt(0, 0, 0)?
+    };
+
+    Some(serde_json::json!({
+        "dateTime": date_time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "timeZone": "UTC",
+    }))
+}
+
+// recurrence is not mirrored. graph uses its own pattern format instead of RRULE.
+fn render_graph_event(event: &GoogleEvent) -> serde_json::Value {
+    serde_json::json!({
+        "subject": event.summary,
+        "body": {
+            "contentType": "text",
+            "content": event.description.clone().unwrap_or_default(),
+        },
+        "start": event.start.as_ref().and_then(graph_date_time),
+        "end": event.end.as_ref().and_then(graph_date_time),
+        "isAllDay": event.start.as_ref().map_or(false, |start| start.date.is_some()),
+        "location": {
+            "displayName": event.location.clone().unwrap_or_default(),
+        },
+    })
+}
+
+impl OutlookBackend<'_> {
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> anyhow::Result<reqwest::RequestBuilder> {
+        let access_token = crate::user::outlook_access_token(self.db_pool, self.user_id)
+            .await
+            .context("Failed to get outlook access token")?;
+
+        Ok(self.client.request(method, url).bearer_auth(access_token))
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for OutlookBackend<'_> {
+    async fn insert_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<String> {
+        #[derive(serde::Deserialize)]
+        struct Created {
+            id: String,
+        }
+
+        let mut body = render_graph_event(event);
+        // graph returns the event created by the previous attempt for the same transaction
+        body["transactionId"] = serde_json::Value::String(event_id.to_string());
+        let created: Created = self
+            .request(reqwest::Method::POST, GRAPH_EVENTS_URL)
+            .await?
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to send graph request")?
+            .error_for_status()
+            .context("Failed to insert outlook event")?
+            .json()
+            .await
+            .context("Failed to parse inserted outlook event")?;
+
+        Ok(created.id)
+    }
+
+    async fn update_event(&self, event_id: &str, event: &GoogleEvent) -> anyhow::Result<()> {
+        self.request(
+            reqwest::Method::PATCH,
+            &format!("{GRAPH_EVENTS_URL}/{event_id}"),
+        )
+        .await?
+        .json(&render_graph_event(event))
+        .send()
+        .await
+        .context("Failed to send graph request")?
+        .error_for_status()
+        .context("Failed to update outlook event")?;
+
+        Ok(())
+    }
+
+    async fn delete_event(&self, event_id: &str) -> anyhow::Result<()> {
+        let response = self
+            .request(
+                reqwest::Method::DELETE,
+                &format!("{GRAPH_EVENTS_URL}/{event_id}"),
+            )
+            .await?
+            .send()
+            .await
+            .context("Failed to send graph request")?;
+        // already removed by the user
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .context("Failed to delete outlook event")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl UserCalendar {
+    // a password not decrypted is an error rather than `None`, not to take the
+    // user as unlinked and drop their syncs
+    fn from_columns(
+        user_id: i64,
+        backend: &str,
+        google_calendar_id: Option<String>,
+        caldav_url: Option<String>,
+        caldav_username: Option<String>,
+        caldav_password: Option<String>,
+        outlook_refresh_token: Option<String>,
+    ) -> anyhow::Result<Option<Self>> {
+        Ok(match backend {
+            "caldav" => {
+                let (Some(url), Some(username), Some(password)) =
+                    (caldav_url, caldav_username, caldav_password)
+                else {
+                    return Ok(None);
+                };
+                let password = crate::user::decrypt_secret(&password).with_context(|| {
+                    format!("Failed to decrypt caldav password of user({user_id})")
+                })?;
+                Some(Self::CalDav {
+                    url,
+                    username,
+                    password,
+                })
+            }
+            "outlook" => outlook_refresh_token.map(|_| Self::Outlook { user_id }),
+            _ => google_calendar_id.map(|calendar_id| Self::Google { calendar_id }),
+        })
+    }
+
+    pub(super) fn backend<'a>(
+        &'a self,
+        db_pool: &'a SqlitePool,
+        hub: &'a GoogleHub,
+    ) -> Box<dyn CalendarBackend + 'a> {
+        match self {
+            Self::Google { calendar_id } => Box::new(GoogleBackend { hub, calendar_id }),
+            Self::CalDav {
+                url,
+                username,
+                password,
+            } => Box::new(CalDavBackend {
+                url,
+                username,
+                password,
+            }),
+            Self::Outlook { user_id } => Box::new(OutlookBackend {
+                client: reqwest::Client::new(),
+                db_pool,
+                user_id: *user_id,
+            }),
+        }
+    }
+}
+
+// passwords stored in plain text before they were encrypted
+pub(super) async fn encrypt_plain_caldav_passwords(db_pool: &SqlitePool) -> anyhow::Result<()> {
+    let users = sqlx::query!(
+        r#"SELECT `user_id` AS "user_id!: i64", `caldav_password` AS "caldav_password!: String"
+        FROM `users`
+        WHERE `caldav_password` IS NOT NULL AND NOT `caldav_password_encrypted`"#
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get plain caldav passwords")?;
+
+    for user in users {
+        let encrypted = crate::user::encrypt_secret(&user.caldav_password)?;
+        sqlx::query!(
+            "UPDATE `users`
+            SET `caldav_password` = ?, `caldav_password_encrypted` = TRUE
+            WHERE `user_id` = ?",
+            encrypted,
+            user.user_id
+        )
+        .execute(db_pool)
+        .await
+        .context("Failed to store encrypted caldav password")?;
+    }
+
+    Ok(())
+}
+
+// users without a connected calendar are omitted. fails on a password not
+// decrypted, to keep syncs of the users until the key is fixed.
+pub(super) async fn fetch_user_calendars(
+    db_pool: &SqlitePool,
+    user_ids: impl IntoIterator<Item = i64>,
+) -> anyhow::Result<HashMap<i64, UserCalendar>> {
+    let calendars = sqlx::query_builder::QueryBuilder::new(
+        "SELECT
+            `user_id`,
+            `calendar_backend`,
+            `google_calendar_id`,
+            `caldav_url`,
+            `caldav_username`,
+            `caldav_password`,
+            `outlook_refresh_token`
+        FROM `users`
+        WHERE `user_id` IN ",
+    )
+    .push_tuples(user_ids, |mut b, id| {
+        b.push_bind(id);
+    })
+    .build()
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get user calendars from DB")?
+    .into_iter()
+    .filter_map(|r| {
+        let user_id = r.get(0);
+        UserCalendar::from_columns(
+            user_id,
+            r.get::<&str, _>(1),
+            r.get(2),
+            r.get(3),
+            r.get(4),
+            r.get(5),
+            r.get(6),
+        )
+        .transpose()
+        .map(|calendar| calendar.map(|calendar| (user_id, calendar)))
+    })
+    .collect::<anyhow::Result<_>>()?;
+
+    Ok(calendars)
+}
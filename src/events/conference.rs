@@ -0,0 +1,87 @@
+use anyhow::Context as _;
+use google_calendar3::api::{
+    ConferenceData, ConferenceSolutionKey, CreateConferenceRequest, Event as GoogleEvent,
+};
+use log::{info, warn};
+use serenity::{model::prelude::ScheduledEvent, prelude::Context};
+
+use super::DiscordHandler;
+
+// organizers ask a meeting link by putting one of these in the description
+const ONLINE_MARKERS: &[&str] = &["[온라인]", "[online]"];
+const MEET_HOST: &str = "https://meet.google.com/";
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+// online events which do not have a meeting link yet
+pub(super) fn wants_meeting(event: &ScheduledEvent) -> bool {
+    event.description.as_deref().map_or(false, |description| {
+        let lowered = description.to_lowercase();
+        ONLINE_MARKERS.iter().any(|marker| lowered.contains(marker))
+            && !description.contains(MEET_HOST)
+    })
+}
+
+// `conference_data_version` of the insert call should be 1 to apply it
+pub(super) fn request_meeting(google_event: &mut GoogleEvent, discord_id: i64) {
+    google_event.conference_data = Some(ConferenceData {
+        create_request: Some(CreateConferenceRequest {
+            conference_solution_key: Some(ConferenceSolutionKey {
+                type_: Some("hangoutsMeet".to_string()),
+            }),
+            // the same request id returns the same conference
+            request_id: Some(format!("futaba-{discord_id}")),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+}
+
+fn meeting_link(google_event: &GoogleEvent) -> Option<&str> {
+    google_event.hangout_link.as_deref().or_else(|| {
+        google_event
+            .conference_data
+            .as_ref()?
+            .entry_points
+            .as_ref()?
+            .iter()
+            .find(|entry| entry.entry_point_type.as_deref() == Some("video"))?
+            .uri
+            .as_deref()
+    })
+}
+
+impl DiscordHandler {
+    // write the created meeting link back to discord. the following update syncs it to calendars.
+    pub(super) async fn write_meeting_link(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+        google_event: &GoogleEvent,
+    ) -> anyhow::Result<()> {
+        let Some(link) = meeting_link(google_event) else {
+            warn!("Meeting of event({}) is not created yet", event.id);
+            return Ok(());
+        };
+
+        let description = format!(
+            "{}\n\n🎥 {link}",
+            event.description.as_deref().unwrap_or_default()
+        );
+        if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            warn!(
+                "Description of event({}) is too long to add meeting link",
+                event.id
+            );
+            return Ok(());
+        }
+
+        info!("Add meeting link to event({})", event.id);
+        event
+            .guild_id
+            .edit_scheduled_event(&context.http, event.id, |e| e.description(description))
+            .await
+            .context("Failed to add meeting link to discord event")?;
+
+        Ok(())
+    }
+}
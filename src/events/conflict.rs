@@ -0,0 +1,109 @@
+use anyhow::Context as _;
+use log::info;
+use serenity::{
+    http::Http,
+    model::prelude::{ScheduledEvent, ScheduledEventStatus},
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+
+// events without end time are regarded to take an hour
+const DEFAULT_DURATION_SECS: i64 = 60 * 60;
+
+fn time_range(event: &ScheduledEvent) -> (i64, i64) {
+    let start = event.start_time.unix_timestamp();
+    let end = event
+        .end_time
+        .map(|end_time| end_time.unix_timestamp())
+        .filter(|end| *end > start)
+        .unwrap_or(start + DEFAULT_DURATION_SECS);
+
+    (start, end)
+}
+
+// upcoming or ongoing events overlapping with the event
+pub(super) async fn find_conflicts(
+    http: &Http,
+    event: &ScheduledEvent,
+) -> anyhow::Result<Vec<ScheduledEvent>> {
+    let (start, end) = time_range(event);
+    let conflicts = http
+        .get_scheduled_events(event.guild_id.0, false)
+        .await
+        .context("Failed to get scheduled events")?
+        .into_iter()
+        .filter(|other| {
+            if other.id == event.id
+                || !matches!(
+                    other.status,
+                    ScheduledEventStatus::Scheduled | ScheduledEventStatus::Active
+                )
+            {
+                return false;
+            }
+            let (other_start, other_end) = time_range(other);
+            other_start < end && start < other_end
+        })
+        .collect();
+
+    Ok(conflicts)
+}
+
+pub(super) fn conflict_message(conflicts: &[ScheduledEvent]) -> String {
+    let mut message = "⚠️ 시간이 겹치는 이벤트가 있습니다.".to_string();
+    for conflict in conflicts {
+        let (start, end) = time_range(conflict);
+        message.push_str(&format!(
+            "\n- **{}** <t:{start}:f> ~ <t:{end}:t>",
+            conflict.name
+        ));
+    }
+
+    message
+}
+
+impl DiscordHandler {
+    // events made by `/event create` are warned in the command response
+    pub(super) async fn warn_conflicts(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<()> {
+        let Some(channel_id) = self.reminder_channel_id else {
+            return Ok(());
+        };
+        if event.creator_id == Some(context.cache.current_user_id()) {
+            return Ok(());
+        }
+
+        let conflicts = find_conflicts(&context.http, event).await?;
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "Event({}) overlaps with {} events",
+            event.id,
+            conflicts.len()
+        );
+        let mention = event
+            .creator_id
+            .map(|creator_id| format!("<@{creator_id}> "))
+            .unwrap_or_default();
+        // names of events are given by members, so only the creator is mentioned
+        channel_id
+            .send_message(context, |m| {
+                m.content(format!(
+                    "{mention}**{}**\n{}",
+                    event.name,
+                    conflict_message(&conflicts)
+                ))
+                .allowed_mentions(|a| a.empty_parse().users(event.creator_id))
+            })
+            .await
+            .context("Failed to warn conflicts")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+};
+
+use anyhow::Context as _;
+use log::{error, info, warn};
+use serenity::{
+    http::Http,
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            InteractionResponseType,
+        },
+        prelude::{ChannelId, GuildId, MessageId, ScheduledEvent, ScheduledEventStatus},
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{can_manage_events, DiscordHandler};
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+const COUNTDOWN_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+// accepts an event id or a link of the event
+fn parse_event_id(input: &str) -> Option<u64> {
+    input
+        .trim()
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn duration_text(secs: i64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    // coarse while far away not to edit the message every minute
+    if days > 0 {
+        format!("{days}일 {hours}시간")
+    } else if hours > 0 {
+        format!("{hours}시간 {minutes}분")
+    } else {
+        format!("{}분", minutes.max(1))
+    }
+}
+
+fn countdown_message(event: &ScheduledEvent, now: i64) -> String {
+    let link = format!("https://discord.com/events/{}/{}", event.guild_id, event.id);
+    match event.status {
+        ScheduledEventStatus::Active => format!("🔴 **{}** 지금 진행 중!\n{link}", event.name),
+        ScheduledEventStatus::Completed | ScheduledEventStatus::Canceled => {
+            format!("✅ **{}** 이벤트가 끝났습니다.", event.name)
+        }
+        _ => {
+            let remaining = event.start_time.unix_timestamp() - now;
+            // the event stays scheduled until the host starts it
+            if remaining > 0 {
+                format!(
+                    "⏳ **{}** 시작까지 {}\n{link}",
+                    event.name,
+                    duration_text(remaining)
+                )
+            } else if remaining > -60 {
+                format!("⏳ **{}** 시작 시간이 되었습니다.\n{link}", event.name)
+            } else {
+                format!(
+                    "⏳ **{}** 시작 시간에서 {} 지났습니다.\n{link}",
+                    event.name,
+                    duration_text(-remaining)
+                )
+            }
+        }
+    }
+}
+
+fn countdown_finished(event: Option<&ScheduledEvent>) -> bool {
+    event.map_or(true, |event| {
+        matches!(
+            event.status,
+            ScheduledEventStatus::Completed | ScheduledEventStatus::Canceled
+        )
+    })
+}
+
+async fn update_countdowns(
+    db_pool: &SqlitePool,
+    http: &Arc<Http>,
+    guild_id: GuildId,
+) -> anyhow::Result<()> {
+    let countdowns = sqlx::query!(
+        "SELECT `discord_id`, `channel_id`, `message_id`, `content` FROM `event_countdowns`"
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get countdowns")?;
+    if countdowns.is_empty() {
+        return Ok(());
+    }
+
+    let events: HashMap<i64, ScheduledEvent> = http
+        .get_scheduled_events(guild_id.0, false)
+        .await
+        .context("Failed to get scheduled events")?
+        .into_iter()
+        .map(|event| (event.id.0 as i64, event))
+        .collect();
+    let now = chrono::Utc::now().timestamp();
+
+    for countdown in countdowns {
+        let event = events.get(&countdown.discord_id);
+        let content = match event {
+            Some(event) => countdown_message(event, now),
+            None => "✅ 이벤트가 끝났거나 취소되었습니다.".to_string(),
+        };
+        if content != countdown.content {
+            let result = ChannelId(countdown.channel_id as u64)
+                .edit_message(http, MessageId(countdown.message_id as u64), |b| {
+                    b.content(&content)
+                })
+                .await;
+            if let Err(e) = result {
+                warn!(
+                    "Failed to edit countdown of event({}) - {e:?}",
+                    countdown.discord_id
+                );
+            }
+        }
+
+        if countdown_finished(event) {
+            info!("Countdown of event({}) is finished", countdown.discord_id);
+            sqlx::query!(
+                "DELETE FROM `event_countdowns` WHERE `discord_id` = ?",
+                countdown.discord_id
+            )
+            .execute(db_pool)
+            .await
+            .context("Failed to delete countdown")?;
+        } else if content != countdown.content {
+            sqlx::query!(
+                "UPDATE `event_countdowns` SET `content` = ? WHERE `discord_id` = ?",
+                content,
+                countdown.discord_id
+            )
+            .execute(db_pool)
+            .await
+            .context("Failed to update countdown")?;
+        }
+    }
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    pub(super) fn start_countdown(&self, context: &Context, guild_id: GuildId) {
+        if self.countdown_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let http = context.http.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = update_countdowns(&db_pool, &http, guild_id).await {
+                    error!("Failed to update countdowns - {e:?}");
+                }
+
+                tokio::time::sleep(COUNTDOWN_TICK).await;
+            }
+        });
+    }
+
+    pub(super) async fn handle_countdown_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let reply = |content: String| async move {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| b.content(content).ephemeral(true))
+                })
+                .await
+        };
+        if !can_manage_events(interaction.member.as_ref()) {
+            reply("이벤트 관리 권한이 필요합니다.".to_string()).await?;
+            return Ok(());
+        }
+
+        let guild_id = interaction
+            .guild_id
+            .context("Command is not used in guild")?;
+        let [event, channel] = option.get_options(&["event", "channel"]);
+        let Some(event_id) = event.as_str().and_then(parse_event_id) else {
+            reply("이벤트 ID나 링크를 이해하지 못했습니다.".to_string()).await?;
+            return Ok(());
+        };
+        let channel_id = match channel.as_str() {
+            Some(channel_id) => ChannelId(channel_id.parse().context("Invalid channel")?),
+            None => interaction.channel_id,
+        };
+
+        let event = match context
+            .http
+            .get_scheduled_event(guild_id.0, event_id, false)
+            .await
+        {
+            Ok(event) => event,
+            Err(e) => {
+                info!("Failed to get event({event_id}) for countdown - {e:?}");
+                reply("이벤트를 찾지 못했습니다.".to_string()).await?;
+                return Ok(());
+            }
+        };
+        if countdown_finished(Some(&event)) {
+            reply("이미 끝난 이벤트입니다.".to_string()).await?;
+            return Ok(());
+        }
+
+        let content = countdown_message(&event, chrono::Utc::now().timestamp());
+        let message = channel_id
+            .say(context, &content)
+            .await
+            .context("Failed to post countdown")?;
+
+        let discord_id = event_id as i64;
+        let raw_channel_id = channel_id.0 as i64;
+        let message_id = message.id.0 as i64;
+        // the previous countdown of the event is left as is
+        sqlx::query!(
+            "INSERT INTO `event_countdowns` (`discord_id`, `channel_id`, `message_id`, `content`)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (`discord_id`) DO UPDATE SET
+                `channel_id` = excluded.`channel_id`,
+                `message_id` = excluded.`message_id`,
+                `content` = excluded.`content`",
+            discord_id,
+            raw_channel_id,
+            message_id,
+            content
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save countdown")?;
+
+        reply(format!("<#{channel_id}>에 카운트다운을 올렸습니다.")).await?;
+
+        Ok(())
+    }
+}
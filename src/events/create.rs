@@ -0,0 +1,263 @@
+use anyhow::Context as _;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, NaiveTime, Utc};
+use log::error;
+use serenity::{
+    model::{
+        application::{
+            component::InputTextStyle,
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOption},
+                modal::ModalSubmitInteraction,
+                InteractionResponseType,
+            },
+        },
+        prelude::ScheduledEventType,
+        Timestamp,
+    },
+    prelude::Context,
+};
+
+use super::{
+    can_manage_events,
+    conflict::{conflict_message, find_conflicts},
+    modal_input, DiscordHandler,
+};
+
+pub(super) const CREATE_EVENT_MODAL_ID: &str = "create_event";
+
+// times without offset are regarded as KST
+const DEFAULT_OFFSET_SECS: i32 = 9 * 60 * 60;
+const DEFAULT_DURATION_MINUTES: i64 = 60;
+// longer durations are taken as mistakes, and chrono panics on too large ones
+const MAX_DURATION_MINUTES: i64 = 366 * 24 * 60;
+
+fn parse_date_time(input: &str, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    let input = input.trim();
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(input) {
+        return Some(date_time);
+    }
+
+    let normalized = input.replace(['/', '.'], "-").replace('T', " ");
+    let offset = *now.offset();
+    let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&normalized, format).ok())
+        .or_else(|| {
+            // year is omitted
+            let with_year = format!("{}-{normalized}", now.year());
+            ["%Y-%m-%d %H:%M", "%Y-%m-%d %H%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(&with_year, format).ok())
+        })
+        .or_else(|| {
+            // date is omitted. the next occurrence of the time
+            let time = ["%H:%M", "%H%M"]
+                .iter()
+                .find_map(|format| NaiveTime::parse_from_str(&normalized, format).ok())?;
+            let date_time = now.date_naive().and_time(time);
+            if date_time < now.naive_local() {
+                Some(date_time + chrono::Duration::days(1))
+            } else {
+                Some(date_time)
+            }
+        })?;
+
+    naive.and_local_timezone(offset).single()
+}
+
+fn minutes_to_duration(minutes: i64) -> Option<chrono::Duration> {
+    (-MAX_DURATION_MINUTES..=MAX_DURATION_MINUTES)
+        .contains(&minutes)
+        .then(|| chrono::Duration::minutes(minutes))
+}
+
+// accepts minutes(`90`), `1:30` and units like `1h30m`, `1시간 30분`
+fn parse_duration(input: &str) -> Option<chrono::Duration> {
+    let input = input.trim();
+    if input.is_empty() {
+        return minutes_to_duration(DEFAULT_DURATION_MINUTES);
+    }
+    if let Ok(minutes) = input.parse::<i64>() {
+        return minutes_to_duration(minutes);
+    }
+    if let Some((hours, minutes)) = input.split_once(':') {
+        let hours: i64 = hours.trim().parse().ok()?;
+        let minutes: i64 = minutes.trim().parse().ok()?;
+        return minutes_to_duration(hours.checked_mul(60)?.checked_add(minutes)?);
+    }
+
+    let mut minutes = 0i64;
+    let mut number = String::new();
+    for c in input.chars() {
+        let added = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'h' | 'H' | '시' => number
+                .drain(..)
+                .as_str()
+                .parse::<i64>()
+                .ok()?
+                .checked_mul(60)?,
+            'm' | 'M' | '분' => number.drain(..).as_str().parse::<i64>().ok()?,
+            '간' => continue,
+            c if c.is_whitespace() => continue,
+            _ => return None,
+        };
+        minutes = minutes.checked_add(added)?;
+    }
+    if !number.is_empty() {
+        minutes = minutes.checked_add(number.parse::<i64>().ok()?)?;
+    }
+
+    minutes_to_duration(minutes)
+}
+
+impl DiscordHandler {
+    pub(super) async fn handle_create_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        if !can_manage_events(interaction.member.as_ref()) {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.content("이벤트 관리 권한이 필요합니다.").ephemeral(true)
+                        })
+                })
+                .await?;
+            return Ok(());
+        }
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::Modal)
+                    .interaction_response_data(|b| {
+                        b.custom_id(CREATE_EVENT_MODAL_ID)
+                            .title("이벤트 만들기")
+                            .components(|b| {
+                                b.create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("이름")
+                                            .required(true)
+                                            .custom_id("name")
+                                            .max_length(100)
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                                .create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("시작 시각 (KST)")
+                                            .required(true)
+                                            .custom_id("start")
+                                            .placeholder("2024-05-01 20:00")
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                                .create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("진행 시간")
+                                            .required(false)
+                                            .custom_id("duration")
+                                            .placeholder("1h30m (기본 1시간)")
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                                .create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("장소")
+                                            .required(true)
+                                            .custom_id("location")
+                                            .max_length(100)
+                                            .style(InputTextStyle::Short)
+                                    })
+                                })
+                                .create_action_row(|b| {
+                                    b.create_input_text(|b| {
+                                        b.label("설명")
+                                            .required(false)
+                                            .custom_id("description")
+                                            .max_length(1000)
+                                            .style(InputTextStyle::Paragraph)
+                                    })
+                                })
+                            })
+                    })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub(super) async fn handle_create_event_modal_submit(
+        &self,
+        context: &Context,
+        modal: &ModalSubmitInteraction,
+    ) -> anyhow::Result<()> {
+        let guild_id = modal.guild_id.context("Modal is not submitted in guild")?;
+        let name = modal_input(modal, "name").context("Could not find name field")?;
+        let location = modal_input(modal, "location").context("Could not find location field")?;
+        let description = modal_input(modal, "description").unwrap_or_default();
+
+        let now = Utc::now().with_timezone(
+            &FixedOffset::east_opt(DEFAULT_OFFSET_SECS).context("Invalid default offset")?,
+        );
+        let parsed = modal_input(modal, "start")
+            .and_then(|start| parse_date_time(start, now))
+            .zip(modal_input(modal, "duration").and_then(parse_duration))
+            .and_then(|(start, duration)| Some((start, start.checked_add_signed(duration)?)));
+        let content = match parsed {
+            None => "시작 시각이나 진행 시간을 이해하지 못했습니다.".to_string(),
+            Some((start, _)) if start <= now => "시작 시각이 이미 지났습니다.".to_string(),
+            Some((start, end)) if end <= start => "진행 시간은 0보다 커야 합니다.".to_string(),
+            Some((start, end)) => {
+                let start_time = Timestamp::from_unix_timestamp(start.timestamp())
+                    .map_err(|e| anyhow::anyhow!("Invalid start time - {e:?}"))?;
+                let end_time = Timestamp::from_unix_timestamp(end.timestamp())
+                    .map_err(|e| anyhow::anyhow!("Invalid end time - {e:?}"))?;
+                // sync of the created event is done by the scheduled event handler
+                let event = guild_id
+                    .create_scheduled_event(context, |e| {
+                        e.name(name)
+                            .kind(ScheduledEventType::External)
+                            .start_time(start_time)
+                            .end_time(end_time)
+                            .location(location);
+                        if !description.is_empty() {
+                            e.description(description);
+                        }
+                        e
+                    })
+                    .await
+                    .context("Failed to create discord event")?;
+
+                let mut content = format!(
+                    "이벤트를 만들었습니다.\nhttps://discord.com/events/{}/{}",
+                    event.guild_id, event.id
+                );
+                match find_conflicts(&context.http, &event).await {
+                    Ok(conflicts) if !conflicts.is_empty() => {
+                        content.push_str(&format!("\n\n{}", conflict_message(&conflicts)));
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to find conflicts of event({}) - {e:?}", event.id),
+                }
+
+                content
+            }
+        };
+
+        modal
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use google_calendar3::api::EventDateTime;
+
+// organizers could force an event to be all day by putting one of these in the description
+const ALL_DAY_MARKERS: &[&str] = &["[종일]", "[all day]", "[all-day]"];
+// google shows zero-length events as a thin line
+const DEFAULT_DURATION_SECS: i64 = 60 * 60;
+
+// time zone of the guild. it is used to find dates of all day events.
+#[derive(Debug, Clone)]
+pub(super) struct EventTimeZone {
+    tz: Tz,
+}
+
+fn has_all_day_marker(description: Option<&str>) -> bool {
+    description.map_or(false, |description| {
+        let description = description.to_lowercase();
+        ALL_DAY_MARKERS
+            .iter()
+            .any(|marker| description.contains(marker))
+    })
+}
+
+// discord has no all day event, so date-only events imported from google are marked instead
+pub(super) fn with_all_day_marker(description: Option<String>) -> String {
+    match description {
+        Some(description) if has_all_day_marker(Some(description.as_str())) => description,
+        Some(description) if !description.is_empty() => {
+            format!("{}\n{description}", ALL_DAY_MARKERS[0])
+        }
+        _ => ALL_DAY_MARKERS[0].to_string(),
+    }
+}
+
+impl EventTimeZone {
+    pub(super) fn new(name: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            tz: name
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid time zone {name} - {e}"))?,
+        })
+    }
+
+    pub(super) fn tz(&self) -> Tz {
+        self.tz
+    }
+
+    fn local(&self, ts: i64) -> Option<DateTime<Tz>> {
+        Some(DateTime::from_timestamp(ts, 0)?.with_timezone(&self.tz))
+    }
+
+    fn timed(&self, ts: i64, time_zone: &str) -> EventDateTime {
+        EventDateTime {
+            date: None,
+            date_time: DateTime::from_timestamp(ts, 0),
+            time_zone: Some(time_zone.to_string()),
+        }
+    }
+
+    // `date` of all day events is inclusive for start and exclusive for end
+    fn all_day(
+        &self,
+        start_ts: i64,
+        end_ts: Option<i64>,
+    ) -> Option<(EventDateTime, EventDateTime)> {
+        let start = self.local(start_ts)?.date_naive();
+        let end = match end_ts {
+            // an event ending at 13:00 still occupies that day
+            Some(end_ts) if end_ts > start_ts => self.local(end_ts - 1)?.date_naive(),
+            _ => start,
+        }
+        .succ_opt()?;
+
+        let date = |date| EventDateTime {
+            date: Some(date),
+            date_time: None,
+            time_zone: None,
+        };
+        Some((date(start), date(end)))
+    }
+
+    // start and end of google event for the given discord schedule
+    pub(super) fn event_date_times(
+        &self,
+        start_ts: i64,
+        end_ts: Option<i64>,
+        description: Option<&str>,
+        recurring: bool,
+    ) -> (EventDateTime, EventDateTime) {
+        // discord recurrence rules are based on UTC. keep them timed to expand on the right days.
+        if recurring {
+            let end_ts = end_ts.unwrap_or(start_ts + DEFAULT_DURATION_SECS);
+            return (self.timed(start_ts, "UTC"), self.timed(end_ts, "UTC"));
+        }
+
+        if has_all_day_marker(description) {
+            if let Some(date_times) = self.all_day(start_ts, end_ts) {
+                return date_times;
+            }
+        }
+
+        let end_ts = end_ts
+            .filter(|end_ts| *end_ts > start_ts)
+            .unwrap_or(start_ts + DEFAULT_DURATION_SECS);
+        (
+            self.timed(start_ts, self.tz.name()),
+            self.timed(end_ts, self.tz.name()),
+        )
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use anyhow::Context as _;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use google_calendar3::{
+    api::{Event as GoogleEvent, EventDateTime},
+    hyper::client::HttpConnector,
+    hyper_rustls::HttpsConnector,
+    CalendarHub,
+};
+use log::{error, info, warn};
+use serenity::{
+    http::Http,
+    model::{
+        prelude::{GuildId, ScheduledEventType},
+        Timestamp,
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{date_time::with_all_day_marker, DiscordHandler};
+
+// discord requires both of start and end time for external events
+const DEFAULT_DURATION_HOURS: i64 = 1;
+const DEFAULT_LOCATION: &str = "Google Calendar";
+
+// dates of all day events start at the midnight of the time zone
+fn google_date_time(date_time: &EventDateTime, time_zone: Tz) -> Option<DateTime<Utc>> {
+    date_time.date_time.or_else(|| {
+        let midnight = date_time.date?.and_hms_opt(0, 0, 0)?;
+        Some(
+            time_zone
+                .from_local_datetime(&midnight)
+                .earliest()?
+                .with_timezone(&Utc),
+        )
+    })
+}
+
+fn to_timestamp(date_time: DateTime<Utc>) -> anyhow::Result<Timestamp> {
+    Timestamp::from_unix_timestamp(date_time.timestamp())
+        .map_err(|e| anyhow::anyhow!("Invalid timestamp - {e:?}"))
+}
+
+async fn sync_event(
+    db_pool: &SqlitePool,
+    http: &Http,
+    guild_id: GuildId,
+    time_zone: Tz,
+    google_event: GoogleEvent,
+) -> anyhow::Result<()> {
+    let Some(google_event_id) = google_event.id else {
+        return Ok(());
+    };
+    let saved = sqlx::query!(
+        "SELECT `discord_id`, `updated_at` FROM `imported_events` WHERE `google_event_id` = ?",
+        google_event_id
+    )
+    .fetch_optional(db_pool)
+    .await
+    .context("Failed to get imported event from DB")?;
+
+    if google_event.status.as_deref() == Some("cancelled") {
+        if let Some(saved) = saved {
+            info!("Delete discord event imported from {google_event_id}");
+            if let Err(e) = guild_id
+                .delete_scheduled_event(http, saved.discord_id as u64)
+                .await
+            {
+                warn!("Failed to delete imported discord event - {e:?}");
+            }
+            sqlx::query!(
+                "DELETE FROM `imported_events` WHERE `google_event_id` = ?",
+                google_event_id
+            )
+            .execute(db_pool)
+            .await
+            .context("Failed to delete imported event in DB")?;
+        }
+        return Ok(());
+    }
+
+    let updated_at = google_event
+        .updated
+        .map(|updated| updated.timestamp())
+        .unwrap_or_default();
+    if saved.as_ref().map(|saved| saved.updated_at) == Some(updated_at) {
+        return Ok(());
+    }
+
+    let start = google_event
+        .start
+        .as_ref()
+        .and_then(|start| google_date_time(start, time_zone))
+        .context("Start time is missing")?;
+    let end = google_event
+        .end
+        .as_ref()
+        .and_then(|end| google_date_time(end, time_zone))
+        .unwrap_or(start + chrono::Duration::hours(DEFAULT_DURATION_HOURS));
+    let all_day = google_event.start.as_ref().map_or(false, |start| {
+        start.date_time.is_none() && start.date.is_some()
+    });
+    let name = google_event.summary.unwrap_or_default();
+    let description = if all_day {
+        Some(with_all_day_marker(google_event.description))
+    } else {
+        google_event.description
+    };
+    let location = google_event
+        .location
+        .unwrap_or_else(|| DEFAULT_LOCATION.to_string());
+
+    let discord_id = if let Some(saved) = saved {
+        info!("Update discord event imported from {google_event_id}");
+        let (start, end) = (to_timestamp(start)?, to_timestamp(end)?);
+        guild_id
+            .edit_scheduled_event(http, saved.discord_id as u64, |e| {
+                e.name(&name)
+                    .start_time(start)
+                    .end_time(end)
+                    .location(&location);
+                if let Some(description) = &description {
+                    e.description(description);
+                }
+                e
+            })
+            .await
+            .context("Failed to edit discord event")?
+            .id
+    } else {
+        // discord does not allow events started in the past
+        if start <= Utc::now() {
+            return Ok(());
+        }
+
+        info!("Create discord event from {google_event_id}");
+        let (start, end) = (to_timestamp(start)?, to_timestamp(end)?);
+        guild_id
+            .create_scheduled_event(http, |e| {
+                e.name(&name)
+                    .kind(ScheduledEventType::External)
+                    .start_time(start)
+                    .end_time(end)
+                    .location(&location);
+                if let Some(description) = &description {
+                    e.description(description);
+                }
+                e
+            })
+            .await
+            .context("Failed to create discord event")?
+            .id
+    };
+
+    let discord_id = *discord_id.as_u64() as i64;
+    sqlx::query!(
+        "INSERT INTO `imported_events` (`google_event_id`, `discord_id`, `updated_at`)
+        VALUES (?, ?, ?)
+        ON CONFLICT (`google_event_id`) DO UPDATE SET
+            `discord_id` = excluded.`discord_id`,
+            `updated_at` = excluded.`updated_at`",
+        google_event_id,
+        discord_id,
+        updated_at
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to save imported event into DB")?;
+
+    Ok(())
+}
+
+async fn sync_source_calendar(
+    db_pool: &SqlitePool,
+    hub: &CalendarHub<HttpsConnector<HttpConnector>>,
+    http: &Http,
+    guild_id: GuildId,
+    time_zone: Tz,
+    calendar_id: &str,
+) -> anyhow::Result<()> {
+    let mut google_events = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut call = hub
+            .events()
+            .list(calendar_id)
+            .time_min(Utc::now())
+            .single_events(true)
+            .show_deleted(true);
+        if let Some(page_token) = &page_token {
+            call = call.page_token(page_token);
+        }
+        let events = call
+            .doit()
+            .await
+            .context("Failed to list events of source calendar")?
+            .1;
+        google_events.extend(events.items.unwrap_or_default());
+        page_token = events.next_page_token;
+        if page_token.is_none() {
+            break;
+        }
+    }
+
+    for google_event in google_events {
+        let google_event_id = google_event.id.clone();
+        if let Err(e) = sync_event(db_pool, http, guild_id, time_zone, google_event).await {
+            error!("Failed to sync google event({google_event_id:?}) - {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    // poll the source calendar and reflect it to discord scheduled events
+    pub(super) fn start_source_sync(&self, context: &Context, guild_id: GuildId) {
+        let Some(calendar_id) = self.source_calendar_id.clone() else {
+            return;
+        };
+        if self.source_sync_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let service_account = self.service_account.clone();
+        let http: Arc<Http> = context.http.clone();
+        let interval = self.source_poll_interval;
+        let time_zone = self.time_zone.tz();
+        tokio::spawn(async move {
+            loop {
+                match Self::service_account_calendar_hub(service_account.clone()).await {
+                    Ok(hub) => {
+                        if let Err(e) = sync_source_calendar(
+                            &db_pool,
+                            &hub,
+                            &http,
+                            guild_id,
+                            time_zone,
+                            &calendar_id,
+                        )
+                        .await
+                        {
+                            error!("Failed to sync source calendar - {e:?}");
+                        }
+                    }
+                    Err(e) => error!("Failed to create google calendar hub - {e:?}"),
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::Context as _;
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::DateTime;
+use log::error;
+use sqlx::SqlitePool;
+
+use super::verify_token;
+
+struct FeedEvent {
+    discord_id: i64,
+    guild_id: i64,
+    name: String,
+    description: Option<String>,
+    location: Option<String>,
+    start_time: i64,
+    end_time: Option<i64>,
+    updated_at: i64,
+}
+
+pub(super) fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+pub(super) fn format_time(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+// content lines longer than 75 octets are folded into continuation lines beginning with a space
+pub(super) fn push_line(ics: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            octets = 1;
+        }
+        ics.push(c);
+        octets += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn render_ics(events: &[FeedEvent]) -> String {
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//futaba//events//KO");
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, "X-WR-CALNAME:Futaba");
+    for event in events {
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:{}@futaba", event.discord_id));
+        push_line(
+            &mut ics,
+            &format!("DTSTAMP:{}", format_time(event.updated_at)),
+        );
+        push_line(
+            &mut ics,
+            &format!("DTSTART:{}", format_time(event.start_time)),
+        );
+        push_line(
+            &mut ics,
+            &format!(
+                "DTEND:{}",
+                format_time(event.end_time.unwrap_or(event.start_time))
+            ),
+        );
+        push_line(&mut ics, &format!("SUMMARY:{}", escape_text(&event.name)));
+        if let Some(description) = &event.description {
+            push_line(
+                &mut ics,
+                &format!("DESCRIPTION:{}", escape_text(description)),
+            );
+        }
+        if let Some(location) = &event.location {
+            push_line(&mut ics, &format!("LOCATION:{}", escape_text(location)));
+        }
+        push_line(
+            &mut ics,
+            &format!(
+                "URL:https://discord.com/events/{}/{}",
+                event.guild_id, event.discord_id
+            ),
+        );
+        push_line(&mut ics, "END:VEVENT");
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+
+    ics
+}
+
+// `None` when the token does not match
+async fn fetch_feed_events(
+    db_pool: &SqlitePool,
+    user_id: i64,
+    token: &str,
+) -> anyhow::Result<Option<Vec<FeedEvent>>> {
+    let saved = sqlx::query_scalar!(
+        "SELECT `ics_token` FROM `users` WHERE `user_id` = ?",
+        user_id
+    )
+    .fetch_optional(db_pool)
+    .await
+    .context("Failed to check feed token")?
+    .flatten();
+    if !saved.is_some_and(|saved| verify_token(&saved, token)) {
+        return Ok(None);
+    }
+
+    let events = sqlx::query_as!(
+        FeedEvent,
+        r#"SELECT
+            `scheduled_events`.`discord_id`,
+            `scheduled_events`.`guild_id`,
+            `scheduled_events`.`name`,
+            `scheduled_events`.`description`,
+            `scheduled_events`.`location`,
+            `scheduled_events`.`start_time`,
+            `scheduled_events`.`end_time`,
+            `scheduled_events`.`updated_at`
+        FROM
+            `scheduled_events`
+        INNER JOIN
+            `scheduled_event_attendees` ON
+                `scheduled_event_attendees`.`discord_id` = `scheduled_events`.`discord_id`
+        WHERE
+            `scheduled_event_attendees`.`user_id` = ?
+        ORDER BY
+            `scheduled_events`.`start_time` ASC"#,
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to fetch events of feed")?;
+
+    Ok(Some(events))
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct FeedQuery {
+    token: String,
+}
+
+pub(crate) async fn events_feed(
+    Extension(db_pool): Extension<SqlitePool>,
+    Path(user_id): Path<i64>,
+    Query(query): Query<FeedQuery>,
+) -> Response {
+    match fetch_feed_events(&db_pool, user_id, &query.token).await {
+        Ok(Some(events)) => (
+            [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+            render_ics(&events),
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Failed to render event feed - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::Context as _;
+use log::info;
+use serenity::{
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            InteractionResponseType,
+        },
+        prelude::ScheduledEvent,
+    },
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+impl DiscordHandler {
+    // mention subscribers of the news role about the new event
+    pub(super) async fn announce_new_event(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<()> {
+        let (Some(role_id), Some(channel_id)) = (self.news_role_id, self.news_channel_id) else {
+            return Ok(());
+        };
+
+        info!("Announce new event({})", event.id);
+        let url = format!("https://discord.com/events/{}/{}", event.guild_id, event.id);
+        channel_id
+            .send_message(context, |m| {
+                m.content(format!("<@&{role_id}> 새 이벤트가 등록되었습니다."))
+                    .allowed_mentions(|a| a.roles([role_id]))
+                    .embed(|e| {
+                        e.title(&event.name).url(&url);
+                        if let Some(description) =
+                            event.description.as_deref().filter(|d| !d.is_empty())
+                        {
+                            e.description(description);
+                        }
+                        let mut time = format!("<t:{}:F>", event.start_time.unix_timestamp());
+                        if let Some(end_time) = event.end_time {
+                            time.push_str(&format!(" ~ <t:{}:t>", end_time.unix_timestamp()));
+                        }
+                        e.field("시간", time, false);
+                        if let Some(metadata) = &event.metadata {
+                            e.field("장소", &metadata.location, false);
+                        }
+                        if let Some(image) = &event.image {
+                            e.image(format!(
+                                "https://cdn.discordapp.com/guild-events/{}/{image}.png?size=1024",
+                                event.id
+                            ));
+                        }
+                        e
+                    })
+            })
+            .await
+            .context("Failed to announce new event")?;
+
+        Ok(())
+    }
+
+    pub(super) async fn handle_subscribe_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let content = if let Some(role_id) = self.news_role_id {
+            let guild_id = interaction
+                .guild_id
+                .context("Command is not used in guild")?;
+            let [enable] = option.get_options(&["enable"]);
+            let user_id = interaction.user.id.0;
+            if enable.as_bool().unwrap_or(true) {
+                context
+                    .http
+                    .add_member_role(guild_id.0, user_id, role_id.0, Some("event subscribe"))
+                    .await
+                    .context("Failed to add news role")?;
+                "새 이벤트가 등록되면 알려드립니다."
+            } else {
+                context
+                    .http
+                    .remove_member_role(guild_id.0, user_id, role_id.0, Some("event unsubscribe"))
+                    .await
+                    .context("Failed to remove news role")?;
+                "새 이벤트 알림을 받지 않습니다."
+            }
+        } else {
+            "이벤트 알림 역할이 설정되지 않았습니다."
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await?;
+
+        Ok(())
+    }
+}
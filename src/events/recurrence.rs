@@ -0,0 +1,114 @@
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serenity::{
+    http::{
+        request::{Request, RequestBuilder},
+        routing::RouteInfo,
+        Http,
+    },
+    model::prelude::ScheduledEvent,
+};
+
+const WEEKDAYS: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+#[derive(Debug, Deserialize)]
+pub(super) struct NWeekday {
+    n: i32,
+    day: usize,
+}
+
+// recurrence rule of discord scheduled events. serenity does not model it yet.
+#[derive(Debug, Deserialize)]
+pub(super) struct RecurrenceRule {
+    pub(super) start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    frequency: u8,
+    interval: u32,
+    by_weekday: Option<Vec<usize>>,
+    by_n_weekday: Option<Vec<NWeekday>>,
+    by_month: Option<Vec<u32>>,
+    by_month_day: Option<Vec<i32>>,
+    by_year_day: Option<Vec<i32>>,
+    count: Option<u32>,
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl RecurrenceRule {
+    pub(super) fn to_rrule(&self) -> anyhow::Result<String> {
+        let frequency = match self.frequency {
+            0 => "YEARLY",
+            1 => "MONTHLY",
+            2 => "WEEKLY",
+            3 => "DAILY",
+            frequency => anyhow::bail!("Unknown recurrence frequency - {frequency}"),
+        };
+        let mut rrule = format!("RRULE:FREQ={frequency};INTERVAL={}", self.interval.max(1));
+
+        let weekday = |day: usize| {
+            WEEKDAYS
+                .get(day)
+                .copied()
+                .with_context(|| format!("Unknown weekday - {day}"))
+        };
+        if let Some(by_weekday) = &self.by_weekday {
+            let days = by_weekday
+                .iter()
+                .map(|day| weekday(*day))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            rrule.push_str(&format!(";BYDAY={}", days.join(",")));
+        } else if let Some(by_n_weekday) = &self.by_n_weekday {
+            let days = by_n_weekday
+                .iter()
+                .map(|nday| weekday(nday.day).map(|day| format!("{}{day}", nday.n)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            rrule.push_str(&format!(";BYDAY={}", days.join(",")));
+        }
+        if let Some(by_month) = &self.by_month {
+            rrule.push_str(&format!(";BYMONTH={}", join(by_month)));
+        }
+        if let Some(by_month_day) = &self.by_month_day {
+            rrule.push_str(&format!(";BYMONTHDAY={}", join(by_month_day)));
+        }
+        if let Some(by_year_day) = &self.by_year_day {
+            rrule.push_str(&format!(";BYYEARDAY={}", join(by_year_day)));
+        }
+        if let Some(count) = self.count {
+            rrule.push_str(&format!(";COUNT={count}"));
+        } else if let Some(end) = self.end {
+            rrule.push_str(&format!(";UNTIL={}", end.format("%Y%m%dT%H%M%SZ")));
+        }
+
+        Ok(rrule)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawScheduledEvent {
+    recurrence_rule: Option<RecurrenceRule>,
+}
+
+pub(super) async fn fetch_recurrence_rule(
+    http: &Http,
+    event: &ScheduledEvent,
+) -> anyhow::Result<Option<RecurrenceRule>> {
+    let raw: RawScheduledEvent = http
+        .fire(Request::new(RequestBuilder::new(
+            RouteInfo::GetScheduledEvent {
+                guild_id: event.guild_id.0,
+                event_id: event.id.0,
+                with_user_count: false,
+            },
+        )))
+        .await
+        .context("Failed to get recurrence rule of event")?;
+
+    Ok(raw.recurrence_rule)
+}
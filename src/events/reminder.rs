@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+};
+
+use anyhow::Context as _;
+use log::{error, info, warn};
+use serenity::{
+    http::Http,
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            InteractionResponseType,
+        },
+        prelude::{ChannelId, GuildId, ScheduledEvent, ScheduledEventStatus},
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{fetch_interested_users, DiscordHandler};
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+const REMINDER_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+const MAX_LEAD_MINUTES: i64 = 24 * 60;
+
+#[derive(Clone, Copy)]
+struct ReminderSetting {
+    channel_id: Option<ChannelId>,
+    before: chrono::Duration,
+    // DM users without their own preference
+    dm: bool,
+}
+
+fn reminder_message(event: &ScheduledEvent) -> String {
+    format!(
+        "⏰ **{}** 이벤트가 <t:{}:R> 시작합니다.\nhttps://discord.com/events/{}/{}",
+        event.name,
+        event.start_time.unix_timestamp(),
+        event.guild_id,
+        event.id
+    )
+}
+
+// push notifications of DMs don't render timestamps, so the time is also written in the zone of the user
+fn dm_reminder_message(event: &ScheduledEvent, time_zone: chrono_tz::Tz) -> String {
+    let start_time = chrono::DateTime::from_timestamp(event.start_time.unix_timestamp(), 0)
+        .map(|start_time| {
+            start_time
+                .with_timezone(&time_zone)
+                .format("%m/%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    format!("{}\n🕒 {start_time} ({time_zone})", reminder_message(event))
+}
+
+async fn remind_channel(
+    db_pool: &SqlitePool,
+    http: &Arc<Http>,
+    event: &ScheduledEvent,
+    channel_id: ChannelId,
+) -> anyhow::Result<()> {
+    // start time is a part of the key to remind again for rescheduled events
+    let discord_id = *event.id.as_u64() as i64;
+    let start_time = event.start_time.unix_timestamp();
+    let inserted = sqlx::query!(
+        "INSERT OR IGNORE INTO `event_reminders` (`discord_id`, `start_time`) VALUES (?, ?)",
+        discord_id,
+        start_time
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to save reminder")?
+    .rows_affected()
+        > 0;
+    if !inserted {
+        return Ok(());
+    }
+
+    info!("Remind event({}) to channel", event.id);
+    channel_id
+        .say(http, reminder_message(event))
+        .await
+        .context("Failed to send reminder to channel")?;
+
+    Ok(())
+}
+
+async fn remind_users(
+    db_pool: &SqlitePool,
+    http: &Arc<Http>,
+    event: &ScheduledEvent,
+    preferences: &HashMap<i64, (bool, i64)>,
+    setting: ReminderSetting,
+    now: i64,
+) -> anyhow::Result<()> {
+    let discord_id = *event.id.as_u64() as i64;
+    let start_time = event.start_time.unix_timestamp();
+    let users = fetch_interested_users(http, event).await?;
+
+    for attendee in users {
+        let user_id = attendee.user.id.0 as i64;
+        let (dm, lead_minutes) = preferences
+            .get(&user_id)
+            .copied()
+            .unwrap_or((setting.dm, setting.before.num_minutes()));
+        if !dm || start_time > now + lead_minutes * 60 {
+            continue;
+        }
+
+        let inserted = sqlx::query!(
+            "INSERT OR IGNORE INTO `event_user_reminders` (`discord_id`, `start_time`, `user_id`)
+            VALUES (?, ?, ?)",
+            discord_id,
+            start_time,
+            user_id
+        )
+        .execute(db_pool)
+        .await
+        .context("Failed to save user reminder")?
+        .rows_affected()
+            > 0;
+        if !inserted {
+            continue;
+        }
+
+        let time_zone = crate::user::user_time_zone(db_pool, user_id).await;
+        let result = async {
+            attendee
+                .user
+                .create_dm_channel(http)
+                .await?
+                .say(http, dm_reminder_message(event, time_zone))
+                .await
+        }
+        .await;
+        if let Err(e) = result {
+            warn!("Failed to send reminder to user({user_id}) - {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn remind_upcoming_events(
+    db_pool: &SqlitePool,
+    http: &Arc<Http>,
+    guild_id: GuildId,
+    setting: ReminderSetting,
+) -> anyhow::Result<()> {
+    let preferences: HashMap<i64, (bool, i64)> =
+        sqlx::query!("SELECT `user_id`, `dm`, `lead_minutes` FROM `event_notify_preferences`")
+            .fetch_all(db_pool)
+            .await
+            .context("Failed to get notify preferences")?
+            .into_iter()
+            .map(|row| (row.user_id, (row.dm, row.lead_minutes)))
+            .collect();
+    // users could be reminded earlier than the channel
+    let dm_lead_minutes = preferences
+        .values()
+        .filter(|(dm, _)| *dm)
+        .map(|(_, lead_minutes)| *lead_minutes)
+        .chain(setting.dm.then(|| setting.before.num_minutes()))
+        .max();
+
+    let now = chrono::Utc::now().timestamp();
+    let events = http
+        .get_scheduled_events(guild_id.0, false)
+        .await
+        .context("Failed to get scheduled events")?;
+
+    for event in events {
+        let start_time = event.start_time.unix_timestamp();
+        if !matches!(event.status, ScheduledEventStatus::Scheduled) || start_time < now {
+            continue;
+        }
+
+        if let Some(channel_id) = setting.channel_id {
+            if start_time <= now + setting.before.num_seconds() {
+                if let Err(e) = remind_channel(db_pool, http, &event, channel_id).await {
+                    error!("Failed to remind event({}) - {e:?}", event.id);
+                }
+            }
+        }
+
+        if let Some(lead_minutes) = dm_lead_minutes {
+            if start_time <= now + lead_minutes * 60 {
+                if let Err(e) =
+                    remind_users(db_pool, http, &event, &preferences, setting, now).await
+                {
+                    error!("Failed to remind event({}) to users - {e:?}", event.id);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    pub(super) fn start_reminder(&self, context: &Context, guild_id: GuildId) {
+        if self.reminder_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let http = context.http.clone();
+        let setting = ReminderSetting {
+            channel_id: self.reminder_channel_id,
+            before: self.reminder_before,
+            dm: self.reminder_dm,
+        };
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = remind_upcoming_events(&db_pool, &http, guild_id, setting).await {
+                    error!("Failed to remind upcoming events - {e:?}");
+                }
+
+                tokio::time::sleep(REMINDER_TICK).await;
+            }
+        });
+    }
+
+    pub(super) async fn handle_notify_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [dm, minutes] = option.get_options(&["dm", "minutes"]);
+        let dm = dm.as_bool().unwrap_or(true);
+        let lead_minutes = minutes
+            .as_i64()
+            .unwrap_or_else(|| self.reminder_before.num_minutes())
+            .clamp(1, MAX_LEAD_MINUTES);
+        let user_id = *interaction.user.id.as_u64() as i64;
+
+        sqlx::query!(
+            "INSERT INTO `event_notify_preferences` (`user_id`, `dm`, `lead_minutes`)
+            VALUES (?, ?, ?)
+            ON CONFLICT (`user_id`) DO UPDATE SET
+                `dm` = excluded.`dm`,
+                `lead_minutes` = excluded.`lead_minutes`",
+            user_id,
+            dm,
+            lead_minutes
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save notify preference")?;
+
+        let content = if dm {
+            format!("관심 있는 이벤트가 시작하기 {lead_minutes}분 전에 DM으로 알려드립니다.")
+        } else {
+            "이벤트 DM 알림을 받지 않습니다.".to_string()
+        };
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use anyhow::Context as _;
+use log::{error, info};
+use serenity::{
+    model::application::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use super::{can_manage_events, sync_queue::SyncSummary, DiscordHandler};
+
+impl DiscordHandler {
+    pub(super) async fn handle_resync_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        if !can_manage_events(interaction.member.as_ref()) {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.content("이벤트 관리 권한이 필요합니다.").ephemeral(true)
+                        })
+                })
+                .await?;
+            return Ok(());
+        }
+
+        // syncing every event takes longer than the interaction deadline
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|b| b.ephemeral(true))
+            })
+            .await?;
+
+        let guild_id = interaction
+            .guild_id
+            .context("Command is not used in guild")?;
+        let events = context
+            .http
+            .get_scheduled_events(guild_id.0, false)
+            .await
+            .context("Failed to get scheduled events")?;
+
+        let mut summary = SyncSummary::default();
+        let mut failed = 0;
+        for event in &events {
+            match self.update_server_event(context, event).await {
+                Ok(event_summary) => summary.merge(event_summary),
+                Err(e) => {
+                    error!("Failed to resync event({}) - {e:?}", event.id);
+                    failed += 1;
+                }
+            }
+        }
+
+        let alive: HashSet<i64> = events.iter().map(|event| event.id.0 as i64).collect();
+        let saved_ids = sqlx::query_scalar!("SELECT DISTINCT `discord_id` FROM `server_events`")
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to get saved events from DB")?;
+        let mut removed = 0;
+        for discord_id in saved_ids {
+            if alive.contains(&discord_id) {
+                continue;
+            }
+            info!("Remove google events of vanished event({discord_id})");
+            match self.remove_server_event(discord_id).await {
+                Ok(event_summary) => {
+                    removed += 1;
+                    summary.merge(event_summary);
+                }
+                Err(e) => {
+                    error!("Failed to remove google events of event({discord_id}) - {e:?}");
+                    failed += 1;
+                }
+            }
+        }
+
+        interaction
+            .edit_original_interaction_response(&context.http, |b| {
+                b.content(format!(
+                    "동기화 완료\n이벤트 {}개 확인, 사라진 이벤트 {removed}개 정리\n추가 {} · 수정 {} · 삭제 {} · 재시도 대기 {} · 실패 {failed}",
+                    events.len(),
+                    summary.inserted,
+                    summary.updated,
+                    summary.deleted,
+                    summary.queued,
+                ))
+            })
+            .await?;
+
+        Ok(())
+    }
+}
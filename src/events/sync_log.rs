@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use axum::{
+    extract::{Extension, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use log::error;
+use serenity::{
+    model::application::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{can_manage_events, sync_queue::SyncTask, verify_token, DiscordHandler};
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+const RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+const COMMAND_LIMIT: i64 = 15;
+const PAGE_LIMIT: i64 = 200;
+// discord rejects longer messages
+const MAX_MESSAGE_LENGTH: usize = 2000;
+const MAX_ERROR_LENGTH: usize = 80;
+
+struct LogEntry {
+    discord_id: i64,
+    user_id: i64,
+    action: String,
+    success: bool,
+    error: Option<String>,
+    created_at: i64,
+}
+
+pub(super) async fn record(
+    db_pool: &SqlitePool,
+    task: &SyncTask,
+    result: &anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let action = task.operation.action();
+    let success = result.is_ok();
+    let error = result.as_ref().err().map(|e| format!("{e:#}"));
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query!(
+        "INSERT INTO `sync_log`
+            (`discord_id`, `user_id`, `action`, `success`, `error`, `created_at`)
+        VALUES (?, ?, ?, ?, ?, ?)",
+        task.discord_id,
+        task.user_id,
+        action,
+        success,
+        error,
+        now
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to write sync log")?;
+
+    Ok(())
+}
+
+pub(super) async fn prune(db_pool: &SqlitePool) -> anyhow::Result<()> {
+    let expired_at = chrono::Utc::now().timestamp() - RETENTION_SECS;
+    sqlx::query!("DELETE FROM `sync_log` WHERE `created_at` < ?", expired_at)
+        .execute(db_pool)
+        .await
+        .context("Failed to prune sync log")?;
+
+    Ok(())
+}
+
+async fn fetch_entries(
+    db_pool: &SqlitePool,
+    failures_only: bool,
+    user_id: Option<i64>,
+    limit: i64,
+) -> anyhow::Result<Vec<LogEntry>> {
+    sqlx::query_as!(
+        LogEntry,
+        r#"SELECT
+            `discord_id`,
+            `user_id`,
+            `action`,
+            `success` AS "success: bool",
+            `error`,
+            `created_at`
+        FROM `sync_log`
+        WHERE (NOT ? OR NOT `success`) AND (? IS NULL OR `user_id` = ?)
+        ORDER BY `id` DESC
+        LIMIT ?"#,
+        failures_only,
+        user_id,
+        user_id,
+        limit
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get sync log")
+}
+
+impl DiscordHandler {
+    pub(super) async fn handle_synclog_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        if !can_manage_events(interaction.member.as_ref()) {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.content("이벤트 관리 권한이 필요합니다.").ephemeral(true)
+                        })
+                })
+                .await?;
+            return Ok(());
+        }
+
+        let [user, all] = option.get_options(&["user", "all"]);
+        let user_id = user
+            .as_str()
+            .map(|user| user.parse::<i64>())
+            .transpose()
+            .context("Invalid user")?;
+        let failures_only = !all.as_bool().unwrap_or(false);
+        let entries = fetch_entries(&self.db_pool, failures_only, user_id, COMMAND_LIMIT).await?;
+
+        let mut content = String::new();
+        for entry in entries {
+            let mut line = format!(
+                "<t:{}:R> {} `{}` <@{}> {}",
+                entry.created_at,
+                if entry.success { "✅" } else { "❌" },
+                entry.action,
+                entry.user_id,
+                entry.discord_id
+            );
+            if let Some(error) = &entry.error {
+                let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+                line.push_str(&format!("\n> {error}"));
+            }
+            if content.len() + line.len() + 1 > MAX_MESSAGE_LENGTH {
+                break;
+            }
+            content.push_str(&line);
+            content.push('\n');
+        }
+        if content.is_empty() {
+            content = "기록이 없습니다.".to_string();
+        }
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct SyncLogQuery {
+    token: String,
+    #[serde(default)]
+    all: bool,
+}
+
+pub(crate) async fn sync_log_page(
+    Extension(db_pool): Extension<SqlitePool>,
+    Extension(config): Extension<Arc<crate::Config>>,
+    Query(query): Query<SyncLogQuery>,
+) -> Response {
+    let verified = config
+        .events
+        .sync_log_token
+        .as_deref()
+        .is_some_and(|expected| verify_token(expected, &query.token));
+    if !verified {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let entries = match fetch_entries(&db_pool, !query.all, None, PAGE_LIMIT).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to render sync log - {e:?}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let mut body = String::new();
+    for entry in entries {
+        let created_at = chrono::DateTime::from_timestamp(entry.created_at, 0)
+            .map(|created_at| created_at.to_rfc3339())
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "{created_at}\t{}\t{}\tevent={}\tuser={}\t{}\n",
+            if entry.success { "ok" } else { "failed" },
+            entry.action,
+            entry.discord_id,
+            entry.user_id,
+            entry.error.as_deref().unwrap_or_default()
+        ));
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response()
+}
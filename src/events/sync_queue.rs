@@ -0,0 +1,342 @@
+use std::sync::atomic::Ordering;
+
+use anyhow::Context as _;
+use google_calendar3::api::Event as GoogleEvent;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use super::{
+    backend::{fetch_user_calendars, GoogleHub, UserCalendar},
+    sync_log, DiscordHandler,
+};
+
+const RETRY_TICK: std::time::Duration = std::time::Duration::from_secs(30);
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 60 * 60;
+const MAX_ATTEMPTS: i64 = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(super) enum SyncOperation {
+    Insert {
+        // generated once per task, to be the same while retrying
+        #[serde(default = "new_event_id")]
+        event_id: String,
+        event: GoogleEvent,
+    },
+    Update {
+        google_event_id: String,
+        event: GoogleEvent,
+    },
+    Delete {
+        google_event_id: String,
+    },
+}
+
+// base32hex, which google accepts as an event id
+pub(super) fn new_event_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+impl SyncOperation {
+    pub(super) fn action(&self) -> &'static str {
+        match self {
+            Self::Insert { .. } => "insert",
+            Self::Update { .. } => "update",
+            Self::Delete { .. } => "delete",
+        }
+    }
+}
+
+// calendar change of a single attendee
+#[derive(Debug)]
+pub(super) struct SyncTask {
+    pub(super) discord_id: i64,
+    pub(super) user_id: i64,
+    pub(super) calendar: UserCalendar,
+    pub(super) operation: SyncOperation,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct SyncSummary {
+    pub(super) inserted: usize,
+    pub(super) updated: usize,
+    pub(super) deleted: usize,
+    pub(super) queued: usize,
+}
+
+impl SyncSummary {
+    pub(super) fn merge(&mut self, other: SyncSummary) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.deleted += other.deleted;
+        self.queued += other.queued;
+    }
+}
+
+fn backoff_secs(attempts: i64) -> i64 {
+    (BASE_BACKOFF_SECS << attempts.clamp(0, 16)).min(MAX_BACKOFF_SECS)
+}
+
+impl SyncTask {
+    pub(super) async fn apply(&self, db_pool: &SqlitePool, hub: &GoogleHub) -> anyhow::Result<()> {
+        let result = self.run(db_pool, hub).await;
+        if let Err(e) = sync_log::record(db_pool, self, &result).await {
+            error!("{e:?}");
+        }
+
+        result
+    }
+
+    async fn run(&self, db_pool: &SqlitePool, hub: &GoogleHub) -> anyhow::Result<()> {
+        let (discord_id, user_id) = (self.discord_id, self.user_id);
+        let backend = self.calendar.backend(db_pool, hub);
+        match &self.operation {
+            SyncOperation::Insert { event_id, event } => {
+                let google_event_id = backend
+                    .insert_event(event_id, event)
+                    .await
+                    .with_context(|| format!("Failed to insert new event for user({user_id})"))?;
+                sqlx::query!(
+                    r#"
+                    INSERT INTO `server_events`
+                        (`discord_id`, `google_event_id`, `user_id`)
+                        VALUES
+                        (?, ?, ?)
+                    "#,
+                    discord_id,
+                    google_event_id,
+                    user_id,
+                )
+                .execute(db_pool)
+                .await
+                .context("Failed to insert google event in DB")?;
+            }
+            SyncOperation::Update {
+                google_event_id,
+                event,
+            } => {
+                backend
+                    .update_event(google_event_id, event)
+                    .await
+                    .with_context(|| format!("Failed update event for user({user_id})"))?;
+            }
+            SyncOperation::Delete { google_event_id } => {
+                backend
+                    .delete_event(google_event_id)
+                    .await
+                    .with_context(|| format!("Failed delete event for user({user_id})"))?;
+
+                sqlx::query!(
+                    "DELETE FROM `server_events`
+                    WHERE `discord_id` = ? AND `user_id` = ?",
+                    discord_id,
+                    user_id
+                )
+                .execute(db_pool)
+                .await
+                .context("Failed to delete events in discord")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) async fn enqueue(&self, db_pool: &SqlitePool, attempts: i64) -> anyhow::Result<()> {
+        let operation =
+            serde_json::to_string(&self.operation).context("Failed to serialize operation")?;
+        let next_attempt_at = chrono::Utc::now().timestamp() + backoff_secs(attempts);
+        sqlx::query!(
+            "INSERT INTO `google_sync_queue`
+                (`discord_id`, `user_id`, `operation`, `attempts`, `next_attempt_at`)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (`discord_id`, `user_id`) DO UPDATE SET
+                `operation` = excluded.`operation`,
+                `attempts` = excluded.`attempts`,
+                `next_attempt_at` = excluded.`next_attempt_at`",
+            self.discord_id,
+            self.user_id,
+            operation,
+            attempts,
+            next_attempt_at
+        )
+        .execute(db_pool)
+        .await
+        .context("Failed to enqueue google sync")?;
+
+        Ok(())
+    }
+}
+
+// keep going on failure not to leave other attendees out of sync
+pub(super) async fn apply_tasks(
+    db_pool: &SqlitePool,
+    hub: &GoogleHub,
+    tasks: Vec<SyncTask>,
+) -> anyhow::Result<SyncSummary> {
+    let mut summary = SyncSummary::default();
+    for task in tasks {
+        if let Err(e) = task.apply(db_pool, hub).await {
+            error!("Failed to sync google event. retry later - {e:?}");
+            task.enqueue(db_pool, 0).await?;
+            summary.queued += 1;
+            continue;
+        }
+
+        match task.operation {
+            SyncOperation::Insert { .. } => summary.inserted += 1,
+            SyncOperation::Update { .. } => summary.updated += 1,
+            SyncOperation::Delete { .. } => summary.deleted += 1,
+        }
+    }
+
+    Ok(summary)
+}
+
+// a fresh sync of the event supersedes pending retries
+pub(super) async fn clear_queue(db_pool: &SqlitePool, discord_id: i64) -> anyhow::Result<()> {
+    sqlx::query!(
+        "DELETE FROM `google_sync_queue` WHERE `discord_id` = ?",
+        discord_id
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to clear google sync queue")?;
+
+    Ok(())
+}
+
+async fn retry_pending_tasks(
+    db_pool: &SqlitePool,
+    service_account: google_calendar3::oauth2::ServiceAccountKey,
+) -> anyhow::Result<()> {
+    let now = chrono::Utc::now().timestamp();
+    let pending = sqlx::query!(
+        "SELECT `discord_id`, `user_id`, `operation`, `attempts`
+        FROM `google_sync_queue`
+        WHERE `next_attempt_at` <= ?",
+        now
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get pending google syncs")?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let hub = DiscordHandler::service_account_calendar_hub(service_account)
+        .await
+        .context("Failed to create google calendar hub")?;
+    // users could change or disconnect their calendars while waiting
+    let calendars = fetch_user_calendars(db_pool, pending.iter().map(|row| row.user_id)).await?;
+    for row in pending {
+        let operation = match serde_json::from_str(&row.operation) {
+            Ok(operation) => operation,
+            Err(e) => {
+                error!("Drop malformed google sync operation - {e:?}");
+                clear_task(db_pool, row.discord_id, row.user_id, &row.operation).await?;
+                continue;
+            }
+        };
+        let Some(calendar) = calendars.get(&row.user_id).cloned() else {
+            warn!(
+                "Drop google sync of event({}). user({}) does not connected to calendar",
+                row.discord_id, row.user_id
+            );
+            clear_task(db_pool, row.discord_id, row.user_id, &row.operation).await?;
+            continue;
+        };
+        let task = SyncTask {
+            discord_id: row.discord_id,
+            user_id: row.user_id,
+            calendar,
+            operation,
+        };
+
+        let attempts = row.attempts + 1;
+        match task.apply(db_pool, &hub).await {
+            Ok(()) => {
+                info!(
+                    "Google sync of event({}) for user({}) is done after {attempts} retries",
+                    task.discord_id, task.user_id
+                );
+                clear_task(db_pool, task.discord_id, task.user_id, &row.operation).await?;
+            }
+            Err(e) if attempts >= MAX_ATTEMPTS => {
+                error!(
+                    "Give up google sync of event({}) for user({}) - {e:?}",
+                    task.discord_id, task.user_id
+                );
+                clear_task(db_pool, task.discord_id, task.user_id, &row.operation).await?;
+            }
+            Err(e) => {
+                error!(
+                    "Failed to retry google sync of event({}) for user({}) - {e:?}",
+                    task.discord_id, task.user_id
+                );
+                let next_attempt_at = chrono::Utc::now().timestamp() + backoff_secs(attempts);
+                sqlx::query!(
+                    "UPDATE `google_sync_queue`
+                    SET `attempts` = ?, `next_attempt_at` = ?
+                    WHERE `discord_id` = ? AND `user_id` = ? AND `operation` = ?",
+                    attempts,
+                    next_attempt_at,
+                    task.discord_id,
+                    task.user_id,
+                    row.operation
+                )
+                .execute(db_pool)
+                .await
+                .context("Failed to update google sync queue")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the operation is compared to keep the task replaced by a newer sync while retrying
+async fn clear_task(
+    db_pool: &SqlitePool,
+    discord_id: i64,
+    user_id: i64,
+    operation: &str,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "DELETE FROM `google_sync_queue`
+        WHERE `discord_id` = ? AND `user_id` = ? AND `operation` = ?",
+        discord_id,
+        user_id,
+        operation
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to delete google sync task")?;
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    pub(super) fn start_sync_retry(&self) {
+        if self.sync_retry_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let service_account = self.service_account.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = retry_pending_tasks(&db_pool, service_account.clone()).await {
+                    error!("Failed to retry google syncs - {e:?}");
+                }
+                if let Err(e) = sync_log::prune(&db_pool).await {
+                    error!("{e:?}");
+                }
+
+                tokio::time::sleep(RETRY_TICK).await;
+            }
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serenity::{
+    model::prelude::{ChannelId, ChannelType, MessageId, ScheduledEvent, ScheduledEventStatus},
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+
+fn summary_message(event: &ScheduledEvent) -> String {
+    let mut summary = format!(
+        "📅 **{}**\n<t:{}:F>",
+        event.name,
+        event.start_time.unix_timestamp()
+    );
+    if let Some(end_time) = event.end_time {
+        summary.push_str(&format!(" ~ <t:{}:F>", end_time.unix_timestamp()));
+    }
+    if let Some(metadata) = &event.metadata {
+        summary.push_str(&format!("\n📍 {}", metadata.location));
+    }
+    if let Some(description) = event.description.as_deref().filter(|d| !d.is_empty()) {
+        summary.push_str(&format!("\n\n{description}"));
+    }
+    summary.push_str(&format!(
+        "\n\nhttps://discord.com/events/{}/{}",
+        event.guild_id, event.id
+    ));
+
+    summary
+}
+
+fn status_message(status: ScheduledEventStatus) -> Option<&'static str> {
+    match status {
+        ScheduledEventStatus::Active => Some("▶️ 이벤트가 시작되었습니다."),
+        ScheduledEventStatus::Completed => Some("⏹️ 이벤트가 끝났습니다."),
+        ScheduledEventStatus::Canceled => Some("🚫 이벤트가 취소되었습니다."),
+        _ => None,
+    }
+}
+
+impl DiscordHandler {
+    // discussion thread of the event with a pinned summary
+    pub(super) async fn sync_event_thread(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<()> {
+        let Some(channel_id) = self.thread_channel_id else {
+            return Ok(());
+        };
+
+        let discord_id = *event.id.as_u64() as i64;
+        let status = event.status as i64;
+        let saved = sqlx::query!(
+            "SELECT `thread_id`, `message_id`, `status` FROM `event_threads` WHERE `discord_id` = ?",
+            discord_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get event thread from DB")?;
+
+        let Some(saved) = saved else {
+            if !matches!(
+                event.status,
+                ScheduledEventStatus::Scheduled | ScheduledEventStatus::Active
+            ) {
+                return Ok(());
+            }
+
+            info!("Create thread of event({})", event.id);
+            let thread = channel_id
+                .create_private_thread(context, |b| {
+                    b.name(&event.name).kind(ChannelType::PublicThread)
+                })
+                .await
+                .context("Failed to create event thread")?;
+            // saved first not to create another thread when the summary fails
+            let thread_id = *thread.id.as_u64() as i64;
+            sqlx::query!(
+                "INSERT INTO `event_threads` (`discord_id`, `thread_id`, `status`)
+                VALUES (?, ?, ?)",
+                discord_id,
+                thread_id,
+                status
+            )
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to save event thread")?;
+
+            return self.post_summary(context, thread.id, event).await;
+        };
+
+        let thread_id = ChannelId(saved.thread_id as u64);
+        match saved.message_id {
+            Some(message_id) => thread_id
+                .edit_message(context, MessageId(message_id as u64), |b| {
+                    b.content(summary_message(event))
+                })
+                .await
+                .map(|_| ())
+                .context("Failed to update event summary")?,
+            None => self.post_summary(context, thread_id, event).await?,
+        }
+        // renaming threads is heavily rate limited
+        let renamed = context
+            .cache
+            .guild_channel(thread_id)
+            .map_or(true, |thread| thread.name != event.name);
+        if renamed {
+            thread_id
+                .edit_thread(context, |b| b.name(&event.name))
+                .await
+                .context("Failed to rename event thread")?;
+        }
+
+        if saved.status != status {
+            if let Some(message) = status_message(event.status) {
+                thread_id
+                    .say(context, message)
+                    .await
+                    .context("Failed to post event status")?;
+            }
+            sqlx::query!(
+                "UPDATE `event_threads` SET `status` = ? WHERE `discord_id` = ?",
+                status,
+                discord_id
+            )
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to update event thread status")?;
+        }
+
+        Ok(())
+    }
+
+    async fn post_summary(
+        &self,
+        context: &Context,
+        thread_id: ChannelId,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<()> {
+        let message = thread_id
+            .say(context, summary_message(event))
+            .await
+            .context("Failed to post event summary")?;
+        let discord_id = *event.id.as_u64() as i64;
+        let message_id = *message.id.as_u64() as i64;
+        sqlx::query!(
+            "UPDATE `event_threads` SET `message_id` = ? WHERE `discord_id` = ?",
+            message_id,
+            discord_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save event summary")?;
+
+        // the summary is still the first message of the thread without the pin
+        if let Err(e) = message.pin(context).await {
+            error!("Failed to pin summary of event({}) - {e:?}", event.id);
+        }
+
+        Ok(())
+    }
+
+    pub(super) async fn close_event_thread(
+        &self,
+        context: &Context,
+        event: &ScheduledEvent,
+    ) -> anyhow::Result<()> {
+        let discord_id = *event.id.as_u64() as i64;
+        let Some(thread_id) = sqlx::query_scalar!(
+            "DELETE FROM `event_threads` WHERE `discord_id` = ? RETURNING `thread_id`",
+            discord_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to delete event thread in DB")?
+        else {
+            return Ok(());
+        };
+
+        let thread_id = ChannelId(thread_id as u64);
+        thread_id
+            .say(context, "🗑️ 이벤트가 삭제되었습니다.")
+            .await
+            .context("Failed to post event deletion")?;
+        thread_id
+            .edit_thread(context, |b| b.archived(true))
+            .await
+            .context("Failed to archive event thread")?;
+
+        Ok(())
+    }
+}
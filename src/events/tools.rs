@@ -0,0 +1,66 @@
+use anyhow::Context as _;
+use serde_json::json;
+use serenity::{
+    model::{guild::ScheduledEventStatus, id::GuildId},
+    prelude::Context,
+};
+
+use crate::llm::LlmTool;
+
+// events given to the model at once
+const EVENTS_LIMIT: usize = 10;
+
+pub(super) fn declarations() -> Vec<LlmTool> {
+    vec![LlmTool {
+        name: "get_upcoming_events",
+        description: "Scheduled events of the server which are ongoing or not started yet, \
+            from the earliest one",
+        parameters: None,
+    }]
+}
+
+pub(super) async fn call(
+    context: &Context,
+    guild_id: GuildId,
+    name: &str,
+) -> Option<anyhow::Result<serde_json::Value>> {
+    match name {
+        "get_upcoming_events" => Some(upcoming_events(context, guild_id).await),
+        _ => None,
+    }
+}
+
+async fn upcoming_events(
+    context: &Context,
+    guild_id: GuildId,
+) -> anyhow::Result<serde_json::Value> {
+    let mut events = context
+        .http
+        .get_scheduled_events(guild_id.0, true)
+        .await
+        .context("Failed to get scheduled events")?;
+    events.retain(|event| {
+        matches!(
+            event.status,
+            ScheduledEventStatus::Scheduled | ScheduledEventStatus::Active
+        )
+    });
+    events.sort_by_key(|event| event.start_time);
+
+    Ok(json!({
+        "events": events
+            .iter()
+            .take(EVENTS_LIMIT)
+            .map(|event| json!({
+                "name": event.name,
+                "description": event.description,
+                "location": event.metadata.as_ref().map(|metadata| metadata.location.clone()),
+                "start_time": event.start_time.to_string(),
+                "end_time": event.end_time.map(|time| time.to_string()),
+                "ongoing": matches!(event.status, ScheduledEventStatus::Active),
+                "interested_users": event.user_count,
+                "link": format!("https://discord.com/events/{}/{}", event.guild_id, event.id),
+            }))
+            .collect::<Vec<_>>(),
+    }))
+}
@@ -0,0 +1,70 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::DateTime;
+use log::error;
+
+use crate::{stats::Stats, user::DEFAULT_TIME_ZONE, web::escape_html};
+
+const PAGE: &str = include_str!("upcoming.html");
+const PAGE_LIMIT: i64 = 50;
+
+// rendered in the default time zone for viewers without javascript
+fn time_tag(time: &str) -> String {
+    let text = DateTime::parse_from_rfc3339(time)
+        .map(|time| {
+            time.with_timezone(&DEFAULT_TIME_ZONE)
+                .format("%Y-%m-%d %H:%M %Z")
+                .to_string()
+        })
+        .unwrap_or_else(|_| time.to_string());
+
+    format!(
+        r#"<time datetime="{}">{}</time>"#,
+        escape_html(time),
+        escape_html(&text)
+    )
+}
+
+// public, to share with people not on discord yet
+pub(crate) async fn upcoming_page(Extension(stats): Extension<Stats>) -> Response {
+    let events = match stats.upcoming_events(PAGE_LIMIT).await {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Failed to render upcoming events - {e:?}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let mut body = String::new();
+    for event in &events {
+        let mut time = time_tag(&event.start_time);
+        if let Some(end_time) = &event.end_time {
+            time.push_str(" ~ ");
+            time.push_str(&time_tag(end_time));
+        }
+
+        body.push_str(&format!(
+            r#"<article><h2>{}</h2><p class="meta">{time}"#,
+            escape_html(&event.name)
+        ));
+        if let Some(location) = &event.location {
+            body.push_str(&format!("<br>장소: {}", escape_html(location)));
+        }
+        body.push_str(&format!("<br>관심: {}명</p>", event.interested_count));
+        if let Some(description) = event.description.as_deref().filter(|d| !d.is_empty()) {
+            body.push_str(&format!(
+                r#"<p class="description">{}</p>"#,
+                escape_html(description)
+            ));
+        }
+        body.push_str("</article>");
+    }
+    if events.is_empty() {
+        body.push_str("<p>예정된 이벤트가 없습니다.</p>");
+    }
+
+    Html(PAGE.replace("{{events}}", &body)).into_response()
+}
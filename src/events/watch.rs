@@ -0,0 +1,432 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::Ordering, Arc},
+};
+
+use anyhow::Context as _;
+use axum::{extract::Extension, http::HeaderMap, http::StatusCode};
+use google_calendar3::api::{Channel, Event as GoogleEvent, EventDateTime};
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
+use serenity::{http::Http, model::prelude::GuildId, prelude::Context};
+use sqlx::SqlitePool;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::{
+    backend::GoogleHub, date_time::EventTimeZone, fetch_recurrence_rule, verify_token,
+    DiscordHandler,
+};
+
+const RENEW_TICK: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+// channels expire in a week at most
+const RENEW_BEFORE_SECS: i64 = 24 * 60 * 60;
+
+// ids of channels notified by google. consumed by the watch task.
+static NOTIFICATIONS: OnceCell<mpsc::UnboundedSender<String>> = OnceCell::new();
+
+struct Watcher {
+    db_pool: SqlitePool,
+    http: Arc<Http>,
+    guild_id: GuildId,
+    time_zone: EventTimeZone,
+    // webhook address for google
+    address: String,
+}
+
+fn same_date_time(lhs: Option<&EventDateTime>, rhs: Option<&EventDateTime>) -> bool {
+    lhs.map(|d| (d.date_time, d.date)) == rhs.map(|d| (d.date_time, d.date))
+}
+
+// whether the mirrored event is edited or deleted outside of discord
+fn drifted(expected: &GoogleEvent, actual: &GoogleEvent) -> bool {
+    expected.status != actual.status
+        || expected.summary != actual.summary
+        || expected.description != actual.description
+        || expected.location != actual.location
+        || !same_date_time(expected.start.as_ref(), actual.start.as_ref())
+        || !same_date_time(expected.end.as_ref(), actual.end.as_ref())
+}
+
+fn is_gone(error: &google_calendar3::Error) -> bool {
+    matches!(error, google_calendar3::Error::BadRequest(body) if body["error"]["code"] == 410)
+}
+
+// sync token of the current state, without looking into changes
+async fn fresh_sync_token(hub: &GoogleHub, calendar_id: &str) -> anyhow::Result<String> {
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut call = hub.events().list(calendar_id).max_results(2500);
+        if let Some(page_token) = &page_token {
+            call = call.page_token(page_token);
+        }
+        let events = call
+            .doit()
+            .await
+            .context("Failed to list events of user calendar")?
+            .1;
+        if let Some(sync_token) = events.next_sync_token {
+            return Ok(sync_token);
+        }
+        page_token = Some(
+            events
+                .next_page_token
+                .context("Neither of sync token and page token is returned")?,
+        );
+    }
+}
+
+impl Watcher {
+    async fn open_channel(
+        &self,
+        hub: &GoogleHub,
+        user_id: i64,
+        calendar_id: &str,
+        sync_token: Option<String>,
+    ) -> anyhow::Result<()> {
+        let channel_id = Uuid::new_v4().to_string();
+        let token = Uuid::new_v4().to_string();
+        let channel = hub
+            .events()
+            .watch(
+                Channel {
+                    id: Some(channel_id.clone()),
+                    type_: Some("web_hook".to_string()),
+                    address: Some(self.address.clone()),
+                    token: Some(token.clone()),
+                    ..Default::default()
+                },
+                calendar_id,
+            )
+            .doit()
+            .await
+            .with_context(|| format!("Failed to watch calendar of user({user_id})"))?
+            .1;
+        let resource_id = channel
+            .resource_id
+            .context("Watch channel has no resource id")?;
+        // milliseconds
+        let expiration = channel.expiration.unwrap_or_default() / 1000;
+        let sync_token = match sync_token {
+            Some(sync_token) => sync_token,
+            None => fresh_sync_token(hub, calendar_id).await?,
+        };
+
+        sqlx::query!(
+            "INSERT INTO `google_watch_channels`
+                (`channel_id`, `user_id`, `calendar_id`, `resource_id`, `token`, `expiration`, `sync_token`)
+            VALUES (?, ?, ?, ?, ?, ?, ?)",
+            channel_id,
+            user_id,
+            calendar_id,
+            resource_id,
+            token,
+            expiration,
+            sync_token
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save watch channel")?;
+
+        Ok(())
+    }
+
+    async fn close_channel(
+        &self,
+        hub: &GoogleHub,
+        channel_id: String,
+        resource_id: String,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "DELETE FROM `google_watch_channels` WHERE `channel_id` = ?",
+            channel_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to delete watch channel")?;
+
+        // expires anyway
+        if let Err(e) = hub
+            .channels()
+            .stop(Channel {
+                id: Some(channel_id),
+                resource_id: Some(resource_id),
+                ..Default::default()
+            })
+            .doit()
+            .await
+        {
+            warn!("Failed to stop watch channel - {e:?}");
+        }
+
+        Ok(())
+    }
+
+    // watch every connected google calendar and renew channels before they expire
+    async fn renew_channels(&self, hub: &GoogleHub) -> anyhow::Result<()> {
+        let calendars: HashMap<i64, String> = sqlx::query!(
+            r#"SELECT `user_id`, `google_calendar_id` AS "calendar_id!"
+            FROM `users`
+            WHERE `calendar_backend` = 'google' AND `google_calendar_id` IS NOT NULL"#
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get google calendars of users")?
+        .into_iter()
+        .map(|row| (row.user_id, row.calendar_id))
+        .collect();
+        let channels = sqlx::query!(
+            "SELECT `channel_id`, `user_id`, `calendar_id`, `resource_id`, `expiration`, `sync_token`
+            FROM `google_watch_channels`"
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get watch channels")?;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut watched = HashSet::new();
+        for channel in channels {
+            if calendars.get(&channel.user_id) != Some(&channel.calendar_id) {
+                info!("Stop watching calendar of user({})", channel.user_id);
+                self.close_channel(hub, channel.channel_id, channel.resource_id)
+                    .await?;
+                continue;
+            }
+
+            watched.insert(channel.user_id);
+            if channel.expiration > now + RENEW_BEFORE_SECS {
+                continue;
+            }
+            info!("Renew watch channel of user({})", channel.user_id);
+            if let Err(e) = self
+                .open_channel(
+                    hub,
+                    channel.user_id,
+                    &channel.calendar_id,
+                    channel.sync_token,
+                )
+                .await
+            {
+                error!("Failed to renew watch channel - {e:?}");
+                continue;
+            }
+            self.close_channel(hub, channel.channel_id, channel.resource_id)
+                .await?;
+        }
+
+        for (user_id, calendar_id) in calendars {
+            if watched.contains(&user_id) {
+                continue;
+            }
+            info!("Watch calendar of user({user_id})");
+            if let Err(e) = self.open_channel(hub, user_id, &calendar_id, None).await {
+                error!("Failed to open watch channel - {e:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_notification(&self, hub: &GoogleHub, channel_id: &str) -> anyhow::Result<()> {
+        let Some(channel) = sqlx::query!(
+            "SELECT `user_id`, `calendar_id`, `sync_token`
+            FROM `google_watch_channels`
+            WHERE `channel_id` = ?",
+            channel_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get watch channel")?
+        else {
+            return Ok(());
+        };
+        let Some(mut sync_token) = channel.sync_token else {
+            return Ok(());
+        };
+
+        let mut changed = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut call = hub
+                .events()
+                .list(&channel.calendar_id)
+                .sync_token(&sync_token);
+            if let Some(page_token) = &page_token {
+                call = call.page_token(page_token);
+            }
+            let events = match call.doit().await {
+                Ok((_, events)) => events,
+                Err(e) if is_gone(&e) => {
+                    warn!(
+                        "Sync token of user({}) is expired. skip changes",
+                        channel.user_id
+                    );
+                    changed.clear();
+                    sync_token = fresh_sync_token(hub, &channel.calendar_id).await?;
+                    break;
+                }
+                Err(e) => return Err(e).context("Failed to list changed events"),
+            };
+            changed.extend(events.items.unwrap_or_default());
+            page_token = events.next_page_token;
+            if let Some(next_sync_token) = events.next_sync_token {
+                sync_token = next_sync_token;
+                break;
+            }
+        }
+
+        sqlx::query!(
+            "UPDATE `google_watch_channels` SET `sync_token` = ? WHERE `channel_id` = ?",
+            sync_token,
+            channel_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to save sync token")?;
+
+        for google_event in changed {
+            let Some(google_event_id) = google_event.id.clone() else {
+                continue;
+            };
+            let discord_id = sqlx::query_scalar!(
+                "SELECT `discord_id` FROM `server_events`
+                WHERE `google_event_id` = ? AND `user_id` = ?",
+                google_event_id,
+                channel.user_id
+            )
+            .fetch_optional(&self.db_pool)
+            .await
+            .context("Failed to get synced event from DB")?;
+            let Some(discord_id) = discord_id else {
+                continue;
+            };
+
+            if let Err(e) = self
+                .restore_event(hub, &channel.calendar_id, discord_id, &google_event)
+                .await
+            {
+                error!("Failed to restore google event({google_event_id}) - {e:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    // discord is the source of truth. edits of mirrored events are reverted.
+    async fn restore_event(
+        &self,
+        hub: &GoogleHub,
+        calendar_id: &str,
+        discord_id: i64,
+        google_event: &GoogleEvent,
+    ) -> anyhow::Result<()> {
+        let Ok(event) = self
+            .http
+            .get_scheduled_event(self.guild_id.0, discord_id as u64, false)
+            .await
+        else {
+            // removed by the next sync of the event
+            return Ok(());
+        };
+        let recurrence_rule = fetch_recurrence_rule(&self.http, &event).await?;
+        let expected = DiscordHandler::discord_event_to_google_event(
+            &event,
+            recurrence_rule.as_ref(),
+            &self.time_zone,
+        )
+        .await?;
+        if !drifted(&expected, google_event) {
+            return Ok(());
+        }
+
+        let google_event_id = google_event.id.as_deref().unwrap_or_default();
+        info!("Restore google event({google_event_id}) of event({discord_id})");
+        // updating a deleted event brings it back
+        hub.events()
+            .update(expected, calendar_id, google_event_id)
+            .doit()
+            .await
+            .context("Failed to restore google event")?;
+
+        Ok(())
+    }
+}
+
+pub(crate) async fn watch_notification(
+    Extension(db_pool): Extension<SqlitePool>,
+    headers: HeaderMap,
+) -> StatusCode {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(channel_id), Some(token)) =
+        (header("x-goog-channel-id"), header("x-goog-channel-token"))
+    else {
+        return StatusCode::BAD_REQUEST;
+    };
+
+    match sqlx::query_scalar!(
+        "SELECT `token` FROM `google_watch_channels` WHERE `channel_id` = ?",
+        channel_id
+    )
+    .fetch_optional(&db_pool)
+    .await
+    {
+        Ok(Some(saved)) if verify_token(&saved, token) => {}
+        Ok(_) => return StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to get watch channel - {e:?}");
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
+
+    // the first notification only confirms the channel
+    if header("x-goog-resource-state") != Some("sync") {
+        if let Some(sender) = NOTIFICATIONS.get() {
+            let _ = sender.send(channel_id.to_string());
+        }
+    }
+
+    StatusCode::OK
+}
+
+impl DiscordHandler {
+    pub(super) fn start_calendar_watch(&self, context: &Context, guild_id: GuildId) {
+        if !self.watch_user_calendars || self.watch_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let _ = NOTIFICATIONS.set(sender);
+        let watcher = Watcher {
+            db_pool: self.db_pool.clone(),
+            http: context.http.clone(),
+            guild_id,
+            time_zone: self.time_zone.clone(),
+            address: format!("{}/events/google/notify", self.web_prefix),
+        };
+        let service_account = self.service_account.clone();
+        tokio::spawn(async move {
+            let mut renew = tokio::time::interval(RENEW_TICK);
+            loop {
+                let channel_id = tokio::select! {
+                    _ = renew.tick() => None,
+                    Some(channel_id) = receiver.recv() => Some(channel_id),
+                };
+
+                let hub = match Self::service_account_calendar_hub(service_account.clone()).await {
+                    Ok(hub) => hub,
+                    Err(e) => {
+                        error!("Failed to create google calendar hub - {e:?}");
+                        continue;
+                    }
+                };
+                let result = match &channel_id {
+                    Some(channel_id) => watcher.handle_notification(&hub, channel_id).await,
+                    None => watcher.renew_channels(&hub).await,
+                };
+                if let Err(e) = result {
+                    error!("Failed to watch user calendars - {e:?}");
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::Context as _;
+use async_trait::async_trait;
+use log::error;
+use serde::Deserialize;
+use serenity::{
+    client::Context,
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            InteractionResponseType,
+        },
+        guild::Member,
+        id::GuildId,
+        Permissions,
+    },
+};
+use sqlx::SqlitePool;
+
+use crate::discord::{
+    application_command::{
+        ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
+    },
+    CommandDataOptionHelper, SubApplication,
+};
+
+// `/hooks/github` receiving webhooks of github
+mod github;
+
+const COMMAND_NAME: &str = "hooks";
+
+#[derive(Debug, Default, Deserialize, Clone)]
+pub(crate) struct Config {
+    // secret of github webhooks. `/hooks/github` is disabled without it.
+    #[serde(default)]
+    github_secret: Option<String>,
+}
+
+pub struct DiscordHandler {
+    db_pool: SqlitePool,
+}
+
+impl DiscordHandler {
+    pub(crate) fn new(db_pool: SqlitePool) -> Self {
+        Self { db_pool }
+    }
+
+    // every subcommand is for admins
+    async fn handle_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        let content = if !can_manage_guild(interaction.member.as_ref()) {
+            "서버 관리 권한이 필요합니다.".to_string()
+        } else {
+            let option = unsafe { interaction.data.options.first().unwrap_unchecked() };
+            match option.name.as_str() {
+                "github" => self.handle_github_command(option).await?,
+                _ => unsafe { std::hint::unreachable_unchecked() },
+            }
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+
+    async fn handle_github_command(&self, option: &CommandDataOption) -> anyhow::Result<String> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        match sub_option.name.as_str() {
+            "add" => self.handle_github_add_command(sub_option).await,
+            "remove" => self.handle_github_remove_command(sub_option).await,
+            "list" => self.handle_github_list_command().await,
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        }
+    }
+
+    async fn handle_github_add_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [repository, channel] = option.get_options(&["repository", "channel"]);
+        let Some(repository) =
+            github::normalize_repository(unsafe { repository.as_str_unchecked() })
+        else {
+            return Ok("저장소는 `owner/name` 형식이어야 합니다.".to_string());
+        };
+        let channel_id = unsafe { channel.as_str_unchecked() }
+            .parse::<i64>()
+            .context("Invalid channel")?;
+
+        sqlx::query!(
+            "INSERT INTO `github_hook_channels` (`repository`, `channel_id`) VALUES (?, ?)
+            ON CONFLICT (`repository`, `channel_id`) DO NOTHING",
+            repository,
+            channel_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to insert github hook channel")?;
+
+        Ok(format!(
+            "{repository}의 이벤트를 <#{channel_id}>에 올립니다."
+        ))
+    }
+
+    async fn handle_github_remove_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [repository, channel] = option.get_options(&["repository", "channel"]);
+        let Some(repository) =
+            github::normalize_repository(unsafe { repository.as_str_unchecked() })
+        else {
+            return Ok("저장소는 `owner/name` 형식이어야 합니다.".to_string());
+        };
+        let channel_id = unsafe { channel.as_str_unchecked() }
+            .parse::<i64>()
+            .context("Invalid channel")?;
+
+        let result = sqlx::query!(
+            "DELETE FROM `github_hook_channels` WHERE `repository` = ? AND `channel_id` = ?",
+            repository,
+            channel_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to delete github hook channel")?;
+        if result.rows_affected() == 0 {
+            return Ok(format!(
+                "{repository}의 이벤트는 <#{channel_id}>에 올리고 있지 않습니다."
+            ));
+        }
+
+        Ok(format!(
+            "{repository}의 이벤트를 더 이상 <#{channel_id}>에 올리지 않습니다."
+        ))
+    }
+
+    async fn handle_github_list_command(&self) -> anyhow::Result<String> {
+        let channels = sqlx::query!(
+            "SELECT `repository`, `channel_id` FROM `github_hook_channels` ORDER BY `repository`, `channel_id`"
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get github hook channels")?;
+        if channels.is_empty() {
+            return Ok("등록된 저장소가 없습니다.".to_string());
+        }
+
+        Ok(channels
+            .into_iter()
+            .map(|channel| format!("{} → <#{}>", channel.repository, channel.channel_id))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+fn can_manage_guild(member: Option<&Member>) -> bool {
+    member
+        .and_then(|member| member.permissions)
+        .map_or(false, |permissions| {
+            permissions.contains(Permissions::MANAGE_GUILD)
+        })
+}
+
+fn github_options() -> Vec<ApplicationCommandOption<'static>> {
+    vec![
+        ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::String,
+            name: "repository",
+            description: "owner/name 형식의 저장소",
+            required: Some(true),
+            ..Default::default()
+        },
+        ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::Channel,
+            name: "channel",
+            description: "이벤트를 올릴 채널",
+            required: Some(true),
+            ..Default::default()
+        },
+    ]
+}
+
+pub(crate) fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new().route("/github", axum::routing::post(github::receive))
+}
+
+#[async_trait]
+impl SubApplication for DiscordHandler {
+    async fn ready(&self, context: &Context, guild_id: GuildId) {
+        let command = ApplicationCommand {
+            name: COMMAND_NAME,
+            description: "외부 서비스 알림 설정",
+            options: vec![ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommandGroup,
+                name: "github",
+                description: "GitHub 저장소의 push, release, issue 알림",
+                options: vec![
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::SubCommand,
+                        name: "add",
+                        description: "저장소의 이벤트를 채널에 올립니다.",
+                        options: github_options(),
+                        ..Default::default()
+                    },
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::SubCommand,
+                        name: "remove",
+                        description: "저장소의 이벤트를 채널에 올리지 않습니다.",
+                        options: github_options(),
+                        ..Default::default()
+                    },
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::SubCommand,
+                        name: "list",
+                        description: "등록된 저장소 목록",
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+        };
+
+        context
+            .http
+            .create_guild_application_command(
+                *guild_id.as_u64(),
+                &serde_json::to_value(command).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn application_command_interaction_create(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> bool {
+        if interaction.data.name != COMMAND_NAME {
+            return false;
+        }
+
+        if let Err(e) = self.handle_command(context, interaction).await {
+            error!("Failed to handle hooks command - {e:?}");
+        }
+
+        true
+    }
+}
@@ -0,0 +1,361 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use axum::{
+    body::Bytes,
+    extract::Extension,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use hmac::Mac;
+use log::{error, info};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+
+const EVENT_HEADER: &str = "X-GitHub-Event";
+const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+const SIGNATURE_PREFIX: &str = "sha256=";
+// commits of a push listed in a message
+const MAX_COMMITS: usize = 5;
+// bodies of issues and releases are cut to this
+const MAX_BODY_CHARS: usize = 500;
+const COLOR_PUSH: u32 = 0x6e7681;
+const COLOR_OPEN: u32 = 0x2da44e;
+const COLOR_CLOSED: u32 = 0x8250df;
+const COLOR_RELEASE: u32 = 0x0969da;
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: String,
+    html_url: String,
+}
+
+#[derive(Deserialize)]
+struct Sender {
+    login: String,
+    html_url: String,
+    avatar_url: String,
+}
+
+#[derive(Deserialize)]
+struct CommitAuthor {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+    message: String,
+    url: String,
+    author: CommitAuthor,
+}
+
+#[derive(Deserialize)]
+struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    compare: String,
+    #[serde(default)]
+    deleted: bool,
+    commits: Vec<Commit>,
+    repository: Repository,
+    sender: Sender,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    name: Option<String>,
+    html_url: String,
+    body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseEvent {
+    action: String,
+    release: Release,
+    repository: Repository,
+    sender: Sender,
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    number: i64,
+    title: String,
+    html_url: String,
+    body: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IssuesEvent {
+    action: String,
+    issue: Issue,
+    repository: Repository,
+    sender: Sender,
+}
+
+// repositories are compared in lower case as github does
+pub(super) fn normalize_repository(repository: &str) -> Option<String> {
+    let repository = repository.trim().to_lowercase();
+    // urls of the repository are accepted as well
+    let repository = ["https://", "http://"]
+        .iter()
+        .find_map(|scheme| repository.strip_prefix(scheme))
+        .unwrap_or(&repository);
+    let repository = repository
+        .strip_prefix("github.com/")
+        .unwrap_or(repository)
+        .trim_matches('/');
+    let repository = repository.strip_suffix(".git").unwrap_or(repository);
+    let (owner, name) = repository.split_once('/')?;
+    if owner.is_empty() || name.is_empty() || name.contains('/') {
+        return None;
+    }
+
+    Some(repository.to_string())
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// `X-Hub-Signature-256` is the HMAC-SHA256 of the body with the secret of the webhook
+fn verify_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some(signature) = signature
+        .strip_prefix(SIGNATURE_PREFIX)
+        .and_then(decode_hex)
+    else {
+        return false;
+    };
+    let Ok(mut mac) = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        let mut truncated = text.chars().take(max_chars).collect::<String>();
+        truncated.push('…');
+        truncated
+    } else {
+        text.to_string()
+    }
+}
+
+fn embed(
+    sender: &Sender,
+    title: String,
+    url: &str,
+    description: String,
+    color: u32,
+) -> serde_json::Value {
+    json!({
+        "title": truncate(&title, 256),
+        "url": url,
+        "description": description,
+        "color": color,
+        "author": {
+            "name": sender.login,
+            "url": sender.html_url,
+            "icon_url": sender.avatar_url,
+        },
+    })
+}
+
+fn format_push(event: PushEvent) -> Option<(Repository, serde_json::Value)> {
+    // deleted branches and pushed tags have no commits to tell
+    if event.deleted || event.commits.is_empty() {
+        return None;
+    }
+
+    let branch = event
+        .git_ref
+        .strip_prefix("refs/heads/")
+        .unwrap_or(&event.git_ref);
+    let mut description = event
+        .commits
+        .iter()
+        .take(MAX_COMMITS)
+        .map(|commit| {
+            format!(
+                "[`{}`]({}) {} - {}",
+                &commit.id[..commit.id.len().min(7)],
+                commit.url,
+                truncate(commit.message.lines().next().unwrap_or_default(), 80),
+                commit.author.name
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if event.commits.len() > MAX_COMMITS {
+        description.push_str(&format!(
+            "\n외 {}개 커밋",
+            event.commits.len() - MAX_COMMITS
+        ));
+    }
+
+    let embed = embed(
+        &event.sender,
+        format!(
+            "[{}:{branch}] {}개 커밋 push",
+            event.repository.full_name,
+            event.commits.len()
+        ),
+        &event.compare,
+        description,
+        COLOR_PUSH,
+    );
+    Some((event.repository, embed))
+}
+
+fn format_release(event: ReleaseEvent) -> Option<(Repository, serde_json::Value)> {
+    if event.action != "published" {
+        return None;
+    }
+
+    let name = match event.release.name {
+        Some(name) if !name.is_empty() => name,
+        _ => event.release.tag_name,
+    };
+    let embed = embed(
+        &event.sender,
+        format!("[{}] 새 릴리스 {name}", event.repository.full_name),
+        &event.release.html_url,
+        truncate(
+            event.release.body.as_deref().unwrap_or_default(),
+            MAX_BODY_CHARS,
+        ),
+        COLOR_RELEASE,
+    );
+    Some((event.repository, embed))
+}
+
+fn format_issues(event: IssuesEvent) -> Option<(Repository, serde_json::Value)> {
+    let (action, color, description) = match event.action.as_str() {
+        "opened" => (
+            "열림",
+            COLOR_OPEN,
+            truncate(
+                event.issue.body.as_deref().unwrap_or_default(),
+                MAX_BODY_CHARS,
+            ),
+        ),
+        "reopened" => ("다시 열림", COLOR_OPEN, String::new()),
+        "closed" => ("닫힘", COLOR_CLOSED, String::new()),
+        _ => return None,
+    };
+
+    let embed = embed(
+        &event.sender,
+        format!(
+            "[{}] 이슈 {action}: #{} {}",
+            event.repository.full_name, event.issue.number, event.issue.title
+        ),
+        &event.issue.html_url,
+        description,
+        color,
+    );
+    Some((event.repository, embed))
+}
+
+// `None` for events and actions not posted
+fn format_event(
+    event: &str,
+    body: &[u8],
+) -> anyhow::Result<Option<(Repository, serde_json::Value)>> {
+    Ok(match event {
+        "push" => format_push(serde_json::from_slice(body).context("Invalid push event")?),
+        "release" => format_release(serde_json::from_slice(body).context("Invalid release event")?),
+        "issues" => format_issues(serde_json::from_slice(body).context("Invalid issues event")?),
+        _ => None,
+    })
+}
+
+async fn post(
+    db_pool: &SqlitePool,
+    config: &crate::Config,
+    repository: &Repository,
+    embed: serde_json::Value,
+) -> anyhow::Result<()> {
+    let Some(repository_name) = normalize_repository(&repository.full_name) else {
+        return Ok(());
+    };
+    let channel_ids = sqlx::query_scalar!(
+        "SELECT `channel_id` FROM `github_hook_channels` WHERE `repository` = ?",
+        repository_name
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get channels of github repository")?;
+    if channel_ids.is_empty() {
+        info!(
+            "No channel for github repository {} ({})",
+            repository.full_name, repository.html_url
+        );
+        return Ok(());
+    }
+
+    let http = crate::discord::http(&config.discord);
+    let message = json!({
+        "embeds": [embed],
+        "allowed_mentions": { "parse": [] },
+    });
+    for channel_id in channel_ids {
+        // a deleted channel does not stop the others
+        if let Err(e) = http.send_message(channel_id as u64, &message).await {
+            error!("Failed to post github event to channel({channel_id}) - {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) async fn receive(
+    Extension(db_pool): Extension<SqlitePool>,
+    Extension(config): Extension<Arc<crate::Config>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(secret) = &config.hooks.github_secret else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !verify_signature(secret, signature, &body) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let event = headers
+        .get(EVENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let (repository, embed) = match format_event(event, &body) {
+        Ok(Some(formatted)) => formatted,
+        // `ping` on registration lands here too
+        Ok(None) => return StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            error!("Failed to parse github {event} event - {e:?}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+    match post(&db_pool, &config, &repository, embed).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            error!("Failed to post github {event} event - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -56,3 +56,46 @@ impl VerifyingAlgorithm for RsaVerifying {
         }
     }
 }
+
+// HS256 signed session token of the web side. `sub` is the discord user id.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SessionClaims {
+    sub: String,
+    exp: i64,
+}
+
+pub struct SessionKey(hmac::Hmac<sha2::Sha256>);
+
+impl SessionKey {
+    pub fn new(secret: &str) -> anyhow::Result<Self> {
+        use hmac::Mac;
+
+        Ok(Self(
+            hmac::Hmac::new_from_slice(secret.as_bytes())
+                .map_err(|_| anyhow::anyhow!("Invalid session secret"))?,
+        ))
+    }
+
+    pub fn sign(&self, user_id: i64, expires_at: i64) -> anyhow::Result<String> {
+        use jwt::SignWithKey;
+
+        SessionClaims {
+            sub: user_id.to_string(),
+            exp: expires_at,
+        }
+        .sign_with_key(&self.0)
+        .map_err(|e| anyhow::anyhow!("Failed to sign session - {e:?}"))
+    }
+
+    // user id of the session if it is valid and not expired
+    pub fn verify(&self, token: &str) -> Option<i64> {
+        use jwt::VerifyWithKey;
+
+        let claims: SessionClaims = token.verify_with_key(&self.0).ok()?;
+        if claims.exp <= chrono::Utc::now().timestamp() {
+            return None;
+        }
+
+        claims.sub.parse().ok()
+    }
+}
@@ -1,30 +1,812 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+};
 
+use anyhow::Context as _;
 use async_trait::async_trait;
-use serenity::{client::Context, model::channel::Message};
+use dashmap::DashMap;
+use log::{error, info};
+use regex::Regex;
+use serde::Deserialize;
+use serenity::{
+    client::Context,
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            message_component::MessageComponentInteraction,
+            InteractionResponseType,
+        },
+        channel::{Message, MessageFlags},
+        guild::Member,
+        id::{ChannelId, GuildId, MessageId},
+        Permissions,
+    },
+};
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
 
-use crate::{discord::SubApplication, regex};
+use crate::{
+    discord::{
+        application_command::{
+            ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
+        },
+        ChannelHelper, CommandDataOptionHelper, CommandHelper, SubApplication,
+    },
+    regex,
+    user::Preference,
+};
 
-pub struct DiscordHandler;
+mod channel;
+mod mirror;
+mod placement;
+mod remove;
+mod repost;
+mod shortener;
+mod tracking;
+
+use placement::Placement;
+use repost::Mode;
+
+const COMMAND_NAME: &str = "linkfix";
+// edits of messages within this are rewritten
+const REPLY_RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+
+// links of users who opted out are left as is
+pub(crate) const OPT_OUT: Preference<bool> = Preference::new("link_rewriter.opt_out");
+
+// built-in rewriters for sites with embed fixing mirrors. each is off unless enabled.
+#[derive(Debug, Default, Deserialize, Clone)]
+pub(crate) struct Config {
+    #[serde(default)]
+    instagram: bool,
+    #[serde(default)]
+    reddit: bool,
+    #[serde(default)]
+    tiktok: bool,
+    #[serde(default)]
+    pixiv: bool,
+    #[serde(default)]
+    mode: Mode,
+    // hide the embed of the original message when replying with the fixed link
+    #[serde(default)]
+    suppress_embeds: bool,
+    // remove query parameters for tracking from every link
+    #[serde(default)]
+    strip_tracking_params: bool,
+    #[serde(default = "tracking::default_params")]
+    tracking_params: Vec<String>,
+    // follow redirects of shortened links to rewrite where they lead
+    #[serde(default)]
+    expand_short_urls: bool,
+    #[serde(default = "shortener::default_domains")]
+    shortener_domains: Vec<String>,
+    // append the links before rewriting as spoilers
+    #[serde(default)]
+    include_original: bool,
+    // where fixes of messages in channels are posted
+    #[serde(default)]
+    placement: Placement,
+}
+
+// punctuations right after a link are not a part of it
+fn link_regex() -> &'static Regex {
+    regex!("https?://[^\\s<>]*[^\\s<>.,;:!?)\\]'\"]")
+}
+
+const ORIGINAL_LINKS_PREFIX: &str = "\n원본: ";
+
+// the suffix appended by `append_original_links` is cut not to rewrite the original links again
+fn without_original_links(text: &str) -> &str {
+    let Some((content, links)) = text.rsplit_once(ORIGINAL_LINKS_PREFIX) else {
+        return text;
+    };
+    let appended = links.split(' ').all(|link| {
+        link.strip_prefix("||<")
+            .and_then(|link| link.strip_suffix(">||"))
+            .map_or(false, |link| link_regex().is_match(link))
+    });
+    if appended {
+        content
+    } else {
+        text
+    }
+}
+
+// links gone by rewriting are appended hidden, without embeds
+fn append_original_links(text: &mut String, original: &str) {
+    let links = link_regex()
+        .find_iter(original)
+        .map(|link| link.as_str())
+        .filter(|link| !text.contains(link))
+        .map(|link| format!("||<{link}>||"))
+        .collect::<Vec<_>>();
+    if links.is_empty() {
+        return;
+    }
+
+    text.push_str(ORIGINAL_LINKS_PREFIX);
+    text.push_str(&links.join(" "));
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: String,
+    // fill the placeholder of the replacement in the order of preference
+    mirrors: Vec<String>,
+}
+
+pub struct DiscordHandler {
+    db_pool: SqlitePool,
+    config: Config,
+    // enabled rules in the order of registration
+    rules: RwLock<Vec<Rule>>,
+    // link rewriting is on or off by channel or category
+    channels: RwLock<HashMap<u64, bool>>,
+    // webhooks to repost with, by parent channel
+    webhook_urls: DashMap<ChannelId, String>,
+    http_client: reqwest::Client,
+    // whether mirrors were reachable at the last check
+    mirror_health: Arc<DashMap<String, bool>>,
+    health_check_started: AtomicBool,
+}
 
 impl DiscordHandler {
-    pub(crate) fn new() -> Self {
-        Self
+    pub(crate) async fn new(db_pool: SqlitePool, config: &crate::Config) -> anyhow::Result<Self> {
+        let rules = load_rules(&db_pool).await?;
+        let channels = channel::load_channels(&db_pool).await?;
+
+        Ok(Self {
+            db_pool,
+            config: config.link_rewriter.clone(),
+            rules: RwLock::new(rules),
+            channels: RwLock::new(channels),
+            webhook_urls: DashMap::new(),
+            http_client: shortener::client(),
+            mirror_health: Arc::new(DashMap::new()),
+            health_check_started: AtomicBool::new(false),
+        })
+    }
+
+    // called whenever the rules are changed, to take effect without restarting
+    async fn reload_rules(&self) -> anyhow::Result<()> {
+        let rules = load_rules(&self.db_pool).await?;
+        *self.rules.write().await = rules;
+
+        Ok(())
+    }
+
+    async fn fix(&self, context: &Context, message: &Message, edited: bool) {
+        // checked before rewriting, as shortened links are requested while rewriting
+        if !self.enabled_in(context, message.channel_id).await {
+            return;
+        }
+
+        let user_id = message.author.id.0 as i64;
+        match OPT_OUT.get(&self.db_pool, user_id).await {
+            Ok(Some(true)) => return,
+            Ok(_) => {}
+            Err(e) => error!("{e:?}"),
+        }
+
+        let content = without_original_links(&message.content);
+        let Some(mut replaced_text) = self.rewrite(content).await else {
+            return;
+        };
+
+        if self.config.include_original {
+            append_original_links(&mut replaced_text, content);
+        }
+
+        if edited {
+            match self.reply_of(message).await {
+                Ok(Some(reply_id)) => {
+                    let thread_id = self.fix_thread(context, message, false).await;
+                    if let Err(e) = thread_id
+                        .unwrap_or(message.channel_id)
+                        .edit_message(context, reply_id, |builder| builder.content(replaced_text))
+                        .await
+                    {
+                        error!("Failed to edit rewritten message - {e:?}");
+                    }
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => error!("{e:?}"),
+            }
+        }
+
+        if let Mode::Repost = self.config.mode {
+            match self.repost(context, message, &replaced_text).await {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(e) => error!("Failed to repost rewritten message - {e:?}"),
+            }
+        }
+
+        let thread_id = self.fix_thread(context, message, true).await;
+        // messages in other channels can't be replied to
+        let reply = match thread_id
+            .unwrap_or(message.channel_id)
+            .send_message(context, |builder| {
+                if thread_id.is_none() {
+                    builder.reference_message(message);
+                }
+                builder
+                    .content(replaced_text)
+                    .components(|components| remove::remove_button(components, message.author.id))
+            })
+            .await
+        {
+            Ok(reply) => reply,
+            Err(e) => {
+                error!("Failed to reply rewritten message - {e:?}");
+                return;
+            }
+        };
+        if let Err(e) = self.record_reply(message, &reply).await {
+            error!("{e:?}");
+        }
+
+        if self.config.suppress_embeds {
+            if let Err(e) = Self::suppress_embeds(context, message).await {
+                error!("Failed to suppress embeds of original message - {e:?}");
+            }
+        }
+    }
+
+    async fn reply_of(&self, message: &Message) -> anyhow::Result<Option<MessageId>> {
+        let message_id = message.id.0 as i64;
+        let reply_id = sqlx::query!(
+            "SELECT `reply_id` FROM `link_rewrite_replies` WHERE `message_id` = ?",
+            message_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get reply of rewritten message")?
+        .map(|r| MessageId(r.reply_id as u64));
+
+        Ok(reply_id)
+    }
+
+    // older replies are cleaned up at the same time
+    async fn record_reply(&self, message: &Message, reply: &Message) -> anyhow::Result<()> {
+        let message_id = message.id.0 as i64;
+        let reply_id = reply.id.0 as i64;
+        let now = chrono::Utc::now().timestamp();
+        let expired = now - REPLY_RETENTION_SECS;
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM `link_rewrite_replies` WHERE `created_at` < ?",
+            expired
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete old replies of rewritten messages")?;
+        sqlx::query!(
+            "INSERT INTO `link_rewrite_replies` (`message_id`, `reply_id`, `created_at`) VALUES (?, ?, ?)
+            ON CONFLICT (`message_id`) DO UPDATE
+            SET `reply_id` = `excluded`.`reply_id`, `created_at` = `excluded`.`created_at`",
+            message_id,
+            reply_id,
+            now
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to record reply of rewritten message")?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // serenity refuses to suppress embeds of messages by others, so the flag is set directly
+    async fn suppress_embeds(context: &Context, message: &Message) -> anyhow::Result<()> {
+        let parent_id = message.channel_id.get_parent_or_self(context).await;
+        if !bot_has_permissions(context, parent_id, Permissions::MANAGE_MESSAGES) {
+            info!(
+                "Embeds in {} are not suppressed without the permission to manage messages",
+                message.channel_id
+            );
+            return Ok(());
+        }
+
+        context
+            .http
+            .edit_message(
+                message.channel_id.0,
+                message.id.0,
+                &serde_json::json!({ "flags": MessageFlags::SUPPRESS_EMBEDS.bits() }),
+            )
+            .await
+            .context("Failed to edit message flags")?;
+
+        Ok(())
+    }
+
+    // built-in rewriters go first, then the rules in the DB, then tracking parameters are stripped.
+    // shortened links are expanded beforehand, but only rewritten ones are worth replying.
+    async fn rewrite(&self, text: &str) -> Option<String> {
+        let expanded = if self.config.expand_short_urls {
+            self.expand_short_urls(text).await
+        } else {
+            Cow::Borrowed(text)
+        };
+
+        let builtins = [
+            (
+                self.config.instagram,
+                regex!("://(?:www\\.)?instagram\\.com/(p|reels?)/([a-zA-Z0-9\\-_]+)/?(\\?[a-zA-Z0-9%\\-_&=]+)?"),
+                "://ddinstagram.com/$1/$2/",
+            ),
+            (
+                self.config.reddit,
+                regex!("://(?:www\\.|old\\.|new\\.)?reddit\\.com/(r/[a-zA-Z0-9_]+/(?:comments|s)/[a-zA-Z0-9/_\\-]+)(\\?[a-zA-Z0-9%\\-_&=]+)?"),
+                "://rxddit.com/$1",
+            ),
+            (
+                self.config.tiktok,
+                regex!("://(?:www\\.)?(vm\\.|vt\\.)?tiktok\\.com/([a-zA-Z0-9@/_\\-\\.]+)(\\?[a-zA-Z0-9%\\-_&=]+)?"),
+                "://${1}vxtiktok.com/$2",
+            ),
+            (
+                self.config.pixiv,
+                regex!("://(?:www\\.)?pixiv\\.net/((?:[a-z]{2}/)?artworks/\\d+)"),
+                "://phixiv.net/$1",
+            ),
+        ];
+
+        let rules = self.rules.read().await;
+        let mut text = Cow::Borrowed(expanded.as_ref());
+        for (regex, replacement) in IntoIterator::into_iter(builtins)
+            .filter_map(|(enabled, regex, replacement)| {
+                enabled.then_some((regex, Cow::Borrowed(replacement)))
+            })
+            .chain(
+                rules
+                    .iter()
+                    .map(|rule| (&rule.regex, self.replacement_of(rule))),
+            )
+        {
+            let replaced = match regex.replace_all(&text, replacement.as_ref()) {
+                Cow::Owned(replaced) => replaced,
+                Cow::Borrowed(_) => continue,
+            };
+            text = Cow::Owned(replaced);
+        }
+        if self.config.strip_tracking_params {
+            let stripped = match tracking::strip(&text, &self.config.tracking_params) {
+                Cow::Owned(stripped) => Some(stripped),
+                Cow::Borrowed(_) => None,
+            };
+            if let Some(stripped) = stripped {
+                text = Cow::Owned(stripped);
+            }
+        }
+
+        match text {
+            Cow::Owned(text) => Some(text),
+            Cow::Borrowed(_) => None,
+        }
+    }
+
+    fn replacement_of<'a>(&self, rule: &'a Rule) -> Cow<'a, str> {
+        match self.active_mirror(&rule.mirrors) {
+            Some(mirror) => {
+                Cow::Owned(rule.replacement.replace(mirror::MIRROR_PLACEHOLDER, mirror))
+            }
+            None => Cow::Borrowed(&rule.replacement),
+        }
+    }
+
+    async fn handle_rule_command(&self, option: &CommandDataOption) -> anyhow::Result<String> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        match sub_option.name.as_str() {
+            "add" => self.handle_rule_add_command(sub_option).await,
+            "remove" => self.handle_rule_remove_command(sub_option).await,
+            "enable" => self.handle_rule_enable_command(sub_option).await,
+            "mirrors" => self.handle_rule_mirrors_command(sub_option).await,
+            "list" => self.handle_rule_list_command().await,
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        }
+    }
+
+    // every subcommand is for admins
+    async fn handle_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        let content = if !can_manage_guild(interaction.member.as_ref()) {
+            "서버 관리 권한이 필요합니다.".to_string()
+        } else {
+            let option = unsafe { interaction.data.options.first().unwrap_unchecked() };
+            match option.name.as_str() {
+                "rule" => self.handle_rule_command(option).await?,
+                "channel" => self.handle_channel_command(option).await?,
+                _ => unsafe { std::hint::unreachable_unchecked() },
+            }
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+
+    async fn handle_rule_add_command(&self, option: &CommandDataOption) -> anyhow::Result<String> {
+        let [pattern, replacement, mirrors] =
+            option.get_options(&["pattern", "replacement", "mirrors"]);
+        let pattern = unsafe { pattern.as_str_unchecked() };
+        let replacement = unsafe { replacement.as_str_unchecked() };
+        let mirrors = mirror::parse_mirrors(mirrors.as_str().unwrap_or_default()).join(",");
+        if let Err(e) = Regex::new(pattern) {
+            return Ok(format!("잘못된 정규식입니다.\n```\n{e}\n```"));
+        }
+        if !mirrors.is_empty() && !replacement.contains(mirror::MIRROR_PLACEHOLDER) {
+            return Ok(format!(
+                "미러를 사용하려면 바꿀 내용에 {}가 있어야 합니다.",
+                mirror::MIRROR_PLACEHOLDER
+            ));
+        }
+
+        let id = sqlx::query!(
+            "INSERT INTO `link_rewrite_rules` (`pattern`, `replacement`, `mirrors`) VALUES (?, ?, ?)",
+            pattern,
+            replacement,
+            mirrors
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to insert link rewrite rule")?
+        .last_insert_rowid();
+        self.reload_rules().await?;
+
+        Ok(format!("{id}번 규칙을 추가했습니다."))
+    }
+
+    async fn handle_rule_remove_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [id] = option.get_options(&["id"]);
+        let id = unsafe { id.as_i64().unwrap_unchecked() };
+
+        let result = sqlx::query!("DELETE FROM `link_rewrite_rules` WHERE `id` = ?", id)
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to delete link rewrite rule")?;
+        if result.rows_affected() == 0 {
+            return Ok(format!("{id}번 규칙이 없습니다."));
+        }
+        self.reload_rules().await?;
+
+        Ok(format!("{id}번 규칙을 삭제했습니다."))
+    }
+
+    async fn handle_rule_enable_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [id, enabled] = option.get_options(&["id", "enabled"]);
+        let id = unsafe { id.as_i64().unwrap_unchecked() };
+        let enabled = unsafe { enabled.as_bool().unwrap_unchecked() };
+
+        let result = sqlx::query!(
+            "UPDATE `link_rewrite_rules` SET `enabled` = ? WHERE `id` = ?",
+            enabled,
+            id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to update link rewrite rule")?;
+        if result.rows_affected() == 0 {
+            return Ok(format!("{id}번 규칙이 없습니다."));
+        }
+        self.reload_rules().await?;
+
+        Ok(if enabled {
+            format!("{id}번 규칙을 켰습니다.")
+        } else {
+            format!("{id}번 규칙을 껐습니다.")
+        })
+    }
+
+    async fn handle_rule_mirrors_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [id, mirrors] = option.get_options(&["id", "mirrors"]);
+        let id = unsafe { id.as_i64().unwrap_unchecked() };
+        let mirrors = mirror::parse_mirrors(mirrors.as_str().unwrap_or_default()).join(",");
+
+        let Some(rule) = sqlx::query!(
+            "SELECT `replacement` FROM `link_rewrite_rules` WHERE `id` = ?",
+            id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get link rewrite rule")?
+        else {
+            return Ok(format!("{id}번 규칙이 없습니다."));
+        };
+        if !mirrors.is_empty() && !rule.replacement.contains(mirror::MIRROR_PLACEHOLDER) {
+            return Ok(format!(
+                "미러를 사용하려면 바꿀 내용에 {}가 있어야 합니다.",
+                mirror::MIRROR_PLACEHOLDER
+            ));
+        }
+
+        sqlx::query!(
+            "UPDATE `link_rewrite_rules` SET `mirrors` = ? WHERE `id` = ?",
+            mirrors,
+            id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to update mirrors of link rewrite rule")?;
+        self.reload_rules().await?;
+
+        Ok(if mirrors.is_empty() {
+            format!("{id}번 규칙의 미러를 지웠습니다.")
+        } else {
+            format!("{id}번 규칙의 미러를 {mirrors}(으)로 설정했습니다.")
+        })
+    }
+
+    async fn handle_rule_list_command(&self) -> anyhow::Result<String> {
+        let rules = sqlx::query!(
+            "SELECT `id`, `pattern`, `replacement`, `enabled`, `mirrors` FROM `link_rewrite_rules` ORDER BY `id`"
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get link rewrite rules")?;
+        if rules.is_empty() {
+            return Ok("등록된 규칙이 없습니다.".to_string());
+        }
+
+        Ok(rules
+            .into_iter()
+            .map(|rule| {
+                let mut line = format!(
+                    "{}. {}`{}` → `{}`",
+                    rule.id,
+                    if rule.enabled { "" } else { "(꺼짐) " },
+                    rule.pattern,
+                    rule.replacement
+                );
+                let mirrors = mirror::parse_mirrors(&rule.mirrors);
+                if let Some(active) = self.active_mirror(&mirrors) {
+                    line.push_str(&format!(" (미러: {}, 사용 중: {active})", rule.mirrors));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+// rules broken by editing the DB directly are skipped not to stop the others
+async fn load_rules(db_pool: &SqlitePool) -> anyhow::Result<Vec<Rule>> {
+    let rows = sqlx::query!(
+        "SELECT `id`, `pattern`, `replacement`, `mirrors` FROM `link_rewrite_rules` WHERE `enabled` = TRUE ORDER BY `id`"
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to load link rewrite rules")?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| match Regex::new(&row.pattern) {
+            Ok(regex) => Some(Rule {
+                regex,
+                replacement: row.replacement,
+                mirrors: mirror::parse_mirrors(&row.mirrors),
+            }),
+            Err(e) => {
+                error!("Invalid pattern of link rewrite rule({}) - {e:?}", row.id);
+                None
+            }
+        })
+        .collect())
+}
+
+// permissions of a thread follow its parent channel
+fn bot_has_permissions(context: &Context, channel_id: ChannelId, permissions: Permissions) -> bool {
+    let Some(channel) = context.cache.guild_channel(channel_id) else {
+        return false;
+    };
+    channel
+        .permissions_for_user(&context.cache, context.cache.current_user_id())
+        .map_or(false, |granted| granted.contains(permissions))
+}
+
+fn can_manage_guild(member: Option<&Member>) -> bool {
+    member
+        .and_then(|member| member.permissions)
+        .map_or(false, |permissions| {
+            permissions.contains(Permissions::MANAGE_GUILD)
+        })
+}
+
+fn id_option(description: &'static str) -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::Integer,
+        name: "id",
+        description,
+        required: Some(true),
+        ..Default::default()
+    }
+}
+
+fn mirrors_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::String,
+        name: "mirrors",
+        description: "바꿀 내용의 {mirror}에 쓸 도메인들. 쉼표로 구분하며, 앞의 것이 안 될 때 다음 것을 씁니다.",
+        required: Some(false),
+        ..Default::default()
     }
 }
 
 #[async_trait]
 impl SubApplication for DiscordHandler {
+    async fn ready(&self, context: &Context, guild_id: GuildId) {
+        self.start_health_check();
+
+        let command = ApplicationCommand {
+            name: COMMAND_NAME,
+            description: "링크 고치기 설정",
+            options: vec![
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommandGroup,
+                    name: "rule",
+                    description: "링크를 고치는 규칙",
+                    options: vec![
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::SubCommand,
+                            name: "add",
+                            description: "규칙 추가",
+                            options: vec![
+                                ApplicationCommandOption {
+                                    kind: ApplicationCommandOptionType::String,
+                                    name: "pattern",
+                                    description: "고칠 링크에 맞는 정규식",
+                                    required: Some(true),
+                                    ..Default::default()
+                                },
+                                ApplicationCommandOption {
+                                    kind: ApplicationCommandOptionType::String,
+                                    name: "replacement",
+                                    description:
+                                        "바꿀 내용. $1 등으로 정규식의 그룹을 사용할 수 있습니다.",
+                                    required: Some(true),
+                                    ..Default::default()
+                                },
+                                mirrors_option(),
+                            ],
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::SubCommand,
+                            name: "remove",
+                            description: "규칙 삭제",
+                            options: vec![id_option("list에 표시된 삭제할 규칙 번호")],
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::SubCommand,
+                            name: "enable",
+                            description: "규칙을 삭제하지 않고 켜거나 끕니다.",
+                            options: vec![
+                                id_option("list에 표시된 규칙 번호"),
+                                ApplicationCommandOption {
+                                    kind: ApplicationCommandOptionType::Boolean,
+                                    name: "enabled",
+                                    description: "규칙 사용 여부",
+                                    required: Some(true),
+                                    ..Default::default()
+                                },
+                            ],
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::SubCommand,
+                            name: "mirrors",
+                            description: "규칙의 미러를 바꿉니다.",
+                            options: vec![id_option("list에 표시된 규칙 번호"), mirrors_option()],
+                            ..Default::default()
+                        },
+                        ApplicationCommandOption {
+                            kind: ApplicationCommandOptionType::SubCommand,
+                            name: "list",
+                            description: "규칙 목록",
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                channel::command_option(),
+            ],
+        };
+
+        context
+            .http
+            .create_guild_application_command(
+                *guild_id.as_u64(),
+                &serde_json::to_value(command).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
     async fn message(&self, context: &Context, message: &Message) {
-        let Cow::Owned(replaced_text) =
-            regex!("://(x|twitter)\\.com/([^/]+)/status/(\\d+)(\\?[a-zA-Z0-9%\\-_&=]+)?")
-                .replace_all(&message.content, "://vxtwitter.com/$2/status/$3")
-        else {
+        if message.guild_id.is_none() {
             return;
-        };
+        }
+        // replies and reposts of the bot would be rewritten again otherwise
+        if message.author.bot || message.webhook_id.is_some() {
+            return;
+        }
 
-        if let Err(e) = message.reply(&context.http, replaced_text).await {
-            log::error!("Failed to reply rewritten message - {e:?}");
+        self.fix(context, message, false).await;
+    }
+
+    async fn message_update(&self, context: &Context, message: &Message) {
+        // edits of the replies would be rewritten again otherwise
+        if message.author.bot || message.webhook_id.is_some() {
+            return;
         }
+        // there would be no reply to edit for older ones
+        let age = chrono::Utc::now().timestamp() - message.timestamp.unix_timestamp();
+        if age > REPLY_RETENTION_SECS {
+            return;
+        }
+
+        self.fix(context, message, true).await;
+    }
+
+    async fn application_command_interaction_create(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> bool {
+        if interaction.data.name != COMMAND_NAME {
+            return false;
+        }
+
+        if let Err(e) = self.handle_command(context, interaction).await {
+            error!("Failed to handle linkfix command - {e:?}");
+        }
+
+        true
+    }
+
+    async fn message_component(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+    ) -> bool {
+        let Some(author_id) = interaction
+            .data
+            .custom_id
+            .strip_prefix(remove::REMOVE_BUTTON)
+        else {
+            return false;
+        };
+        if let Err(e) = self
+            .handle_remove_button(context, interaction, author_id)
+            .await
+        {
+            error!("Failed to handle remove button - {e:?}");
+        }
+
+        true
     }
 }
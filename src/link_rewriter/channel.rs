@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use anyhow::Context as _;
+use serenity::{
+    model::{
+        application::interaction::application_command::CommandDataOption, channel::Channel,
+        id::ChannelId,
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::DiscordHandler;
+use crate::discord::{
+    application_command::{ApplicationCommandOption, ApplicationCommandOptionType},
+    CommandDataOptionHelper,
+};
+
+fn channel_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::Channel,
+        name: "channel",
+        description: "채널 또는 카테고리",
+        required: Some(true),
+        ..Default::default()
+    }
+}
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommandGroup,
+        name: "channel",
+        description: "채널 또는 카테고리마다 링크 고치기를 켜거나 끕니다.",
+        options: vec![
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "set",
+                description: "채널 설정이 카테고리 설정보다 우선합니다.",
+                options: vec![
+                    channel_option(),
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Boolean,
+                        name: "enabled",
+                        description: "링크 고치기 사용 여부",
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "reset",
+                description: "설정을 지우고 상위 카테고리의 설정을 따릅니다.",
+                options: vec![channel_option()],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "list",
+                description: "설정된 채널 목록",
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+pub(super) async fn load_channels(db_pool: &SqlitePool) -> anyhow::Result<HashMap<u64, bool>> {
+    Ok(sqlx::query!(
+        r#"SELECT `channel_id` AS "channel_id!: i64", `enabled` FROM `link_rewrite_channels`"#
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to load link rewrite channels")?
+    .into_iter()
+    .map(|r| (r.channel_id as u64, r.enabled))
+    .collect())
+}
+
+impl DiscordHandler {
+    // the closest setting among the channel, its parent channel of a thread and its category
+    pub(super) async fn enabled_in(&self, context: &Context, channel_id: ChannelId) -> bool {
+        let channels = self.channels.read().await;
+        if channels.is_empty() {
+            return true;
+        }
+
+        let mut channel_id = Some(channel_id);
+        while let Some(id) = channel_id {
+            if let Some(enabled) = channels.get(&id.0) {
+                return *enabled;
+            }
+            channel_id = match id.to_channel(context).await {
+                Ok(Channel::Guild(channel)) => channel.parent_id,
+                _ => None,
+            };
+        }
+
+        true
+    }
+
+    pub(super) async fn handle_channel_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        match sub_option.name.as_str() {
+            "set" => {
+                let [channel, enabled] = sub_option.get_options(&["channel", "enabled"]);
+                let channel_id = unsafe { channel.as_str_unchecked() }
+                    .parse::<u64>()
+                    .context("Invalid channel")?;
+                let raw_channel_id = channel_id as i64;
+                let enabled = unsafe { enabled.as_bool().unwrap_unchecked() };
+
+                sqlx::query!(
+                    "INSERT INTO `link_rewrite_channels` (`channel_id`, `enabled`) VALUES (?, ?)
+                    ON CONFLICT (`channel_id`) DO UPDATE
+                    SET `enabled` = `excluded`.`enabled`",
+                    raw_channel_id,
+                    enabled
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to write link rewrite channel to DB")?;
+                self.channels.write().await.insert(channel_id, enabled);
+
+                Ok(if enabled {
+                    format!("<#{channel_id}>에서 링크를 고칩니다.")
+                } else {
+                    format!("<#{channel_id}>에서 링크를 고치지 않습니다.")
+                })
+            }
+            "reset" => {
+                let [channel] = sub_option.get_options(&["channel"]);
+                let channel_id = unsafe { channel.as_str_unchecked() }
+                    .parse::<u64>()
+                    .context("Invalid channel")?;
+                let raw_channel_id = channel_id as i64;
+
+                sqlx::query!(
+                    "DELETE FROM `link_rewrite_channels` WHERE `channel_id` = ?",
+                    raw_channel_id
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to delete link rewrite channel from DB")?;
+                self.channels.write().await.remove(&channel_id);
+
+                Ok(format!("<#{channel_id}>의 설정을 지웠습니다."))
+            }
+            "list" => {
+                let channels = self.channels.read().await;
+                if channels.is_empty() {
+                    return Ok("모든 채널에서 링크를 고칩니다.".to_string());
+                }
+
+                Ok(channels
+                    .iter()
+                    .map(|(channel_id, enabled)| {
+                        format!(
+                            "<#{channel_id}>: {}",
+                            if *enabled { "사용" } else { "사용 안 함" }
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        }
+    }
+}
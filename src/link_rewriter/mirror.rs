@@ -0,0 +1,93 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use log::{error, info};
+use reqwest::Client;
+use sqlx::SqlitePool;
+
+use super::DiscordHandler;
+
+// the placeholder in replacements filled with the active mirror
+pub(super) const MIRROR_PLACEHOLDER: &str = "{mirror}";
+const HEALTH_CHECK_TICK: Duration = Duration::from_secs(5 * 60);
+
+pub(super) fn parse_mirrors(mirrors: &str) -> Vec<String> {
+    mirrors
+        .split(',')
+        .map(str::trim)
+        .filter(|mirror| !mirror.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// errors of the server itself are taken as down, as mirrors tend to fail that way
+async fn is_reachable(client: &Client, mirror: &str) -> bool {
+    match client.head(format!("https://{mirror}/")).send().await {
+        Ok(response) => !response.status().is_server_error(),
+        Err(_) => false,
+    }
+}
+
+async fn check_mirrors(
+    db_pool: &SqlitePool,
+    client: &Client,
+    health: &DashMap<String, bool>,
+) -> anyhow::Result<()> {
+    let mirrors = sqlx::query!(
+        "SELECT `mirrors` FROM `link_rewrite_rules` WHERE `enabled` = TRUE AND `mirrors` != ''"
+    )
+    .fetch_all(db_pool)
+    .await?
+    .into_iter()
+    .flat_map(|r| parse_mirrors(&r.mirrors))
+    .collect::<HashSet<_>>();
+
+    for mirror in mirrors {
+        let reachable = is_reachable(client, &mirror).await;
+        if health.insert(mirror.clone(), reachable) != Some(reachable) {
+            info!(
+                "Mirror {mirror} is {}",
+                if reachable { "up" } else { "down" }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    pub(super) fn start_health_check(&self) {
+        if self
+            .health_check_started
+            .swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let client = self.http_client.clone();
+        let health = Arc::clone(&self.mirror_health);
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = check_mirrors(&db_pool, &client, &health).await {
+                    error!("Failed to check mirrors - {e:?}");
+                }
+
+                tokio::time::sleep(HEALTH_CHECK_TICK).await;
+            }
+        });
+    }
+
+    // the most preferred one not known to be down. the first one when every one is down.
+    pub(super) fn active_mirror<'a>(&self, mirrors: &'a [String]) -> Option<&'a str> {
+        mirrors
+            .iter()
+            .find(|mirror| {
+                self.mirror_health
+                    .get(mirror.as_str())
+                    .map_or(true, |reachable| *reachable)
+            })
+            .or_else(|| mirrors.first())
+            .map(String::as_str)
+    }
+}
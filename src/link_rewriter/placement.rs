@@ -0,0 +1,95 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serde::Deserialize;
+use serenity::{
+    model::prelude::{ChannelId, Message},
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+use crate::discord::ChannelHelper;
+
+// threads started from messages are named after them, cut to this
+const THREAD_NAME_CHARS: usize = 50;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Placement {
+    // reply to the message where it is
+    #[default]
+    Reply,
+    // post in the thread started from the message if there is one
+    Thread,
+    // post in the thread started from the message, starting one if there is none
+    CreateThread,
+}
+
+fn thread_name(message: &Message) -> String {
+    let name = message
+        .content
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(THREAD_NAME_CHARS)
+        .collect::<String>();
+    if name.trim().is_empty() {
+        format!("{}의 링크", message.author.name)
+    } else {
+        name
+    }
+}
+
+impl DiscordHandler {
+    // the thread to post the fix in. `None` to reply to the message.
+    // threads are not created for edits to find the thread posted in before.
+    pub(super) async fn fix_thread(
+        &self,
+        context: &Context,
+        message: &Message,
+        create: bool,
+    ) -> Option<ChannelId> {
+        match self.find_or_create_thread(context, message, create).await {
+            Ok(thread_id) => thread_id,
+            Err(e) => {
+                error!("{e:?}");
+                None
+            }
+        }
+    }
+
+    async fn find_or_create_thread(
+        &self,
+        context: &Context,
+        message: &Message,
+        create: bool,
+    ) -> anyhow::Result<Option<ChannelId>> {
+        if let Placement::Reply = self.config.placement {
+            return Ok(None);
+        }
+        // replies to messages in threads are already in them
+        if message.channel_id.get_parent_or_self(context).await != message.channel_id {
+            return Ok(None);
+        }
+
+        // a thread started from a message has the id of the message
+        let thread_id = ChannelId(message.id.0);
+        if thread_id.to_channel(context).await.is_ok() {
+            return Ok(Some(thread_id));
+        }
+        if !create || !matches!(self.config.placement, Placement::CreateThread) {
+            return Ok(None);
+        }
+
+        info!("Create thread for link fix of message({})", message.id);
+        let thread = message
+            .channel_id
+            .create_public_thread(context, message.id, |builder| {
+                builder.name(thread_name(message))
+            })
+            .await
+            .context("Failed to create thread for link fix")?;
+
+        Ok(Some(thread.id))
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Context as _;
+use serenity::{
+    builder::CreateComponents,
+    model::{
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+        },
+        id::UserId,
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+
+// followed by the id of the author of the original message
+pub(super) const REMOVE_BUTTON: &str = "linkfix_remove:";
+
+pub(super) fn remove_button(
+    components: &mut CreateComponents,
+    author_id: UserId,
+) -> &mut CreateComponents {
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .label("지우기")
+                .style(ButtonStyle::Secondary)
+                .custom_id(format!("{REMOVE_BUTTON}{author_id}"))
+        })
+    })
+}
+
+impl DiscordHandler {
+    // the author of the original message or mods can remove the reply
+    pub(super) async fn handle_remove_button(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+        author_id: &str,
+    ) -> anyhow::Result<()> {
+        let author_id = author_id.parse::<u64>().context("Invalid author id")?;
+        let is_mod = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .map_or(false, |permissions| {
+                permissions.contains(Permissions::MANAGE_MESSAGES)
+            });
+        if interaction.user.id.0 != author_id && !is_mod {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder
+                                .content("원래 메시지를 올린 사람만 지울 수 있습니다.")
+                                .ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder.kind(InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await
+            .context("Failed to send interaction response")?;
+        interaction
+            .message
+            .delete(context)
+            .await
+            .context("Failed to delete rewritten message")?;
+
+        // not to edit the deleted reply when the original is edited
+        let reply_id = interaction.message.id.0 as i64;
+        sqlx::query!(
+            "DELETE FROM `link_rewrite_replies` WHERE `reply_id` = ?",
+            reply_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to delete reply of rewritten message")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serde::Deserialize;
+use serenity::{
+    model::{
+        prelude::{ChannelId, Message},
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use super::{bot_has_permissions, DiscordHandler};
+use crate::discord::ChannelHelper;
+
+const WEBHOOK_NAME: &str = "futaba-linkfix";
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Mode {
+    #[default]
+    Reply,
+    // delete the original and repost it via webhook as the author
+    Repost,
+}
+
+impl DiscordHandler {
+    async fn webhook_url(
+        &self,
+        context: &Context,
+        channel_id: ChannelId,
+    ) -> anyhow::Result<String> {
+        if let Some(url) = self.webhook_urls.get(&channel_id) {
+            return Ok(url.clone());
+        }
+
+        let webhooks = channel_id
+            .webhooks(context)
+            .await
+            .context("Failed to get webhooks")?;
+        let webhook = if let Some(webhook) = webhooks.into_iter().find(|webhook| {
+            webhook.name.as_deref() == Some(WEBHOOK_NAME) && webhook.token.is_some()
+        }) {
+            webhook
+        } else {
+            info!("Create webhook for link rewriting in {channel_id}");
+            channel_id
+                .create_webhook(context, WEBHOOK_NAME)
+                .await
+                .context("Failed to create webhook")?
+        };
+        let url = webhook.url().context("Failed to get webhook url")?;
+        self.webhook_urls.insert(channel_id, url.clone());
+
+        Ok(url)
+    }
+
+    // returns false when the bot is not allowed to, to reply instead
+    pub(super) async fn repost(
+        &self,
+        context: &Context,
+        message: &Message,
+        content: &str,
+    ) -> anyhow::Result<bool> {
+        // webhooks belong to the parent channel and are executed with thread_id
+        let parent_id = message.channel_id.get_parent_or_self(context).await;
+        // the bot has to manage webhooks and delete messages of others in the channel
+        if !bot_has_permissions(
+            context,
+            parent_id,
+            Permissions::MANAGE_WEBHOOKS | Permissions::MANAGE_MESSAGES,
+        ) {
+            return Ok(false);
+        }
+        let url = self.webhook_url(context, parent_id).await?;
+
+        let username = message
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.clone())
+            .unwrap_or_else(|| message.author.name.clone());
+        // attachments are gone with the original message, so they are uploaded again
+        let mut form = reqwest::multipart::Form::new();
+        let mut attachments = Vec::with_capacity(message.attachments.len());
+        for (index, attachment) in message.attachments.iter().enumerate() {
+            let data = attachment
+                .download()
+                .await
+                .context("Failed to download attachment")?;
+            form = form.part(
+                format!("files[{index}]"),
+                reqwest::multipart::Part::bytes(data).file_name(attachment.filename.clone()),
+            );
+            attachments.push(serde_json::json!({
+                "id": index,
+                "filename": attachment.filename,
+            }));
+        }
+        let payload = serde_json::json!({
+            "content": content,
+            "username": username,
+            "avatar_url": message.author.face(),
+            "allowed_mentions": { "parse": [] },
+            "attachments": attachments,
+        });
+        form = form.text("payload_json", payload.to_string());
+
+        let mut request = reqwest::Client::new().post(url).multipart(form);
+        if parent_id != message.channel_id {
+            request = request.query(&[("thread_id", message.channel_id.0)]);
+        }
+        let result = request
+            .send()
+            .await
+            .context("Failed to execute webhook")?
+            .error_for_status();
+        if let Err(e) = result {
+            // the webhook may have been deleted by someone
+            self.webhook_urls.remove(&parent_id);
+            return Err(e).context("Webhook returned error");
+        }
+
+        // already reposted, so it is not replied again even if the original is left
+        if let Err(e) = message.delete(context).await {
+            error!("Failed to delete original message - {e:?}");
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,98 @@
+use std::{borrow::Cow, time::Duration};
+
+use anyhow::Context as _;
+use log::error;
+use reqwest::{redirect::Policy, Client, Url};
+
+use super::{link_regex, DiscordHandler};
+
+// redirects are followed up to this for a link
+const MAX_HOPS: usize = 5;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(super) fn default_domains() -> Vec<String> {
+    [
+        "t.co",
+        "bit.ly",
+        "tinyurl.com",
+        "ow.ly",
+        "buff.ly",
+        "is.gd",
+        "dlvr.it",
+        "lnkd.in",
+    ]
+    .iter()
+    .map(|domain| domain.to_string())
+    .collect()
+}
+
+// redirects are followed one by one to stop at the limit
+pub(super) fn client() -> Client {
+    Client::builder()
+        .redirect(Policy::none())
+        .timeout(TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+fn is_shortened(url: &Url, domains: &[String]) -> bool {
+    url.host_str()
+        .map_or(false, |host| domains.iter().any(|domain| domain == host))
+}
+
+// redirects to hosts other than the shorteners are not followed but taken as the result
+async fn resolve(client: &Client, url: Url, domains: &[String]) -> anyhow::Result<Url> {
+    let mut url = url;
+    for _ in 0..MAX_HOPS {
+        if !is_shortened(&url, domains) {
+            break;
+        }
+        let response = client
+            .head(url.clone())
+            .send()
+            .await
+            .context("Failed to request shortened link")?;
+        if !response.status().is_redirection() {
+            break;
+        }
+        let Some(location) = response.headers().get(reqwest::header::LOCATION) else {
+            break;
+        };
+        let location = location.to_str().context("Invalid redirect location")?;
+        url = url.join(location).context("Invalid redirect location")?;
+    }
+
+    Ok(url)
+}
+
+impl DiscordHandler {
+    // only links of the shortener domains are requested, not to visit arbitrary links
+    pub(super) async fn expand_short_urls<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut expanded = String::with_capacity(text.len());
+        let mut last = 0;
+        for link in link_regex().find_iter(text) {
+            let Ok(url) = Url::parse(link.as_str()) else {
+                continue;
+            };
+            if !is_shortened(&url, &self.config.shortener_domains) {
+                continue;
+            }
+
+            match resolve(&self.http_client, url, &self.config.shortener_domains).await {
+                Ok(resolved) => {
+                    expanded.push_str(&text[last..link.start()]);
+                    expanded.push_str(resolved.as_str());
+                    last = link.end();
+                }
+                Err(e) => error!("Failed to expand {} - {e:?}", link.as_str()),
+            }
+        }
+
+        if last == 0 {
+            Cow::Borrowed(text)
+        } else {
+            expanded.push_str(&text[last..]);
+            Cow::Owned(expanded)
+        }
+    }
+}
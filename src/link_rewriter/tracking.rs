@@ -0,0 +1,64 @@
+use std::borrow::Cow;
+
+use regex::Captures;
+
+use super::link_regex;
+
+// names ending with `*` are matched by prefix
+pub(super) fn default_params() -> Vec<String> {
+    [
+        "utm_*", "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "igsh", "mc_cid",
+        "mc_eid", "_hsenc", "_hsmi", "ref_src",
+    ]
+    .iter()
+    .map(|param| param.to_string())
+    .collect()
+}
+
+fn is_tracking(key: &str, params: &[String]) -> bool {
+    params.iter().any(|param| match param.strip_suffix('*') {
+        Some(prefix) => key.starts_with(prefix),
+        None => key == param,
+    })
+}
+
+fn clean_url(url: &str, params: &[String]) -> String {
+    let (url, fragment) = match url.find('#') {
+        Some(index) => url.split_at(index),
+        None => (url, ""),
+    };
+    let Some((base, query)) = url.split_once('?') else {
+        return format!("{url}{fragment}");
+    };
+
+    let query = query
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !is_tracking(key, params)
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    if query.is_empty() {
+        format!("{base}{fragment}")
+    } else {
+        format!("{base}?{query}{fragment}")
+    }
+}
+
+// borrowed as is when nothing was stripped
+pub(super) fn strip<'a>(text: &'a str, params: &[String]) -> Cow<'a, str> {
+    let mut stripped = false;
+    let cleaned = link_regex().replace_all(text, |captures: &Captures| {
+        let url = &captures[0];
+        let cleaned = clean_url(url, params);
+        stripped |= cleaned != url;
+        cleaned
+    });
+
+    if stripped {
+        Cow::Owned(cleaned.into_owned())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
@@ -1,67 +1,784 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use anyhow::Context as _;
 use axum::async_trait;
+use dashmap::DashMap;
 use futures::stream::StreamExt;
-use google_generative_ai_rs::v1::{
-    api::Client as GoogleAiClient,
-    gemini::{
-        request::Request, response::GeminiResponse, Content, Model, Part, ResponseType, Role,
-    },
-};
-use log::error;
-use once_cell::sync::OnceCell;
+use log::{error, info};
+use once_cell::sync::{Lazy, OnceCell};
 use serde::Deserialize;
 use serenity::{
     client::Context,
     model::{
         application::interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            message_component::MessageComponentInteraction,
+            modal::ModalSubmitInteraction,
+            InteractionResponseType,
         },
-        channel::Message,
-        id::GuildId,
+        channel::{Message, Reaction},
+        id::{ChannelId, GuildId, MessageId, RoleId, UserId},
     },
 };
 use sqlx::SqlitePool;
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, RwLock};
 
 use crate::discord::{
     application_command::{
-        ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
+        ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionChoice,
+        ApplicationCommandOptionType,
     },
-    SubApplication,
+    CommandDataOptionHelper, CommandHelper, SubApplication,
 };
 
+mod budget;
+mod conversation;
+mod cost;
+mod gemini;
+mod memory;
+mod moderation;
+mod openai;
+mod persona;
+mod prompt;
+mod provider;
+mod quota;
+mod reply;
+mod settings;
+mod summarize;
+mod thread;
+mod tools;
+mod translate;
+mod usage;
+mod window;
+
+use conversation::Turn;
+pub(crate) use memory::{forget_user_memory, OPT_OUT as MEMORY_OPT_OUT};
+pub(crate) use provider::LlmTool;
+use provider::{ChatMessage, ChatRequest, ChatRole, GenerationConfig, LlmProvider};
+use quota::Quota;
+use reply::{StreamingReply, REGENERATE_BUTTON, STOP_BUTTON};
+
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct Config {
+    // key of the Gemini API. not used with `openai`.
+    #[serde(default)]
     api_key: String,
     setting_role_ids: Vec<u64>,
+    // members with any of them can ask. everyone can when empty.
+    #[serde(default)]
+    usage_role_ids: Vec<u64>,
+    // members with any of them can't ask even with a usage role
+    #[serde(default)]
+    blocked_role_ids: Vec<u64>,
+    // answer with an OpenAI compatible API instead of Gemini
+    #[serde(default)]
+    openai: Option<openai::Config>,
+    // models selectable by `/llm model`. the first one is used until another is chosen.
+    #[serde(default = "default_models")]
+    models: Vec<String>,
+    // requests a user can make in an hour and a day. unlimited when not set.
+    #[serde(default)]
+    hourly_limit: Option<u32>,
+    #[serde(default)]
+    daily_limit: Option<u32>,
+    // defaults of generation parameters. `/llm config` overrides them.
+    #[serde(default)]
+    generation: GenerationConfig,
+    // thresholds by harm category like `HARM_CATEGORY_HARASSMENT = "BLOCK_ONLY_HIGH"`. Gemini only.
+    #[serde(default)]
+    safety_settings: HashMap<String, String>,
+    // estimated tokens of a conversation sent to the model. older turns are dropped over it.
+    #[serde(default = "default_context_budget")]
+    context_budget: usize,
+    // budgets by model name instead of `context_budget`
+    #[serde(default)]
+    model_context_budgets: HashMap<String, usize>,
+    // the dropped turns are summarized with another request instead of being forgotten
+    #[serde(default)]
+    summarize_dropped_turns: bool,
+    // answers direct messages when set
+    #[serde(default)]
+    dm: Option<DmConfig>,
+    // models without system instructions. the prompt is prepended to the conversation instead.
+    #[serde(default)]
+    prepend_prompt_models: Vec<String>,
+    // answer with Google Search results. `/llm grounding` overrides it. Gemini only.
+    #[serde(default)]
+    grounding: bool,
+    // remember messages of the channels for `/ask`
+    #[serde(default)]
+    memory: Option<memory::Config>,
+    // generations are refused when the estimated cost of a month is over it
+    #[serde(default)]
+    cost_budget: Option<cost::Config>,
+    // messages of the channels are reviewed against the rules and flagged ones are reported
+    #[serde(default)]
+    moderation: Option<moderation::Config>,
+    // translate messages reacted with a flag into the language of the country
+    #[serde(default)]
+    translate_reactions: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct DmConfig {
+    // members with any of them can talk in DMs. every member can when empty.
+    #[serde(default)]
+    role_ids: Vec<u64>,
+    // limits of requests in DMs, counted apart from the ones in the guild
+    #[serde(default)]
+    hourly_limit: Option<u32>,
+    #[serde(default)]
+    daily_limit: Option<u32>,
+}
+
+fn default_context_budget() -> usize {
+    30_000
+}
+
+fn default_models() -> Vec<String> {
+    vec!["gemini-1.5-pro".to_string(), "gemini-1.5-flash".to_string()]
 }
 
 pub struct DiscordHandler {
     db_pool: SqlitePool,
+    provider: Arc<dyn LlmProvider>,
+    model: RwLock<String>,
     cached_prompt: RwLock<Option<String>>,
+    // prompts overriding `cached_prompt` by channel id
+    channel_prompts: RwLock<HashMap<u64, String>>,
+    // sizes of windows by channel id
+    channel_windows: RwLock<HashMap<u64, u64>>,
+    // set by `/llm config`
+    generation: RwLock<GenerationConfig>,
+    safety_threshold: RwLock<Option<String>>,
+    // set by `/llm grounding`
+    grounding: RwLock<Option<bool>>,
     cached_mention_msg: OnceCell<String>,
+    // the guild functions called by the model look up
+    guild_id: OnceCell<GuildId>,
+    embedding_started: AtomicBool,
     config: Config,
 }
 
 const COMMAND_NAME: &str = "llm";
+// generations being streamed by the id of the question
+static GENERATIONS: Lazy<DashMap<u64, oneshot::Sender<()>>> = Lazy::new(DashMap::new);
+const WORKING_INDICATOR: &str = "`<...>`";
+const END_INDICATOR: &str = "`<DONE>`";
 
 impl DiscordHandler {
     pub async fn new(db_pool: SqlitePool, config: &super::Config) -> anyhow::Result<Self> {
-        let cached_prompt = sqlx::query!("SELECT `prompt` FROM `llm_config`")
-            .fetch_optional(&db_pool)
-            .await?
-            .map(|r| {
-                let mut prompt = r.prompt;
+        let saved = sqlx::query!(
+            r#"SELECT
+                `prompt`, `model`, `temperature`, `top_p`, `max_output_tokens`, `safety_threshold`,
+                `grounding` AS "grounding?: bool"
+            FROM `llm_config`"#
+        )
+        .fetch_optional(&db_pool)
+        .await?;
+        let generation = saved
+            .as_ref()
+            .map(|r| GenerationConfig {
+                temperature: r.temperature,
+                top_p: r.top_p,
+                max_output_tokens: r.max_output_tokens,
+            })
+            .unwrap_or_default();
+        let safety_threshold = saved.as_ref().and_then(|r| r.safety_threshold.clone());
+        let grounding = saved.as_ref().and_then(|r| r.grounding);
+        let cached_prompt = saved
+            .as_ref()
+            .map(|r| r.prompt.clone())
+            .filter(|prompt| !prompt.is_empty())
+            .map(|mut prompt| {
                 prompt.push('\n');
                 prompt
             });
+        let channel_prompts = sqlx::query!(
+            r#"SELECT `channel_id` AS "channel_id!: i64", `prompt` FROM `llm_channel_prompts`"#
+        )
+        .fetch_all(&db_pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.channel_id as u64, format!("{}\n", r.prompt)))
+        .collect();
+        let channel_windows = sqlx::query!(
+            r#"SELECT `channel_id` AS "channel_id!: i64", `size` FROM `llm_channel_windows`"#
+        )
+        .fetch_all(&db_pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.channel_id as u64, r.size as u64))
+        .collect();
+        // the saved model may be removed from the config
+        let model = saved
+            .and_then(|r| r.model)
+            .filter(|model| config.llm.models.contains(model))
+            .or_else(|| config.llm.models.first().cloned())
+            .context("No LLM model is configured")?;
 
         Ok(Self {
             db_pool,
+            provider: match &config.llm.openai {
+                Some(openai) => Arc::new(openai::OpenAiProvider::new(openai)),
+                None => Arc::new(gemini::GeminiProvider::new(&config.llm.api_key)),
+            },
+            model: RwLock::new(model),
             cached_prompt: RwLock::new(cached_prompt),
+            channel_prompts: RwLock::new(channel_prompts),
+            channel_windows: RwLock::new(channel_windows),
+            generation: RwLock::new(generation),
+            safety_threshold: RwLock::new(safety_threshold),
+            grounding: RwLock::new(grounding),
             cached_mention_msg: OnceCell::new(),
+            guild_id: OnceCell::new(),
+            embedding_started: AtomicBool::new(false),
             config: config.llm.clone(),
         })
     }
+
+    // the whole answer without streaming it
+    async fn generate(
+        &self,
+        request: &ChatRequest,
+    ) -> anyhow::Result<(String, Option<provider::TokenUsage>)> {
+        let model = self.model.read().await.clone();
+        let mut response = self.provider.stream(&model, request).await?;
+        let mut text = String::new();
+        let mut usage = None;
+        while let Some(chunk) = response.next().await {
+            let chunk = chunk?;
+            text.push_str(&chunk.text);
+            if chunk.usage.is_some() {
+                usage = chunk.usage;
+            }
+        }
+
+        Ok((text, usage))
+    }
+
+    fn strip_mention(&self, content: &str) -> String {
+        content.replacen(unsafe { self.cached_mention_msg.get_unchecked() }, "", 1)
+    }
+
+    // turns the message replies to, from the oldest one.
+    // stored turns are read from DB and only unknown messages are fetched from discord.
+    async fn load_history(&self, context: &Context, message: &Message) -> Vec<Turn> {
+        let reference = |message: &Message| {
+            message
+                .message_reference
+                .as_ref()
+                .and_then(|reference| Some((reference.channel_id, reference.message_id?)))
+        };
+
+        let mut history = Vec::new();
+        let mut fetched = Vec::new();
+        let mut next: Option<(ChannelId, MessageId)> = reference(message);
+        while let Some((channel_id, message_id)) = next.take() {
+            match conversation::load_chain(&self.db_pool, message_id.0 as i64).await {
+                Ok(chain) if !chain.is_empty() => {
+                    // the oldest stored turn may reply to a message which is not stored yet
+                    next = chain
+                        .last()
+                        .and_then(|turn| turn.parent_message_id)
+                        .map(|parent| (channel_id, MessageId(parent as u64)));
+                    history.extend(chain);
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => error!("{e:?}"),
+            }
+
+            let message = match context.http.get_message(channel_id.0, message_id.0).await {
+                Ok(message) => message,
+                Err(e) => {
+                    info!("Conversation is cut at message({message_id}) - {e:?}");
+                    break;
+                }
+            };
+            let is_model = message.author.id == context.cache.current_user_id();
+            fetched.push(history.len());
+            history.push(Turn {
+                message_id: message.id.0 as i64,
+                parent_message_id: reference(&message).map(|(_, id)| id.0 as i64),
+                root_message_id: message.id.0 as i64,
+                user_id: (!is_model).then(|| message.author.id.0 as i64),
+                role: if is_model {
+                    ChatRole::Model
+                } else {
+                    ChatRole::User
+                },
+                content: if is_model {
+                    message.content.trim_end_matches(END_INDICATOR).to_string()
+                } else {
+                    self.strip_mention(&message.content)
+                },
+            });
+            next = reference(&message);
+        }
+
+        // fetched turns belong to the conversation of the oldest turn
+        if let Some(root_message_id) = history.last().map(|turn| turn.root_message_id) {
+            for index in fetched {
+                let turn = &mut history[index];
+                turn.root_message_id = root_message_id;
+                if let Err(e) = conversation::save_turn(&self.db_pool, turn).await {
+                    error!("{e:?}");
+                }
+            }
+        }
+
+        history.reverse();
+        history
+    }
+
+    async fn handle_model_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [name] = option.get_options(&["name"]);
+        let content = if let Some(name) = name.as_str() {
+            sqlx::query!(
+                "INSERT INTO `llm_config` (`id`, `prompt`, `model`) VALUES (0, '', ?)
+                ON CONFLICT (`id`) DO UPDATE
+                SET `model` = `excluded`.`model`",
+                name
+            )
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to write new model to DB")?;
+            *self.model.write().await = name.to_string();
+            info!("LLM model is changed to {name}");
+
+            format!("모델을 {name}(으)로 변경했습니다.")
+        } else {
+            format!("MODEL: {}", self.model.read().await)
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+
+    // buttons on replies work for the asker only
+    async fn handle_generation_button(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+        question_id: &str,
+        regenerate: bool,
+    ) -> anyhow::Result<()> {
+        let question_id = question_id.parse::<u64>().context("Invalid question id")?;
+        let question = interaction
+            .channel_id
+            .message(context, MessageId(question_id))
+            .await
+            .context("Failed to get question")?;
+        let denied = if question.author.id != interaction.user.id {
+            Some("질문한 사람만 사용할 수 있습니다.")
+        } else if regenerate
+            && !self
+                .may_generate(
+                    context,
+                    interaction.user.id,
+                    interaction
+                        .member
+                        .as_ref()
+                        .map(|member| member.roles.as_slice()),
+                )
+                .await
+        {
+            Some("LLM을 사용할 권한이 없습니다.")
+        } else {
+            None
+        };
+        if let Some(denied) = denied {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder.content(denied).ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+
+        if !regenerate {
+            if let Some((_, stop)) = GENERATIONS.remove(&question_id) {
+                let _ = stop.send(());
+            }
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder.kind(InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+
+        // the previous answer is left as is without the button
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|builder| {
+                        builder.components(|components| components)
+                    })
+            })
+            .await
+            .context("Failed to send interaction response")?;
+        info!("Regenerate answer for message({question_id})");
+        self.answer(context, &question, interaction.guild_id.is_none())
+            .await;
+
+        Ok(())
+    }
+
+    fn roles_allowed(&self, roles: &[RoleId]) -> bool {
+        let has_any = |role_ids: &[u64]| roles.iter().any(|role| role_ids.contains(&role.0));
+
+        (self.config.usage_role_ids.is_empty() || has_any(&self.config.usage_role_ids))
+            && !has_any(&self.config.blocked_role_ids)
+    }
+
+    // whether the user can make generations. roles of the member are fetched when not given.
+    pub(super) async fn may_generate(
+        &self,
+        context: &Context,
+        user_id: UserId,
+        roles: Option<&[RoleId]>,
+    ) -> bool {
+        if self.config.usage_role_ids.is_empty() && self.config.blocked_role_ids.is_empty() {
+            return true;
+        }
+        if let Some(roles) = roles {
+            return self.roles_allowed(roles);
+        }
+
+        let Some(guild_id) = self.guild_id.get() else {
+            return false;
+        };
+        match guild_id.member(context, user_id).await {
+            Ok(member) => self.roles_allowed(&member.roles),
+            Err(e) => {
+                info!("Failed to get member({user_id}) to check LLM access - {e:?}");
+                false
+            }
+        }
+    }
+
+    // members of the guild who can talk in DMs
+    async fn dm_allowed(&self, context: &Context, user_id: UserId) -> bool {
+        let (Some(dm), Some(guild_id)) = (&self.config.dm, self.guild_id.get()) else {
+            return false;
+        };
+        let member = match guild_id.member(context, user_id).await {
+            Ok(member) => member,
+            Err(e) => {
+                info!("DM from user({user_id}) who is not a member - {e:?}");
+                return false;
+            }
+        };
+
+        (dm.role_ids.is_empty()
+            || member
+                .roles
+                .iter()
+                .any(|role| dm.role_ids.contains(&role.0)))
+            && self.roles_allowed(&member.roles)
+    }
+
+    // answers the question in a reply streamed from the model.
+    // `message` may be fetched without its guild id so whether it is in DMs is given.
+    async fn answer(&self, context: &Context, message: &Message, dm: bool) {
+        if dm && !self.dm_allowed(context, message.author.id).await {
+            if let Err(e) = message
+                .reply(context, "DM으로 대화할 권한이 없습니다.")
+                .await
+            {
+                error!("Failed to notify DM permission - {e:?}");
+            }
+            return;
+        }
+        if self.over_budget(context, message.author.id).await {
+            if let Err(e) = message
+                .reply(
+                    context,
+                    "이번 달 LLM 예산을 모두 사용했습니다. 다음 달에 다시 물어봐주세요.",
+                )
+                .await
+            {
+                error!("Failed to notify budget - {e:?}");
+            }
+            return;
+        }
+        let user_id = message.author.id.0 as i64;
+        let model = self.model.read().await.clone();
+        let request_id = match self.record_request(user_id, &model, dm).await {
+            Ok(Quota::Recorded(request_id)) => Some(request_id),
+            Ok(Quota::Exceeded(retry_at)) => {
+                if let Err(e) = message
+                    .reply(
+                        context,
+                        format!(
+                            "사용량 한도에 도달했습니다. <t:{retry_at}:R>에 다시 물어봐주세요."
+                        ),
+                    )
+                    .await
+                {
+                    error!("Failed to notify quota - {e:?}");
+                }
+                return;
+            }
+            Err(e) => {
+                error!("Failed to record request of user({user_id}) - {e:?}");
+                None
+            }
+        };
+
+        let mut history = self.load_history(context, message).await;
+        let question = Turn {
+            message_id: message.id.0 as i64,
+            parent_message_id: history.last().map(|turn| turn.message_id),
+            root_message_id: history
+                .first()
+                .map_or(message.id.0 as i64, |turn| turn.root_message_id),
+            user_id: Some(message.author.id.0 as i64),
+            role: ChatRole::User,
+            content: self.strip_mention(&message.content),
+        };
+        if let Err(e) = conversation::save_turn(&self.db_pool, &question).await {
+            error!("{e:?}");
+        }
+        // a question not replying to the bot continues the thread it is in,
+        // or the recent messages in window mode
+        if history.is_empty() {
+            history = self.load_thread(context, message).await;
+        }
+        if history.is_empty() {
+            history = self.load_window(context, message).await;
+        }
+
+        let prompt = {
+            // the prompt of the channel comes before the global one
+            let channel_prompts = self.channel_prompts.read().await;
+            let cached_prompt = self.cached_prompt.read().await;
+            channel_prompts
+                .get(&message.channel_id.0)
+                .or(cached_prompt.as_ref())
+                .cloned()
+        };
+        let reserved = budget::estimate_tokens(&question.content)
+            + prompt.as_deref().map_or(0, budget::estimate_tokens);
+        let (dropped_summary, summary_usage) = self
+            .fit_history(&model, &mut history, reserved)
+            .await
+            .unzip();
+
+        // a long answer is stored in parts, which are joined back
+        let mut contents = Vec::<ChatMessage>::new();
+        for turn in history.iter().chain(std::iter::once(&question)) {
+            match contents.last_mut() {
+                Some(last) if last.role == turn.role => last.text.push_str(&turn.content),
+                _ => contents.push(ChatMessage {
+                    role: turn.role,
+                    text: turn.content.clone(),
+                    ..Default::default()
+                }),
+            }
+        }
+
+        {
+            // let the model answer with times in the zone of the asker
+            let time_zone =
+                crate::user::user_time_zone(&self.db_pool, message.author.id.0 as i64).await;
+            let now = chrono::Utc::now()
+                .with_timezone(&time_zone)
+                .format("%Y-%m-%d %H:%M");
+            let content = unsafe { contents.get_mut(0).unwrap_unchecked() };
+            content
+                .text
+                .insert_str(0, &format!("현재 시각: {now} ({time_zone})\n"));
+        }
+        if let Some(summary) = dropped_summary {
+            let content = unsafe { contents.get_mut(0).unwrap_unchecked() };
+            content
+                .text
+                .insert_str(0, &format!("이전 대화 요약:\n{summary}\n\n"));
+        }
+        let system_instruction = match prompt {
+            Some(prompt) if self.config.prepend_prompt_models.contains(&model) => {
+                let content = unsafe { contents.get_mut(0).unwrap_unchecked() };
+                content.text.insert_str(0, &prompt);
+                None
+            }
+            prompt => prompt,
+        };
+
+        log::debug!("{system_instruction:?} {contents:?}");
+
+        let applications = tools::applications(context).await;
+        let mut request = ChatRequest {
+            system_instruction,
+            messages: contents,
+            generation: self.generation.read().await.or(&self.config.generation),
+            safety_settings: self.safety_settings().await,
+            tools: applications
+                .as_ref()
+                .map(tools::declarations)
+                .unwrap_or_default(),
+            grounding: self.grounding().await,
+        };
+
+        let mut reply = match StreamingReply::start(context, message).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                error!("{e:?}");
+                return;
+            }
+        };
+
+        let mut response = match self.provider.stream(&model, &request).await {
+            Ok(response) => response,
+            Err(e) => {
+                error!("Received error from {model} - {e:?}");
+                if let Err(e) = reply
+                    .fail(context, &format!("`ERROR: Received error from {model}`"))
+                    .await
+                {
+                    error!("{e:?}");
+                }
+                return;
+            }
+        };
+
+        // the stop button ends the generation with the answer so far
+        let (stop_sender, mut stop) = oneshot::channel();
+        GENERATIONS.insert(message.id.0, stop_sender);
+
+        let context = context.clone();
+        let db_pool = self.db_pool.clone();
+        let provider = self.provider.clone();
+        let guild_id = self.guild_id.get().copied();
+        let cost_budget = self.config.cost_budget.clone();
+        tokio::task::spawn(async move {
+            let mut token_usage = summary_usage.flatten();
+            let mut citations = Vec::new();
+            'rounds: for round in 1..=tools::MAX_ROUNDS {
+                let mut text = String::new();
+                let mut function_calls = Vec::new();
+                let mut round_usage = None;
+                loop {
+                    let chunk = tokio::select! {
+                        chunk = response.next() => chunk,
+                        _ = &mut stop => {
+                            info!("Generation for message({}) is stopped", question.message_id);
+                            break 'rounds;
+                        }
+                    };
+                    let chunk = match chunk {
+                        Some(Ok(chunk)) => chunk,
+                        Some(Err(e)) => {
+                            error!("Received error from {model} - {e:?}");
+                            break 'rounds;
+                        }
+                        None => break,
+                    };
+
+                    if chunk.usage.is_some() {
+                        round_usage = chunk.usage;
+                    }
+                    function_calls.extend(chunk.function_calls);
+                    citations.extend(chunk.citations);
+                    text.push_str(&chunk.text);
+                    if let Err(e) = reply.push(&context, &chunk.text).await {
+                        error!("{e:?}");
+                    }
+                }
+                if let Some(round_usage) = round_usage {
+                    *token_usage.get_or_insert_with(Default::default) += round_usage;
+                }
+
+                let (Some(applications), Some(guild_id)) = (&applications, guild_id) else {
+                    break;
+                };
+                if function_calls.is_empty() || round == tools::MAX_ROUNDS {
+                    break;
+                }
+
+                // answer the calls and let the model continue with the results
+                let mut function_responses = Vec::new();
+                for call in &function_calls {
+                    info!("{model} calls {}({})", call.name, call.args);
+                    function_responses
+                        .push(tools::call(&context, applications, guild_id, call).await);
+                }
+                request.messages.push(ChatMessage {
+                    role: ChatRole::Model,
+                    text,
+                    function_calls,
+                    ..Default::default()
+                });
+                request.messages.push(ChatMessage {
+                    role: ChatRole::User,
+                    function_responses,
+                    ..Default::default()
+                });
+                response = match provider.stream(&model, &request).await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        error!("Received error from {model} - {e:?}");
+                        break;
+                    }
+                };
+            }
+
+            GENERATIONS.remove(&(question.message_id as u64));
+            if let Some(sources) = reply::format_citations(&citations) {
+                if let Err(e) = reply.push(&context, &sources).await {
+                    error!("{e:?}");
+                }
+            }
+
+            // each part of the answer is a turn replying to the previous part
+            let mut parent_message_id = question.message_id;
+            for (part, content) in reply.finish(&context).await {
+                let answer = Turn {
+                    message_id: part.id.0 as i64,
+                    parent_message_id: Some(parent_message_id),
+                    root_message_id: question.root_message_id,
+                    user_id: question.user_id,
+                    role: ChatRole::Model,
+                    content,
+                };
+                if let Err(e) = conversation::save_turn(&db_pool, &answer).await {
+                    error!("{e:?}");
+                }
+                parent_message_id = answer.message_id;
+            }
+            if let (Some(request_id), Some(token_usage)) = (request_id, token_usage) {
+                if let Err(e) = usage::record_tokens(&db_pool, request_id, token_usage).await {
+                    error!("{e:?}");
+                }
+            }
+            cost::warn(&context, &db_pool, cost_budget.as_ref()).await;
+        });
+    }
 }
 
 #[async_trait]
@@ -71,19 +788,47 @@ impl SubApplication for DiscordHandler {
         let command = ApplicationCommand {
             name: COMMAND_NAME,
             description: "LLM 설정",
-            options: vec![ApplicationCommandOption {
-                kind: ApplicationCommandOptionType::SubCommand,
-                name: "prompt",
-                description: "프롬프트 설정",
-                options: vec![ApplicationCommandOption {
-                    kind: ApplicationCommandOptionType::String,
-                    name: "new_prompt",
-                    description: "입력 시 새로 설정하며, 없을 경우 현재 값을 보여줍니다.",
-                    required: Some(false),
+            options: vec![
+                prompt::command_option(),
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "model",
+                    description: "모델 설정",
+                    options: vec![ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "name",
+                        description: "입력 시 새로 설정하며, 없을 경우 현재 값을 보여줍니다.",
+                        required: Some(false),
+                        choices: self
+                            .config
+                            .models
+                            .iter()
+                            .map(|model| ApplicationCommandOptionChoice {
+                                name: model,
+                                value: serde_json::json!(model),
+                            })
+                            .collect(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                persona::command_option(),
+                window::command_option(),
+                settings::command_option(),
+                settings::grounding_command_option(),
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "quota",
+                    description: "남은 사용량 확인",
                     ..Default::default()
-                }],
-                ..Default::default()
-            }],
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "usage",
+                    description: "이번 달 토큰 사용량 확인",
+                    ..Default::default()
+                },
+            ],
         };
 
         context
@@ -94,10 +839,28 @@ impl SubApplication for DiscordHandler {
             )
             .await
             .unwrap();
+        context
+            .http
+            .create_guild_application_command(
+                *guild_id.as_u64(),
+                &serde_json::to_value(summarize::command()).unwrap(),
+            )
+            .await
+            .unwrap();
+        context
+            .http
+            .create_guild_application_command(
+                *guild_id.as_u64(),
+                &serde_json::to_value(memory::command()).unwrap(),
+            )
+            .await
+            .unwrap();
+        self.start_embedding();
 
         let _ = self
             .cached_mention_msg
             .set(format!("<@{}>", context.cache.current_user_id().0));
+        let _ = self.guild_id.set(guild_id);
     }
 
     async fn application_command_interaction_create(
@@ -105,11 +868,34 @@ impl SubApplication for DiscordHandler {
         context: &Context,
         interaction: &ApplicationCommandInteraction,
     ) -> bool {
+        if interaction.data.name == summarize::COMMAND_NAME {
+            if let Err(e) = self.handle_summarize_command(context, interaction).await {
+                error!("Failed to handle summarize command - {e:?}");
+            }
+            return true;
+        }
+        if interaction.data.name == memory::COMMAND_NAME {
+            if let Err(e) = self.handle_ask_command(context, interaction).await {
+                error!("Failed to handle ask command - {e:?}");
+            }
+            return true;
+        }
         if interaction.data.name != COMMAND_NAME {
             return false;
         }
 
         let option = unsafe { interaction.data.options.first().unwrap_unchecked() };
+        // commands for everyone
+        if let Some(result) = match option.name.as_str() {
+            "quota" => Some(self.handle_quota_command(context, interaction).await),
+            _ => None,
+        } {
+            if let Err(e) = result {
+                error!("Failed to handle {} command - {e:?}", option.name);
+            }
+            return true;
+        }
+
         let mut authorized = false;
         for role in &self.config.setting_role_ids {
             match interaction
@@ -147,61 +933,56 @@ impl SubApplication for DiscordHandler {
 
         match option.name.as_str() {
             "prompt" => {
-                if let Some(new_prompt) = option.options.first().and_then(|v| v.value.as_ref()) {
-                    let new_prompt = new_prompt.as_str().unwrap();
-                    if let Err(e) = sqlx::query!(
-                        "INSERT INTO `llm_config` (`prompt`, `id`) VALUES (?, 0)
-                        ON CONFLICT (`id`) DO UPDATE
-                        SET `prompt` = `excluded`.`prompt`
-                        WHERE `id` = `excluded`.`id`",
-                        new_prompt
-                    )
-                    .execute(&self.db_pool)
+                if let Err(e) = self
+                    .handle_prompt_command(context, interaction, option)
                     .await
-                    {
-                        error!("Failed to write new prompt to DB - {e:?}");
-                        return true;
-                    }
-
-                    let _ = self
-                        .cached_prompt
-                        .write()
-                        .await
-                        .insert(format!("{new_prompt}\n"));
-
-                    if let Err(e) = interaction
-                        .create_interaction_response(context, |builder| {
-                            builder
-                                .kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|builder| {
-                                    builder.content("설정 되었습니다.").ephemeral(true)
-                                })
-                        })
-                        .await
-                    {
-                        error!("Failed to send interaction response - {e:?}");
-                    }
-                } else {
-                    let cached_prompt = self.cached_prompt.read().await;
-
-                    if let Err(e) = interaction
-                        .create_interaction_response(context, |builder| {
-                            builder
-                                .kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|builder| {
-                                    builder
-                                        .content(if let Some(prompt) = cached_prompt.as_ref() {
-                                            format!("PROMPT: {}", prompt)
-                                        } else {
-                                            "NO PROMPT".to_string()
-                                        })
-                                        .ephemeral(true)
-                                })
-                        })
-                        .await
-                    {
-                        error!("Failed to send interaction response - {e:?}");
-                    }
+                {
+                    error!("Failed to handle prompt command - {e:?}");
+                }
+            }
+            "model" => {
+                if let Err(e) = self
+                    .handle_model_command(context, interaction, option)
+                    .await
+                {
+                    error!("Failed to handle model command - {e:?}");
+                }
+            }
+            "window" => {
+                if let Err(e) = self
+                    .handle_window_command(context, interaction, option)
+                    .await
+                {
+                    error!("Failed to handle window command - {e:?}");
+                }
+            }
+            "persona" => {
+                if let Err(e) = self
+                    .handle_persona_command(context, interaction, option)
+                    .await
+                {
+                    error!("Failed to handle persona command - {e:?}");
+                }
+            }
+            "config" => {
+                if let Err(e) = self
+                    .handle_config_command(context, interaction, option)
+                    .await
+                {
+                    error!("Failed to handle config command - {e:?}");
+                }
+            }
+            "grounding" => {
+                if let Err(e) = self
+                    .handle_grounding_command(context, interaction, option)
+                    .await
+                {
+                    error!("Failed to handle grounding command - {e:?}");
+                }
+            }
+            "usage" => {
+                if let Err(e) = self.handle_usage_command(context, interaction).await {
+                    error!("Failed to handle usage command - {e:?}");
                 }
             }
             _ => unsafe { std::hint::unreachable_unchecked() },
@@ -210,179 +991,197 @@ impl SubApplication for DiscordHandler {
         true
     }
 
-    async fn message(&self, context: &Context, message: &Message) {
-        const WORKING_INDICATOR: &str = "`<...>`";
-        const END_INDICATOR: &str = "`<DONE>`";
+    async fn message_component(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+    ) -> bool {
+        let custom_id = interaction.data.custom_id.as_str();
+        if let Err(e) = if let Some(id) = custom_id.strip_prefix(STOP_BUTTON) {
+            self.handle_generation_button(context, interaction, id, false)
+                .await
+        } else if let Some(id) = custom_id.strip_prefix(REGENERATE_BUTTON) {
+            self.handle_generation_button(context, interaction, id, true)
+                .await
+        } else {
+            return false;
+        } {
+            error!("Failed to handle message component: {e:?}");
+        }
 
-        let mentioned = match message.mentions_me(context).await {
-            Ok(mentioned) => mentioned,
-            Err(e) => {
-                error!("Failed while calling API - {e:?}");
-                return;
-            }
-        };
+        true
+    }
 
-        let client = GoogleAiClient::new_from_model_response_type(
-            Model::GeminiPro,
-            self.config.api_key.clone(),
-            ResponseType::StreamGenerateContent,
-        );
-        if !mentioned {
-            return;
+    async fn modal_submit(&self, context: &Context, modal: &ModalSubmitInteraction) -> bool {
+        let Some(channel_id) = modal.data.custom_id.strip_prefix(prompt::PROMPT_MODAL) else {
+            return false;
+        };
+        if let Err(e) = self
+            .handle_prompt_modal_submit(context, modal, channel_id)
+            .await
+        {
+            error!("Failed to handle prompt modal submit - {e:?}");
         }
 
-        let mut contents = vec![Content {
-            role: Role::User,
-            parts: vec![Part {
-                text: Some(message.content.replacen(
-                    unsafe { self.cached_mention_msg.get_unchecked() },
-                    "",
-                    1,
-                )),
-                inline_data: None,
-                file_data: None,
-                video_metadata: None,
-            }],
-        }];
-
-        let mut message_reference = message.message_reference.clone();
-        while let Some(ref_msg) = message_reference {
-            let message = context
-                .http
-                .get_message(
-                    *ref_msg.channel_id.as_u64(),
-                    *ref_msg.message_id.unwrap().as_u64(),
-                )
-                .await
-                .unwrap();
-            contents.push(if message.author.id == context.cache.current_user_id() {
-                Content {
-                    role: Role::Model,
-                    parts: vec![Part {
-                        text: Some(message.content.trim_end_matches(END_INDICATOR).to_string()),
-                        inline_data: None,
-                        file_data: None,
-                        video_metadata: None,
-                    }],
-                }
-            } else {
-                Content {
-                    role: Role::User,
-                    parts: vec![Part {
-                        text: Some(message.content.replacen(
-                            unsafe { self.cached_mention_msg.get_unchecked() },
-                            "",
-                            1,
-                        )),
-                        inline_data: None,
-                        file_data: None,
-                        video_metadata: None,
-                    }],
-                }
-            });
-            message_reference = message.message_reference;
-        }
+        true
+    }
 
-        contents.reverse();
+    async fn forget_user(&self, user_id: UserId) -> anyhow::Result<Vec<String>> {
+        let user_id = user_id.0 as i64;
+        let mut tx = self.db_pool.begin().await?;
+        let deleted = sqlx::query!(
+            "DELETE FROM `llm_conversations` WHERE `user_id` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete conversations")?
+        .rows_affected();
+        sqlx::query!("DELETE FROM `llm_requests` WHERE `user_id` = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete LLM requests")?;
+        sqlx::query!(
+            "UPDATE `llm_prompt_history` SET `changed_by` = NULL WHERE `changed_by` = ?",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to forget prompt changes")?;
+        let remembered = sqlx::query!("DELETE FROM `llm_memory` WHERE `user_id` = ?", user_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete remembered messages")?
+            .rows_affected();
+        tx.commit().await?;
 
-        {
-            let cached_prompt = self.cached_prompt.read().await;
-            if let Some(cached_prompt) = cached_prompt.as_ref() {
-                let content = unsafe { contents.get_mut(0).unwrap_unchecked() };
-                let part = unsafe { content.parts.get_mut(0).unwrap_unchecked() };
-                let text = unsafe { part.text.as_mut().unwrap_unchecked() };
-                text.insert_str(0, cached_prompt);
-            }
+        let mut forgotten = Vec::new();
+        if deleted > 0 {
+            forgotten.push(format!("LLM 대화 기록 {deleted}건"));
+        }
+        if remembered > 0 {
+            forgotten.push(format!("LLM이 기억하는 메시지 {remembered}건"));
         }
+        Ok(forgotten)
+    }
 
-        log::debug!("{contents:?}");
+    async fn export_user(
+        &self,
+        user_id: UserId,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        let user_id = user_id.0 as i64;
+        let turns = sqlx::query!(
+            "SELECT `message_id`, `root_message_id`, `role`, `content`, `created_at`
+            FROM `llm_conversations`
+            WHERE `user_id` = ?
+            ORDER BY `message_id`",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get conversations")?;
 
-        let request = Request {
-            contents,
-            tools: vec![],
-            safety_settings: vec![],
-            generation_config: None,
-        };
+        let mut export = serde_json::Map::new();
+        export.insert(
+            "llm_conversations".to_string(),
+            turns
+                .into_iter()
+                .map(|turn| {
+                    serde_json::json!({
+                        "message_id": turn.message_id.to_string(),
+                        "conversation_id": turn.root_message_id.to_string(),
+                        "role": turn.role,
+                        "content": turn.content,
+                        "created_at": turn.created_at,
+                    })
+                })
+                .collect(),
+        );
+        let remembered = sqlx::query!(
+            "SELECT `message_id`, `channel_id`, `content`, `created_at`
+            FROM `llm_memory`
+            WHERE `user_id` = ?
+            ORDER BY `message_id`",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get remembered messages")?;
+        export.insert(
+            "llm_memory".to_string(),
+            remembered
+                .into_iter()
+                .map(|message| {
+                    serde_json::json!({
+                        "message_id": message.message_id.to_string(),
+                        "channel_id": message.channel_id.to_string(),
+                        "content": message.content,
+                        "created_at": message.created_at,
+                    })
+                })
+                .collect(),
+        );
 
-        let mut joined_response = String::from(WORKING_INDICATOR);
-        let mut reply = match message.reply(context, &joined_response).await {
-            Ok(message) => message,
-            Err(e) => {
-                error!("Failed to create reply - {e:?}");
-                return;
+        Ok(export)
+    }
+
+    async fn reaction_add(&self, context: &Context, reaction: &Reaction) {
+        if let Err(e) = self.translate_on_reaction(context, reaction).await {
+            error!("Failed to translate on reaction - {e:?}");
+        }
+    }
+
+    async fn message(&self, context: &Context, message: &Message) {
+        self.remember(message).await;
+        self.moderate(context, message).await;
+
+        // every direct message is a question
+        if message.guild_id.is_none() {
+            if !message.author.bot && self.config.dm.is_some() {
+                self.answer(context, message, true).await;
             }
-        };
+            return;
+        }
 
-        let response = client.post(30, &request);
-        let response = match response.await {
-            Ok(response) => response,
+        let mentioned = match message.mentions_me(context).await {
+            Ok(mentioned) => mentioned,
             Err(e) => {
-                error!("Received error from Google AI - {e:?}");
-                if let Err(e) = reply
-                    .edit(context, |builder| {
-                        builder.content("`ERROR: Received error from Google AI`")
-                    })
-                    .await
-                {
-                    error!("Failed to report error by reply - {e:?}");
-                }
+                error!("Failed while calling API - {e:?}");
                 return;
             }
         };
 
-        let context = context.clone();
-        tokio::task::spawn(async move {
-            if let Some(stream_response) = response.streamed() {
-                if let Some(mut json_stream) = stream_response.response_stream {
-                    while let Some(response) = json_stream.next().await {
-                        let response = match response {
-                            Ok(response) => response,
-                            Err(e) => {
-                                error!("Received error from Google AI - {e:?}");
-                                return;
-                            }
-                        };
-
-                        let response: GeminiResponse = match serde_json::from_value(response) {
-                            Ok(response) => response,
-                            Err(e) => {
-                                error!("Failed to parse received response from Google AI - {e:?}");
-                                return;
-                            }
-                        };
-
-                        joined_response.truncate(joined_response.len() - WORKING_INDICATOR.len());
-                        joined_response.extend(
-                            response.candidates.into_iter().next().into_iter().flat_map(
-                                |candidate| {
-                                    candidate
-                                        .content
-                                        .parts
-                                        .into_iter()
-                                        .filter_map(|part| part.text)
-                                },
-                            ),
-                        );
-                        joined_response.push_str(WORKING_INDICATOR);
-
-                        if let Err(e) = reply
-                            .edit(&context, |builder| builder.content(&joined_response))
-                            .await
-                        {
-                            error!("Failed to report error by reply - {e:?}");
-                        }
-                    }
-                }
-            }
+        if !mentioned {
+            return;
+        }
+        // unauthorized users are ignored quietly
+        if !self
+            .may_generate(
+                context,
+                message.author.id,
+                message
+                    .member
+                    .as_ref()
+                    .map(|member| member.roles.as_slice()),
+            )
+            .await
+        {
+            return;
+        }
 
-            joined_response.truncate(joined_response.len() - WORKING_INDICATOR.len());
-            joined_response.push_str(END_INDICATOR);
-            if let Err(e) = reply
-                .edit(context, |builder| builder.content(joined_response))
-                .await
-            {
-                error!("Failed to report error by reply - {e:?}");
-            }
-        });
+        self.answer(context, message, false).await;
+    }
+
+    async fn message_update(&self, _context: &Context, message: &Message) {
+        self.refresh_memory(message).await;
+    }
+
+    async fn message_delete(
+        &self,
+        _context: &Context,
+        _channel_id: ChannelId,
+        message_ids: &[MessageId],
+    ) {
+        self.forget_messages(message_ids).await;
     }
 }
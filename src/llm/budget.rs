@@ -0,0 +1,98 @@
+use log::{error, info};
+
+use super::{
+    conversation::Turn,
+    provider::{ChatMessage, ChatRequest, ChatRole, TokenUsage},
+    DiscordHandler,
+};
+
+const SUMMARIZE_DROPPED_PROMPT: &str =
+    "다음은 이어지는 대화의 앞부분입니다. 이후 대화에 필요한 내용만 짧게 요약해주세요.";
+
+// rough count without a tokenizer. latin text takes about 4 bytes a token and
+// other scripts like hangul take about a token a character.
+pub(super) fn estimate_tokens(text: &str) -> usize {
+    let (ascii, others) = text.chars().fold((0, 0), |(ascii, others), c| {
+        if c.is_ascii() {
+            (ascii + 1, others)
+        } else {
+            (ascii, others + 1)
+        }
+    });
+
+    (ascii + 3) / 4 + others
+}
+
+// drops the oldest turns until the rest fit in the budget and returns the dropped ones
+fn trim_history(history: &mut Vec<Turn>, budget: usize) -> Vec<Turn> {
+    let mut used = 0;
+    let mut keep = history.len();
+    for turn in history.iter().rev() {
+        used += estimate_tokens(&turn.content);
+        if used > budget {
+            break;
+        }
+        keep -= 1;
+    }
+    // the conversation has to begin with the user
+    while history
+        .get(keep)
+        .map_or(false, |turn| turn.role == ChatRole::Model)
+    {
+        keep += 1;
+    }
+
+    history.drain(..keep).collect()
+}
+
+impl DiscordHandler {
+    fn context_budget(&self, model: &str) -> usize {
+        self.config
+            .model_context_budgets
+            .get(model)
+            .copied()
+            .unwrap_or(self.config.context_budget)
+    }
+
+    // fits the history in the budget of the model. `reserved` is taken by the question and prompts.
+    // returns the summary of dropped turns if it is enabled, with its usage.
+    pub(super) async fn fit_history(
+        &self,
+        model: &str,
+        history: &mut Vec<Turn>,
+        reserved: usize,
+    ) -> Option<(String, Option<TokenUsage>)> {
+        let budget = self.context_budget(model).saturating_sub(reserved);
+        let dropped = trim_history(history, budget);
+        if dropped.is_empty() {
+            return None;
+        }
+        info!("{} old turns are dropped to fit {model}", dropped.len());
+        if !self.config.summarize_dropped_turns {
+            return None;
+        }
+
+        let transcript = dropped
+            .iter()
+            .map(|turn| match turn.role {
+                ChatRole::User => format!("user: {}", turn.content),
+                ChatRole::Model => format!("model: {}", turn.content),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = ChatRequest {
+            messages: vec![ChatMessage {
+                text: format!("{SUMMARIZE_DROPPED_PROMPT}\n\n{transcript}"),
+                ..Default::default()
+            }],
+            generation: self.generation.read().await.or(&self.config.generation),
+            safety_settings: self.safety_settings().await,
+            ..Default::default()
+        };
+
+        self.generate(&request)
+            .await
+            .map_err(|e| error!("Failed to summarize dropped turns - {e:?}"))
+            .ok()
+    }
+}
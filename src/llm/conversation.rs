@@ -0,0 +1,90 @@
+use anyhow::Context as _;
+use sqlx::SqlitePool;
+
+use super::provider::ChatRole;
+
+fn role_name(role: ChatRole) -> &'static str {
+    match role {
+        ChatRole::User => "user",
+        ChatRole::Model => "model",
+    }
+}
+
+fn parse_role(name: &str) -> ChatRole {
+    match name {
+        "model" => ChatRole::Model,
+        _ => ChatRole::User,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Turn {
+    pub(super) message_id: i64,
+    pub(super) parent_message_id: Option<i64>,
+    pub(super) root_message_id: i64,
+    pub(super) user_id: Option<i64>,
+    pub(super) role: ChatRole,
+    pub(super) content: String,
+}
+
+// the stored turn and its ancestors, from the newest one
+pub(super) async fn load_chain(db_pool: &SqlitePool, message_id: i64) -> anyhow::Result<Vec<Turn>> {
+    let rows = sqlx::query!(
+        r#"WITH RECURSIVE `chain` AS (
+            SELECT *, 0 AS `depth` FROM `llm_conversations` WHERE `message_id` = ?
+            UNION ALL
+            SELECT `llm_conversations`.*, `chain`.`depth` + 1
+            FROM `llm_conversations`
+            INNER JOIN `chain` ON `llm_conversations`.`message_id` = `chain`.`parent_message_id`
+        )
+        SELECT
+            `message_id` AS "message_id!: i64",
+            `parent_message_id` AS "parent_message_id?: i64",
+            `root_message_id` AS "root_message_id!: i64",
+            `user_id` AS "user_id?: i64",
+            `role` AS "role!: String",
+            `content` AS "content!: String"
+        FROM `chain`
+        ORDER BY `depth`"#,
+        message_id
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to load conversation")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Turn {
+            message_id: row.message_id,
+            parent_message_id: row.parent_message_id,
+            root_message_id: row.root_message_id,
+            user_id: row.user_id,
+            role: parse_role(&row.role),
+            content: row.content,
+        })
+        .collect())
+}
+
+// turns are immutable, a known message is left as is
+pub(super) async fn save_turn(db_pool: &SqlitePool, turn: &Turn) -> anyhow::Result<()> {
+    let role = role_name(turn.role);
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query!(
+        "INSERT INTO `llm_conversations`
+            (`message_id`, `parent_message_id`, `root_message_id`, `user_id`, `role`, `content`, `created_at`)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (`message_id`) DO NOTHING",
+        turn.message_id,
+        turn.parent_message_id,
+        turn.root_message_id,
+        turn.user_id,
+        role,
+        turn.content,
+        now
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to save conversation turn")?;
+
+    Ok(())
+}
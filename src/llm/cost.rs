@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use anyhow::Context as _;
+use chrono::{Datelike, TimeZone};
+use log::{error, info};
+use serde::Deserialize;
+use serenity::{
+    model::id::{ChannelId, UserId},
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::DiscordHandler;
+
+// the admin channel is warned when the estimated cost reaches this part of the budget
+const WARNING_RATIO: f64 = 0.8;
+
+// prices per a million tokens
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Price {
+    input: f64,
+    output: f64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Config {
+    // estimated cost a month can spend, in the unit of `prices`
+    monthly_limit: f64,
+    // by model name. requests to models without a price are free.
+    prices: HashMap<String, Price>,
+    // members with them can still generate over the budget
+    #[serde(default)]
+    exempt_role_ids: Vec<u64>,
+    #[serde(default)]
+    warning_channel_id: Option<u64>,
+}
+
+// the month is counted in UTC
+fn month_start() -> (String, i64) {
+    let now = chrono::Utc::now();
+    let start = chrono::Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .unwrap();
+
+    (start.format("%Y-%m").to_string(), start.timestamp())
+}
+
+// estimated cost of this month from the recorded tokens
+pub(super) async fn monthly_cost(db_pool: &SqlitePool, config: &Config) -> anyhow::Result<f64> {
+    let (_, since) = month_start();
+    let usages = sqlx::query!(
+        r#"SELECT
+            `model` AS "model?: String",
+            COALESCE(SUM(`prompt_tokens`), 0) AS "prompt_tokens!: i64",
+            COALESCE(SUM(`response_tokens`), 0) AS "response_tokens!: i64"
+        FROM `llm_requests`
+        WHERE `requested_at` >= ?
+        GROUP BY `model`"#,
+        since
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to sum token usage by model")?;
+
+    Ok(usages
+        .into_iter()
+        .filter_map(|usage| {
+            let price = config.prices.get(usage.model.as_ref()?)?;
+            Some(
+                (usage.prompt_tokens as f64 * price.input
+                    + usage.response_tokens as f64 * price.output)
+                    / 1_000_000.0,
+            )
+        })
+        .sum())
+}
+
+async fn warn_once(context: &Context, db_pool: &SqlitePool, config: &Config) -> anyhow::Result<()> {
+    let Some(channel_id) = config.warning_channel_id else {
+        return Ok(());
+    };
+    let cost = monthly_cost(db_pool, config).await?;
+    let level = if cost >= config.monthly_limit {
+        100
+    } else if cost >= config.monthly_limit * WARNING_RATIO {
+        (WARNING_RATIO * 100.0) as i64
+    } else {
+        return Ok(());
+    };
+
+    let (month, _) = month_start();
+    let now = chrono::Utc::now().timestamp();
+    let inserted = sqlx::query!(
+        "INSERT INTO `llm_cost_warnings` (`month`, `level`, `warned_at`) VALUES (?, ?, ?)
+        ON CONFLICT (`month`, `level`) DO NOTHING",
+        month,
+        level,
+        now
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to record cost warning")?
+    .rows_affected();
+    if inserted == 0 {
+        return Ok(());
+    }
+
+    info!("LLM cost of {month} reached {level}% of the budget");
+    let content = if level >= 100 {
+        format!(
+            "이번 달 LLM 예상 비용이 {cost:.2}로 예산 {}을(를) 넘었습니다. 다음 달까지 새 답변을 생성하지 않습니다.",
+            config.monthly_limit
+        )
+    } else {
+        format!(
+            "이번 달 LLM 예상 비용이 {cost:.2}로 예산 {}의 {level}%를 넘었습니다.",
+            config.monthly_limit
+        )
+    };
+    ChannelId(channel_id)
+        .say(context, content)
+        .await
+        .context("Failed to post cost warning")?;
+
+    Ok(())
+}
+
+// called after tokens are recorded
+pub(super) async fn warn(context: &Context, db_pool: &SqlitePool, config: Option<&Config>) {
+    let Some(config) = config else {
+        return;
+    };
+    if let Err(e) = warn_once(context, db_pool, config).await {
+        error!("{e:?}");
+    }
+}
+
+impl DiscordHandler {
+    // whether generations of the user are refused until the month rolls over
+    pub(super) async fn over_budget(&self, context: &Context, user_id: UserId) -> bool {
+        let Some(config) = &self.config.cost_budget else {
+            return false;
+        };
+        match monthly_cost(&self.db_pool, config).await {
+            Ok(cost) if cost >= config.monthly_limit => {}
+            Ok(_) => return false,
+            Err(e) => {
+                error!("{e:?}");
+                return false;
+            }
+        }
+        if config.exempt_role_ids.is_empty() {
+            return true;
+        }
+
+        let Some(guild_id) = self.guild_id.get() else {
+            return true;
+        };
+        match guild_id.member(context, user_id).await {
+            Ok(member) => !member
+                .roles
+                .iter()
+                .any(|role| config.exempt_role_ids.contains(&role.0)),
+            Err(e) => {
+                info!("Failed to get member({user_id}) to check the budget - {e:?}");
+                true
+            }
+        }
+    }
+
+    // a line for `/llm usage`
+    pub(super) async fn describe_cost(&self) -> anyhow::Result<Option<String>> {
+        let Some(config) = &self.config.cost_budget else {
+            return Ok(None);
+        };
+        let cost = monthly_cost(&self.db_pool, config).await?;
+
+        Ok(Some(format!(
+            "예상 비용: {cost:.2} / {} (UTC 기준 이번 달)",
+            config.monthly_limit
+        )))
+    }
+}
@@ -0,0 +1,388 @@
+use anyhow::Context as _;
+use axum::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::provider::{
+    self, event_data, ChatChunk, ChatRequest, ChatRole, LlmProvider, TokenUsage,
+};
+
+const API_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+// sent as a header not to leave the key in URLs of logs and errors
+const API_KEY_HEADER: &str = "x-goog-api-key";
+
+#[derive(Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionResponse {
+    name: String,
+    response: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct SafetySetting<'a> {
+    category: &'a str,
+    threshold: &'a str,
+}
+
+#[derive(Serialize)]
+struct FunctionDeclaration<'a> {
+    name: &'a str,
+    description: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<&'a serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct GoogleSearch {}
+
+// one of the fields is set in a tool
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Tool<'a> {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    function_declarations: Vec<FunctionDeclaration<'a>>,
+    // Gemini 1.x searches with this and later models with `google_search`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    google_search_retrieval: Option<GoogleSearch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    google_search: Option<GoogleSearch>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    contents: Vec<Content>,
+    generation_config: GenerationConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    safety_settings: Vec<SafetySetting<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool<'a>>,
+}
+
+#[derive(Deserialize)]
+struct WebChunk {
+    #[serde(default)]
+    uri: String,
+    #[serde(default)]
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct GroundingChunk {
+    #[serde(default)]
+    web: Option<WebChunk>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroundingMetadata {
+    #[serde(default)]
+    grounding_chunks: Vec<GroundingChunk>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Content,
+    #[serde(default)]
+    grounding_metadata: Option<GroundingMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: i64,
+    #[serde(default)]
+    candidates_token_count: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Serialize)]
+struct EmbedContentRequest<'a> {
+    model: &'a str,
+    content: Content,
+}
+
+#[derive(Serialize)]
+struct BatchEmbedContentsRequest<'a> {
+    requests: Vec<EmbedContentRequest<'a>>,
+}
+
+#[derive(Deserialize)]
+struct ContentEmbedding {
+    values: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct BatchEmbedContentsResponse {
+    #[serde(default)]
+    embeddings: Vec<ContentEmbedding>,
+}
+
+pub(super) struct GeminiProvider {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+impl GeminiProvider {
+    pub(super) fn new(api_key: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.to_string(),
+        }
+    }
+}
+
+fn build_request<'a>(model: &str, request: &'a ChatRequest) -> GenerateContentRequest<'a> {
+    let search = request.grounding.then_some(GoogleSearch {});
+    let search_tool = if model.starts_with("gemini-1.") {
+        Tool {
+            google_search_retrieval: search,
+            ..Default::default()
+        }
+    } else {
+        Tool {
+            google_search: search,
+            ..Default::default()
+        }
+    };
+
+    GenerateContentRequest {
+        system_instruction: request
+            .system_instruction
+            .as_ref()
+            .map(|instruction| Content {
+                role: None,
+                parts: vec![Part {
+                    text: Some(instruction.clone()),
+                    ..Default::default()
+                }],
+            }),
+        contents: request
+            .messages
+            .iter()
+            .map(|message| Content {
+                role: Some(
+                    match message.role {
+                        ChatRole::User => "user",
+                        ChatRole::Model => "model",
+                    }
+                    .to_string(),
+                ),
+                parts: (!message.text.is_empty())
+                    .then(|| Part {
+                        text: Some(message.text.clone()),
+                        ..Default::default()
+                    })
+                    .into_iter()
+                    .chain(message.function_calls.iter().map(|call| Part {
+                        function_call: Some(FunctionCall {
+                            name: call.name.clone(),
+                            args: call.args.clone(),
+                        }),
+                        ..Default::default()
+                    }))
+                    .chain(message.function_responses.iter().map(|response| Part {
+                        function_response: Some(FunctionResponse {
+                            name: response.name.clone(),
+                            response: response.response.clone(),
+                        }),
+                        ..Default::default()
+                    }))
+                    .collect(),
+            })
+            .collect(),
+        generation_config: GenerationConfig {
+            temperature: request.generation.temperature,
+            top_p: request.generation.top_p,
+            max_output_tokens: request.generation.max_output_tokens,
+        },
+        safety_settings: request
+            .safety_settings
+            .iter()
+            .map(|setting| SafetySetting {
+                category: &setting.category,
+                threshold: &setting.threshold,
+            })
+            .collect(),
+        // function calling can't be used together with the search
+        tools: if request.grounding {
+            vec![search_tool]
+        } else {
+            (!request.tools.is_empty())
+                .then(|| Tool {
+                    function_declarations: request
+                        .tools
+                        .iter()
+                        .map(|tool| FunctionDeclaration {
+                            name: tool.name,
+                            description: tool.description,
+                            parameters: tool.parameters.as_ref(),
+                        })
+                        .collect(),
+                    ..Default::default()
+                })
+                .into_iter()
+                .collect()
+        },
+    }
+}
+
+fn parse_chunk(data: &str) -> anyhow::Result<ChatChunk> {
+    let response: GenerateContentResponse =
+        serde_json::from_str(data).context("Failed to parse response from Google AI")?;
+
+    let (parts, grounding_metadata) = response
+        .candidates
+        .into_iter()
+        .next()
+        .map(|candidate| (candidate.content.parts, candidate.grounding_metadata))
+        .unwrap_or_default();
+    let mut chunk = ChatChunk {
+        usage: response.usage_metadata.map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_token_count,
+            response_tokens: usage.candidates_token_count,
+        }),
+        citations: grounding_metadata
+            .into_iter()
+            .flat_map(|metadata| metadata.grounding_chunks)
+            .filter_map(|chunk| chunk.web)
+            .map(|web| provider::Citation {
+                title: web.title,
+                uri: web.uri,
+            })
+            .collect(),
+        ..Default::default()
+    };
+    for part in parts {
+        if let Some(text) = part.text {
+            chunk.text.push_str(&text);
+        }
+        if let Some(call) = part.function_call {
+            chunk.function_calls.push(provider::FunctionCall {
+                name: call.name,
+                args: call.args,
+            });
+        }
+    }
+
+    Ok(chunk)
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    async fn stream(
+        &self,
+        model: &str,
+        request: &ChatRequest,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<ChatChunk>>> {
+        let response = self
+            .client
+            .post(format!("{API_URL}/{model}:streamGenerateContent"))
+            .query(&[("alt", "sse")])
+            .header(API_KEY_HEADER, &self.api_key)
+            .json(&build_request(model, request))
+            .send()
+            .await
+            .context("Failed to send request to Google AI")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Google AI rejected the request({status}) - {body}");
+        }
+
+        Ok(event_data(response)
+            .map(|data| data.and_then(|data| parse_chunk(&data)))
+            .boxed())
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let name = format!("models/{model}");
+        let body = BatchEmbedContentsRequest {
+            requests: texts
+                .iter()
+                .map(|text| EmbedContentRequest {
+                    model: &name,
+                    content: Content {
+                        role: None,
+                        parts: vec![Part {
+                            text: Some(text.clone()),
+                            ..Default::default()
+                        }],
+                    },
+                })
+                .collect(),
+        };
+        let response = self
+            .client
+            .post(format!("{API_URL}/{model}:batchEmbedContents"))
+            .header(API_KEY_HEADER, &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to send embedding request to Google AI")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Google AI rejected the embedding request({status}) - {body}");
+        }
+        let response: BatchEmbedContentsResponse = response
+            .json()
+            .await
+            .context("Failed to parse embeddings from Google AI")?;
+
+        Ok(response
+            .embeddings
+            .into_iter()
+            .map(|embedding| embedding.values)
+            .collect())
+    }
+}
@@ -0,0 +1,578 @@
+use anyhow::Context as _;
+use futures::TryStreamExt;
+use log::{error, info};
+use serde::Deserialize;
+use serenity::{
+    model::{
+        application::interaction::{
+            application_command::ApplicationCommandInteraction, InteractionResponseType,
+        },
+        channel::Message,
+        guild::Member,
+        id::{ChannelId, MessageId, UserId},
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{
+    cost,
+    provider::{ChatMessage, ChatRequest, LlmProvider, TokenUsage},
+    quota::Quota,
+    reply::split_content,
+    usage, DiscordHandler,
+};
+use crate::{
+    discord::{
+        application_command::{
+            ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
+        },
+        CommandDataOptionHelper, CommandHelper,
+    },
+    regex,
+    user::Preference,
+};
+
+pub(super) const COMMAND_NAME: &str = "ask";
+// messages of users who opted out are not remembered
+pub(crate) const OPT_OUT: Preference<bool> = Preference::new("llm.memory_opt_out");
+// shorter messages like reactions are not worth remembering
+const MIN_CHARS: usize = 10;
+// embedding models take limited input
+const MAX_CHARS: usize = 2000;
+// pending messages are embedded in batches by this interval
+const EMBED_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+const EMBED_BATCH: i64 = 100;
+// messages given to the model for a question
+const TOP_K: usize = 10;
+
+const ASK_PROMPT: &str = "당신은 디스코드 서버의 지난 대화를 찾아 질문에 답합니다. \
+    주어진 메시지만 근거로 한국어로 답하고, 근거로 삼은 메시지를 [번호]로 표시해주세요. \
+    메시지에서 답을 찾을 수 없다면 모른다고 답해주세요.";
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Config {
+    // messages in them are remembered
+    channel_ids: Vec<u64>,
+    #[serde(default = "default_embedding_model")]
+    embedding_model: String,
+}
+
+fn default_embedding_model() -> String {
+    "text-embedding-004".to_string()
+}
+
+pub(super) fn command() -> ApplicationCommand<'static> {
+    ApplicationCommand {
+        name: COMMAND_NAME,
+        description: "지난 대화에서 찾아 답합니다.",
+        options: vec![ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::String,
+            name: "question",
+            description: "질문",
+            required: Some(true),
+            ..Default::default()
+        }],
+    }
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+    let norm = |v: &[f32]| v.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+// returns whether any message is left to embed
+async fn embed_pending(
+    db_pool: &SqlitePool,
+    provider: &dyn LlmProvider,
+    model: &str,
+) -> anyhow::Result<bool> {
+    let pending = sqlx::query!(
+        r#"SELECT `message_id` AS "message_id!: i64", `content`
+        FROM `llm_memory`
+        WHERE `embedding` IS NULL
+        ORDER BY `message_id`
+        LIMIT ?"#,
+        EMBED_BATCH
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get messages to embed")?;
+    if pending.is_empty() {
+        return Ok(false);
+    }
+
+    let texts = pending
+        .iter()
+        .map(|row| row.content.clone())
+        .collect::<Vec<_>>();
+    let embeddings = provider.embed(model, &texts).await?;
+    anyhow::ensure!(
+        embeddings.len() == pending.len(),
+        "{} embeddings are given for {} messages",
+        embeddings.len(),
+        pending.len()
+    );
+
+    let mut tx = db_pool.begin().await?;
+    for (row, embedding) in pending.iter().zip(embeddings) {
+        let embedding = encode(&embedding);
+        sqlx::query!(
+            "UPDATE `llm_memory` SET `embedding` = ? WHERE `message_id` = ?",
+            embedding,
+            row.message_id
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to save embedding")?;
+    }
+    tx.commit().await?;
+    info!("{} messages are embedded", pending.len());
+
+    Ok(pending.len() as i64 == EMBED_BATCH)
+}
+
+pub(crate) async fn forget_user_memory(db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<u64> {
+    Ok(
+        sqlx::query!("DELETE FROM `llm_memory` WHERE `user_id` = ?", user_id)
+            .execute(db_pool)
+            .await
+            .context("Failed to delete remembered messages")?
+            .rows_affected(),
+    )
+}
+
+// remembered channels the member can view. nothing is viewable without the member.
+fn viewable_channels(context: &Context, config: &Config, member: Option<&Member>) -> Vec<i64> {
+    let Some(member) = member else {
+        return Vec::new();
+    };
+    let Some(guild) = context.cache.guild(member.guild_id) else {
+        return Vec::new();
+    };
+    config
+        .channel_ids
+        .iter()
+        .filter(|channel_id| {
+            guild
+                .channels
+                .get(&ChannelId(**channel_id))
+                .and_then(|channel| channel.clone().guild())
+                .and_then(|channel| guild.user_permissions_in(&channel, member).ok())
+                .map_or(false, |permissions| permissions.view_channel())
+        })
+        .map(|channel_id| *channel_id as i64)
+        .collect()
+}
+
+struct Recalled {
+    message_id: i64,
+    channel_id: i64,
+    user_id: i64,
+    content: String,
+    created_at: i64,
+    similarity: f32,
+}
+
+impl DiscordHandler {
+    // messages are stored as they come and embedded later in batches
+    pub(super) async fn remember(&self, message: &Message) {
+        let Some(config) = &self.config.memory else {
+            return;
+        };
+        if message.author.bot
+            || !config.channel_ids.contains(&message.channel_id.0)
+            || message.content.chars().count() < MIN_CHARS
+        {
+            return;
+        }
+        let user_id = message.author.id.0 as i64;
+        match OPT_OUT.get(&self.db_pool, user_id).await {
+            Ok(Some(true)) => return,
+            Ok(_) => {}
+            Err(e) => {
+                error!("{e:?}");
+                return;
+            }
+        }
+
+        let message_id = message.id.0 as i64;
+        let channel_id = message.channel_id.0 as i64;
+        let content = message.content.chars().take(MAX_CHARS).collect::<String>();
+        let created_at = message.timestamp.unix_timestamp();
+        if let Err(e) = sqlx::query!(
+            "INSERT INTO `llm_memory` (`message_id`, `channel_id`, `user_id`, `content`, `created_at`)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (`message_id`) DO NOTHING",
+            message_id,
+            channel_id,
+            user_id,
+            content,
+            created_at
+        )
+        .execute(&self.db_pool)
+        .await
+        {
+            error!("Failed to remember message({message_id}) - {e:?}");
+        }
+    }
+
+    // edited messages are embedded again, and ones too short now are forgotten
+    pub(super) async fn refresh_memory(&self, message: &Message) {
+        if self.config.memory.is_none() {
+            return;
+        }
+
+        let message_id = message.id.0 as i64;
+        let result = if message.content.chars().count() < MIN_CHARS {
+            sqlx::query!(
+                "DELETE FROM `llm_memory` WHERE `message_id` = ?",
+                message_id
+            )
+            .execute(&self.db_pool)
+            .await
+        } else {
+            let content = message.content.chars().take(MAX_CHARS).collect::<String>();
+            sqlx::query!(
+                "UPDATE `llm_memory` SET `content` = ?, `embedding` = NULL
+                WHERE `message_id` = ? AND `content` <> ?",
+                content,
+                message_id,
+                content
+            )
+            .execute(&self.db_pool)
+            .await
+        };
+        if let Err(e) = result {
+            error!("Failed to refresh remembered message({message_id}) - {e:?}");
+        }
+    }
+
+    pub(super) async fn forget_messages(&self, message_ids: &[MessageId]) {
+        if self.config.memory.is_none() {
+            return;
+        }
+
+        let result = async {
+            let mut tx = self.db_pool.begin().await?;
+            for message_id in message_ids {
+                let message_id = message_id.0 as i64;
+                sqlx::query!(
+                    "DELETE FROM `llm_memory` WHERE `message_id` = ?",
+                    message_id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await
+        }
+        .await;
+        if let Err(e) = result {
+            error!("Failed to forget deleted messages - {e:?}");
+        }
+    }
+
+    pub(super) fn start_embedding(&self) {
+        let Some(config) = &self.config.memory else {
+            return;
+        };
+        if self
+            .embedding_started
+            .swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            return;
+        }
+
+        let db_pool = self.db_pool.clone();
+        let provider = self.provider.clone();
+        let model = config.embedding_model.clone();
+        tokio::spawn(async move {
+            loop {
+                match embed_pending(&db_pool, provider.as_ref(), &model).await {
+                    // the backlog is drained without waiting
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to embed messages - {e:?}"),
+                }
+
+                tokio::time::sleep(EMBED_TICK).await;
+            }
+        });
+    }
+
+    // the most similar messages to the question in the channels, from the most similar one
+    async fn recall(
+        &self,
+        config: &Config,
+        question: &str,
+        channel_ids: &[i64],
+    ) -> anyhow::Result<Vec<Recalled>> {
+        let embedding = self
+            .provider
+            .embed(&config.embedding_model, &[question.to_string()])
+            .await?
+            .pop()
+            .context("No embedding is given for the question")?;
+
+        let mut recalled = Vec::<Recalled>::new();
+        let mut rows = sqlx::query!(
+            r#"SELECT
+                `message_id` AS "message_id!: i64",
+                `channel_id` AS "channel_id!: i64",
+                `user_id` AS "user_id!: i64",
+                `content`,
+                `created_at` AS "created_at!: i64",
+                `embedding` AS "embedding!: Vec<u8>"
+            FROM `llm_memory`
+            WHERE `embedding` IS NOT NULL"#
+        )
+        .fetch(&self.db_pool);
+        // only the best ones are kept not to load every vector at once
+        while let Some(row) = rows
+            .try_next()
+            .await
+            .context("Failed to read remembered messages")?
+        {
+            if !channel_ids.contains(&row.channel_id) {
+                continue;
+            }
+            let similarity = cosine_similarity(&embedding, &decode(&row.embedding));
+            if recalled.len() == TOP_K
+                && recalled
+                    .last()
+                    .map_or(false, |last| last.similarity >= similarity)
+            {
+                continue;
+            }
+            recalled.push(Recalled {
+                message_id: row.message_id,
+                channel_id: row.channel_id,
+                user_id: row.user_id,
+                content: row.content,
+                created_at: row.created_at,
+                similarity,
+            });
+            recalled.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+            recalled.truncate(TOP_K);
+        }
+
+        Ok(recalled)
+    }
+
+    async fn answer_from_memory(
+        &self,
+        context: &Context,
+        config: &Config,
+        member: Option<&Member>,
+        user_id: i64,
+        question: &str,
+        usage: &mut TokenUsage,
+    ) -> anyhow::Result<String> {
+        let channel_ids = viewable_channels(context, config, member);
+        let recalled = self.recall(config, question, &channel_ids).await?;
+        if recalled.is_empty() {
+            return Ok("기억하고 있는 메시지가 없습니다.".to_string());
+        }
+
+        let time_zone = crate::user::user_time_zone(&self.db_pool, user_id).await;
+        let lines = recalled
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                let author = context
+                    .cache
+                    .user(UserId(message.user_id as u64))
+                    .map_or_else(|| "알 수 없음".to_string(), |user| user.name);
+                let at = chrono::DateTime::from_timestamp(message.created_at, 0)
+                    .unwrap_or_default()
+                    .with_timezone(&time_zone)
+                    .format("%Y-%m-%d %H:%M");
+                format!("[{}] {at} {author}: {}", i + 1, message.content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = ChatRequest {
+            system_instruction: Some(ASK_PROMPT.to_string()),
+            messages: vec![ChatMessage {
+                text: format!("메시지:\n{lines}\n\n질문: {question}"),
+                ..Default::default()
+            }],
+            generation: self.generation.read().await.or(&self.config.generation),
+            safety_settings: self.safety_settings().await,
+            ..Default::default()
+        };
+        let (answer, request_usage) = self.generate(&request).await?;
+        if let Some(request_usage) = request_usage {
+            *usage += request_usage;
+        }
+
+        // the cited numbers become links to the messages
+        let guild_id = self.guild_id.get().map_or(0, |guild_id| guild_id.0);
+        Ok(regex!(r"\[(\d+)\]")
+            .replace_all(&answer, |captures: &regex::Captures| {
+                let cited = captures[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| recalled.get(n.checked_sub(1)?));
+                match cited {
+                    Some(message) => format!(
+                        "[[{}]](<https://discord.com/channels/{guild_id}/{}/{}>)",
+                        &captures[1], message.channel_id, message.message_id
+                    ),
+                    None => captures[0].to_string(),
+                }
+            })
+            .into_owned())
+    }
+
+    pub(super) async fn handle_ask_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        let Some(config) = &self.config.memory else {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder
+                                .content("기억할 채널이 설정되지 않았습니다.")
+                                .ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        };
+        if !self
+            .may_generate(
+                context,
+                interaction.user.id,
+                interaction
+                    .member
+                    .as_ref()
+                    .map(|member| member.roles.as_slice()),
+            )
+            .await
+        {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder
+                                .content("LLM을 사용할 권한이 없습니다.")
+                                .ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+        if self.over_budget(context, interaction.user.id).await {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder
+                                .content("이번 달 LLM 예산을 모두 사용했습니다.")
+                                .ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+        let user_id = interaction.user.id.0 as i64;
+        let model = self.model.read().await.clone();
+        let request_id = match self.record_request(user_id, &model, false).await? {
+            Quota::Recorded(request_id) => request_id,
+            Quota::Exceeded(retry_at) => {
+                interaction
+                    .create_interaction_response(context, |builder| {
+                        builder
+                            .kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|builder| {
+                                builder
+                                    .content(format!(
+                                        "사용량 한도에 도달했습니다. <t:{retry_at}:R>에 다시 요청해주세요."
+                                    ))
+                                    .ephemeral(true)
+                            })
+                    })
+                    .await
+                    .context("Failed to send interaction response")?;
+                return Ok(());
+            }
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+            })
+            .await
+            .context("Failed to defer interaction response")?;
+
+        let [question] = interaction.data.options.get_options(&["question"]);
+        let question = unsafe { question.as_str_unchecked() };
+        let mut usage = TokenUsage::default();
+        let answer = match self
+            .answer_from_memory(
+                context,
+                config,
+                interaction.member.as_ref(),
+                user_id,
+                question,
+                &mut usage,
+            )
+            .await
+        {
+            Ok(answer) => answer,
+            Err(e) => {
+                error!("Failed to answer from memory - {e:?}");
+                format!("`ERROR: Received error from {model}`")
+            }
+        };
+        if let Err(e) = usage::record_tokens(&self.db_pool, request_id, usage).await {
+            error!("{e:?}");
+        }
+        cost::warn(context, &self.db_pool, self.config.cost_budget.as_ref()).await;
+
+        let mut parts = split_content(format!("> {question}\n{answer}")).into_iter();
+        let first = parts.next().unwrap_or_default();
+        interaction
+            .edit_original_interaction_response(context, |builder| builder.content(first))
+            .await
+            .context("Failed to send answer")?;
+        for part in parts {
+            interaction
+                .create_followup_message(context, |builder| builder.content(part))
+                .await
+                .context("Failed to send rest of answer")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use futures::StreamExt;
+use log::{error, info};
+use serde::Deserialize;
+use serenity::{
+    model::{channel::Message, id::ChannelId},
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{
+    cost,
+    provider::{ChatMessage, ChatRequest, LlmProvider, TokenUsage},
+    usage, DiscordHandler,
+};
+
+// messages are quoted in reports up to this
+const QUOTE_CHARS: usize = 500;
+
+const MODERATION_PROMPT: &str = "당신은 디스코드 서버의 관리를 돕습니다. \
+    아래 서버 규칙을 기준으로 주어진 메시지가 규칙을 어기는 정도를 0(문제 없음)부터 10(명백한 위반)까지 매겨주세요. \
+    다른 설명 없이 {\"score\": 점수, \"reason\": \"한국어 사유\", \"action\": \"한국어로 제안하는 조치\"} 형식의 JSON으로만 답해주세요.";
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Config {
+    // messages in them are reviewed
+    channel_ids: Vec<u64>,
+    // flagged messages are reported here. nothing is deleted by the bot.
+    report_channel_id: u64,
+    rules: String,
+    // messages scored this or higher out of 10 are flagged
+    #[serde(default = "default_threshold")]
+    threshold: i64,
+    // a cheaper model may be used instead of the one answering questions
+    #[serde(default)]
+    model: Option<String>,
+}
+
+fn default_threshold() -> i64 {
+    7
+}
+
+#[derive(Debug, Deserialize)]
+struct Review {
+    score: i64,
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    action: String,
+}
+
+// the model may wrap the JSON in a code block
+fn parse_review(text: &str) -> anyhow::Result<Review> {
+    let start = text.find('{').context("No JSON in the review")?;
+    let end = text.rfind('}').context("No JSON in the review")?;
+    serde_json::from_str(&text[start..=end]).context("Failed to parse the review")
+}
+
+async fn review(
+    provider: &dyn LlmProvider,
+    model: &str,
+    config: &Config,
+    message: &Message,
+) -> anyhow::Result<(Review, Option<TokenUsage>)> {
+    let request = ChatRequest {
+        system_instruction: Some(format!(
+            "{MODERATION_PROMPT}\n\n서버 규칙:\n{}",
+            config.rules
+        )),
+        messages: vec![ChatMessage {
+            text: format!("{}: {}", message.author.name, message.content),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let mut response = provider.stream(model, &request).await?;
+    let mut text = String::new();
+    let mut usage = None;
+    while let Some(chunk) = response.next().await {
+        let chunk = chunk?;
+        text.push_str(&chunk.text);
+        if chunk.usage.is_some() {
+            usage = chunk.usage;
+        }
+    }
+
+    Ok((parse_review(&text)?, usage))
+}
+
+async fn report(
+    context: &Context,
+    config: &Config,
+    message: &Message,
+    review: &Review,
+) -> anyhow::Result<()> {
+    let mut quote = message
+        .content
+        .chars()
+        .take(QUOTE_CHARS)
+        .collect::<String>();
+    if message.content.chars().nth(QUOTE_CHARS).is_some() {
+        quote.push('…');
+    }
+
+    ChannelId(config.report_channel_id)
+        .send_message(context, |b| {
+            b.embed(|e| {
+                e.title("검토가 필요한 메시지")
+                    .description(quote)
+                    .field("작성자", format!("<@{}>", message.author.id), true)
+                    .field("채널", format!("<#{}>", message.channel_id), true)
+                    .field("점수", format!("{}/10", review.score), true)
+                    .field("사유", &review.reason, false)
+                    .field("제안하는 조치", &review.action, false)
+                    .field("메시지", message.link(), false)
+            })
+        })
+        .await
+        .context("Failed to report flagged message")?;
+
+    Ok(())
+}
+
+// reviews are recorded as requests of the bot to be counted in the budget
+async fn review_and_report(
+    context: Context,
+    db_pool: SqlitePool,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+    config: Config,
+    cost_budget: Option<cost::Config>,
+    message: Message,
+) {
+    let bot_id = context.cache.current_user_id().0 as i64;
+    let now = chrono::Utc::now().timestamp();
+    let request_id = sqlx::query!(
+        "INSERT INTO `llm_requests` (`user_id`, `requested_at`, `model`) VALUES (?, ?, ?)",
+        bot_id,
+        now,
+        model
+    )
+    .execute(&db_pool)
+    .await
+    .map(|result| result.last_insert_rowid());
+
+    let (review, token_usage) = match review(provider.as_ref(), &model, &config, &message).await {
+        Ok(review) => review,
+        Err(e) => {
+            error!("Failed to review message({}) - {e:?}", message.id);
+            return;
+        }
+    };
+    match (request_id, token_usage) {
+        (Ok(request_id), Some(token_usage)) => {
+            if let Err(e) = usage::record_tokens(&db_pool, request_id, token_usage).await {
+                error!("{e:?}");
+            }
+        }
+        (Ok(_), None) => {}
+        (Err(e), _) => error!("Failed to record moderation request - {e:?}"),
+    }
+    cost::warn(&context, &db_pool, cost_budget.as_ref()).await;
+
+    if review.score < config.threshold {
+        return;
+    }
+    info!(
+        "Message({}) is flagged with score {}",
+        message.id, review.score
+    );
+    if let Err(e) = report(&context, &config, &message, &review).await {
+        error!("{e:?}");
+    }
+}
+
+impl DiscordHandler {
+    // reviewed in the background not to delay answers
+    pub(super) async fn moderate(&self, context: &Context, message: &Message) {
+        let Some(config) = &self.config.moderation else {
+            return;
+        };
+        if message.author.bot
+            || message.content.is_empty()
+            || !config.channel_ids.contains(&message.channel_id.0)
+        {
+            return;
+        }
+        if self
+            .over_budget(context, context.cache.current_user_id())
+            .await
+        {
+            return;
+        }
+
+        let model = match &config.model {
+            Some(model) => model.clone(),
+            None => self.model.read().await.clone(),
+        };
+        tokio::spawn(review_and_report(
+            context.clone(),
+            self.db_pool.clone(),
+            self.provider.clone(),
+            model,
+            config.clone(),
+            self.config.cost_budget.clone(),
+            message.clone(),
+        ));
+    }
+}
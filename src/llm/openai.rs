@@ -0,0 +1,214 @@
+use anyhow::Context as _;
+use axum::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::provider::{event_data, ChatChunk, ChatRequest, ChatRole, LlmProvider, TokenUsage};
+
+// the stream is closed by this instead of a json payload
+const DONE: &str = "[DONE]";
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Config {
+    // like `https://api.openai.com/v1`. `/chat/completions` is appended.
+    base_url: String,
+    // self-hosted servers may not require it
+    #[serde(default)]
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    stream: bool,
+    // the last chunk has usage of the request with this
+    stream_options: StreamOptions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+struct Delta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    #[serde(default)]
+    prompt_tokens: i64,
+    #[serde(default)]
+    completion_tokens: i64,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    #[serde(default)]
+    data: Vec<Embedding>,
+}
+
+pub(super) struct OpenAiProvider {
+    client: reqwest::Client,
+    config: Config,
+}
+
+impl OpenAiProvider {
+    pub(super) fn new(config: &Config) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config: config.clone(),
+        }
+    }
+
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        let builder = self.client.post(format!(
+            "{}/{path}",
+            self.config.base_url.trim_end_matches('/')
+        ));
+        match &self.config.api_key {
+            Some(api_key) => builder.bearer_auth(api_key),
+            None => builder,
+        }
+    }
+}
+
+fn parse_chunk(data: &str) -> anyhow::Result<ChatChunk> {
+    let chunk: ChatCompletionChunk =
+        serde_json::from_str(data).context("Failed to parse chat completion chunk")?;
+
+    Ok(ChatChunk {
+        usage: chunk.usage.map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            response_tokens: usage.completion_tokens,
+        }),
+        text: chunk
+            .choices
+            .into_iter()
+            .filter_map(|choice| choice.delta.content)
+            .collect(),
+        ..Default::default()
+    })
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    async fn stream(
+        &self,
+        model: &str,
+        request: &ChatRequest,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<ChatChunk>>> {
+        // tools are not sent as function calling is only wired up for Gemini
+        let body = ChatCompletionRequest {
+            model,
+            messages: request
+                .system_instruction
+                .iter()
+                .map(|instruction| Message {
+                    role: "system",
+                    content: instruction,
+                })
+                .chain(request.messages.iter().map(|message| Message {
+                    role: match message.role {
+                        ChatRole::User => "user",
+                        ChatRole::Model => "assistant",
+                    },
+                    content: &message.text,
+                }))
+                .collect(),
+            stream: true,
+            stream_options: StreamOptions {
+                include_usage: true,
+            },
+            temperature: request.generation.temperature,
+            top_p: request.generation.top_p,
+            max_tokens: request.generation.max_output_tokens,
+        };
+        let response = self
+            .post("chat/completions")
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to send chat completion request")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Chat completion request is rejected({status}) - {body}");
+        }
+
+        Ok(event_data(response)
+            .take_while(|data| futures::future::ready(!matches!(data, Ok(data) if data == DONE)))
+            .map(|data| data.and_then(|data| parse_chunk(&data)))
+            .boxed())
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let response = self
+            .post("embeddings")
+            .json(&EmbeddingRequest {
+                model,
+                input: texts,
+            })
+            .send()
+            .await
+            .context("Failed to send embedding request")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Embedding request is rejected({status}) - {body}");
+        }
+        let mut response: EmbeddingResponse = response
+            .json()
+            .await
+            .context("Failed to parse embeddings")?;
+        // the order of the inputs is given by `index`
+        response.data.sort_by_key(|embedding| embedding.index);
+
+        Ok(response
+            .data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect())
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::Context as _;
+use serenity::{
+    model::application::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+use crate::discord::{
+    application_command::{ApplicationCommandOption, ApplicationCommandOptionType},
+    CommandDataOptionHelper, CommandHelper,
+};
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommandGroup,
+        name: "persona",
+        description: "저장된 프롬프트",
+        options: vec![
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "save",
+                description: "프롬프트를 이름을 붙여 저장합니다. 같은 이름은 덮어씁니다.",
+                options: vec![
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "name",
+                        description: "이름",
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "prompt",
+                        description: "프롬프트",
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "use",
+                description: "저장된 프롬프트로 바꿉니다.",
+                options: vec![
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "name",
+                        description: "이름",
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Channel,
+                        name: "channel",
+                        description: "입력 시 이 채널의 프롬프트만 바꿉니다.",
+                        required: Some(false),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "list",
+                description: "저장된 프롬프트 목록",
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+impl DiscordHandler {
+    async fn persona_names(&self) -> anyhow::Result<Vec<String>> {
+        sqlx::query_scalar!("SELECT `name` FROM `llm_personas` ORDER BY `name`")
+            .fetch_all(&self.db_pool)
+            .await
+            .context("Failed to get personas")
+    }
+
+    pub(super) async fn handle_persona_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        let [name, prompt, channel] = sub_option.get_options(&["name", "prompt", "channel"]);
+        let content = match sub_option.name.as_str() {
+            "save" => {
+                let name = unsafe { name.unwrap_unchecked().as_str_unchecked() };
+                let prompt = unsafe { prompt.unwrap_unchecked().as_str_unchecked() };
+                sqlx::query!(
+                    "INSERT INTO `llm_personas` (`name`, `prompt`) VALUES (?, ?)
+                    ON CONFLICT (`name`) DO UPDATE SET `prompt` = `excluded`.`prompt`",
+                    name,
+                    prompt
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to save persona")?;
+
+                format!("{name}(으)로 저장했습니다.")
+            }
+            "use" => {
+                let name = unsafe { name.unwrap_unchecked().as_str_unchecked() };
+                let channel_id = channel
+                    .as_str()
+                    .map(|channel_id| channel_id.parse::<u64>())
+                    .transpose()
+                    .context("Invalid channel")?;
+                let prompt = sqlx::query_scalar!(
+                    "SELECT `prompt` FROM `llm_personas` WHERE `name` = ?",
+                    name
+                )
+                .fetch_optional(&self.db_pool)
+                .await
+                .context("Failed to get persona")?;
+
+                match prompt {
+                    Some(prompt) => {
+                        self.set_prompt(channel_id, Some(&prompt), interaction.user.id)
+                            .await?;
+                        match channel_id {
+                            Some(channel_id) => {
+                                format!("<#{channel_id}>에서 {name}을(를) 사용합니다.")
+                            }
+                            None => format!("{name}을(를) 사용합니다."),
+                        }
+                    }
+                    None => format!("{name}(이)라는 프롬프트는 없습니다."),
+                }
+            }
+            "list" => {
+                let names = self.persona_names().await?;
+                if names.is_empty() {
+                    "저장된 프롬프트가 없습니다.".to_string()
+                } else {
+                    names.join("\n")
+                }
+            }
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+}
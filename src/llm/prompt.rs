@@ -0,0 +1,459 @@
+use anyhow::Context as _;
+use serenity::{
+    model::{
+        application::{
+            component::{ActionRowComponent, InputTextStyle},
+            interaction::{
+                application_command::{ApplicationCommandInteraction, CommandDataOption},
+                modal::ModalSubmitInteraction,
+                InteractionResponseType,
+            },
+        },
+        id::UserId,
+    },
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+use crate::discord::{
+    application_command::{ApplicationCommandOption, ApplicationCommandOptionType},
+    CommandDataOptionHelper, CommandHelper,
+};
+
+// versions shown by `/llm prompt history`
+const HISTORY_LIMIT: i64 = 10;
+// prompts in the history are cut to this many characters
+const HISTORY_PREVIEW_CHARS: usize = 100;
+// followed by the id of the channel, empty for the global prompt
+pub(super) const PROMPT_MODAL: &str = "llm_prompt:";
+const PROMPT_INPUT: &str = "prompt";
+// the longest value a text input of a modal can have
+const PROMPT_INPUT_LIMIT: u64 = 4000;
+
+fn channel_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::Channel,
+        name: "channel",
+        description: "입력 시 이 채널에서만 사용할 프롬프트를 다룹니다.",
+        required: Some(false),
+        ..Default::default()
+    }
+}
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommandGroup,
+        name: "prompt",
+        description: "프롬프트 설정",
+        options: vec![
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "set",
+                description: "프롬프트 설정",
+                options: vec![
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "new_prompt",
+                        description: "입력 시 새로 설정하며, 없을 경우 입력 창을 엽니다.",
+                        required: Some(false),
+                        ..Default::default()
+                    },
+                    channel_option(),
+                    ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Boolean,
+                        name: "reset",
+                        description: "프롬프트를 지웁니다. 채널 프롬프트를 지우면 전체 프롬프트를 사용합니다.",
+                        required: Some(false),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "history",
+                description: "프롬프트 변경 기록",
+                options: vec![channel_option()],
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "rollback",
+                description: "기록된 버전의 프롬프트로 되돌립니다.",
+                options: vec![ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::Integer,
+                    name: "version",
+                    description: "history에 표시된 버전",
+                    required: Some(true),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+fn parse_channel(channel: Option<&CommandDataOption>) -> anyhow::Result<Option<u64>> {
+    channel
+        .as_str()
+        .map(|channel_id| channel_id.parse::<u64>())
+        .transpose()
+        .context("Invalid channel")
+}
+
+fn preview(prompt: &str) -> String {
+    if prompt.is_empty() {
+        return "(지움)".to_string();
+    }
+    let mut preview = prompt
+        .chars()
+        .take(HISTORY_PREVIEW_CHARS)
+        .collect::<String>()
+        .replace('\n', " ");
+    if prompt.chars().nth(HISTORY_PREVIEW_CHARS).is_some() {
+        preview.push('…');
+    }
+
+    preview
+}
+
+impl DiscordHandler {
+    // sets the prompt of the channel or the global one. `None` clears it.
+    // every change is kept in the history to roll back.
+    pub(super) async fn set_prompt(
+        &self,
+        channel_id: Option<u64>,
+        prompt: Option<&str>,
+        changed_by: UserId,
+    ) -> anyhow::Result<()> {
+        let raw_channel_id = channel_id.map(|channel_id| channel_id as i64);
+        // an empty prompt is not used
+        let prompt = prompt.unwrap_or_default();
+        let mut tx = self.db_pool.begin().await?;
+        match raw_channel_id {
+            Some(raw_channel_id) if prompt.is_empty() => {
+                sqlx::query!(
+                    "DELETE FROM `llm_channel_prompts` WHERE `channel_id` = ?",
+                    raw_channel_id
+                )
+                .execute(&mut *tx)
+                .await
+                .context("Failed to delete channel prompt from DB")?;
+            }
+            Some(raw_channel_id) => {
+                sqlx::query!(
+                    "INSERT INTO `llm_channel_prompts` (`channel_id`, `prompt`) VALUES (?, ?)
+                    ON CONFLICT (`channel_id`) DO UPDATE
+                    SET `prompt` = `excluded`.`prompt`",
+                    raw_channel_id,
+                    prompt
+                )
+                .execute(&mut *tx)
+                .await
+                .context("Failed to write channel prompt to DB")?;
+            }
+            None => {
+                sqlx::query!(
+                    "INSERT INTO `llm_config` (`prompt`, `id`) VALUES (?, 0)
+                    ON CONFLICT (`id`) DO UPDATE
+                    SET `prompt` = `excluded`.`prompt`
+                    WHERE `id` = `excluded`.`id`",
+                    prompt
+                )
+                .execute(&mut *tx)
+                .await
+                .context("Failed to write new prompt to DB")?;
+            }
+        }
+        let changed_by = changed_by.0 as i64;
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query!(
+            "INSERT INTO `llm_prompt_history` (`channel_id`, `prompt`, `changed_by`, `changed_at`)
+            VALUES (?, ?, ?, ?)",
+            raw_channel_id,
+            prompt,
+            changed_by,
+            now
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to record prompt history")?;
+        tx.commit().await?;
+
+        let cached = (!prompt.is_empty()).then(|| format!("{prompt}\n"));
+        match channel_id {
+            Some(channel_id) => {
+                let mut channel_prompts = self.channel_prompts.write().await;
+                match cached {
+                    Some(cached) => channel_prompts.insert(channel_id, cached),
+                    None => channel_prompts.remove(&channel_id),
+                };
+            }
+            None => *self.cached_prompt.write().await = cached,
+        }
+
+        Ok(())
+    }
+
+    async fn open_prompt_modal(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        channel_id: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let current = match channel_id {
+            Some(channel_id) => self.channel_prompts.read().await.get(&channel_id).cloned(),
+            None => self.cached_prompt.read().await.clone(),
+        };
+        // the cached one has a trailing newline
+        let current = current
+            .as_deref()
+            .map(|prompt| prompt.trim_end_matches('\n'))
+            .unwrap_or_default()
+            .to_string();
+        let custom_id = format!(
+            "{PROMPT_MODAL}{}",
+            channel_id
+                .map(|channel_id| channel_id.to_string())
+                .unwrap_or_default()
+        );
+        let title = if channel_id.is_some() {
+            "채널 프롬프트 설정"
+        } else {
+            "프롬프트 설정"
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::Modal)
+                    .interaction_response_data(|builder| {
+                        builder
+                            .custom_id(custom_id)
+                            .title(title)
+                            .components(|builder| {
+                                builder.create_action_row(|builder| {
+                                    builder.create_input_text(|builder| {
+                                        builder
+                                            .label("프롬프트")
+                                            .custom_id(PROMPT_INPUT)
+                                            .placeholder("비워두면 프롬프트를 지웁니다.")
+                                            .required(false)
+                                            .max_length(PROMPT_INPUT_LIMIT)
+                                            .style(InputTextStyle::Paragraph);
+                                        // a longer one set by the command option can't be filled in
+                                        if !current.is_empty()
+                                            && current.chars().count()
+                                                <= PROMPT_INPUT_LIMIT as usize
+                                        {
+                                            builder.value(&current);
+                                        }
+                                        builder
+                                    })
+                                })
+                            })
+                    })
+            })
+            .await
+            .context("Failed to open prompt modal")?;
+
+        Ok(())
+    }
+
+    // `None` when a modal is opened instead of a reply
+    async fn handle_prompt_set_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<Option<String>> {
+        let [new_prompt, channel, reset] = option.get_options(&["new_prompt", "channel", "reset"]);
+        let channel_id = parse_channel(channel)?;
+        let reset = reset.as_bool().unwrap_or(false);
+        let new_prompt = new_prompt.as_str();
+        if !reset && new_prompt.is_none() {
+            self.open_prompt_modal(context, interaction, channel_id)
+                .await?;
+            return Ok(None);
+        }
+
+        self.set_prompt(
+            channel_id,
+            new_prompt.filter(|_| !reset),
+            interaction.user.id,
+        )
+        .await?;
+
+        Ok(Some(match channel_id {
+            Some(channel_id) if reset => {
+                format!("<#{channel_id}>에서 전체 프롬프트를 사용합니다.")
+            }
+            _ => "설정 되었습니다.".to_string(),
+        }))
+    }
+
+    // the modal is only opened for the setting roles
+    pub(super) async fn handle_prompt_modal_submit(
+        &self,
+        context: &Context,
+        modal: &ModalSubmitInteraction,
+        channel_id: &str,
+    ) -> anyhow::Result<()> {
+        let channel_id = (!channel_id.is_empty())
+            .then(|| channel_id.parse::<u64>())
+            .transpose()
+            .context("Invalid channel")?;
+        let prompt = modal
+            .data
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) if input.custom_id == PROMPT_INPUT => {
+                    Some(input.value.trim())
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        self.set_prompt(
+            channel_id,
+            (!prompt.is_empty()).then_some(prompt),
+            modal.user.id,
+        )
+        .await?;
+
+        let content = match channel_id {
+            Some(channel_id) if prompt.is_empty() => {
+                format!("<#{channel_id}>에서 전체 프롬프트를 사용합니다.")
+            }
+            None if prompt.is_empty() => "프롬프트를 지웠습니다.".to_string(),
+            _ => "설정 되었습니다.".to_string(),
+        };
+        modal
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+
+    async fn handle_prompt_history_command(
+        &self,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [channel] = option.get_options(&["channel"]);
+        let raw_channel_id = parse_channel(channel)?.map(|channel_id| channel_id as i64);
+        let history = sqlx::query!(
+            r#"SELECT
+                `version` AS "version!: i64",
+                `prompt`,
+                `changed_by` AS "changed_by?: i64",
+                `changed_at` AS "changed_at!: i64"
+            FROM `llm_prompt_history`
+            WHERE `channel_id` IS ?
+            ORDER BY `version` DESC
+            LIMIT ?"#,
+            raw_channel_id,
+            HISTORY_LIMIT
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get prompt history")?;
+
+        if history.is_empty() {
+            return Ok("변경 기록이 없습니다.".to_string());
+        }
+
+        Ok(history
+            .into_iter()
+            .map(|row| {
+                let changed_by = row.changed_by.map_or_else(
+                    || "알 수 없음".to_string(),
+                    |user_id| format!("<@{user_id}>"),
+                );
+                format!(
+                    "v{} <t:{}:f> {changed_by}: {}",
+                    row.version,
+                    row.changed_at,
+                    preview(&row.prompt)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    async fn handle_prompt_rollback_command(
+        &self,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<String> {
+        let [version] = option.get_options(&["version"]);
+        let version = unsafe { version.as_i64_unchecked() };
+        let Some(row) = sqlx::query!(
+            r#"SELECT `channel_id` AS "channel_id?: i64", `prompt`
+            FROM `llm_prompt_history`
+            WHERE `version` = ?"#,
+            version
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get prompt version")?
+        else {
+            return Ok(format!("v{version}은(는) 없는 버전입니다."));
+        };
+
+        let channel_id = row.channel_id.map(|channel_id| channel_id as u64);
+        self.set_prompt(channel_id, Some(&row.prompt), interaction.user.id)
+            .await?;
+
+        Ok(match channel_id {
+            Some(channel_id) => {
+                format!("<#{channel_id}>의 프롬프트를 v{version}(으)로 되돌렸습니다.")
+            }
+            None => format!("프롬프트를 v{version}(으)로 되돌렸습니다."),
+        })
+    }
+
+    pub(super) async fn handle_prompt_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        let content = match sub_option.name.as_str() {
+            "set" => {
+                match self
+                    .handle_prompt_set_command(context, interaction, sub_option)
+                    .await?
+                {
+                    Some(content) => content,
+                    None => return Ok(()),
+                }
+            }
+            "history" => self.handle_prompt_history_command(sub_option).await?,
+            "rollback" => {
+                self.handle_prompt_rollback_command(interaction, sub_option)
+                    .await?
+            }
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+}
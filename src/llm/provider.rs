@@ -0,0 +1,155 @@
+use anyhow::Context as _;
+use axum::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum ChatRole {
+    #[default]
+    User,
+    Model,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct FunctionCall {
+    pub(super) name: String,
+    pub(super) args: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct FunctionResponse {
+    pub(super) name: String,
+    pub(super) response: serde_json::Value,
+}
+
+// calls are made by the model and their responses are given back by the user
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChatMessage {
+    pub(super) role: ChatRole,
+    pub(super) text: String,
+    pub(super) function_calls: Vec<FunctionCall>,
+    pub(super) function_responses: Vec<FunctionResponse>,
+}
+
+// a function the model can call. `parameters` is an OpenAPI schema object.
+#[derive(Debug, Clone)]
+pub(crate) struct LlmTool {
+    pub(crate) name: &'static str,
+    pub(crate) description: &'static str,
+    pub(crate) parameters: Option<serde_json::Value>,
+}
+
+// parameters of generation. the default of the backend is used for unset ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct GenerationConfig {
+    #[serde(default)]
+    pub(super) temperature: Option<f64>,
+    #[serde(default)]
+    pub(super) top_p: Option<f64>,
+    #[serde(default)]
+    pub(super) max_output_tokens: Option<i64>,
+}
+
+impl GenerationConfig {
+    // values of `self` come first
+    pub(super) fn or(&self, other: &Self) -> Self {
+        Self {
+            temperature: self.temperature.or(other.temperature),
+            top_p: self.top_p.or(other.top_p),
+            max_output_tokens: self.max_output_tokens.or(other.max_output_tokens),
+        }
+    }
+}
+
+// block threshold of a harm category. only Gemini uses them.
+#[derive(Debug, Clone)]
+pub(super) struct SafetySetting {
+    pub(super) category: String,
+    pub(super) threshold: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChatRequest {
+    // given apart from the messages as the instruction of the whole conversation
+    pub(super) system_instruction: Option<String>,
+    pub(super) messages: Vec<ChatMessage>,
+    pub(super) generation: GenerationConfig,
+    pub(super) safety_settings: Vec<SafetySetting>,
+    pub(super) tools: Vec<LlmTool>,
+    // let the model search the web. only Gemini supports it.
+    pub(super) grounding: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct TokenUsage {
+    pub(super) prompt_tokens: i64,
+    pub(super) response_tokens: i64,
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.response_tokens += other.response_tokens;
+    }
+}
+
+// a web page the answer is grounded on
+#[derive(Debug, Clone)]
+pub(super) struct Citation {
+    pub(super) title: String,
+    pub(super) uri: String,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ChatChunk {
+    pub(super) text: String,
+    // usage of the whole request so far. only some chunks have it.
+    pub(super) usage: Option<TokenUsage>,
+    pub(super) function_calls: Vec<FunctionCall>,
+    pub(super) citations: Vec<Citation>,
+}
+
+// backend generating answers. models are given by name as each backend has its own.
+#[async_trait]
+pub(super) trait LlmProvider: Send + Sync {
+    async fn stream(
+        &self,
+        model: &str,
+        request: &ChatRequest,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<ChatChunk>>>;
+
+    // vectors of the texts in the same order
+    async fn embed(&self, model: &str, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>>;
+}
+
+// payloads of `data:` lines of a server-sent events response
+pub(super) fn event_data(
+    response: reqwest::Response,
+) -> BoxStream<'static, anyhow::Result<String>> {
+    let bytes = Box::pin(response.bytes_stream());
+    // bytes are buffered until a whole line arrives not to split multibyte characters
+    futures::stream::unfold((bytes, Vec::new()), |(mut bytes, mut buffer)| async move {
+        loop {
+            if let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                let line = String::from_utf8_lossy(&line);
+                if let Some(data) = line.trim_end().strip_prefix("data:") {
+                    return Some((Ok(data.trim_start().to_string()), (bytes, buffer)));
+                }
+                continue;
+            }
+
+            match bytes.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => {
+                    return Some((
+                        Err(e).context("Failed to receive response stream"),
+                        (bytes, buffer),
+                    ))
+                }
+                None => return None,
+            }
+        }
+    })
+    .boxed()
+}
@@ -0,0 +1,202 @@
+use anyhow::Context as _;
+use serenity::{
+    model::application::interaction::{
+        application_command::ApplicationCommandInteraction, InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+
+const HOUR_SECS: i64 = 60 * 60;
+const DAY_SECS: i64 = 24 * HOUR_SECS;
+
+pub(super) enum Quota {
+    // id of the request to record its usage later
+    Recorded(i64),
+    // when the user can request again
+    Exceeded(i64),
+}
+
+pub(super) struct Usage {
+    label: &'static str,
+    used: i64,
+    limit: u32,
+    // when the oldest request in the window leaves it
+    reset_at: Option<i64>,
+}
+
+impl Usage {
+    fn exceeded(&self) -> bool {
+        self.used >= self.limit as i64
+    }
+}
+
+impl DiscordHandler {
+    async fn usage_in(
+        &self,
+        user_id: i64,
+        dm: bool,
+        label: &'static str,
+        window_secs: i64,
+        limit: u32,
+    ) -> anyhow::Result<Usage> {
+        let since = chrono::Utc::now().timestamp() - window_secs;
+        let usage = sqlx::query!(
+            r#"SELECT COUNT(*) AS "used!: i64", MIN(`requested_at`) AS "oldest?: i64"
+            FROM `llm_requests`
+            WHERE `user_id` = ? AND `is_dm` = ? AND `requested_at` > ?"#,
+            user_id,
+            dm,
+            since
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to count LLM requests")?;
+
+        Ok(Usage {
+            label,
+            used: usage.used,
+            limit,
+            reset_at: usage.oldest.map(|oldest| oldest + window_secs),
+        })
+    }
+
+    // usages of configured limits only. DMs have their own limits.
+    pub(super) async fn usages(&self, user_id: i64, dm: bool) -> anyhow::Result<Vec<Usage>> {
+        let (hourly_limit, daily_limit, labels) = match (dm, &self.config.dm) {
+            (false, _) => (
+                self.config.hourly_limit,
+                self.config.daily_limit,
+                ["1시간", "24시간"],
+            ),
+            (true, Some(config)) => (
+                config.hourly_limit,
+                config.daily_limit,
+                ["DM 1시간", "DM 24시간"],
+            ),
+            (true, None) => return Ok(Vec::new()),
+        };
+        let mut usages = Vec::new();
+        if let Some(limit) = hourly_limit {
+            usages.push(
+                self.usage_in(user_id, dm, labels[0], HOUR_SECS, limit)
+                    .await?,
+            );
+        }
+        if let Some(limit) = daily_limit {
+            usages.push(
+                self.usage_in(user_id, dm, labels[1], DAY_SECS, limit)
+                    .await?,
+            );
+        }
+
+        Ok(usages)
+    }
+
+    // when the user can request again if any limit is reached
+    async fn check_quota(&self, user_id: i64, dm: bool) -> anyhow::Result<Option<i64>> {
+        Ok(self
+            .usages(user_id, dm)
+            .await?
+            .into_iter()
+            .filter(Usage::exceeded)
+            .filter_map(|usage| usage.reset_at)
+            .max())
+    }
+
+    // records the request only if it is within the limits. checking and
+    // recording in one statement keeps concurrent requests from passing the
+    // same remaining quota.
+    pub(super) async fn record_request(
+        &self,
+        user_id: i64,
+        model: &str,
+        dm: bool,
+    ) -> anyhow::Result<Quota> {
+        let (hourly_limit, daily_limit) = match (dm, &self.config.dm) {
+            (false, _) => (self.config.hourly_limit, self.config.daily_limit),
+            (true, Some(config)) => (config.hourly_limit, config.daily_limit),
+            (true, None) => (None, None),
+        };
+        let now = chrono::Utc::now().timestamp();
+        let hour_since = now - HOUR_SECS;
+        let day_since = now - DAY_SECS;
+        let result = sqlx::query!(
+            "INSERT INTO `llm_requests` (`user_id`, `requested_at`, `model`, `is_dm`)
+            SELECT ?, ?, ?, ?
+            WHERE (? IS NULL OR (
+                SELECT COUNT(*) FROM `llm_requests`
+                WHERE `user_id` = ? AND `is_dm` = ? AND `requested_at` > ?
+            ) < ?)
+            AND (? IS NULL OR (
+                SELECT COUNT(*) FROM `llm_requests`
+                WHERE `user_id` = ? AND `is_dm` = ? AND `requested_at` > ?
+            ) < ?)",
+            user_id,
+            now,
+            model,
+            dm,
+            hourly_limit,
+            user_id,
+            dm,
+            hour_since,
+            hourly_limit,
+            daily_limit,
+            user_id,
+            dm,
+            day_since,
+            daily_limit
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to record LLM request")?;
+
+        if result.rows_affected() > 0 {
+            return Ok(Quota::Recorded(result.last_insert_rowid()));
+        }
+        // a limit of 0 has no request to wait for
+        let retry_at = self
+            .check_quota(user_id, dm)
+            .await?
+            .unwrap_or(now + DAY_SECS);
+        Ok(Quota::Exceeded(retry_at))
+    }
+
+    pub(super) async fn handle_quota_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        let user_id = interaction.user.id.0 as i64;
+        let mut usages = self.usages(user_id, false).await?;
+        usages.extend(self.usages(user_id, true).await?);
+        let content = if usages.is_empty() {
+            "사용 제한이 없습니다.".to_string()
+        } else {
+            usages
+                .iter()
+                .map(|usage| {
+                    let mut line =
+                        format!("{}: {}/{}회 사용", usage.label, usage.used, usage.limit);
+                    if let (true, Some(reset_at)) = (usage.exceeded(), usage.reset_at) {
+                        line.push_str(&format!(" (<t:{reset_at}:R> 다시 사용 가능)"));
+                    }
+                    line
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::Context as _;
+use log::error;
+use serenity::{
+    builder::CreateComponents,
+    model::prelude::{component::ButtonStyle, Message},
+    prelude::Context,
+};
+use tokio::time::{Duration, Instant};
+
+use super::{provider::Citation, END_INDICATOR, WORKING_INDICATOR};
+
+// buttons on the reply, followed by the id of the question
+pub(super) const STOP_BUTTON: &str = "llm_stop:";
+pub(super) const REGENERATE_BUTTON: &str = "llm_regenerate:";
+
+// discord rejects longer message contents
+const MESSAGE_LIMIT: usize = 2000;
+// streamed chunks are buffered not to be rate limited by editing for each of them
+const EDIT_INTERVAL: Duration = Duration::from_millis(1500);
+
+// answer streamed into replies. continues in a reply to the last one when it gets too long.
+pub(super) struct StreamingReply {
+    question_id: u64,
+    // finished messages with their contents
+    finished: Vec<(Message, String)>,
+    message: Message,
+    content: String,
+    // length of `content` shown in `message` and when it is edited
+    shown_len: usize,
+    edited_at: Instant,
+}
+
+// sources appended to a grounded answer as links without embeds
+pub(super) fn format_citations(citations: &[Citation]) -> Option<String> {
+    let mut uris = Vec::new();
+    let lines = citations
+        .iter()
+        .filter(|citation| {
+            let new = !uris.contains(&&citation.uri);
+            uris.push(&citation.uri);
+            new
+        })
+        .enumerate()
+        .map(|(i, citation)| format!("{}. [{}](<{}>)", i + 1, citation.title, citation.uri))
+        .collect::<Vec<_>>();
+
+    (!lines.is_empty()).then(|| format!("\n\n출처:\n{}", lines.join("\n")))
+}
+
+// where the content should be cut to leave room for indicators
+fn split_at(content: &str) -> Option<usize> {
+    let limit = MESSAGE_LIMIT - WORKING_INDICATOR.len().max(END_INDICATOR.len());
+    let (end, _) = content.char_indices().nth(limit)?;
+    // prefer splitting after a line not to break a sentence
+    Some(
+        content[..end]
+            .rfind('\n')
+            .map_or(end, |newline| newline + 1),
+    )
+}
+
+// a finished content split into contents discord accepts
+pub(super) fn split_content(mut content: String) -> Vec<String> {
+    let mut parts = Vec::new();
+    while let Some(end) = split_at(&content) {
+        let rest = content.split_off(end);
+        parts.push(std::mem::replace(&mut content, rest));
+    }
+    parts.push(content);
+
+    parts
+}
+
+fn button<'a>(
+    components: &'a mut CreateComponents,
+    label: &str,
+    prefix: &str,
+    question_id: u64,
+) -> &'a mut CreateComponents {
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .label(label)
+                .style(ButtonStyle::Secondary)
+                .custom_id(format!("{prefix}{question_id}"))
+        })
+    })
+}
+
+// a part being streamed, replying to the question or the previous part
+async fn create_part(
+    context: &Context,
+    reference: &Message,
+    question_id: u64,
+) -> anyhow::Result<Message> {
+    reference
+        .channel_id
+        .send_message(context, |builder| {
+            builder
+                .content(WORKING_INDICATOR)
+                .reference_message(reference)
+                .components(|components| button(components, "중단", STOP_BUTTON, question_id))
+        })
+        .await
+        .context("Failed to create reply")
+}
+
+impl StreamingReply {
+    pub(super) async fn start(context: &Context, message: &Message) -> anyhow::Result<Self> {
+        let question_id = message.id.0;
+        let reply = create_part(context, message, question_id).await?;
+
+        Ok(Self {
+            question_id,
+            finished: Vec::new(),
+            message: reply,
+            content: String::new(),
+            shown_len: 0,
+            edited_at: Instant::now(),
+        })
+    }
+
+    // replaces the current part with the error
+    pub(super) async fn fail(&mut self, context: &Context, error: &str) -> anyhow::Result<()> {
+        self.message
+            .edit(context, |builder| {
+                builder.content(error).components(|components| components)
+            })
+            .await
+            .context("Failed to report error by reply")
+    }
+
+    pub(super) async fn push(&mut self, context: &Context, text: &str) -> anyhow::Result<()> {
+        self.content.push_str(text);
+        while let Some(end) = split_at(&self.content) {
+            let rest = self.content.split_off(end);
+            let content = std::mem::replace(&mut self.content, rest);
+            self.message
+                .edit(context, |builder| {
+                    builder
+                        .content(&content)
+                        .components(|components| components)
+                })
+                .await
+                .context("Failed to finish a part of reply")?;
+            let next = create_part(context, &self.message, self.question_id).await?;
+            self.finished
+                .push((std::mem::replace(&mut self.message, next), content));
+            self.shown_len = 0;
+            self.edited_at = Instant::now();
+        }
+
+        // the rest is shown by a later chunk or `finish`
+        if self.content.len() == self.shown_len || self.edited_at.elapsed() < EDIT_INTERVAL {
+            return Ok(());
+        }
+        let content = format!("{}{WORKING_INDICATOR}", self.content);
+        self.message
+            .edit(context, |builder| builder.content(content))
+            .await
+            .context("Failed to update reply")?;
+        self.shown_len = self.content.len();
+        self.edited_at = Instant::now();
+
+        Ok(())
+    }
+
+    // returns the messages of the answer with their contents, from the first one
+    pub(super) async fn finish(mut self, context: &Context) -> Vec<(Message, String)> {
+        let content = format!("{}{END_INDICATOR}", self.content);
+        let question_id = self.question_id;
+        if let Err(e) = self
+            .message
+            .edit(context, |builder| {
+                builder.content(content).components(|components| {
+                    button(components, "다시 생성", REGENERATE_BUTTON, question_id)
+                })
+            })
+            .await
+        {
+            error!("Failed to finish reply - {e:?}");
+        }
+        self.finished.push((self.message, self.content));
+
+        self.finished
+    }
+}
@@ -0,0 +1,293 @@
+use anyhow::Context as _;
+use serenity::{
+    model::application::interaction::{
+        application_command::{ApplicationCommandInteraction, CommandDataOption},
+        InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use super::{
+    provider::{GenerationConfig, SafetySetting},
+    DiscordHandler,
+};
+use crate::discord::{
+    application_command::{
+        ApplicationCommandOption, ApplicationCommandOptionChoice, ApplicationCommandOptionType,
+    },
+    CommandDataOptionHelper, CommandHelper,
+};
+
+// a threshold set by the command is applied to all of them
+const HARM_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+const SAFETY_THRESHOLDS: [&str; 4] = [
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+];
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommand,
+        name: "config",
+        description: "생성 설정. 아무것도 입력하지 않으면 현재 값을 보여줍니다.",
+        options: vec![
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Number,
+                name: "temperature",
+                description: "0 ~ 2",
+                required: Some(false),
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Number,
+                name: "top_p",
+                description: "0 ~ 1",
+                required: Some(false),
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Integer,
+                name: "max_output_tokens",
+                description: "답변의 최대 토큰 수",
+                required: Some(false),
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::String,
+                name: "safety",
+                description: "모든 유해 카테고리의 차단 기준 (Gemini 전용)",
+                required: Some(false),
+                choices: SAFETY_THRESHOLDS
+                    .iter()
+                    .map(|threshold| ApplicationCommandOptionChoice {
+                        name: threshold,
+                        value: serde_json::json!(threshold),
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Boolean,
+                name: "reset",
+                description: "설정을 지우고 설정 파일의 값을 사용합니다.",
+                required: Some(false),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+pub(super) fn grounding_command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommand,
+        name: "grounding",
+        description: "Google 검색 사용 설정 (Gemini 전용)",
+        options: vec![ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::String,
+            name: "state",
+            description: "입력 시 새로 설정하며, 없을 경우 현재 값을 보여줍니다.",
+            required: Some(false),
+            choices: ["on", "off"]
+                .iter()
+                .map(|state| ApplicationCommandOptionChoice {
+                    name: state,
+                    value: serde_json::json!(state),
+                })
+                .collect(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn describe<T: std::fmt::Display>(name: &str, value: Option<T>) -> String {
+    match value {
+        Some(value) => format!("{name}: {value}"),
+        None => format!("{name}: 기본값"),
+    }
+}
+
+impl DiscordHandler {
+    // the threshold set by the command overrides the config file
+    pub(super) async fn safety_settings(&self) -> Vec<SafetySetting> {
+        match self.safety_threshold.read().await.as_ref() {
+            Some(threshold) => HARM_CATEGORIES
+                .iter()
+                .map(|category| SafetySetting {
+                    category: category.to_string(),
+                    threshold: threshold.clone(),
+                })
+                .collect(),
+            None => self
+                .config
+                .safety_settings
+                .iter()
+                .map(|(category, threshold)| SafetySetting {
+                    category: category.clone(),
+                    threshold: threshold.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    async fn save_settings(
+        &self,
+        generation: GenerationConfig,
+        safety_threshold: Option<String>,
+    ) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO `llm_config`
+                (`id`, `prompt`, `temperature`, `top_p`, `max_output_tokens`, `safety_threshold`)
+            VALUES (0, '', ?, ?, ?, ?)
+            ON CONFLICT (`id`) DO UPDATE
+            SET `temperature` = `excluded`.`temperature`,
+                `top_p` = `excluded`.`top_p`,
+                `max_output_tokens` = `excluded`.`max_output_tokens`,
+                `safety_threshold` = `excluded`.`safety_threshold`",
+            generation.temperature,
+            generation.top_p,
+            generation.max_output_tokens,
+            safety_threshold
+        )
+        .execute(&self.db_pool)
+        .await
+        .context("Failed to write generation config to DB")?;
+        *self.generation.write().await = generation;
+        *self.safety_threshold.write().await = safety_threshold;
+
+        Ok(())
+    }
+
+    pub(super) async fn handle_config_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [temperature, top_p, max_output_tokens, safety, reset] = option.get_options(&[
+            "temperature",
+            "top_p",
+            "max_output_tokens",
+            "safety",
+            "reset",
+        ]);
+        let changed = GenerationConfig {
+            temperature: temperature.as_f64(),
+            top_p: top_p.as_f64(),
+            max_output_tokens: max_output_tokens.as_i64(),
+        };
+
+        let invalid = if changed
+            .temperature
+            .map_or(false, |value| !(0.0..=2.0).contains(&value))
+        {
+            Some("temperature는 0 ~ 2 사이여야 합니다.")
+        } else if changed
+            .top_p
+            .map_or(false, |value| !(0.0..=1.0).contains(&value))
+        {
+            Some("top_p는 0 ~ 1 사이여야 합니다.")
+        } else if changed.max_output_tokens.map_or(false, |value| value < 1) {
+            Some("max_output_tokens는 1 이상이어야 합니다.")
+        } else {
+            None
+        };
+
+        if invalid.is_none() {
+            if reset.as_bool().unwrap_or(false) {
+                self.save_settings(GenerationConfig::default(), None)
+                    .await?;
+            } else if option.options.iter().any(|option| option.value.is_some()) {
+                let generation = changed.or(&*self.generation.read().await);
+                let safety_threshold = match safety.as_str() {
+                    Some(threshold) => Some(threshold.to_string()),
+                    None => self.safety_threshold.read().await.clone(),
+                };
+                self.save_settings(generation, safety_threshold).await?;
+            }
+        }
+
+        let content =
+            match invalid {
+                Some(invalid) => invalid.to_string(),
+                None => {
+                    let generation = self.generation.read().await.or(&self.config.generation);
+                    let safety_settings = self.safety_settings().await;
+                    let mut lines = vec![
+                        describe("temperature", generation.temperature),
+                        describe("top_p", generation.top_p),
+                        describe("max_output_tokens", generation.max_output_tokens),
+                    ];
+                    if safety_settings.is_empty() {
+                        lines.push("safety: 기본값".to_string());
+                    }
+                    lines.extend(safety_settings.iter().map(|setting| {
+                        format!("safety({}): {}", setting.category, setting.threshold)
+                    }));
+                    lines.join("\n")
+                }
+            };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+
+    // the value set by the command overrides the config file
+    pub(super) async fn grounding(&self) -> bool {
+        self.grounding.read().await.unwrap_or(self.config.grounding)
+    }
+
+    pub(super) async fn handle_grounding_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [state] = option.get_options(&["state"]);
+        if let Some(state) = state.as_str() {
+            let grounding = state == "on";
+            sqlx::query!(
+                "INSERT INTO `llm_config` (`id`, `prompt`, `grounding`) VALUES (0, '', ?)
+                ON CONFLICT (`id`) DO UPDATE
+                SET `grounding` = `excluded`.`grounding`",
+                grounding
+            )
+            .execute(&self.db_pool)
+            .await
+            .context("Failed to write grounding to DB")?;
+            *self.grounding.write().await = Some(grounding);
+        }
+
+        let content = if self.grounding().await {
+            "Google 검색을 사용합니다. 검색 중에는 다른 기능을 호출하지 않습니다."
+        } else {
+            "Google 검색을 사용하지 않습니다."
+        };
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+}
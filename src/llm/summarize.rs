@@ -0,0 +1,300 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serenity::{
+    model::{
+        application::interaction::{
+            application_command::ApplicationCommandInteraction, InteractionResponseType,
+        },
+        channel::{Channel, Message},
+        id::MessageId,
+    },
+    prelude::Context,
+};
+
+use super::{
+    cost,
+    provider::{ChatMessage, ChatRequest, TokenUsage},
+    quota::Quota,
+    reply::split_content,
+    usage, DiscordHandler,
+};
+use crate::discord::{
+    application_command::{
+        ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
+    },
+    CommandDataOptionHelper, CommandHelper,
+};
+
+pub(super) const COMMAND_NAME: &str = "summarize";
+const DEFAULT_MESSAGES: u64 = 100;
+// a whole thread is read up to this
+const MAX_MESSAGES: u64 = 1000;
+// discord gives 100 messages at most for a request
+const PAGE_SIZE: u64 = 100;
+// longer transcripts are summarized by parts, then the summaries are merged
+const CHUNK_CHARS: usize = 20_000;
+
+const SUMMARIZE_PROMPT: &str =
+    "다음 디스코드 대화를 핵심만 추려 한국어 글머리 기호 목록으로 요약해주세요. \
+    누가 무엇을 말했는지 드러나게 해주세요.";
+const SUMMARIZE_PART_PROMPT: &str = "다음은 긴 디스코드 대화의 일부입니다. \
+    핵심만 추려 한국어 글머리 기호 목록으로 요약해주세요.";
+const MERGE_PROMPT: &str = "다음은 하나의 디스코드 대화를 나누어 요약한 것입니다. \
+    시간 순서대로 하나의 한국어 글머리 기호 목록으로 합쳐주세요.";
+
+pub(super) fn command() -> ApplicationCommand<'static> {
+    ApplicationCommand {
+        name: COMMAND_NAME,
+        description: "최근 대화를 요약합니다.",
+        options: vec![ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::Integer,
+            name: "count",
+            description: "요약할 메시지 수. 스레드에서는 입력하지 않으면 스레드 전체를 요약합니다.",
+            required: Some(false),
+            ..Default::default()
+        }],
+    }
+}
+
+// the transcript is split on lines not to cut a message
+fn chunk_transcript(lines: &[String]) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    for line in lines {
+        let chunk = unsafe { chunks.last_mut().unwrap_unchecked() };
+        if !chunk.is_empty() && chunk.len() + line.len() > CHUNK_CHARS {
+            chunks.push(String::new());
+        }
+        let chunk = unsafe { chunks.last_mut().unwrap_unchecked() };
+        chunk.push_str(line);
+        chunk.push('\n');
+    }
+
+    chunks
+}
+
+impl DiscordHandler {
+    // messages from the oldest one
+    async fn fetch_recent_messages(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        count: u64,
+    ) -> anyhow::Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        let mut before: Option<MessageId> = None;
+        while (messages.len() as u64) < count {
+            let limit = PAGE_SIZE.min(count - messages.len() as u64);
+            let page = interaction
+                .channel_id
+                .messages(context, |builder| {
+                    if let Some(before) = before {
+                        builder.before(before);
+                    }
+                    builder.limit(limit)
+                })
+                .await
+                .context("Failed to get message history")?;
+            // pages are given from the newest one
+            before = page.last().map(|message| message.id);
+            let done = (page.len() as u64) < limit;
+            messages.extend(page);
+            if done {
+                break;
+            }
+        }
+        messages.reverse();
+
+        Ok(messages)
+    }
+
+    async fn summarize(
+        &self,
+        prompt: &str,
+        text: String,
+        usage: &mut TokenUsage,
+    ) -> anyhow::Result<String> {
+        let request = ChatRequest {
+            messages: vec![ChatMessage {
+                text: format!("{prompt}\n\n{text}"),
+                ..Default::default()
+            }],
+            generation: self.generation.read().await.or(&self.config.generation),
+            safety_settings: self.safety_settings().await,
+            ..Default::default()
+        };
+        let (summary, request_usage) = self.generate(&request).await?;
+        if let Some(request_usage) = request_usage {
+            *usage += request_usage;
+        }
+
+        Ok(summary)
+    }
+
+    async fn summarize_lines(
+        &self,
+        lines: &[String],
+        usage: &mut TokenUsage,
+    ) -> anyhow::Result<String> {
+        let chunks = chunk_transcript(lines);
+        if chunks.len() == 1 {
+            return self
+                .summarize(SUMMARIZE_PROMPT, chunks.concat(), usage)
+                .await;
+        }
+
+        let mut summaries = Vec::new();
+        for chunk in chunks {
+            summaries.push(self.summarize(SUMMARIZE_PART_PROMPT, chunk, usage).await?);
+        }
+        self.summarize(MERGE_PROMPT, summaries.join("\n\n"), usage)
+            .await
+    }
+
+    pub(super) async fn handle_summarize_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        if !self
+            .may_generate(
+                context,
+                interaction.user.id,
+                interaction
+                    .member
+                    .as_ref()
+                    .map(|member| member.roles.as_slice()),
+            )
+            .await
+        {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder
+                                .content("LLM을 사용할 권한이 없습니다.")
+                                .ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+        if self.over_budget(context, interaction.user.id).await {
+            interaction
+                .create_interaction_response(context, |builder| {
+                    builder
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|builder| {
+                            builder
+                                .content("이번 달 LLM 예산을 모두 사용했습니다.")
+                                .ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+        let user_id = interaction.user.id.0 as i64;
+        let model = self.model.read().await.clone();
+        let request_id = match self.record_request(user_id, &model, false).await? {
+            Quota::Recorded(request_id) => request_id,
+            Quota::Exceeded(retry_at) => {
+                interaction
+                    .create_interaction_response(context, |builder| {
+                        builder
+                            .kind(InteractionResponseType::ChannelMessageWithSource)
+                            .interaction_response_data(|builder| {
+                                builder
+                                    .content(format!(
+                                        "사용량 한도에 도달했습니다. <t:{retry_at}:R>에 다시 요청해주세요."
+                                    ))
+                                    .ephemeral(true)
+                            })
+                    })
+                    .await
+                    .context("Failed to send interaction response")?;
+                return Ok(());
+            }
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+            })
+            .await
+            .context("Failed to defer interaction response")?;
+
+        let [count] = interaction.data.options.get_options(&["count"]);
+        let in_thread = matches!(
+            interaction.channel_id.to_channel(context).await,
+            Ok(Channel::Guild(channel)) if channel.thread_metadata.is_some()
+        );
+        let count = match count.as_i64() {
+            Some(count) => (count.max(1) as u64).min(MAX_MESSAGES),
+            None if in_thread => MAX_MESSAGES,
+            None => DEFAULT_MESSAGES,
+        };
+        info!("Summarize {count} messages in {}", interaction.channel_id);
+
+        let time_zone = crate::user::user_time_zone(&self.db_pool, user_id).await;
+        let lines = self
+            .fetch_recent_messages(context, interaction, count)
+            .await?
+            .into_iter()
+            .filter(|message| !message.content.is_empty())
+            .map(|message| {
+                format!(
+                    "[{}] {}: {}",
+                    message
+                        .timestamp
+                        .with_timezone(&time_zone)
+                        .format("%m-%d %H:%M"),
+                    message.author.name,
+                    message.content
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut usage = TokenUsage::default();
+        let summary = if lines.is_empty() {
+            "요약할 메시지가 없습니다.".to_string()
+        } else {
+            match self.summarize_lines(&lines, &mut usage).await {
+                Ok(summary) => summary,
+                Err(e) => {
+                    error!("Failed to summarize - {e:?}");
+                    format!("`ERROR: Received error from {model}`")
+                }
+            }
+        };
+        if let Err(e) = usage::record_tokens(&self.db_pool, request_id, usage).await {
+            error!("{e:?}");
+        }
+        cost::warn(context, &self.db_pool, self.config.cost_budget.as_ref()).await;
+
+        // summaries quote names of the messages, which should not ping anyone
+        let mut parts = split_content(summary).into_iter();
+        let first = parts.next().unwrap_or_default();
+        interaction
+            .edit_original_interaction_response(context, |builder| {
+                builder
+                    .content(first)
+                    .allowed_mentions(|mentions| mentions.empty_parse())
+            })
+            .await
+            .context("Failed to send summary")?;
+        for part in parts {
+            interaction
+                .create_followup_message(context, |builder| {
+                    builder
+                        .content(part)
+                        .allowed_mentions(|mentions| mentions.empty_parse())
+                })
+                .await
+                .context("Failed to send rest of summary")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use log::{error, info};
+use serenity::{
+    model::{
+        channel::{Channel, Message},
+        id::MessageId,
+    },
+    prelude::Context,
+};
+
+use super::{conversation::Turn, DiscordHandler};
+
+// recent messages of a thread given with its starter message
+const THREAD_HISTORY: u64 = 50;
+
+impl DiscordHandler {
+    // the starter message and recent messages of the thread the question is in, from the oldest one
+    pub(super) async fn load_thread(&self, context: &Context, message: &Message) -> Vec<Turn> {
+        let thread = match message.channel_id.to_channel(context).await {
+            Ok(Channel::Guild(channel)) if channel.thread_metadata.is_some() => channel,
+            Ok(_) => return Vec::new(),
+            Err(e) => {
+                error!("Failed to get channel of message({}) - {e:?}", message.id);
+                return Vec::new();
+            }
+        };
+
+        let mut messages = match thread
+            .id
+            .messages(context, |builder| {
+                builder.before(message.id).limit(THREAD_HISTORY)
+            })
+            .await
+        {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("Failed to get messages of thread({}) - {e:?}", thread.id);
+                return Vec::new();
+            }
+        };
+        // pages are given from the newest one
+        messages.reverse();
+
+        // a thread started from a message has the id of the message, which is in the parent
+        let starter_id = MessageId(thread.id.0);
+        if message.id != starter_id
+            && messages.first().map(|message| message.id) != Some(starter_id)
+        {
+            if let Some(parent_id) = thread.parent_id {
+                match parent_id.message(context, starter_id).await {
+                    Ok(starter) => messages.insert(0, starter),
+                    Err(e) => info!("No starter message of thread({}) - {e:?}", thread.id),
+                }
+            }
+        }
+
+        self.message_turns(context, messages)
+    }
+}
@@ -0,0 +1,55 @@
+use log::error;
+use serenity::{model::id::GuildId, prelude::Context};
+
+use super::provider::{FunctionCall, FunctionResponse, LlmTool};
+use crate::discord::{Applications, SubApplications};
+
+// rounds of function calls for a question. the model has to answer after that.
+pub(super) const MAX_ROUNDS: usize = 5;
+
+pub(super) async fn applications(context: &Context) -> Option<Applications> {
+    context.data.read().await.get::<SubApplications>().cloned()
+}
+
+pub(super) fn declarations(applications: &Applications) -> Vec<LlmTool> {
+    applications
+        .iter()
+        .flat_map(|application| application.llm_tools())
+        .collect()
+}
+
+pub(super) async fn call(
+    context: &Context,
+    applications: &Applications,
+    guild_id: GuildId,
+    call: &FunctionCall,
+) -> FunctionResponse {
+    let mut result = None;
+    for application in applications.iter() {
+        result = application
+            .call_llm_tool(context, guild_id, &call.name, &call.args)
+            .await;
+        if result.is_some() {
+            break;
+        }
+    }
+
+    let response = match result {
+        Some(Ok(response)) => response,
+        Some(Err(e)) => {
+            error!("Failed to call {} - {e:?}", call.name);
+            serde_json::json!({ "error": e.to_string() })
+        }
+        None => serde_json::json!({ "error": format!("unknown function {}", call.name) }),
+    };
+
+    FunctionResponse {
+        name: call.name.clone(),
+        // gemini takes objects only
+        response: if response.is_object() {
+            response
+        } else {
+            serde_json::json!({ "result": response })
+        },
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serenity::{
+    model::channel::{Reaction, ReactionType},
+    prelude::Context,
+};
+
+use super::{
+    cost,
+    provider::{ChatMessage, ChatRequest, TokenUsage},
+    quota::Quota,
+    reply::split_content,
+    usage, DiscordHandler,
+};
+
+const TRANSLATE_PROMPT: &str = "주어진 디스코드 메시지를 {language}(으)로 번역해주세요. \
+    설명 없이 번역한 내용만 답해주세요.";
+
+// the country of a flag emoji made of two regional indicators
+fn flag_country(emoji: &str) -> Option<String> {
+    let mut chars = emoji.chars();
+    let country = chars
+        .by_ref()
+        .take(2)
+        .map(|c| {
+            let offset = (c as u32)
+                .checked_sub(0x1F1E6)
+                .filter(|offset| *offset < 26)?;
+            char::from_u32('A' as u32 + offset)
+        })
+        .collect::<Option<String>>()?;
+
+    (country.len() == 2 && chars.next().is_none()).then_some(country)
+}
+
+// the language a flag stands for, named in the language itself
+fn country_language(country: &str) -> Option<&'static str> {
+    Some(match country {
+        "KR" => "한국어",
+        "US" | "GB" | "AU" | "CA" | "NZ" | "IE" => "English",
+        "JP" => "日本語",
+        "CN" | "SG" => "简体中文",
+        "TW" | "HK" => "繁體中文",
+        "FR" => "Français",
+        "DE" | "AT" => "Deutsch",
+        "ES" | "MX" | "AR" => "Español",
+        "PT" | "BR" => "Português",
+        "IT" => "Italiano",
+        "RU" => "Русский",
+        "VN" => "Tiếng Việt",
+        "TH" => "ภาษาไทย",
+        "ID" => "Bahasa Indonesia",
+        "PH" => "Filipino",
+        "NL" => "Nederlands",
+        "PL" => "Polski",
+        "TR" => "Türkçe",
+        "UA" => "Українська",
+        "SA" | "AE" | "EG" => "العربية",
+        "IN" => "हिन्दी",
+        _ => return None,
+    })
+}
+
+impl DiscordHandler {
+    async fn translate(
+        &self,
+        text: &str,
+        language: &str,
+    ) -> anyhow::Result<(String, Option<TokenUsage>)> {
+        let request = ChatRequest {
+            system_instruction: Some(TRANSLATE_PROMPT.replace("{language}", language)),
+            messages: vec![ChatMessage {
+                text: text.to_string(),
+                ..Default::default()
+            }],
+            generation: self.generation.read().await.or(&self.config.generation),
+            safety_settings: self.safety_settings().await,
+            ..Default::default()
+        };
+
+        self.generate(&request).await
+    }
+
+    // the message is translated for the first reaction of a flag, in a reply to it.
+    // the one who reacted pays the quota.
+    pub(super) async fn translate_on_reaction(
+        &self,
+        context: &Context,
+        reaction: &Reaction,
+    ) -> anyhow::Result<()> {
+        if !self.config.translate_reactions {
+            return Ok(());
+        }
+        let ReactionType::Unicode(emoji) = &reaction.emoji else {
+            return Ok(());
+        };
+        let Some(language) = flag_country(emoji).as_deref().and_then(country_language) else {
+            return Ok(());
+        };
+        let Some(user_id) = reaction.user_id else {
+            return Ok(());
+        };
+        if user_id == context.cache.current_user_id() {
+            return Ok(());
+        }
+
+        let message = reaction
+            .message(context)
+            .await
+            .context("Failed to get reacted message")?;
+        if message.content.is_empty() || message.author.bot {
+            return Ok(());
+        }
+        let first = message
+            .reactions
+            .iter()
+            .find(|r| r.reaction_type == reaction.emoji)
+            .map_or(true, |r| r.count <= 1);
+        if !first {
+            return Ok(());
+        }
+        if !self
+            .may_generate(
+                context,
+                user_id,
+                reaction
+                    .member
+                    .as_ref()
+                    .map(|member| member.roles.as_slice()),
+            )
+            .await
+            || self.over_budget(context, user_id).await
+        {
+            return Ok(());
+        }
+        let raw_user_id = user_id.0 as i64;
+        let model = self.model.read().await.clone();
+        let request_id = match self.record_request(raw_user_id, &model, false).await? {
+            Quota::Recorded(request_id) => request_id,
+            Quota::Exceeded(_) => {
+                info!("Translation for user({user_id}) is skipped by the quota");
+                return Ok(());
+            }
+        };
+
+        info!("Translate message({}) into {language}", message.id);
+        let (translation, token_usage) = self.translate(&message.content, language).await?;
+        if let Some(token_usage) = token_usage {
+            if let Err(e) = usage::record_tokens(&self.db_pool, request_id, token_usage).await {
+                error!("{e:?}");
+            }
+        }
+        cost::warn(context, &self.db_pool, self.config.cost_budget.as_ref()).await;
+
+        let mut parts = split_content(format!("{emoji} {translation}")).into_iter();
+        let first = parts.next().unwrap_or_default();
+        message
+            .channel_id
+            .send_message(context, |builder| {
+                builder
+                    .content(first)
+                    .reference_message(&message)
+                    .allowed_mentions(|mentions| mentions.empty_parse())
+            })
+            .await
+            .context("Failed to send translation")?;
+        for part in parts {
+            message
+                .channel_id
+                .send_message(context, |builder| {
+                    builder
+                        .content(part)
+                        .allowed_mentions(|mentions| mentions.empty_parse())
+                })
+                .await
+                .context("Failed to send rest of translation")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Context as _;
+use chrono::{Datelike, TimeZone};
+use serenity::{
+    model::application::interaction::{
+        application_command::ApplicationCommandInteraction, InteractionResponseType,
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::{provider::TokenUsage, DiscordHandler};
+
+// users listed in the usage ranking
+const TOP_USERS: i64 = 10;
+
+pub(super) async fn record_tokens(
+    db_pool: &SqlitePool,
+    request_id: i64,
+    usage: TokenUsage,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "UPDATE `llm_requests` SET `prompt_tokens` = ?, `response_tokens` = ? WHERE `id` = ?",
+        usage.prompt_tokens,
+        usage.response_tokens,
+        request_id
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to record token usage")?;
+
+    Ok(())
+}
+
+impl DiscordHandler {
+    pub(super) async fn handle_usage_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+    ) -> anyhow::Result<()> {
+        // the month is counted in the time zone of the viewer
+        let time_zone =
+            crate::user::user_time_zone(&self.db_pool, interaction.user.id.0 as i64).await;
+        let today = chrono::Utc::now().with_timezone(&time_zone).date_naive();
+        let month_start = unsafe { today.with_day(1).unwrap_unchecked() }
+            .and_hms_opt(0, 0, 0)
+            .and_then(|date| time_zone.from_local_datetime(&date).earliest())
+            .context("Failed to get the start of the month")?
+            .timestamp();
+
+        let total = sqlx::query!(
+            r#"SELECT
+                COUNT(*) AS "requests!: i64",
+                COALESCE(SUM(`prompt_tokens`), 0) AS "prompt_tokens!: i64",
+                COALESCE(SUM(`response_tokens`), 0) AS "response_tokens!: i64"
+            FROM `llm_requests`
+            WHERE `requested_at` >= ?"#,
+            month_start
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to sum token usage")?;
+        let users = sqlx::query!(
+            r#"SELECT
+                `user_id` AS "user_id!: i64",
+                COUNT(*) AS "requests!: i64",
+                COALESCE(SUM(`prompt_tokens`), 0) AS "prompt_tokens!: i64",
+                COALESCE(SUM(`response_tokens`), 0) AS "response_tokens!: i64"
+            FROM `llm_requests`
+            WHERE `requested_at` >= ?
+            GROUP BY `user_id`
+            ORDER BY COALESCE(SUM(`prompt_tokens`), 0) + COALESCE(SUM(`response_tokens`), 0) DESC
+            LIMIT ?"#,
+            month_start,
+            TOP_USERS
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to sum token usage by user")?;
+
+        let mut content = format!(
+            "<t:{month_start}:D>부터\n전체: {}회 요청, 입력 {} / 출력 {} 토큰",
+            total.requests, total.prompt_tokens, total.response_tokens
+        );
+        if let Some(cost) = self.describe_cost().await? {
+            content.push('\n');
+            content.push_str(&cost);
+        }
+        for user in users {
+            content.push_str(&format!(
+                "\n<@{}>: {}회 요청, 입력 {} / 출력 {} 토큰",
+                user.user_id, user.requests, user.prompt_tokens, user.response_tokens
+            ));
+        }
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+}
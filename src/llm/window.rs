@@ -0,0 +1,181 @@
+use anyhow::Context as _;
+use log::error;
+use serenity::{
+    model::{
+        application::interaction::{
+            application_command::{ApplicationCommandInteraction, CommandDataOption},
+            InteractionResponseType,
+        },
+        channel::Message,
+    },
+    prelude::Context,
+};
+
+use super::{conversation::Turn, provider::ChatRole, DiscordHandler, END_INDICATOR};
+use crate::discord::{
+    application_command::{ApplicationCommandOption, ApplicationCommandOptionType},
+    CommandDataOptionHelper, CommandHelper,
+};
+
+// discord gives 100 messages at most for a request
+const MAX_SIZE: i64 = 100;
+
+pub(super) fn command_option() -> ApplicationCommandOption<'static> {
+    ApplicationCommandOption {
+        kind: ApplicationCommandOptionType::SubCommand,
+        name: "window",
+        description: "답장이 아닌 질문에 채널의 최근 메시지를 대화로 사용합니다.",
+        options: vec![
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Integer,
+                name: "size",
+                description: "사용할 최근 메시지 수. 0이면 끄고, 없을 경우 현재 값을 보여줍니다.",
+                required: Some(false),
+                ..Default::default()
+            },
+            ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Channel,
+                name: "channel",
+                description: "입력하지 않으면 현재 채널",
+                required: Some(false),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+impl DiscordHandler {
+    // recent messages of the channel before the question, from the oldest one
+    pub(super) async fn load_window(&self, context: &Context, message: &Message) -> Vec<Turn> {
+        let Some(size) = self
+            .channel_windows
+            .read()
+            .await
+            .get(&message.channel_id.0)
+            .copied()
+        else {
+            return Vec::new();
+        };
+
+        let mut messages = match message
+            .channel_id
+            .messages(context, |builder| builder.before(message.id).limit(size))
+            .await
+        {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!(
+                    "Failed to get recent messages of {} - {e:?}",
+                    message.channel_id
+                );
+                return Vec::new();
+            }
+        };
+        // pages are given from the newest one
+        messages.reverse();
+
+        self.message_turns(context, messages)
+    }
+
+    // messages from the oldest one given as the conversation.
+    // they are not stored as turns as they are not a conversation with the bot.
+    pub(super) fn message_turns(&self, context: &Context, messages: Vec<Message>) -> Vec<Turn> {
+        let bot_id = context.cache.current_user_id();
+        messages
+            .into_iter()
+            .filter(|message| !message.content.is_empty())
+            .map(|message| {
+                let is_model = message.author.id == bot_id;
+                Turn {
+                    message_id: message.id.0 as i64,
+                    parent_message_id: None,
+                    root_message_id: message.id.0 as i64,
+                    user_id: (!is_model).then(|| message.author.id.0 as i64),
+                    role: if is_model {
+                        ChatRole::Model
+                    } else {
+                        ChatRole::User
+                    },
+                    // messages of several members are told apart by names
+                    content: if is_model {
+                        message.content.trim_end_matches(END_INDICATOR).to_string()
+                    } else {
+                        format!(
+                            "{}: {}\n",
+                            message.author.name,
+                            self.strip_mention(&message.content)
+                        )
+                    },
+                }
+            })
+            // the conversation starts with a question
+            .skip_while(|turn| turn.role == ChatRole::Model)
+            .collect()
+    }
+
+    pub(super) async fn handle_window_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [size, channel] = option.get_options(&["size", "channel"]);
+        let channel_id = match channel.as_str() {
+            Some(channel_id) => channel_id.parse::<u64>().context("Invalid channel")?,
+            None => interaction.channel_id.0,
+        };
+        let raw_channel_id = channel_id as i64;
+
+        let content = match size.as_i64() {
+            Some(size) if !(0..=MAX_SIZE).contains(&size) => {
+                format!("size는 0 ~ {MAX_SIZE} 사이여야 합니다.")
+            }
+            Some(0) => {
+                sqlx::query!(
+                    "DELETE FROM `llm_channel_windows` WHERE `channel_id` = ?",
+                    raw_channel_id
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to delete channel window from DB")?;
+                self.channel_windows.write().await.remove(&channel_id);
+
+                format!("<#{channel_id}>에서 답장한 대화만 사용합니다.")
+            }
+            Some(size) => {
+                sqlx::query!(
+                    "INSERT INTO `llm_channel_windows` (`channel_id`, `size`) VALUES (?, ?)
+                    ON CONFLICT (`channel_id`) DO UPDATE
+                    SET `size` = `excluded`.`size`",
+                    raw_channel_id,
+                    size
+                )
+                .execute(&self.db_pool)
+                .await
+                .context("Failed to write channel window to DB")?;
+                self.channel_windows
+                    .write()
+                    .await
+                    .insert(channel_id, size as u64);
+
+                format!("<#{channel_id}>에서 최근 메시지 {size}개를 대화로 사용합니다.")
+            }
+            None => match self.channel_windows.read().await.get(&channel_id) {
+                Some(size) => format!("<#{channel_id}>: 최근 메시지 {size}개"),
+                None => format!("<#{channel_id}>: 사용 안 함"),
+            },
+        };
+
+        interaction
+            .create_interaction_response(context, |builder| {
+                builder
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|builder| builder.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to send interaction response")?;
+
+        Ok(())
+    }
+}
@@ -7,9 +7,11 @@ use sqlx::sqlite::SqlitePoolOptions;
 mod discord;
 mod eueoeo;
 mod events;
+mod hooks;
 pub(crate) mod jwt_util;
 mod link_rewriter;
 mod llm;
+mod stats;
 mod user;
 mod web;
 
@@ -29,6 +31,10 @@ pub(crate) struct Config {
     eueoeo: eueoeo::Config,
     user: user::Config,
     llm: llm::Config,
+    #[serde(default)]
+    link_rewriter: link_rewriter::Config,
+    #[serde(default)]
+    hooks: hooks::Config,
 }
 
 #[tokio::main]
@@ -51,16 +57,30 @@ async fn main() -> anyhow::Result<()> {
     // run DB migration
     sqlx::migrate!().run(&db_pool).await?;
 
+    let mut args = std::env::args().skip(1);
+    if let Some("--import-eueoeo-history") = args.next().as_deref() {
+        let path = args
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Path of exported json is required"))?;
+        eueoeo::import_history(db_pool.clone(), &config, &path).await?;
+        db_pool.close().await;
+
+        return Ok(());
+    }
+
     let (stop_sender, _) = tokio::sync::broadcast::channel(1);
+    let gateway_status = discord::GatewayStatus::default();
 
     let discord_join = tokio::task::spawn({
         let db_pool = db_pool.clone();
         let stop_receiver = stop_sender.subscribe();
         let stop_sender = stop_sender.clone();
         let config = config.clone();
+        let gateway_status = gateway_status.clone();
         async move {
             type BoxedHandler = Box<dyn discord::SubApplication + Send + Sync>;
             if let Err(e) = discord::start(
+                db_pool.clone(),
                 &config,
                 IntoIterator::into_iter([
                     Box::new(eueoeo::DiscordHandler::new(db_pool.clone(), &config).await)
@@ -75,7 +95,12 @@ async fn main() -> anyhow::Result<()> {
                             .await
                             .unwrap(),
                     ) as BoxedHandler,
-                    Box::new(link_rewriter::DiscordHandler::new()) as BoxedHandler,
+                    Box::new(
+                        link_rewriter::DiscordHandler::new(db_pool.clone(), &config)
+                            .await
+                            .unwrap(),
+                    ) as BoxedHandler,
+                    Box::new(hooks::DiscordHandler::new(db_pool.clone())) as BoxedHandler,
                     Box::new(
                         llm::DiscordHandler::new(db_pool.clone(), &config)
                             .await
@@ -83,6 +108,7 @@ async fn main() -> anyhow::Result<()> {
                     ) as BoxedHandler,
                 ])
                 .collect(),
+                gateway_status,
                 stop_receiver,
             )
             .await
@@ -97,7 +123,7 @@ async fn main() -> anyhow::Result<()> {
         let stop_receiver = stop_sender.subscribe();
         let stop_sender = stop_sender.clone();
         async move {
-            if let Err(e) = web::start(db_pool, config, stop_receiver).await {
+            if let Err(e) = web::start(db_pool, config, gateway_status, stop_receiver).await {
                 error!("Web task failed with - {e:?}");
                 let _ = stop_sender.send(());
             }
@@ -0,0 +1,250 @@
+use anyhow::Context as _;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+// read-only statistics shared by consumers outside of discord, such as the web API.
+// ids are given as strings not to lose precision in javascript.
+#[derive(Clone)]
+pub(crate) struct Stats {
+    db_pool: SqlitePool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RankingEntry {
+    pub(crate) user_id: String,
+    pub(crate) name: String,
+    pub(crate) count: i64,
+    pub(crate) rank: i64,
+    pub(crate) departed: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct UserStats {
+    pub(crate) user_id: String,
+    pub(crate) name: String,
+    pub(crate) count: i64,
+    pub(crate) longest_streak: i64,
+    pub(crate) current_streak: i64,
+    pub(crate) first_date: Option<String>,
+    pub(crate) last_date: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct UpcomingEvent {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    pub(crate) location: Option<String>,
+    pub(crate) start_time: String,
+    pub(crate) end_time: Option<String>,
+    pub(crate) interested_count: i64,
+}
+
+// dates of eueoeo are stored as timestamps of the midnight
+#[derive(Serialize)]
+pub(crate) struct LlmUsage {
+    pub(crate) requests: i64,
+    pub(crate) prompt_tokens: i64,
+    pub(crate) response_tokens: i64,
+}
+
+#[derive(Serialize)]
+pub(crate) struct UserLlmUsage {
+    pub(crate) user_id: String,
+    pub(crate) name: Option<String>,
+    #[serde(flatten)]
+    pub(crate) usage: LlmUsage,
+}
+
+fn date_string(timestamp: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(timestamp, 0).map(|date| date.date_naive().to_string())
+}
+
+fn time_string(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+impl Stats {
+    pub(crate) fn new(db_pool: SqlitePool) -> Self {
+        Self { db_pool }
+    }
+
+    // opted out users are hidden as in the rankings on discord
+    pub(crate) async fn eueoeo_total(&self) -> anyhow::Result<Vec<RankingEntry>> {
+        let rows = sqlx::query!(
+            r#"SELECT
+                user_id,
+                name,
+                count,
+                departed AS "departed: bool",
+                RANK() OVER (ORDER BY count DESC) AS "rank!: i64"
+            FROM
+                users
+            WHERE
+                count > 0 AND
+                NOT opted_out
+            ORDER BY
+                count DESC"#
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch eueoeo ranking")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RankingEntry {
+                user_id: row.user_id.to_string(),
+                name: row.name,
+                count: row.count,
+                rank: row.rank,
+                departed: row.departed,
+            })
+            .collect())
+    }
+
+    // `None` for users who never eueoeo or opted out
+    pub(crate) async fn eueoeo_user(&self, user_id: i64) -> anyhow::Result<Option<UserStats>> {
+        let row = sqlx::query!(
+            r#"SELECT
+                user_id,
+                name,
+                count,
+                longest_streaks,
+                current_streaks,
+                first_date,
+                last_date
+            FROM
+                users
+            WHERE
+                user_id = ? AND
+                count > 0 AND
+                NOT opted_out"#,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to fetch eueoeo stats of user")?;
+
+        Ok(row.map(|row| UserStats {
+            user_id: row.user_id.to_string(),
+            name: row.name,
+            count: row.count,
+            longest_streak: row.longest_streaks,
+            current_streak: row.current_streaks,
+            first_date: row.first_date.and_then(date_string),
+            last_date: date_string(row.last_date),
+        }))
+    }
+
+    pub(crate) async fn upcoming_events(&self, limit: i64) -> anyhow::Result<Vec<UpcomingEvent>> {
+        let now = chrono::Utc::now().timestamp();
+        let rows = sqlx::query!(
+            r#"SELECT
+                `scheduled_events`.`discord_id` AS "discord_id!: i64",
+                `scheduled_events`.`name` AS "name!: String",
+                `scheduled_events`.`description`,
+                `scheduled_events`.`location`,
+                `scheduled_events`.`start_time` AS "start_time!: i64",
+                `scheduled_events`.`end_time`,
+                count(`scheduled_event_attendees`.`user_id`) AS "interested_count!: i64"
+            FROM
+                `scheduled_events`
+            LEFT JOIN
+                `scheduled_event_attendees` ON
+                    `scheduled_event_attendees`.`discord_id` = `scheduled_events`.`discord_id`
+            WHERE
+                coalesce(`scheduled_events`.`end_time`, `scheduled_events`.`start_time`) >= ?
+            GROUP BY
+                `scheduled_events`.`discord_id`
+            ORDER BY
+                `scheduled_events`.`start_time` ASC
+            LIMIT ?"#,
+            now,
+            limit
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch upcoming events")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| UpcomingEvent {
+                id: row.discord_id.to_string(),
+                name: row.name,
+                description: row.description,
+                location: row.location,
+                start_time: time_string(row.start_time),
+                end_time: row.end_time.map(time_string),
+                interested_count: row.interested_count,
+            })
+            .collect())
+    }
+
+    pub(crate) async fn llm_usage(&self, user_id: i64, since: i64) -> anyhow::Result<LlmUsage> {
+        let usage = sqlx::query!(
+            r#"SELECT
+                count(*) AS "requests!: i64",
+                coalesce(sum(`prompt_tokens`), 0) AS "prompt_tokens!: i64",
+                coalesce(sum(`response_tokens`), 0) AS "response_tokens!: i64"
+            FROM
+                `llm_requests`
+            WHERE
+                `user_id` = ? AND
+                `requested_at` >= ?"#,
+            user_id,
+            since
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .context("Failed to fetch LLM usage of user")?;
+
+        Ok(LlmUsage {
+            requests: usage.requests,
+            prompt_tokens: usage.prompt_tokens,
+            response_tokens: usage.response_tokens,
+        })
+    }
+
+    // the heaviest users first
+    pub(crate) async fn llm_usage_by_user(&self, since: i64) -> anyhow::Result<Vec<UserLlmUsage>> {
+        let rows = sqlx::query!(
+            r#"SELECT
+                `llm_requests`.`user_id`,
+                `users`.`name` AS "name?: String",
+                count(*) AS "requests!: i64",
+                coalesce(sum(`llm_requests`.`prompt_tokens`), 0) AS "prompt_tokens!: i64",
+                coalesce(sum(`llm_requests`.`response_tokens`), 0) AS "response_tokens!: i64"
+            FROM
+                `llm_requests`
+            LEFT JOIN
+                `users` ON `users`.`user_id` = `llm_requests`.`user_id`
+            WHERE
+                `llm_requests`.`requested_at` >= ?
+            GROUP BY
+                `llm_requests`.`user_id`
+            ORDER BY
+                coalesce(sum(`llm_requests`.`prompt_tokens`), 0) +
+                    coalesce(sum(`llm_requests`.`response_tokens`), 0) DESC,
+                count(*) DESC"#,
+            since
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to fetch LLM usage by user")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| UserLlmUsage {
+                user_id: row.user_id.to_string(),
+                name: row.name,
+                usage: LlmUsage {
+                    requests: row.requests,
+                    prompt_tokens: row.prompt_tokens,
+                    response_tokens: row.response_tokens,
+                },
+            })
+            .collect())
+    }
+}
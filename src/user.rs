@@ -4,7 +4,12 @@ use log::error;
 use serde::Deserialize;
 use serenity::{
     model::{
-        application::{component::ButtonStyle, interaction::InteractionResponseType},
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+        },
         prelude::{
             interaction::application_command::{ApplicationCommandInteraction, CommandDataOption},
             GuildId, UserId,
@@ -14,56 +19,117 @@ use serenity::{
 };
 use sqlx::{Row, SqlitePool};
 
-mod google;
+// `google::user_calendar_hub` gives other modules a hub acting as the user
+mod export;
+mod forget;
+pub(crate) mod google;
+mod links;
+mod outlook;
+mod preferences;
+mod profile;
+mod time_zone;
 
 use crate::discord::{
     application_command::{
         ApplicationCommand, ApplicationCommandOption, ApplicationCommandOptionType,
     },
-    SubApplication,
+    CommandDataOptionHelper, CommandHelper, SubApplication, FORGET_USER_CANCEL,
 };
 
-use self::google::GoogleUserHandler;
+use self::{
+    google::{GoogleUserHandler, Unlinked},
+    outlook::OutlookUserHandler,
+};
+pub(crate) use google::{check_token_key, decrypt_secret, encrypt_secret, MissingTokenKey};
+pub(crate) use outlook::access_token as outlook_access_token;
+pub(crate) use preferences::Preference;
+pub(crate) use time_zone::{user_time_zone, DEFAULT_TIME_ZONE};
 
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct Config {
     google_oauth_secret_path: String,
     google_service_account_path: String,
     redirect_prefix: String,
+    // base64url encoded 32 bytes key to encrypt google refresh tokens. they are not stored without it.
+    #[serde(default)]
+    google_token_key: Option<String>,
+    // microsoft graph application for outlook calendar. `/user outlook` is disabled without it.
+    #[serde(default)]
+    outlook: Option<outlook::Config>,
 }
 
 pub struct DiscordHandler {
     db_pool: SqlitePool,
     google: GoogleUserHandler,
+    outlook: Option<OutlookUserHandler>,
 }
 
 const COMMAND_NAME: &str = "user";
 
 impl DiscordHandler {
     pub async fn new(db_pool: SqlitePool, config: &super::Config) -> anyhow::Result<Self> {
-        Ok(Self {
+        let handler = Self {
             db_pool,
             google: GoogleUserHandler::new(
                 &config.user.google_oauth_secret_path,
                 &config.user.google_service_account_path,
                 &config.user.redirect_prefix,
+                config.user.google_token_key.as_deref(),
             )
             .await?,
-        })
+            outlook: config
+                .user
+                .outlook
+                .as_ref()
+                .map(|outlook| OutlookUserHandler::new(outlook, &config.user.redirect_prefix)),
+        };
+        // the key of tokens is ready after the google handler is created
+        google::check_stored_secrets(&handler.db_pool).await?;
+        if let Err(e) = outlook::encrypt_plain_tokens(&handler.db_pool).await {
+            error!("Failed to encrypt outlook tokens - {e:?}");
+        }
+
+        Ok(handler)
     }
 
     async fn handle_google_command(
         &self,
         context: &Context,
         interaction: &ApplicationCommandInteraction,
-        _option: &CommandDataOption,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let sub_option = unsafe { option.options.first().unwrap_unchecked() };
+        match sub_option.name.as_str() {
+            "link" => {
+                self.handle_google_link_command(context, interaction, sub_option)
+                    .await
+            }
+            "unlink" => {
+                self.handle_google_unlink_command(context, interaction, sub_option)
+                    .await
+            }
+            _ => unsafe { std::hint::unreachable_unchecked() },
+        }
+    }
+
+    async fn handle_google_link_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
     ) -> anyhow::Result<()> {
         let user_id = interaction.user.id;
+        let [calendar_name] = option.get_options(&["calendar_name"]);
+        let calendar_name = calendar_name
+            .as_str()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
 
         let url = self
             .google
             .auth(
                 user_id,
+                calendar_name,
                 self.db_pool.clone(),
                 context.clone(),
                 interaction.clone(),
@@ -90,6 +156,179 @@ impl DiscordHandler {
         Ok(())
     }
 
+    async fn handle_google_switch_answer(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+        id: &str,
+        confirmed: bool,
+    ) -> anyhow::Result<()> {
+        let answered = id
+            .parse()
+            .map_or(false, |id| google::answer_account_switch(id, confirmed));
+        let content = match (answered, confirmed) {
+            (false, _) => "만료된 요청입니다. 다시 연결해주세요.",
+            (true, true) => "새 계정으로 변경하는 중입니다.",
+            (true, false) => "기존 계정을 유지합니다.",
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|b| b.content(content).components(|b| b))
+            })
+            .await
+            .context("Failed to answer switching google account")?;
+
+        Ok(())
+    }
+
+    async fn handle_google_unlink_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let [remove_access] = option.get_options(&["remove_access"]);
+        let unlinked = self
+            .google
+            .unlink(
+                &self.db_pool,
+                interaction.user.id,
+                remove_access.as_bool().unwrap_or(false),
+            )
+            .await?;
+        let content = match unlinked {
+            Unlinked::NotLinked => "연결된 구글 계정이 없습니다.",
+            Unlinked::Unlinked => "구글 계정 연결을 해제했습니다.",
+            Unlinked::AccessLeft => {
+                "구글 계정 연결을 해제했습니다. 캘린더 공유 설정에서 후타바ID를 직접 제거해주세요."
+            }
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to update interaction response")?;
+
+        Ok(())
+    }
+
+    async fn handle_outlook_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let outlook = self.outlook.as_ref().context("Outlook is not configured")?;
+        // tokens are not stored without the key
+        if let Err(e) = check_token_key() {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.content(format!("연동 실패. {e}")).ephemeral(true)
+                        })
+                })
+                .await
+                .context("Failed to send interaction response")?;
+            return Ok(());
+        }
+        let url = outlook
+            .auth(
+                interaction.user.id,
+                self.db_pool.clone(),
+                context.clone(),
+                interaction.clone(),
+            )
+            .await?;
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| {
+                        b.components(|b| {
+                            b.create_action_row(|b| {
+                                b.create_button(|b| {
+                                    b.label("Login").style(ButtonStyle::Link).url(url)
+                                })
+                            })
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await
+            .context("Failed to update interaction response")?;
+
+        Ok(())
+    }
+
+    async fn handle_linkfix_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let user_id = *interaction.user.id.as_u64() as i64;
+        let [enable] = option.get_options(&["enable"]);
+        let content = if enable.as_bool().unwrap_or(true) {
+            crate::link_rewriter::OPT_OUT
+                .remove(&self.db_pool, user_id)
+                .await?;
+            "링크를 고쳐서 다시 올립니다."
+        } else {
+            crate::link_rewriter::OPT_OUT
+                .set(&self.db_pool, user_id, &true)
+                .await?;
+            "링크를 고치지 않습니다."
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to update interaction response")?;
+
+        Ok(())
+    }
+
+    async fn handle_memory_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let user_id = *interaction.user.id.as_u64() as i64;
+        let [enable] = option.get_options(&["enable"]);
+        let content = if enable.as_bool().unwrap_or(true) {
+            crate::llm::MEMORY_OPT_OUT
+                .remove(&self.db_pool, user_id)
+                .await?;
+            "설정된 채널의 메시지를 /ask 에서 찾을 수 있도록 기억합니다.".to_string()
+        } else {
+            crate::llm::MEMORY_OPT_OUT
+                .set(&self.db_pool, user_id, &true)
+                .await?;
+            let forgotten = crate::llm::forget_user_memory(&self.db_pool, user_id).await?;
+            format!("메시지를 기억하지 않습니다. 기억하던 메시지 {forgotten}건을 지웠습니다.")
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to update interaction response")?;
+
+        Ok(())
+    }
+
     pub async fn get_google_id(db: &SqlitePool, user_id: UserId) -> anyhow::Result<Option<String>> {
         let user_id = *user_id.as_u64() as i64;
         let ret = sqlx::query!(
@@ -129,15 +368,118 @@ impl DiscordHandler {
 impl SubApplication for DiscordHandler {
     async fn ready(&self, context: &Context, guild_id: GuildId) {
         // register or update slash command
-        let command = ApplicationCommand {
-            name: COMMAND_NAME,
-            description: "user setting",
+        let mut options = vec![ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommandGroup,
+            name: "google",
+            description: "google account",
+            options: vec![
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "link",
+                    description: "link google id",
+                    options: vec![ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::String,
+                        name: "calendar_name",
+                        description: "name of the mirrored calendar. the server name by default",
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ApplicationCommandOption {
+                    kind: ApplicationCommandOptionType::SubCommand,
+                    name: "unlink",
+                    description: "unlink google id",
+                    options: vec![ApplicationCommandOption {
+                        kind: ApplicationCommandOptionType::Boolean,
+                        name: "remove_access",
+                        description: "stop sharing the calendar with the bot",
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }];
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "profile",
+            description: "show linked services and records",
             options: vec![ApplicationCommandOption {
-                kind: ApplicationCommandOptionType::SubCommand,
-                name: "google",
-                description: "link google id",
+                kind: ApplicationCommandOptionType::User,
+                name: "user",
+                description: "user to show. yourself by default",
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "timezone",
+            description: "time zone to show times in",
+            options: vec![ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::String,
+                name: "zone",
+                description: "IANA time zone like Asia/Seoul. shows the current one without it",
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "linkfix",
+            description: "rewrite twitter links in your messages",
+            options: vec![ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Boolean,
+                name: "enable",
+                description: "rewrite or not",
+                required: Some(true),
                 ..Default::default()
             }],
+            ..Default::default()
+        });
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "memory",
+            description: "let /ask remember your messages",
+            options: vec![ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::Boolean,
+                name: "enable",
+                description: "remember or not",
+                required: Some(true),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "links",
+            description: "linked services of members (admin only)",
+            ..Default::default()
+        });
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "export",
+            description: "send every data of yourself by DM",
+            ..Default::default()
+        });
+        options.push(ApplicationCommandOption {
+            kind: ApplicationCommandOptionType::SubCommand,
+            name: "forget",
+            description: "delete every data of yourself",
+            ..Default::default()
+        });
+        if self.outlook.is_some() {
+            options.push(ApplicationCommandOption {
+                kind: ApplicationCommandOptionType::SubCommand,
+                name: "outlook",
+                description: "link outlook calendar",
+                ..Default::default()
+            });
+        }
+        let command = ApplicationCommand {
+            name: COMMAND_NAME,
+            description: "user setting",
+            options,
         };
 
         let guild = context.cache.guild(guild_id);
@@ -170,6 +512,38 @@ impl SubApplication for DiscordHandler {
                 self.handle_google_command(context, interaction, option)
                     .await
             }
+            "outlook" => {
+                self.handle_outlook_command(context, interaction, option)
+                    .await
+            }
+            "profile" => {
+                self.handle_profile_command(context, interaction, option)
+                    .await
+            }
+            "timezone" => {
+                self.handle_time_zone_command(context, interaction, option)
+                    .await
+            }
+            "linkfix" => {
+                self.handle_linkfix_command(context, interaction, option)
+                    .await
+            }
+            "memory" => {
+                self.handle_memory_command(context, interaction, option)
+                    .await
+            }
+            "links" => {
+                self.handle_links_command(context, interaction, option)
+                    .await
+            }
+            "export" => {
+                self.handle_export_command(context, interaction, option)
+                    .await
+            }
+            "forget" => {
+                self.handle_forget_command(context, interaction, option)
+                    .await
+            }
             _ => unsafe { std::hint::unreachable_unchecked() },
         } {
             error!("Failed to handle message: {:?}", e);
@@ -177,8 +551,48 @@ impl SubApplication for DiscordHandler {
 
         true
     }
+
+    async fn message_component(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+    ) -> bool {
+        let custom_id = interaction.data.custom_id.as_str();
+        if let Err(e) = if custom_id == FORGET_USER_CANCEL {
+            self.handle_forget_cancel(context, interaction).await
+        } else if let Some(id) = custom_id.strip_prefix(google::SWITCH_ACCOUNT_CONFIRM) {
+            self.handle_google_switch_answer(context, interaction, id, true)
+                .await
+        } else if let Some(id) = custom_id.strip_prefix(google::SWITCH_ACCOUNT_CANCEL) {
+            self.handle_google_switch_answer(context, interaction, id, false)
+                .await
+        } else {
+            return false;
+        } {
+            error!("Failed to handle message component: {:?}", e);
+        }
+
+        true
+    }
+
+    async fn forget_user(&self, user_id: UserId) -> anyhow::Result<Vec<String>> {
+        self.forget_user_records(user_id).await
+    }
+
+    async fn export_user(
+        &self,
+        user_id: UserId,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        self.export_user_records(*user_id.as_u64() as i64).await
+    }
 }
 
 pub fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
-    axum::Router::new().nest("/google", google::web_router())
+    axum::Router::new()
+        .nest("/google", google::web_router())
+        .nest("/outlook", outlook::web_router())
+        .route(
+            "/:id/events.ics",
+            axum::routing::get(crate::events::events_feed),
+        )
 }
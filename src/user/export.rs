@@ -0,0 +1,142 @@
+use anyhow::Context as _;
+use log::{error, info};
+use serenity::{
+    model::{
+        application::interaction::InteractionResponseType,
+        prelude::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+    },
+    prelude::Context,
+};
+
+use super::DiscordHandler;
+use crate::discord::SubApplications;
+
+const EXPORT_FILENAME: &str = "futaba-export.json";
+
+impl DiscordHandler {
+    pub(super) async fn handle_export_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|b| b.ephemeral(true))
+            })
+            .await?;
+
+        let user_id = interaction.user.id;
+        info!("Export user({user_id})");
+        let applications = context.data.read().await.get::<SubApplications>().cloned();
+        let mut export = serde_json::Map::new();
+        export.insert("user_id".to_string(), user_id.to_string().into());
+        export.insert(
+            "exported_at".to_string(),
+            chrono::Utc::now().to_rfc3339().into(),
+        );
+        let mut failed = false;
+        for app in applications
+            .iter()
+            .flat_map(|applications| applications.iter())
+        {
+            match app.export_user(user_id).await {
+                Ok(records) => export.extend(records),
+                Err(e) => {
+                    error!("Failed to export user({user_id}) - {e:?}");
+                    failed = true;
+                }
+            }
+        }
+
+        let content = if failed {
+            "일부 데이터를 모으지 못했습니다. 관리자에게 문의해주세요."
+        } else {
+            let data = serde_json::to_vec_pretty(&export).context("Failed to serialize export")?;
+            let sent = interaction
+                .user
+                .direct_message(context, |m| {
+                    m.content("저장된 데이터입니다.")
+                        .add_file((data.as_slice(), EXPORT_FILENAME))
+                })
+                .await;
+            match sent {
+                Ok(_) => "DM으로 데이터를 보냈습니다.",
+                Err(e) => {
+                    info!("Failed to send export to user({user_id}) - {e:?}");
+                    "DM을 보내지 못했습니다. 서버 멤버의 DM을 허용했는지 확인해주세요."
+                }
+            }
+        };
+
+        interaction
+            .edit_original_interaction_response(&context.http, |b| b.content(content))
+            .await?;
+
+        Ok(())
+    }
+
+    // credentials are left out, the user can't use them anyway
+    pub(super) async fn export_user_records(
+        &self,
+        user_id: i64,
+    ) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        let user = sqlx::query!(
+            r#"SELECT
+                `name`,
+                `count`,
+                `longest_streaks`,
+                `current_streaks`,
+                `first_date`,
+                `google_email`,
+                `google_calendar_id`,
+                `calendar_backend`,
+                `caldav_url`,
+                `caldav_username`,
+                `outlook_refresh_token` IS NOT NULL AS "outlook_linked!: bool",
+                `opted_out` AS "opted_out: bool",
+                `time_zone`
+            FROM `users` WHERE `user_id` = ?"#,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get user")?;
+
+        let mut export = serde_json::Map::new();
+        export.insert(
+            "user".to_string(),
+            user.map(|user| {
+                serde_json::json!({
+                    "name": user.name,
+                    "count": user.count,
+                    "longest_streaks": user.longest_streaks,
+                    "current_streaks": user.current_streaks,
+                    "first_date": user.first_date
+                        .and_then(|date| chrono::DateTime::from_timestamp(date, 0))
+                        .map(|date| date.date_naive().to_string()),
+                    "google_email": user.google_email,
+                    "google_calendar_id": user.google_calendar_id,
+                    "calendar_backend": user.calendar_backend,
+                    "caldav_url": user.caldav_url,
+                    "caldav_username": user.caldav_username,
+                    "outlook_linked": user.outlook_linked,
+                    "opted_out": user.opted_out,
+                    "time_zone": user.time_zone,
+                })
+            })
+            .into(),
+        );
+        export.insert(
+            "preferences".to_string(),
+            super::preferences::get_all(&self.db_pool, user_id)
+                .await?
+                .into(),
+        );
+
+        Ok(export)
+    }
+}
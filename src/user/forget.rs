@@ -0,0 +1,93 @@
+use anyhow::Context as _;
+use serenity::{
+    model::{
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+        },
+        prelude::{
+            interaction::application_command::{ApplicationCommandInteraction, CommandDataOption},
+            UserId,
+        },
+    },
+    prelude::Context,
+};
+
+use super::{google::Unlinked, DiscordHandler};
+use crate::discord::{FORGET_USER_CANCEL, FORGET_USER_CONFIRM};
+
+impl DiscordHandler {
+    pub(super) async fn handle_forget_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| {
+                        b.content(
+                            "으어어 기록, 이벤트 참석 기록, 연결된 계정 등 저장된 모든 데이터를 삭제합니다. 되돌릴 수 없습니다.",
+                        )
+                        .components(|b| {
+                            b.create_action_row(|b| {
+                                b.create_button(|b| {
+                                    b.label("삭제")
+                                        .style(ButtonStyle::Danger)
+                                        .custom_id(FORGET_USER_CONFIRM)
+                                })
+                                .create_button(|b| {
+                                    b.label("취소")
+                                        .style(ButtonStyle::Secondary)
+                                        .custom_id(FORGET_USER_CANCEL)
+                                })
+                            })
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await
+            .context("Failed to ask confirmation")?;
+
+        Ok(())
+    }
+
+    // confirmation is handled by the discord module as every application takes part in it
+    pub(super) async fn handle_forget_cancel(
+        &self,
+        context: &Context,
+        interaction: &MessageComponentInteraction,
+    ) -> anyhow::Result<()> {
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|b| b.content("취소했습니다.").components(|b| b))
+            })
+            .await
+            .context("Failed to cancel forget")?;
+
+        Ok(())
+    }
+
+    // the `users` row is deleted by the discord module after every application forgot the user
+    pub(super) async fn forget_user_records(&self, user_id: UserId) -> anyhow::Result<Vec<String>> {
+        let mut removed = Vec::new();
+        if !matches!(
+            self.google.unlink(&self.db_pool, user_id, true).await?,
+            Unlinked::NotLinked
+        ) {
+            removed.push("구글 계정 연결".to_string());
+        }
+
+        let raw_user_id = *user_id.as_u64() as i64;
+        let preferences = super::preferences::remove_all(&self.db_pool, raw_user_id).await?;
+        if preferences > 0 {
+            removed.push(format!("설정 {preferences}건"));
+        }
+
+        Ok(removed)
+    }
+}
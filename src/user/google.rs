@@ -15,13 +15,16 @@ use google_calendar3::{
     oauth2::{self, authenticator_delegate::InstalledFlowDelegate},
     CalendarHub,
 };
-use log::{error, info};
-use once_cell::sync::OnceCell;
+use log::{error, info, warn};
+use once_cell::sync::{Lazy, OnceCell};
 use serenity::{
     http::Http,
     model::{
-        application::interaction::{
-            application_command::ApplicationCommandInteraction, InteractionResponseType,
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                application_command::ApplicationCommandInteraction, InteractionResponseType,
+            },
         },
         id::UserId,
     },
@@ -30,6 +33,14 @@ use sqlx::SqlitePool;
 use tokio::sync::{oneshot, Mutex};
 use uuid::Uuid;
 
+mod token;
+
+use self::token::{CapturedToken, TokenCipher};
+use super::Preference;
+
+// summary of the mirrored calendar chosen by the user, kept for re-creation
+const CALENDAR_NAME: Preference<String> = Preference::new("google.calendar_name");
+
 #[repr(transparent)]
 #[derive(Debug, Clone)]
 struct LoginCallbackCode(String);
@@ -40,6 +51,17 @@ pub struct RedirectUrl(pub String);
 
 type LoginStateMap = DashMap<Uuid, oneshot::Sender<LoginCallbackCode>>;
 
+const REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+// buttons to confirm switching the google account, followed by the login id
+pub(super) const SWITCH_ACCOUNT_CONFIRM: &str = "google_switch_confirm:";
+pub(super) const SWITCH_ACCOUNT_CANCEL: &str = "google_switch_cancel:";
+const SWITCH_ACCOUNT_TIMEOUT_SECS: u64 = 5 * 60;
+// refresh a bit early not to expire in the middle of a request
+const TOKEN_EXPIRY_MARGIN_SECS: i64 = 60;
+
+pub(crate) type UserCalendarHub =
+    CalendarHub<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>;
+
 const CALENDAR_SCOPE: &[&str] = &[
     "https://www.googleapis.com/auth/calendar",
     "https://www.googleapis.com/auth/calendar.readonly",
@@ -92,7 +114,30 @@ impl InstalledFlowDelegate for LoginDelegate {
     }
 }
 
-async fn fetch_google_key_store() -> anyhow::Result<BTreeMap<String, RsaVerifying>> {
+const GOOGLE_CERTS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+// used when google doesn't tell how long the keys are valid
+const DEFAULT_KEYS_MAX_AGE_SECS: i64 = 60 * 60;
+// unknown key ids don't trigger refreshes more often than this
+const MIN_KEYS_REFRESH_SECS: i64 = 60;
+
+struct CachedKeys {
+    keys: BTreeMap<String, RsaVerifying>,
+    fetched_at: i64,
+    expires_at: i64,
+}
+
+fn max_age(headers: &reqwest::header::HeaderMap) -> Option<i64> {
+    headers
+        .get(reqwest::header::CACHE_CONTROL)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .find_map(|directive| directive.trim().strip_prefix("max-age="))?
+        .parse()
+        .ok()
+}
+
+async fn fetch_google_key_store() -> anyhow::Result<CachedKeys> {
     #[derive(serde::Deserialize)]
     struct Key {
         n: String,
@@ -104,44 +149,441 @@ async fn fetch_google_key_store() -> anyhow::Result<BTreeMap<String, RsaVerifyin
     struct R {
         keys: Vec<Key>,
     }
-    let resp: R = reqwest::get("https://www.googleapis.com/oauth2/v3/certs")
-        .await?
-        .json()
-        .await?;
+    let resp = reqwest::get(GOOGLE_CERTS_URL).await?.error_for_status()?;
+    let max_age = max_age(resp.headers()).unwrap_or(DEFAULT_KEYS_MAX_AGE_SECS);
+    let resp: R = resp.json().await?;
 
-    let mut ret = BTreeMap::new();
+    let mut keys = BTreeMap::new();
 
     for key in resp.keys {
-        ret.insert(
-            key.kid.to_string(),
-            RsaVerifying(
-                rsa::RsaPublicKey::new(
-                    rsa::BigUint::from_bytes_be(&base64_url::decode(&key.n).unwrap()),
-                    rsa::BigUint::from_bytes_be(&base64_url::decode(&key.e).unwrap()),
-                )
-                .unwrap(),
-                match key.alg.as_str() {
-                    "RS256" => RsAlgorithm::Rs256,
-                    "RS384" => RsAlgorithm::Rs384,
-                    "RS512" => RsAlgorithm::Rs512,
-                    alg => unreachable!("Invalid algorithm type - {}", alg),
-                },
-            ),
-        );
+        let algorithm = match key.alg.as_str() {
+            "RS256" => RsAlgorithm::Rs256,
+            "RS384" => RsAlgorithm::Rs384,
+            "RS512" => RsAlgorithm::Rs512,
+            alg => {
+                warn!("Skip key({}) of unsupported algorithm - {alg}", key.kid);
+                continue;
+            }
+        };
+        let public_key = rsa::RsaPublicKey::new(
+            rsa::BigUint::from_bytes_be(&base64_url::decode(&key.n).context("Invalid modulus")?),
+            rsa::BigUint::from_bytes_be(&base64_url::decode(&key.e).context("Invalid exponent")?),
+        )
+        .context("Invalid public key")?;
+        keys.insert(key.kid, RsaVerifying(public_key, algorithm));
     }
 
-    Ok(ret)
+    let now = chrono::Utc::now().timestamp();
+    Ok(CachedKeys {
+        keys,
+        fetched_at: now,
+        expires_at: now + max_age,
+    })
+}
+
+// keys to verify google ID tokens. refreshed when they expire or a token is signed by an unknown key.
+#[derive(Clone)]
+pub(super) struct GoogleKeyStore(Arc<tokio::sync::RwLock<CachedKeys>>);
+
+impl GoogleKeyStore {
+    async fn new() -> anyhow::Result<Self> {
+        Ok(Self(Arc::new(tokio::sync::RwLock::new(
+            fetch_google_key_store().await?,
+        ))))
+    }
+
+    async fn refresh(&self) -> anyhow::Result<()> {
+        info!("Refresh google key store");
+        let keys = fetch_google_key_store()
+            .await
+            .context("Failed to fetch google key store")?;
+        *self.0.write().await = keys;
+
+        Ok(())
+    }
+
+    pub async fn verify(&self, token: &str) -> anyhow::Result<BTreeMap<String, serde_json::Value>> {
+        use jwt::VerifyWithStore;
+
+        let now = chrono::Utc::now().timestamp();
+        let expired = self.0.read().await.expires_at <= now;
+        if expired {
+            // stale keys are still worth trying
+            if let Err(e) = self.refresh().await {
+                warn!("{e:?}");
+            }
+        }
+
+        let result = token.verify_with_store(&self.0.read().await.keys);
+        let result = match result {
+            Err(jwt::Error::NoKeyWithKeyId(key_id)) => {
+                let fetched_at = self.0.read().await.fetched_at;
+                if fetched_at + MIN_KEYS_REFRESH_SECS > now {
+                    Err(jwt::Error::NoKeyWithKeyId(key_id))
+                } else {
+                    info!("Unknown key({key_id}) is used");
+                    self.refresh().await?;
+                    token.verify_with_store(&self.0.read().await.keys)
+                }
+            }
+            result => result,
+        };
+
+        result.context("jwt verification failed")
+    }
 }
 
 static LOGIN_STATE: once_cell::sync::Lazy<LoginStateMap> =
     once_cell::sync::Lazy::new(|| LoginStateMap::new());
 
+struct Application {
+    secret: oauth2::ApplicationSecret,
+    // refresh tokens are not stored without the key
+    cipher: Option<TokenCipher>,
+}
+
+static APPLICATION: OnceCell<Application> = OnceCell::new();
+// logins waiting for the user to confirm switching to another google account
+static SWITCH_CONFIRMATIONS: Lazy<DashMap<Uuid, oneshot::Sender<bool>>> = Lazy::new(DashMap::new);
+// access tokens are short-lived, so they are kept in memory only
+static ACCESS_TOKENS: Lazy<DashMap<i64, (String, i64)>> = Lazy::new(DashMap::new);
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+// credentials given by users are not stored without the key. told to the user.
+#[derive(Debug)]
+pub(crate) struct MissingTokenKey;
+
+impl std::fmt::Display for MissingTokenKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("봇 설정에 user.google_token_key가 없어 인증 정보를 저장할 수 없습니다. 관리자에게 문의해주세요.")
+    }
+}
+
+impl std::error::Error for MissingTokenKey {}
+
+fn token_cipher() -> Result<&'static TokenCipher, MissingTokenKey> {
+    APPLICATION
+        .get()
+        .and_then(|application| application.cipher.as_ref())
+        .ok_or(MissingTokenKey)
+}
+
+pub(crate) fn check_token_key() -> Result<(), MissingTokenKey> {
+    token_cipher().map(|_| ())
+}
+
+// stored credentials of other calendars can't be used without the key or with
+// another key. checked at startup not to take their users as unlinked.
+pub(super) async fn check_stored_secrets(db_pool: &SqlitePool) -> anyhow::Result<()> {
+    let stored = sqlx::query!(
+        r#"SELECT
+            CASE WHEN `caldav_password_encrypted` THEN `caldav_password` END AS "caldav_password?: String",
+            CASE WHEN `outlook_tokens_encrypted` THEN `outlook_refresh_token` END AS "outlook_refresh_token?: String"
+        FROM `users`
+        WHERE `caldav_password` IS NOT NULL
+            OR `outlook_access_token` IS NOT NULL
+            OR `outlook_refresh_token` IS NOT NULL"#
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get stored secrets")?;
+    if stored.is_empty() {
+        return Ok(());
+    }
+
+    let cipher = token_cipher().context(
+        "user.google_token_key is required to use stored caldav passwords and outlook tokens",
+    )?;
+    // all of them are encrypted with the same key
+    if let Some(encrypted) = stored.iter().find_map(|row| {
+        row.caldav_password
+            .as_ref()
+            .or(row.outlook_refresh_token.as_ref())
+    }) {
+        cipher
+            .decrypt(encrypted)
+            .context("user.google_token_key does not decrypt stored secrets")?;
+    }
+
+    Ok(())
+}
+
+// other credentials stored in the DB, such as passwords of calendars, share the key of google tokens
+pub(crate) fn encrypt_secret(secret: &str) -> anyhow::Result<String> {
+    token_cipher()?.encrypt(secret)
+}
+
+pub(crate) fn decrypt_secret(encrypted: &str) -> anyhow::Result<String> {
+    token_cipher()?.decrypt(encrypted)
+}
+
+async fn stored_refresh_token(
+    db_pool: &SqlitePool,
+    user_id: i64,
+) -> anyhow::Result<Option<String>> {
+    let encrypted = sqlx::query_scalar!(
+        "SELECT `google_refresh_token` FROM `users` WHERE `user_id` = ?",
+        user_id
+    )
+    .fetch_optional(db_pool)
+    .await
+    .context("Failed to get google refresh token from DB")?
+    .flatten();
+    let Some(encrypted) = encrypted else {
+        return Ok(None);
+    };
+
+    decrypt_secret(&encrypted).map(Some)
+}
+
+// valid access token of the user. refreshed with the stored refresh token when it is about to expire.
+pub(crate) async fn access_token(db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<String> {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(entry) = ACCESS_TOKENS.get(&user_id) {
+        let (access_token, expires_at) = entry.value();
+        if *expires_at > now + TOKEN_EXPIRY_MARGIN_SECS {
+            return Ok(access_token.clone());
+        }
+    }
+
+    let refresh_token = stored_refresh_token(db_pool, user_id)
+        .await?
+        .with_context(|| format!("Google token of user({user_id}) is not stored"))?;
+    let application = APPLICATION.get().context("Google is not configured")?;
+    info!("Refresh google token of user({user_id})");
+    let token: TokenResponse = reqwest::Client::new()
+        .post(&application.secret.token_uri)
+        .form(&[
+            ("client_id", application.secret.client_id.as_str()),
+            ("client_secret", application.secret.client_secret.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ])
+        .send()
+        .await
+        .context("Failed to send token request")?
+        .error_for_status()
+        .context("Token request is rejected")?
+        .json()
+        .await
+        .context("Failed to parse token response")?;
+
+    ACCESS_TOKENS.insert(
+        user_id,
+        (token.access_token.clone(), now + token.expires_in),
+    );
+    Ok(token.access_token)
+}
+
+// calendar hub acting as the user. it should not be kept longer than the access token lives.
+pub(crate) async fn user_calendar_hub(
+    db_pool: &SqlitePool,
+    user_id: i64,
+) -> anyhow::Result<UserCalendarHub> {
+    let access_token = access_token(db_pool, user_id).await?;
+    let auth = oauth2::AccessTokenAuthenticator::builder(access_token)
+        .build()
+        .await
+        .context("Failed to build access token auth")?;
+
+    Ok(CalendarHub::new(
+        hyper::Client::builder().build(
+            hyper_rustls::HttpsConnectorBuilder::new()
+                .with_native_roots()
+                .https_or_http()
+                .enable_http1()
+                .build(),
+        ),
+        auth,
+    ))
+}
+
+async fn store_refresh_token(
+    db_pool: &SqlitePool,
+    user_id: i64,
+    token: &oauth2::storage::TokenInfo,
+) -> anyhow::Result<()> {
+    if let (Some(access_token), Some(expires_at)) = (&token.access_token, token.expires_at) {
+        ACCESS_TOKENS.insert(user_id, (access_token.clone(), expires_at.unix_timestamp()));
+    }
+    // google returns refresh token only on the first consent
+    let Some(refresh_token) = &token.refresh_token else {
+        return Ok(());
+    };
+    let Some(cipher) = APPLICATION
+        .get()
+        .and_then(|application| application.cipher.as_ref())
+    else {
+        warn!("Google token key is not configured. Refresh token of user({user_id}) is dropped");
+        return Ok(());
+    };
+
+    let encrypted = cipher.encrypt(refresh_token)?;
+    sqlx::query!(
+        "UPDATE `users` SET `google_refresh_token` = ? WHERE `user_id` = ?",
+        encrypted,
+        user_id
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to store google refresh token to DB")?;
+
+    Ok(())
+}
+
+async fn revoke_token(token: &str) -> anyhow::Result<()> {
+    reqwest::Client::new()
+        .post(REVOKE_URL)
+        .form(&[("token", token)])
+        .send()
+        .await
+        .context("Failed to send revoke request")?
+        .error_for_status()
+        .context("Revoke request is rejected")?;
+
+    Ok(())
+}
+
+// revokes the token and forgets the account of the user
+async fn release_account(db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<()> {
+    // revoking is best effort. the token is forgotten anyway.
+    match stored_refresh_token(db_pool, user_id).await {
+        Ok(Some(refresh_token)) => {
+            if let Err(e) = revoke_token(&refresh_token).await {
+                warn!("Failed to revoke google token of user({user_id}) - {e:?}");
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read google token of user({user_id}) - {e:?}"),
+    }
+    ACCESS_TOKENS.remove(&user_id);
+
+    let mut tx = db_pool.begin().await?;
+    // synced events are left in the calendar, only mappings of them are forgotten
+    sqlx::query!(
+        "DELETE FROM `server_events`
+        WHERE `user_id` = ? AND EXISTS (
+            SELECT 1 FROM `users` WHERE `user_id` = ? AND `calendar_backend` = 'google'
+        )",
+        user_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to delete synced events in DB")?;
+    sqlx::query!(
+        "DELETE FROM `google_sync_queue`
+        WHERE `user_id` = ? AND EXISTS (
+            SELECT 1 FROM `users` WHERE `user_id` = ? AND `calendar_backend` = 'google'
+        )",
+        user_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to delete pending google syncs in DB")?;
+    sqlx::query!(
+        "UPDATE `users`
+        SET
+            `google_email` = NULL,
+            `google_calendar_id` = NULL,
+            `google_calendar_acl_id` = NULL,
+            `google_refresh_token` = NULL
+        WHERE `user_id` = ?",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to clear google account of user")?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// the owner token is gone after releasing, so sharing of the previous calendar is removed first
+async fn remove_previous_access(db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<()> {
+    let record = sqlx::query!(
+        "SELECT `google_calendar_id`, `google_calendar_acl_id` FROM `users` WHERE `user_id` = ?",
+        user_id
+    )
+    .fetch_one(db_pool)
+    .await
+    .context("Failed to get previous calendar from DB")?;
+    if let (Some(calendar_id), Some(acl_id)) =
+        (record.google_calendar_id, record.google_calendar_acl_id)
+    {
+        user_calendar_hub(db_pool, user_id)
+            .await?
+            .acl()
+            .delete(&calendar_id, &acl_id)
+            .doit()
+            .await
+            .context("Failed to delete ACL of previous calendar")?;
+    }
+
+    Ok(())
+}
+
+// asks the user on the login message whether to replace the previous account
+async fn confirm_account_switch(
+    http: impl AsRef<Http>,
+    response_message: &ApplicationCommandInteraction,
+    id: Uuid,
+    previous_email: &str,
+) -> anyhow::Result<bool> {
+    let (sender, receiver) = oneshot::channel();
+    SWITCH_CONFIRMATIONS.insert(id, sender);
+    response_message
+        .edit_original_interaction_response(http, |b| {
+            b.content(format!(
+                "이미 {previous_email} 계정이 연결되어 있습니다. 새 계정으로 바꾸면 기존 캘린더 동기화가 중단되고 새 캘린더로 다시 동기화됩니다."
+            ))
+            .components(|b| {
+                b.create_action_row(|b| {
+                    b.create_button(|b| {
+                        b.label("새 계정으로 변경")
+                            .style(ButtonStyle::Danger)
+                            .custom_id(format!("{SWITCH_ACCOUNT_CONFIRM}{id}"))
+                    })
+                    .create_button(|b| {
+                        b.label("기존 계정 유지")
+                            .style(ButtonStyle::Secondary)
+                            .custom_id(format!("{SWITCH_ACCOUNT_CANCEL}{id}"))
+                    })
+                })
+            })
+        })
+        .await
+        .context("Failed to ask switching google account")?;
+
+    let confirmed = tokio::time::timeout(
+        std::time::Duration::from_secs(SWITCH_ACCOUNT_TIMEOUT_SECS),
+        receiver,
+    )
+    .await;
+    SWITCH_CONFIRMATIONS.remove(&id);
+
+    Ok(matches!(confirmed, Ok(Ok(true))))
+}
+
+// answer of the user from the buttons. false if the login is already gone.
+pub(super) fn answer_account_switch(id: Uuid, confirmed: bool) -> bool {
+    SWITCH_CONFIRMATIONS
+        .remove(&id)
+        .map_or(false, |(_, sender)| sender.send(confirmed).is_ok())
+}
+
 pub struct GoogleUserHandler {
     secret: oauth2::ApplicationSecret,
     redirect_prefix: String,
     service_account: google_calendar3::oauth2::ServiceAccountKey,
     pub(super) calendar_name: OnceCell<String>,
-    pub(super) key_store: Arc<BTreeMap<String, RsaVerifying>>,
+    pub(super) key_store: GoogleKeyStore,
 }
 
 impl GoogleUserHandler {
@@ -149,6 +591,7 @@ impl GoogleUserHandler {
         application_secret_path: &str,
         service_account_key_path: &str,
         redirect_prefix: &str,
+        token_key: Option<&str>,
     ) -> anyhow::Result<Self> {
         let service_account =
             google_calendar3::oauth2::read_service_account_key(service_account_key_path)
@@ -157,23 +600,30 @@ impl GoogleUserHandler {
         let secret = google_calendar3::oauth2::read_application_secret(application_secret_path)
             .await
             .context("Failed to read application secret")?;
+        let cipher = token_key
+            .map(TokenCipher::new)
+            .transpose()
+            .context("Invalid google token key")?;
+        let _ = APPLICATION.set(Application {
+            secret: secret.clone(),
+            cipher,
+        });
 
         Ok(Self {
             secret,
             service_account,
             redirect_prefix: redirect_prefix.to_string(),
             calendar_name: OnceCell::new(),
-            key_store: Arc::new(
-                fetch_google_key_store()
-                    .await
-                    .context("Failed to fetch google key store")?,
-            ),
+            key_store: GoogleKeyStore::new()
+                .await
+                .context("Failed to fetch google key store")?,
         })
     }
 
     pub async fn auth(
         &self,
         user_id: UserId,
+        calendar_name: Option<String>,
         db_pool: SqlitePool,
         context: impl AsRef<Http> + Send + 'static,
         response_message: ApplicationCommandInteraction,
@@ -188,10 +638,14 @@ impl GoogleUserHandler {
         let key_store = self.key_store.clone();
         let redirect_uri = format!("{}/user/google/login_callback", self.redirect_prefix);
         let service_account = self.service_account.client_email.clone();
-        let calendar_name = unsafe { self.calendar_name.get_unchecked() }.clone();
+        let server_name = unsafe { self.calendar_name.get_unchecked() }.clone();
 
         tokio::spawn(async move {
-            let result: anyhow::Result<()> = async move {
+            // the login message is updated after the login as well
+            let (http, login_message) = (&context, &response_message);
+            // false when the user keeps the previous account
+            let result: anyhow::Result<bool> = async move {
+                let captured_token = CapturedToken::default();
                 let auth = oauth2::InstalledFlowAuthenticator::builder(
                     secret,
                     oauth2::InstalledFlowReturnMethod::Interactive,
@@ -201,17 +655,14 @@ impl GoogleUserHandler {
                     redirect_uri,
                     context_id: id,
                 }))
+                .with_storage(Box::new(captured_token.clone()))
                 .build()
                 .await
                 .context("Failed to installed flow")?;
 
                 let (_subject, email) = {
-                    use jwt::VerifyWithStore;
-
                     let id_token = auth.id_token(CALENDAR_SCOPE).await.unwrap().unwrap();
-                    let mut claims: BTreeMap<String, serde_json::Value> = id_token
-                        .verify_with_store(key_store.as_ref())
-                        .context("jwt verification failed")?;
+                    let mut claims = key_store.verify(&id_token).await?;
                     (
                         claims
                             .remove("sub")
@@ -234,6 +685,35 @@ impl GoogleUserHandler {
                 log::info!("Login succeed {email}");
 
                 let raw_user_id = *user_id.as_u64() as i64;
+                let previous_email = sqlx::query_scalar!(
+                    "SELECT `google_email` FROM `users` WHERE `user_id` = ?",
+                    raw_user_id
+                )
+                .fetch_optional(&db_pool)
+                .await
+                .context("Failed to get previous google email from DB")?
+                .flatten();
+                if let Some(previous_email) = previous_email.filter(|previous| *previous != email) {
+                    if !confirm_account_switch(http, login_message, id, &previous_email).await? {
+                        info!("User({user_id}) keeps google account {previous_email}");
+                        // the new account is not used, so its grant is revoked as unlinking does
+                        let token = captured_token
+                            .get()
+                            .and_then(|token| token.refresh_token.or(token.access_token));
+                        if let Some(token) = token {
+                            if let Err(e) = revoke_token(&token).await {
+                                warn!("Failed to revoke google token of discarded account {email} - {e:?}");
+                            }
+                        }
+                        return Ok(false);
+                    }
+                    info!("User({user_id}) switches google account from {previous_email}");
+                    if let Err(e) = remove_previous_access(&db_pool, raw_user_id).await {
+                        info!("Previous calendar is left shared - {e:?}");
+                    }
+                    release_account(&db_pool, raw_user_id).await?;
+                }
+
                 sqlx::query!(
                     "UPDATE `users` SET `google_email` = ? WHERE `user_id` = ?",
                     email,
@@ -242,6 +722,9 @@ impl GoogleUserHandler {
                 .execute(&db_pool)
                 .await
                 .context("Failed to store google email to DB")?;
+                if let Some(token) = captured_token.get() {
+                    store_refresh_token(&db_pool, raw_user_id, &token).await?;
+                }
 
                 let calendar_hub = CalendarHub::new(
                     hyper::Client::builder().build(
@@ -287,9 +770,36 @@ impl GoogleUserHandler {
                     (None, None)
                 };
 
+                if let Some(calendar_name) = &calendar_name {
+                    CALENDAR_NAME
+                        .set(&db_pool, raw_user_id, calendar_name)
+                        .await?;
+                }
                 let calendar_id = if let Some(calendar_id) = calendar_id {
+                    if let Some(calendar_name) = calendar_name {
+                        info!("Rename calendar {calendar_id}");
+                        calendar_hub
+                            .calendars()
+                            .patch(
+                                Calendar {
+                                    summary: Some(calendar_name),
+                                    ..Default::default()
+                                },
+                                &calendar_id,
+                            )
+                            .doit()
+                            .await
+                            .context("Failed to rename calendar")?;
+                    }
                     calendar_id
                 } else {
+                    let calendar_name = match calendar_name {
+                        Some(calendar_name) => calendar_name,
+                        None => CALENDAR_NAME
+                            .get(&db_pool, raw_user_id)
+                            .await?
+                            .unwrap_or(server_name),
+                    };
                     info!("Create new calendar");
                     calendar_hub
                         .calendars()
@@ -344,35 +854,132 @@ impl GoogleUserHandler {
                 .await
                 .context("Failed to save calendar data into DB")?;
 
-                Ok(())
+                Ok(true)
             }
             .await;
 
-            if let Err(e) = result {
-                error!("Error occurred while login - {e:?}");
-                if let Err(e) = response_message
-                    .create_interaction_response(context, |b| {
-                        b.kind(InteractionResponseType::DeferredUpdateMessage)
-                            .interaction_response_data(|b| b.content("실패").ephemeral(true))
-                    })
-                    .await
-                {
-                    error!("Failed to update response - {e:?}");
+            let content = match result {
+                Ok(true) => "완료",
+                Ok(false) => "기존 계정을 유지합니다.",
+                Err(e) => {
+                    error!("Error occurred while login - {e:?}");
+                    "실패"
                 }
-            } else {
-                if let Err(e) = response_message
-                    .create_interaction_response(context, |b| {
-                        b.kind(InteractionResponseType::DeferredUpdateMessage)
-                            .interaction_response_data(|b| b.content("완료").ephemeral(true))
-                    })
+            };
+            if let Err(e) = response_message
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::DeferredUpdateMessage)
+                        .interaction_response_data(|b| b.content(content).ephemeral(true))
+                })
+                .await
+            {
+                error!("Failed to update response - {e:?}");
+            }
+        });
+
+        url_receiver.await.context("Url")
+    }
+}
+
+pub enum Unlinked {
+    NotLinked,
+    Unlinked,
+    // the calendar is still shared with the service account
+    AccessLeft,
+}
+
+impl GoogleUserHandler {
+    pub async fn unlink(
+        &self,
+        db_pool: &SqlitePool,
+        user_id: UserId,
+        remove_access: bool,
+    ) -> anyhow::Result<Unlinked> {
+        let raw_user_id = *user_id.as_u64() as i64;
+        let Some(record) = sqlx::query!(
+            "SELECT `google_email`, `google_calendar_id`, `google_calendar_acl_id`
+            FROM `users`
+            WHERE `user_id` = ?",
+            raw_user_id
+        )
+        .fetch_optional(db_pool)
+        .await
+        .context("Failed to get google account from DB")?
+        else {
+            return Ok(Unlinked::NotLinked);
+        };
+        if record.google_email.is_none() && record.google_calendar_id.is_none() {
+            return Ok(Unlinked::NotLinked);
+        }
+
+        let access_removed = match (&record.google_calendar_id, &record.google_calendar_acl_id) {
+            (Some(calendar_id), Some(acl_id)) if remove_access => {
+                match self
+                    .remove_access(db_pool, raw_user_id, calendar_id, acl_id)
                     .await
                 {
-                    error!("Failed to update response - {e:?}");
+                    Ok(()) => true,
+                    Err(e) => {
+                        info!("Failed to remove ACL of calendar({calendar_id}) - {e:?}");
+                        false
+                    }
                 }
             }
-        });
+            (Some(_), Some(_)) => false,
+            _ => true,
+        };
 
-        url_receiver.await.context("Url")
+        release_account(db_pool, raw_user_id).await?;
+
+        info!("Unlinked google account of user({user_id})");
+        Ok(if access_removed {
+            Unlinked::Unlinked
+        } else {
+            Unlinked::AccessLeft
+        })
+    }
+
+    pub(super) async fn service_account_hub(&self) -> anyhow::Result<UserCalendarHub> {
+        let auth = oauth2::ServiceAccountAuthenticator::builder(self.service_account.clone())
+            .build()
+            .await
+            .context("Failed to get service account auth")?;
+
+        Ok(CalendarHub::new(
+            hyper::Client::builder().build(
+                hyper_rustls::HttpsConnectorBuilder::new()
+                    .with_native_roots()
+                    .https_or_http()
+                    .enable_http1()
+                    .build(),
+            ),
+            auth,
+        ))
+    }
+
+    // the owner can always edit sharing, but the service account may not be allowed to
+    async fn remove_access(
+        &self,
+        db_pool: &SqlitePool,
+        user_id: i64,
+        calendar_id: &str,
+        acl_id: &str,
+    ) -> anyhow::Result<()> {
+        let calendar_hub = match user_calendar_hub(db_pool, user_id).await {
+            Ok(calendar_hub) => calendar_hub,
+            Err(e) => {
+                info!("Remove ACL with service account - {e:?}");
+                self.service_account_hub().await?
+            }
+        };
+        calendar_hub
+            .acl()
+            .delete(calendar_id, acl_id)
+            .doit()
+            .await
+            .context("Failed to delete ACL of calendar")?;
+
+        Ok(())
     }
 }
 
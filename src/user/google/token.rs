@@ -0,0 +1,72 @@
+use std::sync::{Arc, Mutex};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use anyhow::Context as _;
+use async_trait::async_trait;
+use google_calendar3::oauth2::storage::{TokenInfo, TokenStorage};
+
+const NONCE_LENGTH: usize = 12;
+
+// refresh tokens are stored as base64url(nonce || ciphertext)
+pub(super) struct TokenCipher(Aes256Gcm);
+
+impl TokenCipher {
+    // `key` is base64url encoded 32 bytes
+    pub fn new(key: &str) -> anyhow::Result<Self> {
+        let key = base64_url::decode(key).context("Token key is not valid base64url")?;
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|_| anyhow::anyhow!("Token key should be 32 bytes"))?;
+
+        Ok(Self(cipher))
+    }
+
+    pub fn encrypt(&self, token: &str) -> anyhow::Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, token.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt token"))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(base64_url::encode(&data))
+    }
+
+    pub fn decrypt(&self, encrypted: &str) -> anyhow::Result<String> {
+        let data = base64_url::decode(encrypted).context("Stored token is not valid base64url")?;
+        anyhow::ensure!(data.len() > NONCE_LENGTH, "Stored token is too short");
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let token = self
+            .0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt token. Token key might be changed"))?;
+
+        String::from_utf8(token).context("Decrypted token is not utf-8")
+    }
+}
+
+// keeps the token of the login flow to take its refresh token out after login
+#[derive(Default, Clone)]
+pub(super) struct CapturedToken(Arc<Mutex<Option<TokenInfo>>>);
+
+impl CapturedToken {
+    pub fn get(&self) -> Option<TokenInfo> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl TokenStorage for CapturedToken {
+    // login flow requests a single set of scopes
+    async fn set(&self, _scopes: &[&str], token: TokenInfo) -> anyhow::Result<()> {
+        *self.0.lock().unwrap() = Some(token);
+        Ok(())
+    }
+
+    async fn get(&self, _scopes: &[&str]) -> Option<TokenInfo> {
+        CapturedToken::get(self)
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Context as _;
+use log::info;
+use serenity::{
+    model::{
+        application::interaction::InteractionResponseType,
+        prelude::{
+            interaction::application_command::{ApplicationCommandInteraction, CommandDataOption},
+            Permissions,
+        },
+    },
+    prelude::Context,
+};
+
+use super::{
+    google::{user_calendar_hub, UserCalendarHub},
+    DiscordHandler,
+};
+
+// discord rejects longer messages
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+fn is_not_found(error: &google_calendar3::Error) -> bool {
+    matches!(error, google_calendar3::Error::BadRequest(body) if body["error"]["code"] == 404)
+}
+
+enum CalendarHealth {
+    Ok,
+    // the calendar is deleted, or not shared with the service account anymore
+    Unreachable,
+    AclMissing,
+    Unknown(String),
+}
+
+impl DiscordHandler {
+    // the owner can tell a deleted calendar from a missing ACL, the service account can't
+    async fn probe_calendar(
+        &self,
+        service_account_hub: &UserCalendarHub,
+        user_id: i64,
+        calendar_id: &str,
+        acl_id: Option<&str>,
+    ) -> CalendarHealth {
+        if let Ok(owner_hub) = user_calendar_hub(&self.db_pool, user_id).await {
+            if let Err(e) = owner_hub.calendars().get(calendar_id).doit().await {
+                return if is_not_found(&e) {
+                    CalendarHealth::Unreachable
+                } else {
+                    CalendarHealth::Unknown(format!("{e}"))
+                };
+            }
+            let Some(acl_id) = acl_id else {
+                return CalendarHealth::AclMissing;
+            };
+            return match owner_hub.acl().get(calendar_id, acl_id).doit().await {
+                Ok(_) => CalendarHealth::Ok,
+                Err(e) if is_not_found(&e) => CalendarHealth::AclMissing,
+                Err(e) => CalendarHealth::Unknown(format!("{e}")),
+            };
+        }
+
+        match service_account_hub
+            .calendars()
+            .get(calendar_id)
+            .doit()
+            .await
+        {
+            Ok(_) if acl_id.is_none() => CalendarHealth::AclMissing,
+            Ok(_) => CalendarHealth::Ok,
+            Err(e) if is_not_found(&e) => CalendarHealth::Unreachable,
+            Err(e) => CalendarHealth::Unknown(format!("{e}")),
+        }
+    }
+
+    pub(super) async fn handle_links_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let is_admin = interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .map_or(false, |permissions| {
+                permissions.contains(Permissions::MANAGE_GUILD)
+            });
+        if !is_admin {
+            interaction
+                .create_interaction_response(context, |b| {
+                    b.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|b| {
+                            b.content("서버 관리 권한이 필요합니다.").ephemeral(true)
+                        })
+                })
+                .await?;
+            return Ok(());
+        }
+
+        // probing every calendar takes longer than the interaction deadline
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|b| b.ephemeral(true))
+            })
+            .await?;
+
+        let links = sqlx::query!(
+            r#"SELECT
+                `users`.`user_id`,
+                `users`.`google_email`,
+                `users`.`google_calendar_id`,
+                `users`.`google_calendar_acl_id`,
+                `users`.`calendar_backend`,
+                `users`.`caldav_url` IS NOT NULL AS "caldav_linked!: bool",
+                `users`.`outlook_refresh_token` IS NOT NULL AS "outlook_linked!: bool",
+                (
+                    SELECT COUNT(*) FROM `server_events`
+                    WHERE `server_events`.`user_id` = `users`.`user_id`
+                ) AS "mirrored!: i64"
+            FROM `users`
+            WHERE
+                `google_email` IS NOT NULL
+                OR `google_calendar_id` IS NOT NULL
+                OR `caldav_url` IS NOT NULL
+                OR `outlook_refresh_token` IS NOT NULL
+            ORDER BY `users`.`user_id`"#
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to get linked users")?;
+
+        let service_account_hub = self.google.service_account_hub().await?;
+        let mut lines = Vec::new();
+        let mut broken = 0;
+        for link in &links {
+            let mut services = Vec::new();
+            if link.google_email.is_some() || link.google_calendar_id.is_some() {
+                services.push("Google");
+            }
+            if link.outlook_linked {
+                services.push("Outlook");
+            }
+            if link.caldav_linked {
+                services.push("CalDAV");
+            }
+            let mut line = format!(
+                "<@{}> {} · 동기화 {} · 이벤트 {}개",
+                link.user_id,
+                services.join(", "),
+                link.calendar_backend,
+                link.mirrored
+            );
+
+            if let Some(calendar_id) = &link.google_calendar_id {
+                let health = self
+                    .probe_calendar(
+                        &service_account_hub,
+                        link.user_id,
+                        calendar_id,
+                        link.google_calendar_acl_id.as_deref(),
+                    )
+                    .await;
+                let problem = match health {
+                    CalendarHealth::Ok => None,
+                    CalendarHealth::Unreachable => Some("캘린더에 접근할 수 없음".to_string()),
+                    CalendarHealth::AclMissing => Some("공유 설정 없음".to_string()),
+                    CalendarHealth::Unknown(e) => {
+                        info!("Failed to probe calendar of user({}) - {e}", link.user_id);
+                        Some("확인 실패".to_string())
+                    }
+                };
+                if let Some(problem) = problem {
+                    broken += 1;
+                    line.push_str(&format!(" ⚠️ {problem}"));
+                }
+            }
+            lines.push(line);
+        }
+
+        let mut content = format!("연결된 사용자 {}명 · 문제 {broken}명", links.len());
+        for line in lines {
+            if content.len() + line.len() + 1 > MAX_MESSAGE_LENGTH {
+                break;
+            }
+            content.push('\n');
+            content.push_str(&line);
+        }
+
+        interaction
+            .edit_original_interaction_response(&context.http, |b| b.content(content))
+            .await?;
+
+        Ok(())
+    }
+}
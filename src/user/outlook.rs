@@ -0,0 +1,319 @@
+use anyhow::Context as _;
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use log::{error, info};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Deserialize;
+use serenity::{
+    http::Http,
+    model::{
+        application::interaction::application_command::ApplicationCommandInteraction, id::UserId,
+    },
+};
+use sqlx::SqlitePool;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::google::{decrypt_secret, encrypt_secret};
+
+const AUTHORIZE_URL: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/authorize";
+const TOKEN_URL: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/token";
+const CALENDAR_SCOPE: &str = "offline_access User.Read Calendars.ReadWrite";
+const LOGIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+// refresh a bit early not to expire in the middle of a sync
+const TOKEN_EXPIRY_MARGIN_SECS: i64 = 60;
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Config {
+    client_id: String,
+    client_secret: String,
+}
+
+type LoginStateMap = DashMap<Uuid, oneshot::Sender<String>>;
+
+static LOGIN_STATE: Lazy<LoginStateMap> = Lazy::new(LoginStateMap::new);
+// tokens are refreshed by calendar syncs of the events module
+static APPLICATION: OnceCell<Config> = OnceCell::new();
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    // only returned when rotated
+    refresh_token: Option<String>,
+    expires_in: i64,
+}
+
+async fn request_token(config: &Config, grant: &[(&str, &str)]) -> anyhow::Result<TokenResponse> {
+    let mut form = vec![
+        ("client_id", config.client_id.as_str()),
+        ("client_secret", config.client_secret.as_str()),
+        ("scope", CALENDAR_SCOPE),
+    ];
+    form.extend_from_slice(grant);
+
+    reqwest::Client::new()
+        .post(TOKEN_URL)
+        .form(&form)
+        .send()
+        .await
+        .context("Failed to send token request")?
+        .error_for_status()
+        .context("Token request is rejected")?
+        .json()
+        .await
+        .context("Failed to parse token response")
+}
+
+// tokens are stored encrypted with the key of google tokens
+async fn store_token(
+    executor: impl sqlx::SqliteExecutor<'_>,
+    user_id: i64,
+    token: &TokenResponse,
+) -> anyhow::Result<()> {
+    let expires_at = chrono::Utc::now().timestamp() + token.expires_in;
+    let access_token = encrypt_secret(&token.access_token)?;
+    let refresh_token = token
+        .refresh_token
+        .as_deref()
+        .map(encrypt_secret)
+        .transpose()?;
+    let result = sqlx::query!(
+        "UPDATE `users`
+        SET
+            `outlook_access_token` = ?,
+            `outlook_refresh_token` = COALESCE(?, `outlook_refresh_token`),
+            `outlook_token_expires_at` = ?,
+            `outlook_tokens_encrypted` = TRUE
+        WHERE `user_id` = ?",
+        access_token,
+        refresh_token,
+        expires_at,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to store outlook token to DB")?;
+    anyhow::ensure!(
+        result.rows_affected() > 0,
+        "User({user_id}) to store outlook token is not found"
+    );
+
+    Ok(())
+}
+
+// tokens stored in plain text before they were encrypted
+pub(super) async fn encrypt_plain_tokens(db_pool: &SqlitePool) -> anyhow::Result<()> {
+    let users = sqlx::query!(
+        r#"SELECT `user_id` AS "user_id!: i64", `outlook_access_token`, `outlook_refresh_token`
+        FROM `users`
+        WHERE NOT `outlook_tokens_encrypted`
+            AND (`outlook_access_token` IS NOT NULL OR `outlook_refresh_token` IS NOT NULL)"#
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get plain outlook tokens")?;
+
+    for user in users {
+        let access_token = user
+            .outlook_access_token
+            .as_deref()
+            .map(encrypt_secret)
+            .transpose()?;
+        let refresh_token = user
+            .outlook_refresh_token
+            .as_deref()
+            .map(encrypt_secret)
+            .transpose()?;
+        sqlx::query!(
+            "UPDATE `users`
+            SET `outlook_access_token` = ?, `outlook_refresh_token` = ?, `outlook_tokens_encrypted` = TRUE
+            WHERE `user_id` = ?",
+            access_token,
+            refresh_token,
+            user.user_id
+        )
+        .execute(db_pool)
+        .await
+        .context("Failed to store encrypted outlook tokens")?;
+    }
+
+    Ok(())
+}
+
+// valid access token of the user. refreshed when it is about to expire.
+pub(crate) async fn access_token(db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<String> {
+    let record = sqlx::query!(
+        "SELECT `outlook_access_token`, `outlook_refresh_token`, `outlook_token_expires_at`
+        FROM `users`
+        WHERE `user_id` = ?",
+        user_id
+    )
+    .fetch_one(db_pool)
+    .await
+    .context("Failed to get outlook token from DB")?;
+
+    let now = chrono::Utc::now().timestamp();
+    if let (Some(access_token), Some(expires_at)) =
+        (record.outlook_access_token, record.outlook_token_expires_at)
+    {
+        if expires_at > now + TOKEN_EXPIRY_MARGIN_SECS {
+            return decrypt_secret(&access_token);
+        }
+    }
+
+    let refresh_token = record
+        .outlook_refresh_token
+        .with_context(|| format!("Outlook is not linked to user({user_id})"))?;
+    let refresh_token = decrypt_secret(&refresh_token)?;
+    let config = APPLICATION.get().context("Outlook is not configured")?;
+    info!("Refresh outlook token of user({user_id})");
+    let token = request_token(
+        config,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+        ],
+    )
+    .await?;
+    store_token(db_pool, user_id, &token).await?;
+
+    Ok(token.access_token)
+}
+
+pub struct OutlookUserHandler {
+    config: Config,
+    redirect_uri: String,
+}
+
+impl OutlookUserHandler {
+    pub fn new(config: &Config, redirect_prefix: &str) -> Self {
+        let _ = APPLICATION.set(config.clone());
+
+        Self {
+            config: config.clone(),
+            redirect_uri: format!("{redirect_prefix}/user/outlook/login_callback"),
+        }
+    }
+
+    pub async fn auth(
+        &self,
+        user_id: UserId,
+        db_pool: SqlitePool,
+        context: impl AsRef<Http> + Send + 'static,
+        response_message: ApplicationCommandInteraction,
+    ) -> anyhow::Result<String> {
+        let (code_sender, code_receiver) = oneshot::channel();
+        let id = Uuid::new_v4();
+        LOGIN_STATE.insert(id, code_sender);
+
+        let url = reqwest::Url::parse_with_params(
+            AUTHORIZE_URL,
+            &[
+                ("client_id", self.config.client_id.as_str()),
+                ("response_type", "code"),
+                ("response_mode", "query"),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", CALENDAR_SCOPE),
+                ("state", &id.to_string()),
+            ],
+        )
+        .context("Failed to build authorize url")?;
+
+        let config = self.config.clone();
+        let redirect_uri = self.redirect_uri.clone();
+        tokio::spawn(async move {
+            let result: anyhow::Result<()> = async move {
+                let code = match tokio::time::timeout(LOGIN_TIMEOUT, code_receiver).await {
+                    Ok(code) => code.context("Login is canceled")?,
+                    Err(_) => {
+                        LOGIN_STATE.remove(&id);
+                        anyhow::bail!("Login is timed out");
+                    }
+                };
+
+                let token = request_token(
+                    &config,
+                    &[
+                        ("grant_type", "authorization_code"),
+                        ("code", &code),
+                        ("redirect_uri", &redirect_uri),
+                    ],
+                )
+                .await?;
+
+                info!("Outlook login succeed {user_id}");
+                let raw_user_id = *user_id.as_u64() as i64;
+                let mut tx = db_pool.begin().await?;
+                crate::events::DiscordHandler::forget_events_of_other_backend(
+                    &mut tx,
+                    raw_user_id,
+                    "outlook",
+                )
+                .await?;
+                store_token(&mut *tx, raw_user_id, &token).await?;
+                sqlx::query!(
+                    "UPDATE `users` SET `calendar_backend` = 'outlook' WHERE `user_id` = ?",
+                    raw_user_id
+                )
+                .execute(&mut *tx)
+                .await
+                .context("Failed to store calendar backend to DB")?;
+                tx.commit().await?;
+
+                Ok(())
+            }
+            .await;
+
+            let content = if let Err(e) = result {
+                error!("Error occurred while outlook login - {e:?}");
+                "실패"
+            } else {
+                "완료"
+            };
+            if let Err(e) = response_message
+                .edit_original_interaction_response(context, |b| {
+                    b.content(content).components(|b| b)
+                })
+                .await
+            {
+                error!("Failed to update response - {e:?}");
+            }
+        });
+
+        Ok(url.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginCallbackQuery {
+    state: Uuid,
+    // missing when the user declined the consent
+    code: Option<String>,
+}
+
+async fn login_callback(Query(query): Query<LoginCallbackQuery>) -> Response {
+    let Some((_, code_sender)) = LOGIN_STATE.remove(&query.state) else {
+        log::debug!("Invalid request");
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    if let Some(code) = query.code {
+        if code_sender.send(code).is_err() {
+            log::debug!("Login is already finished");
+            return StatusCode::GONE.into_response();
+        }
+        log::debug!("Successfully logged in");
+        "Done. Close this page".into_response()
+    } else {
+        // dropping the sender fails the login
+        "Canceled. Close this page".into_response()
+    }
+}
+
+pub fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new().route("/login_callback", axum::routing::get(login_callback))
+}
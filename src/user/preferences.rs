@@ -0,0 +1,110 @@
+use std::marker::PhantomData;
+
+use anyhow::Context as _;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::SqlitePool;
+
+// a typed key of `user_preferences`. modules declare their own keys prefixed with the module name.
+pub(crate) struct Preference<T> {
+    key: &'static str,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Preference<T> {
+    pub(crate) const fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            _value: PhantomData,
+        }
+    }
+
+    pub(crate) async fn get(
+        &self,
+        db_pool: &SqlitePool,
+        user_id: i64,
+    ) -> anyhow::Result<Option<T>> {
+        let value = sqlx::query_scalar!(
+            "SELECT `value` FROM `user_preferences` WHERE `user_id` = ? AND `key` = ?",
+            user_id,
+            self.key
+        )
+        .fetch_optional(db_pool)
+        .await
+        .with_context(|| format!("Failed to get preference({})", self.key))?;
+
+        value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()
+            .with_context(|| format!("Invalid value of preference({})", self.key))
+    }
+
+    pub(crate) async fn set(
+        &self,
+        db_pool: &SqlitePool,
+        user_id: i64,
+        value: &T,
+    ) -> anyhow::Result<()> {
+        let value = serde_json::to_string(value)
+            .with_context(|| format!("Failed to serialize preference({})", self.key))?;
+        sqlx::query!(
+            "INSERT INTO `user_preferences` (`user_id`, `key`, `value`) VALUES (?, ?, ?)
+            ON CONFLICT (`user_id`, `key`) DO UPDATE SET `value` = excluded.`value`",
+            user_id,
+            self.key,
+            value
+        )
+        .execute(db_pool)
+        .await
+        .with_context(|| format!("Failed to set preference({})", self.key))?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn remove(&self, db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<()> {
+        sqlx::query!(
+            "DELETE FROM `user_preferences` WHERE `user_id` = ? AND `key` = ?",
+            user_id,
+            self.key
+        )
+        .execute(db_pool)
+        .await
+        .with_context(|| format!("Failed to remove preference({})", self.key))?;
+
+        Ok(())
+    }
+}
+
+// every preference of the user, for `/user forget`
+pub(super) async fn remove_all(db_pool: &SqlitePool, user_id: i64) -> anyhow::Result<u64> {
+    Ok(sqlx::query!(
+        "DELETE FROM `user_preferences` WHERE `user_id` = ?",
+        user_id
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to delete preferences")?
+    .rows_affected())
+}
+
+// every preference of the user, for `/user export`
+pub(super) async fn get_all(
+    db_pool: &SqlitePool,
+    user_id: i64,
+) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+    let preferences = sqlx::query!(
+        "SELECT `key`, `value` FROM `user_preferences` WHERE `user_id` = ? ORDER BY `key`",
+        user_id
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get preferences")?;
+
+    preferences
+        .into_iter()
+        .map(|preference| {
+            let value = serde_json::from_str(&preference.value)
+                .with_context(|| format!("Invalid value of preference({})", preference.key))?;
+            Ok((preference.key, value))
+        })
+        .collect()
+}
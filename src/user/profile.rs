@@ -0,0 +1,160 @@
+use anyhow::Context as _;
+use serenity::{
+    model::{
+        application::interaction::InteractionResponseType,
+        prelude::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+    },
+    prelude::Context,
+};
+
+use super::{time_zone::DEFAULT_TIME_ZONE, DiscordHandler};
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+const VISIBLE_EMAIL_CHARS: usize = 2;
+
+// `abcdef@gmail.com` to `ab****@gmail.com`
+fn mask_email(email: &str) -> String {
+    let (local, domain) = email.split_once('@').unwrap_or((email, ""));
+    let visible: String = local.chars().take(VISIBLE_EMAIL_CHARS).collect();
+    let hidden = local.chars().count().saturating_sub(VISIBLE_EMAIL_CHARS);
+    if domain.is_empty() {
+        format!("{visible}{}", "*".repeat(hidden))
+    } else {
+        format!("{visible}{}@{domain}", "*".repeat(hidden))
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "예"
+    } else {
+        "아니오"
+    }
+}
+
+impl DiscordHandler {
+    pub(super) async fn handle_profile_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let guild_id = interaction
+            .guild_id
+            .context("Command is not used in guild")?;
+        let [user] = option.get_options(&["user"]);
+        let user_id: u64 = match user.as_str() {
+            Some(user) => user.parse().context("Invalid user")?,
+            None => interaction.user.id.0,
+        };
+        let is_self = user_id == interaction.user.id.0;
+        let raw_user_id = user_id as i64;
+
+        let record = sqlx::query!(
+            r#"SELECT
+                `users`.`count`,
+                `users`.`longest_streaks`,
+                `users`.`current_streaks`,
+                `users`.`first_date`,
+                `users`.`opted_out`,
+                `users`.`google_email`,
+                `users`.`google_calendar_id` IS NOT NULL AS "google_calendar_linked!: bool",
+                `users`.`calendar_backend`,
+                `users`.`time_zone`,
+                `users`.`caldav_url` IS NOT NULL AS "caldav_linked!: bool",
+                `users`.`outlook_refresh_token` IS NOT NULL AS "outlook_linked!: bool",
+                `teams`.`name` AS "team?",
+                `event_notify_preferences`.`dm` AS "notify_dm?: bool",
+                `event_notify_preferences`.`lead_minutes` AS "notify_lead_minutes?"
+            FROM `users`
+            LEFT JOIN `teams` ON `teams`.`user_id` = `users`.`user_id`
+            LEFT JOIN `event_notify_preferences`
+                ON `event_notify_preferences`.`user_id` = `users`.`user_id`
+            WHERE `users`.`user_id` = ?"#,
+            raw_user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .context("Failed to get user profile")?;
+        let member = guild_id.member(context, user_id).await.ok();
+
+        let name = member
+            .as_ref()
+            .map(|member| member.display_name().into_owned())
+            .unwrap_or_else(|| format!("<@{user_id}>"));
+        let joined_at = member
+            .as_ref()
+            .and_then(|member| member.joined_at)
+            .map(|joined_at| format!("<t:{}:D>", joined_at.unix_timestamp()))
+            .unwrap_or_else(|| "-".to_string());
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| {
+                        b.embed(|e| {
+                            e.title(format!("{name}의 프로필")).field(
+                                "서버 가입일",
+                                joined_at,
+                                false,
+                            );
+                            let Some(record) = &record else {
+                                return e.description("아직 기록이 없습니다.");
+                            };
+
+                            let google = match &record.google_email {
+                                Some(email) => format!(
+                                    "{}\n캘린더 연결: {}",
+                                    mask_email(email),
+                                    yes_no(record.google_calendar_linked)
+                                ),
+                                None => "연결 안 됨".to_string(),
+                            };
+                            e.field("구글", google, true)
+                                .field("Outlook", yes_no(record.outlook_linked), true)
+                                .field("CalDAV", yes_no(record.caldav_linked), true)
+                                .field("동기화 캘린더", &record.calendar_backend, true);
+
+                            // opted out users keep their records private from others
+                            if is_self || !record.opted_out {
+                                let mut eueoeo = format!(
+                                    "총 {}회\n최장 연속 {}일\n현재 연속 {}일",
+                                    record.count, record.longest_streaks, record.current_streaks
+                                );
+                                if let Some(first_date) = record.first_date {
+                                    eueoeo.push_str(&format!("\n첫 으어어 <t:{first_date}:D>"));
+                                }
+                                if let Some(team) = &record.team {
+                                    eueoeo.push_str(&format!("\n팀 {team}"));
+                                }
+                                e.field("으어어", eueoeo, false);
+                            }
+
+                            let notify = match (record.notify_dm, record.notify_lead_minutes) {
+                                (Some(dm), Some(lead_minutes)) => {
+                                    format!("DM 알림: {}\n{lead_minutes}분 전 알림", yes_no(dm))
+                                }
+                                _ => "기본값".to_string(),
+                            };
+                            e.field("으어어 기록 공개", yes_no(!record.opted_out), true)
+                                .field("이벤트 알림", notify, true)
+                                .field(
+                                    "시간대",
+                                    record
+                                        .time_zone
+                                        .as_deref()
+                                        .unwrap_or(DEFAULT_TIME_ZONE.name()),
+                                    true,
+                                )
+                        })
+                        .ephemeral(true)
+                    })
+            })
+            .await
+            .context("Failed to send profile")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::Context as _;
+use chrono_tz::Tz;
+use log::warn;
+use serenity::{
+    model::{
+        application::interaction::InteractionResponseType,
+        prelude::interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption,
+        },
+    },
+    prelude::Context,
+};
+use sqlx::SqlitePool;
+
+use super::DiscordHandler;
+use crate::discord::{CommandDataOptionHelper, CommandHelper};
+
+// the server has been running on KST
+pub(crate) const DEFAULT_TIME_ZONE: Tz = chrono_tz::Asia::Seoul;
+
+// time zone of the user to render times for. falls back to the default when it is not set.
+pub(crate) async fn user_time_zone(db_pool: &SqlitePool, user_id: i64) -> Tz {
+    let time_zone = sqlx::query_scalar!(
+        "SELECT `time_zone` FROM `users` WHERE `user_id` = ?",
+        user_id
+    )
+    .fetch_optional(db_pool)
+    .await;
+
+    match time_zone {
+        Ok(time_zone) => time_zone
+            .flatten()
+            .and_then(|time_zone| time_zone.parse().ok())
+            .unwrap_or(DEFAULT_TIME_ZONE),
+        Err(e) => {
+            warn!("Failed to get time zone of user({user_id}) - {e:?}");
+            DEFAULT_TIME_ZONE
+        }
+    }
+}
+
+impl DiscordHandler {
+    pub(super) async fn handle_time_zone_command(
+        &self,
+        context: &Context,
+        interaction: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> anyhow::Result<()> {
+        let user_id = *interaction.user.id.as_u64() as i64;
+        let [zone] = option.get_options(&["zone"]);
+
+        let content = match zone.as_str() {
+            None => format!(
+                "현재 시간대는 {} 입니다.",
+                user_time_zone(&self.db_pool, user_id).await
+            ),
+            Some(zone) => match zone.trim().parse::<Tz>() {
+                Ok(time_zone) => {
+                    let name = time_zone.name();
+                    let user_name = &interaction.user.name;
+                    sqlx::query!(
+                        "INSERT INTO `users` (`user_id`, `name`, `time_zone`) VALUES (?, ?, ?)
+                        ON CONFLICT (`user_id`) DO UPDATE SET `time_zone` = excluded.`time_zone`",
+                        user_id,
+                        user_name,
+                        name
+                    )
+                    .execute(&self.db_pool)
+                    .await
+                    .context("Failed to save time zone")?;
+                    format!("시간대를 {name}(으)로 설정했습니다.")
+                }
+                Err(_) => format!(
+                    "`{zone}`은(는) 알 수 없는 시간대입니다. `Asia/Seoul`처럼 IANA 시간대 이름을 입력해주세요."
+                ),
+            },
+        };
+
+        interaction
+            .create_interaction_response(context, |b| {
+                b.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|b| b.content(content).ephemeral(true))
+            })
+            .await
+            .context("Failed to update interaction response")?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
     sync::Arc,
 };
 
@@ -9,9 +9,42 @@ use log::info;
 use serde::Deserialize;
 use sqlx::SqlitePool;
 
+// read-only JSON API of statistics under `/api/v1`
+mod api;
+// `/healthz` and `/readyz` for the deployment to check and restart
+mod health;
+// pages for signed in users under `/dashboard`
+mod dashboard;
+// per-IP token bucket in front of every route but the health checks
+mod rate_limit;
+// `auth::Session` extracts the signed in user for personal pages
+pub(crate) mod auth;
+
 #[derive(Debug, Deserialize)]
 pub(crate) struct Config {
     pub(crate) domain: String,
+    // sign in with discord on `/auth/discord`. disabled without it.
+    #[serde(default)]
+    pub(crate) discord_oauth: Option<auth::Config>,
+    // requests are not limited without it
+    #[serde(default)]
+    rate_limit: Option<rate_limit::Config>,
+}
+
+// for texts put in pages rendered without templates
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 async fn root() -> &'static str {
@@ -21,6 +54,7 @@ async fn root() -> &'static str {
 pub(crate) async fn start(
     db_pool: SqlitePool,
     config: Arc<crate::Config>,
+    gateway_status: crate::discord::GatewayStatus,
     mut stop_signal: tokio::sync::broadcast::Receiver<()>,
 ) -> anyhow::Result<()> {
     let port: u16 = std::env::var("WEB_PORT")
@@ -29,9 +63,24 @@ pub(crate) async fn start(
         .unwrap_or(Ok(8000))
         .context("Failed to parse WEB_PORT")?;
 
-    let router = axum::Router::new()
+    let mut router = axum::Router::new()
         .route("/", get(root))
+        .nest("/auth", auth::web_router())
         .nest("/user", crate::user::web_router())
+        .nest("/events", crate::events::web_router())
+        .nest("/hooks", crate::hooks::web_router())
+        .nest("/api/v1", api::web_router())
+        .nest("/dashboard", dashboard::web_router());
+    if let Some(rate_limit) = &config.web.rate_limit {
+        router = router.layer(axum::middleware::from_fn_with_state(
+            rate_limit::RateLimiter::new(rate_limit.clone()),
+            rate_limit::limit,
+        ));
+    }
+    let router = router
+        .merge(health::web_router())
+        .layer(Extension(crate::stats::Stats::new(db_pool.clone())))
+        .layer(Extension(gateway_status))
         .layer(Extension(db_pool))
         .layer(Extension(config.clone()));
 
@@ -41,7 +90,7 @@ pub(crate) async fn start(
         tokio::net::TcpListener::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port))
             .await
             .unwrap(),
-        router.into_make_service(),
+        router.into_make_service_with_connect_info::<SocketAddr>(),
     )
     .with_graceful_shutdown(async move {
         let _ = stop_signal.recv().await;
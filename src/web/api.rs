@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json,
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::Stats;
+
+const DEFAULT_EVENT_LIMIT: i64 = 20;
+const MAX_EVENT_LIMIT: i64 = 100;
+
+fn json_response<T: Serialize>(result: anyhow::Result<T>) -> Response {
+    match result {
+        Ok(body) => Json(body).into_response(),
+        Err(e) => {
+            error!("Failed to serve API - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn eueoeo_total(Extension(stats): Extension<Stats>) -> Response {
+    json_response(stats.eueoeo_total().await)
+}
+
+async fn eueoeo_user(Extension(stats): Extension<Stats>, Path(user_id): Path<i64>) -> Response {
+    match stats.eueoeo_user(user_id).await {
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        result => json_response(result),
+    }
+}
+
+#[derive(Deserialize)]
+struct UpcomingQuery {
+    limit: Option<i64>,
+}
+
+async fn upcoming_events(
+    Extension(stats): Extension<Stats>,
+    Query(query): Query<UpcomingQuery>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_EVENT_LIMIT)
+        .clamp(1, MAX_EVENT_LIMIT);
+    json_response(stats.upcoming_events(limit).await)
+}
+
+// read-only, so no authentication is required
+pub(crate) fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new()
+        .route("/eueoeo/total", get(eueoeo_total))
+        .route("/eueoeo/users/:id", get(eueoeo_user))
+        .route("/events/upcoming", get(upcoming_events))
+}
@@ -0,0 +1,293 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use axum::{
+    async_trait,
+    extract::{Extension, FromRequestParts, Query},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json,
+};
+use dashmap::DashMap;
+use log::{error, info};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::jwt_util::SessionKey;
+
+const AUTHORIZE_URL: &str = "https://discord.com/oauth2/authorize";
+const TOKEN_URL: &str = "https://discord.com/api/oauth2/token";
+const CURRENT_USER_URL: &str = "https://discord.com/api/users/@me";
+const SESSION_COOKIE: &str = "futaba_session";
+// ties the login to the browser which started it
+const LOGIN_STATE_COOKIE: &str = "futaba_login_state";
+const SESSION_SECS: i64 = 7 * 24 * 60 * 60;
+const LOGIN_TIMEOUT_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Config {
+    client_secret: String,
+    // key to sign session tokens. sessions are invalidated when it is changed.
+    session_secret: String,
+}
+
+// pending logins and when they are started
+static LOGIN_STATE: Lazy<DashMap<Uuid, i64>> = Lazy::new(DashMap::new);
+
+fn redirect_uri(config: &crate::Config) -> String {
+    format!("https://{}/auth/discord/callback", config.web.domain)
+}
+
+fn session_key(config: &crate::Config) -> Option<SessionKey> {
+    let auth = config.web.discord_oauth.as_ref()?;
+    SessionKey::new(&auth.session_secret)
+        .map_err(|e| error!("Failed to create session key - {e:?}"))
+        .ok()
+}
+
+fn session_cookie(value: &str, max_age: i64) -> String {
+    format!("{SESSION_COOKIE}={value}; Path=/; Max-Age={max_age}; HttpOnly; Secure; SameSite=Lax")
+}
+
+// `Lax` as it is sent back on the redirect from discord
+fn login_state_cookie(value: &str, max_age: i64) -> String {
+    format!(
+        "{LOGIN_STATE_COOKIE}={value}; Path=/auth/discord/callback; Max-Age={max_age}; HttpOnly; Secure; SameSite=Lax"
+    )
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(cookie_name, _)| *cookie_name == name)
+        .map(|(_, value)| value)
+}
+
+// signed in discord user. rejects requests without a valid session.
+#[derive(Clone)]
+pub(crate) struct Session {
+    pub(crate) user_id: i64,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Session {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let config = parts
+            .extensions
+            .get::<Arc<crate::Config>>()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        let key = session_key(config).ok_or(StatusCode::NOT_FOUND)?;
+
+        cookie(&parts.headers, SESSION_COOKIE)
+            .and_then(|token| key.verify(token))
+            .map(|user_id| Session { user_id })
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+async fn login(Extension(config): Extension<Arc<crate::Config>>) -> Response {
+    if config.web.discord_oauth.is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    LOGIN_STATE.retain(|_, started_at| *started_at + LOGIN_TIMEOUT_SECS > now);
+    let state = Uuid::new_v4();
+    LOGIN_STATE.insert(state, now);
+
+    let client_id = config.discord.application_id.to_string();
+    match reqwest::Url::parse_with_params(
+        AUTHORIZE_URL,
+        &[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", redirect_uri(&config).as_str()),
+            ("scope", "identify"),
+            ("state", &state.to_string()),
+        ],
+    ) {
+        Ok(url) => (
+            [(
+                header::SET_COOKIE,
+                login_state_cookie(&state.to_string(), LOGIN_TIMEOUT_SECS),
+            )],
+            Redirect::to(url.as_str()),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to build discord authorize url - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    state: Uuid,
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct CurrentUser {
+    id: String,
+    username: String,
+}
+
+// `None` for users not in the guild
+async fn sign_in(
+    db_pool: &SqlitePool,
+    config: &crate::Config,
+    auth: &Config,
+    code: &str,
+) -> anyhow::Result<Option<String>> {
+    let client = reqwest::Client::new();
+    let client_id = config.discord.application_id.to_string();
+    let token: TokenResponse = client
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", auth.client_secret.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri(config).as_str()),
+        ])
+        .send()
+        .await
+        .context("Failed to send token request")?
+        .error_for_status()
+        .context("Token request is rejected")?
+        .json()
+        .await
+        .context("Failed to parse token response")?;
+    let user: CurrentUser = client
+        .get(CURRENT_USER_URL)
+        .bearer_auth(&token.access_token)
+        .send()
+        .await
+        .context("Failed to get current user")?
+        .error_for_status()
+        .context("Current user request is rejected")?
+        .json()
+        .await
+        .context("Failed to parse current user")?;
+    let user_id: i64 = user.id.parse().context("Invalid user id")?;
+    if !crate::discord::is_member(&config.discord, user_id as u64).await? {
+        info!("User({user_id}) not in the guild tried to sign in to web");
+        return Ok(None);
+    }
+
+    // sessions are always mapped to a `users` row
+    sqlx::query!(
+        "INSERT INTO `users` (`user_id`, `name`) VALUES (?, ?)
+        ON CONFLICT (`user_id`) DO NOTHING",
+        user_id,
+        user.username
+    )
+    .execute(db_pool)
+    .await
+    .context("Failed to map session to user")?;
+
+    info!("User({user_id}) signed in to web");
+    SessionKey::new(&auth.session_secret)?
+        .sign(user_id, chrono::Utc::now().timestamp() + SESSION_SECS)
+        .map(Some)
+}
+
+async fn callback(
+    Extension(db_pool): Extension<SqlitePool>,
+    Extension(config): Extension<Arc<crate::Config>>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Response {
+    let Some(auth) = &config.web.discord_oauth else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // logins started by others are rejected not to sign in as them
+    if cookie(&headers, LOGIN_STATE_COOKIE) != Some(query.state.to_string().as_str()) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let now = chrono::Utc::now().timestamp();
+    let valid_state = LOGIN_STATE
+        .remove(&query.state)
+        .map_or(false, |(_, started_at)| {
+            started_at + LOGIN_TIMEOUT_SECS > now
+        });
+    if !valid_state {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let clear_state = (header::SET_COOKIE, login_state_cookie("", 0));
+    match sign_in(&db_pool, &config, auth, &query.code).await {
+        Ok(Some(token)) => (
+            [
+                clear_state,
+                (header::SET_COOKIE, session_cookie(&token, SESSION_SECS)),
+            ],
+            Redirect::to("/dashboard"),
+        )
+            .into_response(),
+        Ok(None) => ([clear_state], StatusCode::FORBIDDEN).into_response(),
+        Err(e) => {
+            error!("Failed to sign in with discord - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// POST not to be triggered by links or images of other sites
+async fn logout() -> Response {
+    (
+        [(header::SET_COOKIE, session_cookie("", 0))],
+        Redirect::to("/"),
+    )
+        .into_response()
+}
+
+#[derive(serde::Serialize)]
+struct Me {
+    user_id: String,
+    name: Option<String>,
+}
+
+async fn me(Extension(db_pool): Extension<SqlitePool>, session: Session) -> Response {
+    let name = sqlx::query_scalar!(
+        "SELECT `name` FROM `users` WHERE `user_id` = ?",
+        session.user_id
+    )
+    .fetch_optional(&db_pool)
+    .await;
+
+    match name {
+        Ok(name) => Json(Me {
+            user_id: session.user_id.to_string(),
+            name,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Failed to get user of session - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub(crate) fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new()
+        .route("/discord", get(login))
+        .route("/discord/callback", get(callback))
+        .route("/logout", post(logout))
+        .route("/me", get(me))
+}
@@ -0,0 +1,402 @@
+use std::{fmt::Write as _, sync::Arc};
+
+use anyhow::Context as _;
+use axum::{
+    extract::{Extension, Request},
+    http::{header, StatusCode},
+    middleware::{from_fn, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+};
+use chrono::{Datelike, TimeZone};
+use chrono_tz::Tz;
+use dashmap::DashMap;
+use log::error;
+use once_cell::sync::Lazy;
+use serenity::model::permissions::Permissions;
+use sqlx::SqlitePool;
+
+use super::{auth::Session, escape_html};
+use crate::stats::Stats;
+
+const LAYOUT: &str = include_str!("dashboard/layout.html");
+const STYLESHEET: &str = include_str!("dashboard/dashboard.css");
+// permissions of members are asked to discord again after this
+const ADMIN_CACHE_SECS: i64 = 5 * 60;
+const RECENT_ROWS: i64 = 20;
+
+// whether the user manages the server, and until when it is trusted
+static ADMINS: Lazy<DashMap<i64, (bool, i64)>> = Lazy::new(DashMap::new);
+
+// `content` is put as is, so every text in it should be escaped
+fn render(title: &str, is_admin: bool, content: &str) -> Response {
+    let mut nav = r#"<a href="/dashboard">내 대시보드</a>"#.to_string();
+    if is_admin {
+        nav.push_str(r#"<a href="/dashboard/admin">서버</a>"#);
+    }
+
+    Html(
+        LAYOUT
+            .replace("{{title}}", &escape_html(title))
+            .replace("{{nav}}", &nav)
+            .replace("{{content}}", content),
+    )
+    .into_response()
+}
+
+fn section(title: &str, body: &str) -> String {
+    format!("<section><h2>{}</h2>{body}</section>", escape_html(title))
+}
+
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    if rows.is_empty() {
+        return r#"<p class="empty">기록이 없습니다.</p>"#.to_string();
+    }
+
+    let mut html = "<table><tr>".to_string();
+    for header in headers {
+        let _ = write!(html, "<th>{}</th>", escape_html(header));
+    }
+    html.push_str("</tr>");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            let _ = write!(html, "<td>{}</td>", escape_html(cell));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "예" } else { "아니오" }.to_string()
+}
+
+fn format_time(timestamp: i64, time_zone: Tz) -> String {
+    time_zone
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+// dates of eueoeo are stored as timestamps of the midnight
+fn format_date(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.date_naive().to_string())
+        .unwrap_or_default()
+}
+
+// usage is counted from the start of the month in UTC, as the LLM budget
+fn month_start() -> i64 {
+    let now = chrono::Utc::now();
+    chrono::Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .unwrap()
+        .timestamp()
+}
+
+async fn is_admin(config: &crate::Config, user_id: i64) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(admin) = ADMINS.get(&user_id) {
+        if admin.1 > now {
+            return admin.0;
+        }
+    }
+
+    match crate::discord::member_permissions(&config.discord, user_id as u64).await {
+        Ok(permissions) => {
+            let admin = permissions.contains(Permissions::MANAGE_GUILD);
+            ADMINS.insert(user_id, (admin, now + ADMIN_CACHE_SECS));
+            admin
+        }
+        Err(e) => {
+            error!("Failed to check permissions of user({user_id}) - {e:?}");
+            false
+        }
+    }
+}
+
+// pages are for signed in users. others are sent to sign in.
+async fn require_session(
+    session: Result<Session, StatusCode>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match session {
+        Ok(session) => {
+            request.extensions_mut().insert(session);
+            next.run(request).await
+        }
+        Err(StatusCode::UNAUTHORIZED) => Redirect::to("/auth/discord").into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn require_admin(
+    Extension(config): Extension<Arc<crate::Config>>,
+    Extension(session): Extension<Session>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if is_admin(&config, session.user_id).await {
+        next.run(request).await
+    } else {
+        StatusCode::FORBIDDEN.into_response()
+    }
+}
+
+async fn home_content(db_pool: &SqlitePool, stats: &Stats, user_id: i64) -> anyhow::Result<String> {
+    let time_zone = crate::user::user_time_zone(db_pool, user_id).await;
+    let user = sqlx::query!(
+        r#"SELECT
+            `count`,
+            `longest_streaks`,
+            `current_streaks`,
+            `first_date`,
+            `last_date`,
+            `google_email`,
+            `google_calendar_id` IS NOT NULL AS "google_calendar_linked!: bool",
+            `outlook_refresh_token` IS NOT NULL AS "outlook_linked!: bool",
+            `caldav_url` IS NOT NULL AS "caldav_linked!: bool",
+            `calendar_backend`
+        FROM `users`
+        WHERE `user_id` = ?"#,
+        user_id
+    )
+    .fetch_optional(db_pool)
+    .await
+    .context("Failed to get user of dashboard")?;
+    let Some(user) = user else {
+        return Ok(section(
+            "으어어",
+            r#"<p class="empty">기록이 없습니다.</p>"#,
+        ));
+    };
+
+    let mut content = section(
+        "으어어",
+        &table(
+            &[
+                "총 횟수",
+                "최장 연속",
+                "현재 연속",
+                "첫 으어어",
+                "마지막 으어어",
+            ],
+            &[vec![
+                user.count.to_string(),
+                format!("{}일", user.longest_streaks),
+                format!("{}일", user.current_streaks),
+                user.first_date.map(format_date).unwrap_or_default(),
+                if user.last_date > 0 {
+                    format_date(user.last_date)
+                } else {
+                    String::new()
+                },
+            ]],
+        ),
+    );
+
+    content.push_str(&section(
+        "연결된 계정",
+        &table(
+            &["구글", "구글 캘린더", "Outlook", "CalDAV", "동기화 캘린더"],
+            &[vec![
+                user.google_email
+                    .unwrap_or_else(|| "연결 안 됨".to_string()),
+                yes_no(user.google_calendar_linked),
+                yes_no(user.outlook_linked),
+                yes_no(user.caldav_linked),
+                user.calendar_backend,
+            ]],
+        ),
+    ));
+
+    let events = sqlx::query!(
+        r#"SELECT
+            `scheduled_events`.`name`,
+            `scheduled_events`.`start_time`
+        FROM `server_events`
+        INNER JOIN `scheduled_events`
+            ON `scheduled_events`.`discord_id` = `server_events`.`discord_id`
+        WHERE `server_events`.`user_id` = ?
+        ORDER BY `scheduled_events`.`start_time` DESC
+        LIMIT ?"#,
+        user_id,
+        RECENT_ROWS
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get synced events of user")?
+    .into_iter()
+    .map(|event| vec![event.name, format_time(event.start_time, time_zone)])
+    .collect::<Vec<_>>();
+    content.push_str(&section(
+        "동기화된 이벤트",
+        &table(&["이벤트", "시작"], &events),
+    ));
+
+    let usage = stats.llm_usage(user_id, month_start()).await?;
+    content.push_str(&section(
+        "이번 달 LLM 사용량",
+        &table(
+            &["요청", "입력 토큰", "출력 토큰"],
+            &[vec![
+                usage.requests.to_string(),
+                usage.prompt_tokens.to_string(),
+                usage.response_tokens.to_string(),
+            ]],
+        ),
+    ));
+
+    Ok(content)
+}
+
+async fn admin_content(
+    db_pool: &SqlitePool,
+    stats: &Stats,
+    user_id: i64,
+) -> anyhow::Result<String> {
+    let time_zone = crate::user::user_time_zone(db_pool, user_id).await;
+
+    let ranking = stats
+        .eueoeo_total()
+        .await?
+        .into_iter()
+        .map(|entry| {
+            vec![
+                entry.rank.to_string(),
+                entry.name,
+                entry.count.to_string(),
+                yes_no(entry.departed),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let mut content = section(
+        "으어어 순위",
+        &table(&["순위", "이름", "횟수", "탈퇴"], &ranking),
+    );
+
+    let usages = stats
+        .llm_usage_by_user(month_start())
+        .await?
+        .into_iter()
+        .map(|usage| {
+            vec![
+                usage.name.unwrap_or(usage.user_id),
+                usage.usage.requests.to_string(),
+                usage.usage.prompt_tokens.to_string(),
+                usage.usage.response_tokens.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    content.push_str(&section(
+        "이번 달 LLM 사용량",
+        &table(&["사용자", "요청", "입력 토큰", "출력 토큰"], &usages),
+    ));
+
+    let events = stats
+        .upcoming_events(RECENT_ROWS)
+        .await?
+        .into_iter()
+        .map(|event| {
+            vec![
+                event.name,
+                chrono::DateTime::parse_from_rfc3339(&event.start_time)
+                    .map(|time| format_time(time.timestamp(), time_zone))
+                    .unwrap_or(event.start_time),
+                event.interested_count.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    content.push_str(&section(
+        "다가오는 이벤트",
+        &table(&["이벤트", "시작", "관심"], &events),
+    ));
+
+    let failures = sqlx::query!(
+        r#"SELECT
+            `sync_log`.`action`,
+            `sync_log`.`error`,
+            `sync_log`.`created_at`,
+            `sync_log`.`user_id`,
+            `users`.`name` AS "name?: String"
+        FROM `sync_log`
+        LEFT JOIN `users` ON `users`.`user_id` = `sync_log`.`user_id`
+        WHERE NOT `sync_log`.`success`
+        ORDER BY `sync_log`.`created_at` DESC
+        LIMIT ?"#,
+        RECENT_ROWS
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Failed to get sync failures")?
+    .into_iter()
+    .map(|failure| {
+        vec![
+            format_time(failure.created_at, time_zone),
+            failure.name.unwrap_or_else(|| failure.user_id.to_string()),
+            failure.action,
+            failure.error.unwrap_or_default(),
+        ]
+    })
+    .collect::<Vec<_>>();
+    content.push_str(&section(
+        "최근 동기화 실패",
+        &table(&["시각", "사용자", "작업", "오류"], &failures),
+    ));
+
+    Ok(content)
+}
+
+async fn home(
+    Extension(db_pool): Extension<SqlitePool>,
+    Extension(config): Extension<Arc<crate::Config>>,
+    Extension(stats): Extension<Stats>,
+    Extension(session): Extension<Session>,
+) -> Response {
+    match home_content(&db_pool, &stats, session.user_id).await {
+        Ok(content) => render(
+            "내 대시보드",
+            is_admin(&config, session.user_id).await,
+            &content,
+        ),
+        Err(e) => {
+            error!("Failed to render dashboard - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn admin(
+    Extension(db_pool): Extension<SqlitePool>,
+    Extension(stats): Extension<Stats>,
+    Extension(session): Extension<Session>,
+) -> Response {
+    match admin_content(&db_pool, &stats, session.user_id).await {
+        Ok(content) => render("서버", true, &content),
+        Err(e) => {
+            error!("Failed to render admin dashboard - {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn stylesheet() -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        STYLESHEET,
+    )
+        .into_response()
+}
+
+pub(crate) fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new()
+        .route("/", get(home))
+        .route("/admin", get(admin).route_layer(from_fn(require_admin)))
+        .route_layer(from_fn(require_session))
+        // static assets are served without sessions
+        .route("/static/dashboard.css", get(stylesheet))
+}
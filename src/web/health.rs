@@ -0,0 +1,46 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use log::warn;
+use sqlx::SqlitePool;
+
+use crate::discord::GatewayStatus;
+
+// the process is serving
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+// unavailable until every dependency is reachable. the failed ones are listed.
+async fn readyz(
+    Extension(db_pool): Extension<SqlitePool>,
+    Extension(gateway_status): Extension<GatewayStatus>,
+) -> Response {
+    let mut failures = Vec::new();
+    if let Err(e) = sqlx::query!("SELECT 1 AS `one`").fetch_one(&db_pool).await {
+        warn!("Database is not reachable - {e:?}");
+        failures.push("database");
+    }
+    if !gateway_status.is_connected() {
+        failures.push("discord gateway");
+    }
+
+    if failures.is_empty() {
+        "ok".into_response()
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("not ready: {}", failures.join(", ")),
+        )
+            .into_response()
+    }
+}
+
+pub(crate) fn web_router<S: Sync + Send + Clone + 'static>() -> axum::Router<S> {
+    axum::Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+}
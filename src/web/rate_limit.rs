@@ -0,0 +1,138 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    num::NonZeroU32,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use log::warn;
+use serde::{Deserialize, Deserializer};
+
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+const CLEANUP_TICK: Duration = Duration::from_secs(60);
+
+// token bucket per client IP. a request takes a token and `per_second` tokens are refilled up to `burst`.
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Config {
+    // no request passes with 0
+    burst: NonZeroU32,
+    #[serde(deserialize_with = "deserialize_per_second")]
+    per_second: f64,
+    // take the client IP from `X-Forwarded-For` set by the reverse proxy in front
+    #[serde(default)]
+    trust_forwarded_for: bool,
+}
+
+// waiting for a token never ends without refilling
+fn deserialize_per_second<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let per_second = f64::deserialize(deserializer)?;
+    if per_second.is_finite() && per_second > 0.0 {
+        Ok(per_second)
+    } else {
+        Err(serde::de::Error::custom("per_second must be positive"))
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+pub(crate) struct RateLimiter {
+    config: Config,
+    buckets: DashMap<IpAddr, Bucket>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: Config) -> Arc<Self> {
+        let limiter = Arc::new(Self {
+            config,
+            buckets: DashMap::new(),
+        });
+
+        // full buckets are the same as new ones
+        let weak = Arc::downgrade(&limiter);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(CLEANUP_TICK).await;
+                let Some(limiter) = weak.upgrade() else {
+                    break;
+                };
+                let now = Instant::now();
+                limiter
+                    .buckets
+                    .retain(|_, bucket| limiter.refilled(bucket, now) < limiter.burst());
+            }
+        });
+
+        limiter
+    }
+
+    fn burst(&self) -> f64 {
+        f64::from(self.config.burst.get())
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        (bucket.tokens + elapsed * self.config.per_second).min(self.burst())
+    }
+
+    // `Err` with the seconds until a token is available
+    fn take(&self, ip: IpAddr) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut bucket = self.buckets.entry(ip).or_insert_with(|| Bucket {
+            tokens: self.burst(),
+            updated_at: now,
+        });
+        let tokens = self.refilled(&bucket, now);
+        bucket.updated_at = now;
+        if tokens >= 1.0 {
+            bucket.tokens = tokens - 1.0;
+            Ok(())
+        } else {
+            bucket.tokens = tokens;
+            Err(((1.0 - tokens) / self.config.per_second).ceil() as u64)
+        }
+    }
+
+    fn client_ip(&self, headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+        if !self.config.trust_forwarded_for {
+            return peer.ip();
+        }
+
+        // the last one is appended by the proxy, the others are told by the client
+        headers
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+            .unwrap_or_else(|| peer.ip())
+    }
+}
+
+pub(crate) async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = limiter.client_ip(request.headers(), peer);
+    match limiter.take(ip) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            warn!("Rate limited {ip} on {}", request.uri().path());
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.max(1).to_string())],
+            )
+                .into_response()
+        }
+    }
+}